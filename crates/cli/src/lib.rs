@@ -0,0 +1,505 @@
+//! The `merklerust` command line tool for release pipelines that publish Merkle tree dumps.
+//! It reads the dumps the core crate writes: OpenZeppelin `standard-v1` and `simple-v1` (or
+//! `simple-rle-v1`) dumps, and [`MerkleTree::dump`] dumps with their config.
+//!
+//! `merklerust diff a.json b.json` compares two dumps, such as candidate airdrop snapshots,
+//! leaf by leaf. Standard leaves are matched by their first value, the address of an
+//! `[address, uint256]` snapshot, so a leaf whose other values change is reported as changed;
+//! other leaves have no identity beyond their value and are only added or removed.
+//!
+//! `merklerust proofs tree.json --out-dir claims` writes the proof of every leaf to its own
+//! JSON file, the layout airdrop frontends fetch from static hosting: `<address>.json` when
+//! the leaves start with an address, else `<leaf hash>.json`.
+//!
+//! Outputs are byte-stable: reports and claims are sorted, and proofs do not depend on how
+//! many threads built them. Release pipelines that diff artifacts between builds can pass
+//! `--verify-deterministic` to any command, which runs it a second time on one thread and
+//! fails without writing anything unless both runs produce the same bytes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use merklerust_core::diff::try_get_change_proof;
+use merklerust_core::dump::DumpError;
+use merklerust_core::hashes::Hasher;
+use merklerust_core::merkle::{try_get_proofs, try_get_proofs_parallel, Bytes, MerkleError};
+use merklerust_core::simple::SimpleMerkleTree;
+use merklerust_core::standard::StandardMerkleTree;
+use merklerust_core::tree::{MerkleTree, TreeConfig};
+
+pub const USAGE: &str = "usage: merklerust diff <old.json> <new.json> [--verify-deterministic]
+       merklerust proofs <tree.json> --out-dir <dir> [--verify-deterministic]";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("{0}\n{USAGE}")]
+    Usage(String),
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    Dump { path: PathBuf, source: DumpError },
+    #[error(transparent)]
+    Merkle(#[from] MerkleError),
+    /// Two leaves would be written to the same proof file.
+    #[error("Two leaves share the proof file {0}")]
+    DuplicateClaim(String),
+    /// `--verify-deterministic` found runs that differ, in stdout or the named file.
+    #[error("Output is not deterministic: {0} differs between runs")]
+    NotDeterministic(String),
+}
+
+/// A loaded dump of any supported format.
+pub enum Dump {
+    Standard(StandardMerkleTree),
+    Simple(SimpleMerkleTree),
+    Plain(MerkleTree<Box<dyn Hasher + Send + Sync>>),
+}
+
+/// One leaf of a dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Leaf {
+    /// What the leaf is matched by across dumps: the first value of a standard leaf, else the
+    /// whole value.
+    pub key: String,
+    /// Values of a standard leaf, the hex value of a simple leaf, or the hex leaf of a plain
+    /// tree.
+    pub value: Vec<String>,
+}
+
+fn hex0x(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+impl Dump {
+    /// Parse `dump`, choosing the loader by its `format` field.
+    pub fn parse(dump: &str) -> Result<Self, DumpError> {
+        let json: serde_json::Value =
+            serde_json::from_str(dump).map_err(|e| DumpError::InvalidJson(e.to_string()))?;
+        match json.get("format").map(|f| f.as_str()) {
+            Some(Some("standard-v1")) => StandardMerkleTree::load(dump).map(Dump::Standard),
+            Some(Some("simple-v1" | "simple-rle-v1")) => {
+                SimpleMerkleTree::load(dump).map(Dump::Simple)
+            }
+            Some(Some(other)) => Err(DumpError::UnknownFormat(other.to_string())),
+            Some(None) => Err(DumpError::InvalidDump("format is not a string".to_string())),
+            None => {
+                let config = json.get("config").cloned().unwrap_or_default();
+                let config: TreeConfig = serde_json::from_value(config)
+                    .map_err(|e| DumpError::InvalidDump(e.to_string()))?;
+                let hasher = config
+                    .build_hasher()
+                    .map_err(|e| DumpError::InvalidDump(e.to_string()))?;
+                MerkleTree::load(dump, hasher).map(Dump::Plain)
+            }
+        }
+    }
+
+    /// Read and parse the dump at `path`.
+    pub fn read(path: &Path) -> Result<Self, CliError> {
+        let dump = std::fs::read_to_string(path).map_err(|source| CliError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&dump).map_err(|source| CliError::Dump {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn root(&self) -> &[u8] {
+        match self {
+            Dump::Standard(tree) => tree.root(),
+            Dump::Simple(tree) => tree.root(),
+            Dump::Plain(tree) => tree.root(),
+        }
+    }
+
+    /// The flat tree, root first.
+    pub fn tree(&self) -> &[Bytes] {
+        match self {
+            Dump::Standard(tree) => tree.tree(),
+            Dump::Simple(tree) => tree.tree(),
+            Dump::Plain(tree) => tree.as_slice(),
+        }
+    }
+
+    /// Leaves in value order, or leaf order for plain trees.
+    pub fn leaves(&self) -> Vec<Leaf> {
+        match self {
+            Dump::Standard(tree) => tree
+                .values()
+                .iter()
+                .map(|v| Leaf {
+                    key: v.value.first().cloned().unwrap_or_default(),
+                    value: v.value.clone(),
+                })
+                .collect(),
+            Dump::Simple(tree) => tree
+                .values()
+                .iter()
+                .map(|v| Leaf {
+                    key: hex0x(&v.value),
+                    value: vec![hex0x(&v.value)],
+                })
+                .collect(),
+            Dump::Plain(tree) => tree
+                .leaves()
+                .map(|leaf| Leaf {
+                    key: hex0x(leaf),
+                    value: vec![hex0x(leaf)],
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Proof of one leaf, as written by `merklerust proofs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Claim {
+    /// File name without the `.json` extension.
+    pub name: String,
+    /// Values of a standard leaf or the hex value of a simple leaf; plain trees have none.
+    pub value: Option<Vec<String>>,
+    pub leaf: Bytes,
+    pub proof: Vec<Bytes>,
+}
+
+impl Claim {
+    /// The file contents: pretty JSON with sorted keys and a trailing newline.
+    pub fn to_json(&self, root: &[u8]) -> String {
+        let mut claim = serde_json::json!({
+            "leaf": hex0x(&self.leaf),
+            "proof": self.proof.iter().map(|p| hex0x(p)).collect::<Vec<_>>(),
+            "root": hex0x(root),
+        });
+        if let Some(value) = &self.value {
+            claim["value"] = serde_json::json!(value);
+        }
+        let mut json = serde_json::to_string_pretty(&claim).expect("claims serialize");
+        json.push('\n');
+        json
+    }
+}
+
+impl Dump {
+    /// Proofs of every leaf, sorted by file name. Names are lowercase addresses for standard
+    /// trees whose first leaf type is `address`, and `0x` leaf hashes otherwise; a repeated
+    /// address is an error, while a repeated leaf is written once.
+    pub fn claims(&self) -> Result<Vec<Claim>, CliError> {
+        self.collect_claims(true)
+    }
+
+    fn collect_claims(&self, parallel: bool) -> Result<Vec<Claim>, CliError> {
+        let tree = self.tree();
+        let get_proofs = if parallel {
+            try_get_proofs_parallel
+        } else {
+            try_get_proofs
+        };
+        let mut claims = match self {
+            Dump::Standard(standard) => {
+                let indices: Vec<usize> = standard.values().iter().map(|v| v.tree_index).collect();
+                let by_address =
+                    standard.leaf_encoding().first().map(String::as_str) == Some("address");
+                get_proofs(tree, &indices)?
+                    .into_iter()
+                    .zip(standard.values())
+                    .map(|(proof, v)| Claim {
+                        name: if by_address {
+                            v.value[0].to_lowercase()
+                        } else {
+                            hex0x(&tree[v.tree_index])
+                        },
+                        value: Some(v.value.clone()),
+                        leaf: tree[v.tree_index].clone(),
+                        proof,
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Dump::Simple(simple) => {
+                let indices: Vec<usize> = simple.values().iter().map(|v| v.tree_index).collect();
+                get_proofs(tree, &indices)?
+                    .into_iter()
+                    .zip(simple.values())
+                    .map(|(proof, v)| Claim {
+                        name: hex0x(&v.value),
+                        value: Some(vec![hex0x(&v.value)]),
+                        leaf: v.value.to_vec(),
+                        proof,
+                    })
+                    .collect()
+            }
+            Dump::Plain(plain) => plain
+                .leaves()
+                .enumerate()
+                .map(|(position, leaf)| {
+                    Ok(Claim {
+                        name: hex0x(leaf),
+                        value: None,
+                        leaf: leaf.to_vec(),
+                        proof: plain.try_proof(position)?,
+                    })
+                })
+                .collect::<Result<_, MerkleError>>()?,
+        };
+        claims.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut deduped: Vec<Claim> = Vec::with_capacity(claims.len());
+        for claim in claims {
+            match deduped.last() {
+                Some(last) if last.name == claim.name && last.leaf == claim.leaf => {}
+                Some(last) if last.name == claim.name => {
+                    return Err(CliError::DuplicateClaim(format!("{}.json", claim.name)))
+                }
+                _ => deduped.push(claim),
+            }
+        }
+        Ok(deduped)
+    }
+}
+
+/// Everything a command produces, computed before anything is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Output {
+    pub stdout: String,
+    /// Files to write, with their contents.
+    pub files: Vec<(PathBuf, String)>,
+    pub status: i32,
+}
+
+impl Output {
+    /// Where two outputs first differ: `stdout` or a file path.
+    fn first_difference(&self, other: &Output) -> Option<String> {
+        if self.stdout != other.stdout || self.status != other.status {
+            return Some("stdout".to_string());
+        }
+        if self.files.len() != other.files.len() {
+            return Some("the set of files".to_string());
+        }
+        self.files
+            .iter()
+            .zip(&other.files)
+            .find(|(a, b)| a != b)
+            .map(|((path, _), _)| path.display().to_string())
+    }
+
+    /// Write the files, creating their directories, and return stdout and the exit status.
+    pub fn write(self) -> Result<(String, i32), CliError> {
+        for (path, contents) in &self.files {
+            let io = |source| CliError::Io {
+                path: path.clone(),
+                source,
+            };
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(io)?;
+            }
+            std::fs::write(path, contents).map_err(io)?;
+        }
+        Ok((self.stdout, self.status))
+    }
+}
+
+fn proofs_output(dump: &Dump, out_dir: &Path, parallel: bool) -> Result<Output, CliError> {
+    let claims = dump.collect_claims(parallel)?;
+    let files = claims
+        .iter()
+        .map(|claim| {
+            let path = out_dir.join(format!("{}.json", claim.name));
+            (path, claim.to_json(dump.root()))
+        })
+        .collect::<Vec<_>>();
+    Ok(Output {
+        stdout: format!("wrote {} proofs to {}\n", files.len(), out_dir.display()),
+        files,
+        status: 0,
+    })
+}
+
+/// Write every claim of `dump` to `out_dir`, creating it if needed, and return how many files
+/// were written.
+pub fn write_proofs(dump: &Dump, out_dir: &Path) -> Result<usize, CliError> {
+    let output = proofs_output(dump, out_dir, true)?;
+    let written = output.files.len();
+    output.write()?;
+    Ok(written)
+}
+
+/// Leaf-level difference between two dumps, with leaves sorted by key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpDiff {
+    pub old_root: Bytes,
+    pub new_root: Bytes,
+    pub added: Vec<Leaf>,
+    pub removed: Vec<Leaf>,
+    /// `(old, new)` leaves with the same key and different values.
+    pub changed: Vec<(Leaf, Leaf)>,
+    /// Tree indices whose leaf differs, from [`try_get_change_proof`]; `None` unless both
+    /// trees have the same shape.
+    pub changed_positions: Option<Vec<usize>>,
+}
+
+impl DumpDiff {
+    pub fn roots_match(&self) -> bool {
+        self.old_root == self.new_root
+    }
+
+    /// The report `merklerust diff` prints.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "old root {}", hex0x(&self.old_root)).unwrap();
+        writeln!(out, "new root {}", hex0x(&self.new_root)).unwrap();
+        for leaf in &self.added {
+            writeln!(out, "+ {}", leaf.value.join(",")).unwrap();
+        }
+        for leaf in &self.removed {
+            writeln!(out, "- {}", leaf.value.join(",")).unwrap();
+        }
+        for (old, new) in &self.changed {
+            writeln!(out, "~ {} -> {}", old.value.join(","), new.value.join(",")).unwrap();
+        }
+        write!(
+            out,
+            "{} added, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+        .unwrap();
+        if let Some(positions) = &self.changed_positions {
+            write!(out, ", {} leaf positions differ", positions.len()).unwrap();
+        }
+        let verdict = if self.roots_match() {
+            "roots match"
+        } else {
+            "roots differ"
+        };
+        writeln!(out, "; {}", verdict).unwrap();
+        out
+    }
+}
+
+fn by_key(leaves: Vec<Leaf>) -> BTreeMap<String, Vec<Leaf>> {
+    let mut map: BTreeMap<String, Vec<Leaf>> = BTreeMap::new();
+    for leaf in leaves {
+        map.entry(leaf.key.clone()).or_default().push(leaf);
+    }
+    map
+}
+
+/// Compare `old` with `new`. Leaves sharing a key are paired in value order; a key listed
+/// more often on one side adds or removes the rest.
+pub fn diff(old: &Dump, new: &Dump) -> Result<DumpDiff, CliError> {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+    let mut new_leaves = by_key(new.leaves());
+    for (key, mut old_group) in by_key(old.leaves()) {
+        let mut new_group = new_leaves.remove(&key).unwrap_or_default();
+        // identical leaves pair up first, so a repeated key only changes what really changed
+        old_group.retain(
+            |o| match new_group.iter().position(|n| n.value == o.value) {
+                Some(i) => {
+                    new_group.remove(i);
+                    false
+                }
+                None => true,
+            },
+        );
+        let pairs = old_group.len().min(new_group.len());
+        let rest_new = new_group.split_off(pairs);
+        let rest_old = old_group.split_off(pairs);
+        changed.extend(old_group.into_iter().zip(new_group));
+        removed.extend(rest_old);
+        added.extend(rest_new);
+    }
+    added.extend(new_leaves.into_values().flatten());
+    let changed_positions = match try_get_change_proof(old.tree(), new.tree()) {
+        Ok(proof) => {
+            let mut indices = proof.indices;
+            indices.sort_unstable();
+            Some(indices)
+        }
+        Err(MerkleError::ShapeMismatch { .. }) => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(DumpDiff {
+        old_root: old.root().to_vec(),
+        new_root: new.root().to_vec(),
+        added,
+        removed,
+        changed,
+        changed_positions,
+    })
+}
+
+fn usage(message: impl Into<String>) -> CliError {
+    CliError::Usage(message.into())
+}
+
+/// Remove `--name <value>` from `args` and return the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, CliError> {
+    let Some(at) = args.iter().position(|a| a == name) else {
+        return Ok(None);
+    };
+    if at + 1 == args.len() {
+        return Err(usage(format!("{} needs a value", name)));
+    }
+    let value = args.remove(at + 1);
+    args.remove(at);
+    Ok(Some(value))
+}
+
+/// Build the output of `command`, with proofs on every core when `parallel` is set.
+fn execute(command: &str, rest: &[String], parallel: bool) -> Result<Output, CliError> {
+    let mut rest = rest.to_vec();
+    match command {
+        "diff" => {
+            let [old, new] = rest.as_slice() else {
+                return Err(usage("diff takes two dump files"));
+            };
+            let report = diff(&Dump::read(Path::new(old))?, &Dump::read(Path::new(new))?)?;
+            let status = if report.roots_match() { 0 } else { 1 };
+            Ok(Output {
+                stdout: report.render(),
+                files: Vec::new(),
+                status,
+            })
+        }
+        "proofs" => {
+            let out_dir = take_option(&mut rest, "--out-dir")?
+                .ok_or_else(|| usage("proofs needs --out-dir"))?;
+            let [dump] = rest.as_slice() else {
+                return Err(usage("proofs takes one dump file"));
+            };
+            proofs_output(&Dump::read(Path::new(dump))?, Path::new(&out_dir), parallel)
+        }
+        other => Err(usage(format!("unknown command {:?}", other))),
+    }
+}
+
+/// Run the command in `args` (without the program name) and return its output with the
+/// exit status. `diff` exits 0 for equal roots and 1 otherwise, as `diff(1)` does. With
+/// `--verify-deterministic` the command runs again on one thread, and nothing is written
+/// unless both runs agree byte for byte.
+pub fn run(args: &[String]) -> Result<(String, i32), CliError> {
+    let (command, rest) = args.split_first().ok_or_else(|| usage("missing command"))?;
+    let mut rest = rest.to_vec();
+    let verify = match rest.iter().position(|a| a == "--verify-deterministic") {
+        Some(at) => {
+            rest.remove(at);
+            true
+        }
+        None => false,
+    };
+    let output = execute(command, &rest, true)?;
+    if verify {
+        let again = execute(command, &rest, false)?;
+        if let Some(what) = output.first_difference(&again) {
+            return Err(CliError::NotDeterministic(what));
+        }
+    }
+    output.write()
+}
@@ -0,0 +1,17 @@
+use std::io::Write;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match merklerust_cli::run(&args) {
+        Ok((output, status)) => {
+            // a closed stdout (e.g. `| head`) is not an error worth reporting
+            let _ = std::io::stdout().write_all(output.as_bytes());
+            ExitCode::from(status as u8)
+        }
+        Err(e) => {
+            eprintln!("merklerust: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use merklerust_cli::{diff, write_proofs, CliError, Dump};
+use merklerust_core::hashes::{PositionalSha256Hasher, Sha256Hasher};
+use merklerust_core::simple::SimpleMerkleTree;
+use merklerust_core::standard::StandardMerkleTree;
+use merklerust_core::tree::MerkleTree;
+
+fn strings(xs: &[&str]) -> Vec<String> {
+    xs.iter().map(|s| s.to_string()).collect()
+}
+
+fn snapshot(balances: &[(u64, u64)]) -> StandardMerkleTree {
+    let values = balances
+        .iter()
+        .map(|(a, b)| strings(&[&format!("0x{:040x}", a), &b.to_string()]))
+        .collect();
+    StandardMerkleTree::of(values, strings(&["address", "uint256"]))
+}
+
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("merklerust-cli-{}", std::process::id()))
+}
+
+fn write_dump(name: &str, dump: &str) -> PathBuf {
+    let dir = temp_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, dump).unwrap();
+    path
+}
+
+#[test]
+fn reports_added_removed_and_changed_standard_leaves() {
+    let old = Dump::parse(&snapshot(&[(1, 100), (2, 200), (3, 300)]).dump()).unwrap();
+    let new = Dump::parse(&snapshot(&[(1, 100), (2, 250), (4, 400)]).dump()).unwrap();
+    let report = diff(&old, &new).unwrap();
+
+    assert!(!report.roots_match());
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.added[0].value[1], "400");
+    assert_eq!(report.removed.len(), 1);
+    assert_eq!(report.removed[0].value[1], "300");
+    assert_eq!(report.changed.len(), 1);
+    assert_eq!(report.changed[0].0.value[1], "200");
+    assert_eq!(report.changed[0].1.value[1], "250");
+    // both trees have three leaves, so the changed positions come from the tree diff
+    assert!(report.changed_positions.is_some());
+    let render = report.render();
+    let summary = render.lines().last().unwrap();
+    assert!(summary.starts_with("1 added, 1 removed, 1 changed, "));
+    assert!(summary.ends_with("; roots differ"));
+
+    let same = diff(&old, &old).unwrap();
+    assert!(same.roots_match());
+    assert_eq!(same.changed_positions, Some(Vec::new()));
+}
+
+#[test]
+fn diffs_simple_and_plain_dumps_by_value() {
+    let hash = |i: u8| [i; 32];
+    let old = SimpleMerkleTree::of(vec![hash(1), hash(2)]);
+    let new = SimpleMerkleTree::of(vec![hash(1), hash(2), hash(3)]);
+    let report = diff(
+        &Dump::parse(&old.dump()).unwrap(),
+        &Dump::parse(&new.dump_compressed()).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(report.added.len(), 1);
+    assert!(report.removed.is_empty() && report.changed.is_empty());
+    assert_eq!(report.changed_positions, None);
+
+    let plain = MerkleTree::new(vec![vec![1u8; 32], vec![2u8; 32]], Sha256Hasher);
+    let loaded = Dump::parse(&plain.dump()).unwrap();
+    assert_eq!(loaded.root(), plain.root());
+    assert!(diff(&loaded, &loaded).unwrap().roots_match());
+}
+
+#[test]
+fn exits_like_diff() {
+    let a = write_dump("a.json", &snapshot(&[(1, 100), (2, 200)]).dump());
+    let b = write_dump("b.json", &snapshot(&[(1, 100), (2, 201)]).dump());
+    let bin = env!("CARGO_BIN_EXE_merklerust");
+
+    let same = Command::new(bin)
+        .args(["diff"])
+        .arg(&a)
+        .arg(&a)
+        .output()
+        .unwrap();
+    assert_eq!(same.status.code(), Some(0));
+    assert!(String::from_utf8(same.stdout)
+        .unwrap()
+        .contains("roots match"));
+
+    let changed = Command::new(bin)
+        .args(["diff"])
+        .arg(&a)
+        .arg(&b)
+        .output()
+        .unwrap();
+    assert_eq!(changed.status.code(), Some(1));
+    let stdout = String::from_utf8(changed.stdout).unwrap();
+    assert!(stdout.contains(&format!("~ 0x{:040x},200 -> 0x{:040x},201", 2, 2)));
+
+    let missing = Command::new(bin)
+        .args(["diff", "missing.json"])
+        .arg(&a)
+        .output()
+        .unwrap();
+    assert_eq!(missing.status.code(), Some(2));
+    assert!(Command::new(bin)
+        .arg("frobnicate")
+        .output()
+        .unwrap()
+        .stderr
+        .starts_with(b"merklerust: unknown command"));
+}
+
+#[test]
+fn writes_one_proof_file_per_address() {
+    let tree = snapshot(&[(0xab, 100), (2, 200), (3, 300)]);
+    let out = temp_dir().join("claims");
+    let written = write_proofs(&Dump::parse(&tree.dump()).unwrap(), &out).unwrap();
+    assert_eq!(written, 3);
+
+    let file = std::fs::read_to_string(out.join(format!("0x{:040x}.json", 0xab))).unwrap();
+    let claim: serde_json::Value = serde_json::from_str(&file).unwrap();
+    let value = [format!("0x{:040x}", 0xab), "100".to_string()];
+    assert_eq!(claim["value"], serde_json::json!(value));
+    assert_eq!(claim["root"], format!("0x{}", hex::encode(tree.root())));
+    let proof: Vec<Vec<u8>> = claim["proof"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| hex::decode(&p.as_str().unwrap()[2..]).unwrap())
+        .collect();
+    assert!(tree.verify_proof(&value, &proof));
+
+    // leaves without an address are named by leaf hash
+    let plain = MerkleTree::new(vec![vec![1u8; 32], vec![2u8; 32]], Sha256Hasher);
+    let claims = Dump::parse(&plain.dump()).unwrap().claims().unwrap();
+    assert_eq!(claims[0].name, format!("0x{}", hex::encode([1u8; 32])));
+    assert_eq!(claims[0].proof, plain.proof(0));
+}
+
+#[test]
+fn rejects_claims_that_would_share_a_file() {
+    let repeated = snapshot(&[(1, 100), (1, 200)]);
+    assert!(matches!(
+        Dump::parse(&repeated.dump()).unwrap().claims(),
+        Err(CliError::DuplicateClaim(_))
+    ));
+    // positional trees have no single-list proofs to write
+    let positional = MerkleTree::new_positional(vec![vec![1u8; 32]; 3], PositionalSha256Hasher);
+    assert!(matches!(
+        Dump::parse(&positional.dump()).unwrap().claims(),
+        Err(CliError::Merkle(_))
+    ));
+
+    let bin = env!("CARGO_BIN_EXE_merklerust");
+    let dump = write_dump("proofs.json", &snapshot(&[(1, 100)]).dump());
+    let missing = Command::new(bin).arg("proofs").arg(&dump).output().unwrap();
+    assert_eq!(missing.status.code(), Some(2));
+    assert!(String::from_utf8(missing.stderr)
+        .unwrap()
+        .contains("proofs needs --out-dir"));
+}
+
+#[test]
+fn verified_runs_write_the_same_bytes() {
+    let balances: Vec<(u64, u64)> = (1..=200).map(|i| (i, i * 7)).collect();
+    let dump = write_dump("many.json", &snapshot(&balances).dump());
+    let run = |out: &str, verify: bool| {
+        let mut args = vec![
+            "proofs".to_string(),
+            dump.display().to_string(),
+            "--out-dir".to_string(),
+            temp_dir().join(out).display().to_string(),
+        ];
+        if verify {
+            args.push("--verify-deterministic".to_string());
+        }
+        merklerust_cli::run(&args).unwrap()
+    };
+    assert_eq!(run("plain", false).1, 0);
+    assert_eq!(run("verified", true).1, 0);
+
+    let read = |out: &str| {
+        let mut files: Vec<(String, Vec<u8>)> = std::fs::read_dir(temp_dir().join(out))
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let name = entry.file_name().into_string().unwrap();
+                (name, std::fs::read(entry.path()).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let plain = read("plain");
+    assert_eq!(plain.len(), 200);
+    assert_eq!(plain, read("verified"));
+
+    let a = write_dump("stable.json", &snapshot(&balances[..3]).dump());
+    let report = merklerust_cli::run(&[
+        "diff".to_string(),
+        "--verify-deterministic".to_string(),
+        a.display().to_string(),
+        dump.display().to_string(),
+    ])
+    .unwrap();
+    assert_eq!(report.1, 1);
+    assert!(report.0.contains("197 added, 0 removed, 0 changed"));
+}
@@ -0,0 +1,61 @@
+//! `cargo bench --bench multiproof`: times `get_multi_proof` against the previous
+//! `Vec::remove(0)` sweep for large index sets.
+
+use std::time::{Duration, Instant};
+
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::{get_multi_proof, leaf_tree_index, make_merkle_tree_bytes, Bytes};
+
+fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak256(&[left, right].concat()).to_vec()
+}
+
+/// The sweep `get_multi_proof` used before, quadratic in the number of indices.
+fn remove_front_sweep(tree: &[Bytes], mut indices: Vec<usize>) -> (Vec<Bytes>, Vec<bool>) {
+    indices.sort_by(|a, b| b.cmp(a));
+    let mut stack = indices;
+    let mut proof = Vec::new();
+    let mut proof_flags = Vec::new();
+    while !stack.is_empty() && stack[0] > 0 {
+        let j = stack.remove(0);
+        let s = if j.is_multiple_of(2) { j - 1 } else { j + 1 };
+        if !stack.is_empty() && s == stack[0] {
+            proof_flags.push(true);
+            stack.remove(0);
+        } else {
+            proof_flags.push(false);
+            proof.push(tree[s].clone());
+        }
+        stack.push((j - 1) / 2);
+    }
+    (proof, proof_flags)
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let out = f();
+    (out, start.elapsed())
+}
+
+fn main() {
+    let leaf_count = 1 << 17;
+    let leaves: Vec<Bytes> = (0..leaf_count as u32)
+        .map(|i| keccak256(&i.to_be_bytes()).to_vec())
+        .collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+
+    for count in [1_000, 10_000, 50_000] {
+        // every k-th leaf, spread over the whole tree
+        let indices: Vec<usize> = (0..count)
+            .map(|i| leaf_tree_index(tree.len(), i * (leaf_count / count)))
+            .collect();
+        let (new, new_time) = time(|| get_multi_proof(&tree, indices.clone()));
+        let (old, old_time) = time(|| remove_front_sweep(&tree, indices.clone()));
+        assert_eq!((new.proof, new.proof_flags), old);
+        println!(
+            "{:>6} indices: VecDeque {:>10.2?}  remove(0) {:>10.2?}",
+            count, new_time, old_time
+        );
+    }
+}
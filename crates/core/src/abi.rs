@@ -0,0 +1,227 @@
+//! Minimal Solidity ABI encoder (`abi.encode`) for the elementary types used by
+//! standard Merkle tree leaves: `address`, `bool`, `uintN`, `intN`, `bytesN`, `bytes` and
+//! `string`. Values are given as strings, the way they appear in JSON tree dumps: numbers in
+//! decimal or `0x` hex, byte values and addresses as `0x` hex.
+
+use crate::merkle::Bytes;
+
+type Word = [u8; 32];
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AbiError {
+    #[error("Expected {expected} values for ABI types, got {got}")]
+    ValueCount { expected: usize, got: usize },
+    #[error("Unsupported ABI type {0:?}")]
+    UnsupportedType(String),
+    #[error("Expected 0x-prefixed hex value, got {0:?}")]
+    MissingHexPrefix(String),
+    #[error("Invalid hex value {0:?}")]
+    InvalidHex(String),
+    #[error("Invalid integer value {0:?}")]
+    InvalidInteger(String),
+    #[error("Integer value {0:?} overflows 256 bits")]
+    IntegerOverflow(String),
+    #[error("Invalid address {0:?}")]
+    InvalidAddress(String),
+    #[error("Invalid bool value {0:?}")]
+    InvalidBool(String),
+    #[error("Expected {size} bytes for {ty}, got {got}")]
+    ByteLength { size: usize, ty: String, got: usize },
+    #[error("Value {value:?} out of range for {ty}")]
+    OutOfRange { value: String, ty: String },
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, AbiError> {
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .ok_or_else(|| AbiError::MissingHexPrefix(value.to_string()))?;
+    hex::decode(digits).map_err(|_| AbiError::InvalidHex(value.to_string()))
+}
+
+fn parse_uint(value: &str) -> Result<Word, AbiError> {
+    let invalid = || AbiError::InvalidInteger(value.to_string());
+    let mut word = [0u8; 32];
+    if value.starts_with("0x") || value.starts_with("0X") {
+        let digits = &value[2..];
+        if digits.is_empty() || digits.len() > 64 {
+            return Err(invalid());
+        }
+        let padded = format!("{:0>64}", digits);
+        word.copy_from_slice(&hex::decode(padded).map_err(|_| invalid())?);
+        return Ok(word);
+    }
+
+    if value.is_empty() || !value.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    for digit in value.bytes().map(|c| c - b'0') {
+        // word = word * 10 + digit
+        let mut carry = digit as u32;
+        for byte in word.iter_mut().rev() {
+            let v = (*byte as u32) * 10 + carry;
+            *byte = (v & 0xff) as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return Err(AbiError::IntegerOverflow(value.to_string()));
+        }
+    }
+    Ok(word)
+}
+
+/// Number of significant bits in a big-endian word.
+fn bit_len(word: &Word) -> usize {
+    match word.iter().position(|&b| b != 0) {
+        Some(i) => (32 - i) * 8 - word[i].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+fn negate(word: &mut Word) {
+    for byte in word.iter_mut() {
+        *byte = !*byte;
+    }
+    for byte in word.iter_mut().rev() {
+        let (v, overflow) = byte.overflowing_add(1);
+        *byte = v;
+        if !overflow {
+            break;
+        }
+    }
+}
+
+fn unsupported(ty: &str) -> AbiError {
+    AbiError::UnsupportedType(ty.to_string())
+}
+
+fn int_bits(ty: &str, prefix: &str) -> Result<usize, AbiError> {
+    let bits = &ty[prefix.len()..];
+    if bits.is_empty() {
+        return Ok(256);
+    }
+    let bits: usize = bits.parse().map_err(|_| unsupported(ty))?;
+    if bits == 0 || bits > 256 || !bits.is_multiple_of(8) {
+        return Err(unsupported(ty));
+    }
+    Ok(bits)
+}
+
+fn encode_static(ty: &str, value: &str) -> Result<Word, AbiError> {
+    let out_of_range = || AbiError::OutOfRange {
+        value: value.to_string(),
+        ty: ty.to_string(),
+    };
+    let mut word = [0u8; 32];
+    if ty == "address" {
+        let bytes = parse_hex(value)?;
+        if bytes.len() != 20 {
+            return Err(AbiError::InvalidAddress(value.to_string()));
+        }
+        word[12..].copy_from_slice(&bytes);
+    } else if ty == "bool" {
+        word[31] = match value {
+            "true" => 1,
+            "false" => 0,
+            _ => return Err(AbiError::InvalidBool(value.to_string())),
+        };
+    } else if let Some(size) = ty.strip_prefix("bytes") {
+        let size: usize = size.parse().map_err(|_| unsupported(ty))?;
+        if size == 0 || size > 32 {
+            return Err(unsupported(ty));
+        }
+        let bytes = parse_hex(value)?;
+        if bytes.len() != size {
+            return Err(AbiError::ByteLength {
+                size,
+                ty: ty.to_string(),
+                got: bytes.len(),
+            });
+        }
+        word[..size].copy_from_slice(&bytes);
+    } else if ty.starts_with("uint") {
+        let bits = int_bits(ty, "uint")?;
+        word = parse_uint(value)?;
+        if bit_len(&word) > bits {
+            return Err(out_of_range());
+        }
+    } else if ty.starts_with("int") {
+        let bits = int_bits(ty, "int")?;
+        let (negative, magnitude) = match value.strip_prefix('-') {
+            Some(m) => (true, m),
+            None => (false, value),
+        };
+        word = parse_uint(magnitude)?;
+        let len = bit_len(&word);
+        // |v| < 2^(bits-1), or exactly 2^(bits-1) for the most negative value
+        let in_range = len < bits || (negative && len == bits && is_power_of_two(&word));
+        if !in_range {
+            return Err(out_of_range());
+        }
+        if negative {
+            negate(&mut word);
+        }
+    } else {
+        return Err(unsupported(ty));
+    }
+    Ok(word)
+}
+
+fn is_power_of_two(word: &Word) -> bool {
+    word.iter().map(|b| b.count_ones()).sum::<u32>() == 1
+}
+
+fn is_dynamic(ty: &str) -> bool {
+    ty == "bytes" || ty == "string"
+}
+
+/// ABI-encode `values` as the tuple `types`, like Solidity's `abi.encode`. Panics on a value
+/// that does not fit its type; see [`try_encode`].
+pub fn encode<T, V>(types: &[T], values: &[V]) -> Bytes
+where
+    T: AsRef<str>,
+    V: AsRef<str>,
+{
+    try_encode(types, values).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_encode<T, V>(types: &[T], values: &[V]) -> Result<Bytes, AbiError>
+where
+    T: AsRef<str>,
+    V: AsRef<str>,
+{
+    if types.len() != values.len() {
+        return Err(AbiError::ValueCount {
+            expected: types.len(),
+            got: values.len(),
+        });
+    }
+
+    let mut head: Vec<u8> = Vec::with_capacity(32 * types.len());
+    let mut tail: Vec<u8> = Vec::new();
+
+    for (ty, value) in types.iter().zip(values.iter()) {
+        let (ty, value) = (ty.as_ref(), value.as_ref());
+        if is_dynamic(ty) {
+            let mut offset = [0u8; 32];
+            offset[24..].copy_from_slice(&((32 * types.len() + tail.len()) as u64).to_be_bytes());
+            head.extend_from_slice(&offset);
+
+            let data = if ty == "bytes" {
+                parse_hex(value)?
+            } else {
+                value.as_bytes().to_vec()
+            };
+            let mut len = [0u8; 32];
+            len[24..].copy_from_slice(&(data.len() as u64).to_be_bytes());
+            tail.extend_from_slice(&len);
+            tail.extend_from_slice(&data);
+            tail.resize(tail.len() + (32 - data.len() % 32) % 32, 0);
+        } else {
+            head.extend_from_slice(&encode_static(ty, value)?);
+        }
+    }
+
+    head.extend_from_slice(&tail);
+    Ok(head)
+}
@@ -0,0 +1,235 @@
+//! Trees over Arrow record batches and Parquet files (features `arrow` and `parquet`), one leaf
+//! per row. A row is serialized column by column, each cell as `0x00` when null and as
+//! `0x01 ‖ u32_be(len) ‖ value` otherwise, where the value is the little-endian bytes of
+//! numbers, dates and timestamps, a single `0`/`1` byte for booleans and the raw bytes of
+//! strings and binaries. The leaf is `hash_leaf` of those bytes. Column types are not part of
+//! the encoding, so commit to the schema separately where it matters.
+//!
+//! In the other direction [`proofs_batch`] and [`write_proofs_parquet`] emit one
+//! `(leaf_index, leaf, proof)` row per leaf for claim services and analytics.
+
+use std::sync::Arc;
+
+use arrow_array::builder::{FixedSizeBinaryBuilder, ListBuilder, UInt64Builder};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Date32Type, Date64Type, Decimal128Type, Float32Type, Float64Type, Int16Type, Int32Type,
+    Int64Type, Int8Type, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+
+use crate::consts::HASH_LEN;
+use crate::hashes::Hasher;
+use crate::merkle::Bytes;
+use crate::tree::MerkleTree;
+
+fn push_cell(row: &mut Bytes, value: Option<&[u8]>) {
+    match value {
+        None => row.push(0x00),
+        Some(v) => {
+            row.push(0x01);
+            row.extend_from_slice(&(v.len() as u32).to_be_bytes());
+            row.extend_from_slice(v);
+        }
+    }
+}
+
+fn append_column(array: &dyn Array, rows: &mut [Bytes]) -> Result<(), ArrowError> {
+    macro_rules! cells {
+        ($a:expr, |$v:ident| $bytes:expr) => {{
+            let a = $a;
+            for (i, row) in rows.iter_mut().enumerate() {
+                let cell = a.is_valid(i).then(|| {
+                    let $v = a.value(i);
+                    $bytes
+                });
+                push_cell(row, cell.as_ref().map(|c| c.as_ref()));
+            }
+        }};
+    }
+    macro_rules! primitive {
+        ($t:ty) => {
+            cells!(array.as_primitive::<$t>(), |v| v.to_le_bytes())
+        };
+    }
+    match array.data_type() {
+        DataType::Boolean => cells!(array.as_boolean(), |v| [v as u8]),
+        DataType::Int8 => primitive!(Int8Type),
+        DataType::Int16 => primitive!(Int16Type),
+        DataType::Int32 => primitive!(Int32Type),
+        DataType::Int64 => primitive!(Int64Type),
+        DataType::UInt8 => primitive!(UInt8Type),
+        DataType::UInt16 => primitive!(UInt16Type),
+        DataType::UInt32 => primitive!(UInt32Type),
+        DataType::UInt64 => primitive!(UInt64Type),
+        DataType::Float32 => primitive!(Float32Type),
+        DataType::Float64 => primitive!(Float64Type),
+        DataType::Decimal128(_, _) => primitive!(Decimal128Type),
+        DataType::Date32 => primitive!(Date32Type),
+        DataType::Date64 => primitive!(Date64Type),
+        DataType::Timestamp(TimeUnit::Second, _) => primitive!(TimestampSecondType),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => primitive!(TimestampMillisecondType),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => primitive!(TimestampMicrosecondType),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => primitive!(TimestampNanosecondType),
+        DataType::Utf8 => cells!(array.as_string::<i32>(), |v| v.as_bytes()),
+        DataType::LargeUtf8 => cells!(array.as_string::<i64>(), |v| v.as_bytes()),
+        DataType::Binary => cells!(array.as_binary::<i32>(), |v| v),
+        DataType::LargeBinary => cells!(array.as_binary::<i64>(), |v| v),
+        DataType::FixedSizeBinary(_) => cells!(array.as_fixed_size_binary(), |v| v),
+        other => {
+            return Err(ArrowError::NotYetImplemented(format!(
+                "Unsupported column type {}",
+                other
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Serialized form of each row of `columns`, which must all have the same length.
+pub fn row_bytes(columns: &[ArrayRef]) -> Result<Vec<Bytes>, ArrowError> {
+    let len = columns.first().map_or(0, |c| c.len());
+    if columns.iter().any(|c| c.len() != len) {
+        return Err(ArrowError::InvalidArgumentError(
+            "Columns have different lengths".to_string(),
+        ));
+    }
+    let mut rows = vec![Vec::new(); len];
+    for column in columns {
+        append_column(column.as_ref(), &mut rows)?;
+    }
+    Ok(rows)
+}
+
+/// One leaf per row of `batch`.
+pub fn leaves_from_batch<H: Hasher>(
+    batch: &RecordBatch,
+    hasher: &H,
+) -> Result<Vec<Bytes>, ArrowError> {
+    Ok(row_bytes(batch.columns())?
+        .iter()
+        .map(|row| hasher.hash_leaf(row).to_vec())
+        .collect())
+}
+
+/// Tree over the rows of `batches` in order, e.g. from a Parquet or IPC reader.
+pub fn tree_from_batches<I, H>(batches: I, hasher: H) -> Result<MerkleTree<H>, ArrowError>
+where
+    I: IntoIterator<Item = Result<RecordBatch, ArrowError>>,
+    H: Hasher,
+{
+    let mut leaves = Vec::new();
+    for batch in batches {
+        leaves.extend(leaves_from_batch(&batch?, &hasher)?);
+    }
+    MerkleTree::try_new(leaves, hasher).map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+}
+
+/// Tree over the rows of a Parquet file, reading only the named top-level `columns` if given.
+#[cfg(feature = "parquet")]
+pub fn tree_from_parquet<R, H>(
+    reader: R,
+    columns: Option<&[&str]>,
+    hasher: H,
+) -> Result<MerkleTree<H>, parquet::errors::ParquetError>
+where
+    R: parquet::file::reader::ChunkReader + 'static,
+    H: Hasher,
+{
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::arrow::ProjectionMask;
+
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+    if let Some(columns) = columns {
+        let mask = ProjectionMask::columns(builder.parquet_schema(), columns.iter().copied());
+        builder = builder.with_projection(mask);
+    }
+    Ok(tree_from_batches(builder.build()?, hasher)?)
+}
+
+/// Schema of [`proofs_batch`]: `leaf_index: UInt64`, `leaf: FixedSizeBinary(32)` and
+/// `proof: List<FixedSizeBinary(32)>`, all non-null.
+pub fn proofs_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("leaf_index", DataType::UInt64, false),
+        Field::new("leaf", node_type(), false),
+        Field::new("proof", DataType::List(proof_item()), false),
+    ])
+}
+
+fn node_type() -> DataType {
+    DataType::FixedSizeBinary(HASH_LEN as i32)
+}
+
+fn proof_item() -> Arc<Field> {
+    Arc::new(Field::new("item", node_type(), false))
+}
+
+/// One row per leaf position in `positions`, with the leaf and its [`MerkleTree::proof`].
+/// Panics on a position out of range.
+pub fn proofs_batch<H, I>(tree: &MerkleTree<H>, positions: I) -> Result<RecordBatch, ArrowError>
+where
+    H: Hasher,
+    I: IntoIterator<Item = usize>,
+{
+    let mut index = UInt64Builder::new();
+    let mut leaf = FixedSizeBinaryBuilder::new(HASH_LEN as i32);
+    let mut proof =
+        ListBuilder::new(FixedSizeBinaryBuilder::new(HASH_LEN as i32)).with_field(proof_item());
+    for position in positions {
+        index.append_value(position as u64);
+        leaf.append_value(tree.leaf(position))?;
+        for node in tree.proof(position) {
+            proof.values().append_value(node)?;
+        }
+        proof.append(true);
+    }
+    RecordBatch::try_new(
+        Arc::new(proofs_schema()),
+        vec![
+            Arc::new(index.finish()),
+            Arc::new(leaf.finish()),
+            Arc::new(proof.finish()),
+        ],
+    )
+}
+
+/// Parquet metadata key holding the root in [`write_proofs_parquet`] output.
+#[cfg(feature = "parquet")]
+pub const ROOT_METADATA_KEY: &str = "merklerust.root";
+
+/// Write [`proofs_batch`] rows for every leaf of `tree` to a Parquet file, `rows_per_batch` at
+/// a time so only one batch of proofs is in memory. The root is stored as `0x` hex under
+/// [`ROOT_METADATA_KEY`] in the file metadata.
+#[cfg(feature = "parquet")]
+pub fn write_proofs_parquet<W, H>(
+    tree: &MerkleTree<H>,
+    writer: W,
+    rows_per_batch: usize,
+) -> Result<(), parquet::errors::ParquetError>
+where
+    W: std::io::Write + Send,
+    H: Hasher,
+{
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::metadata::KeyValue;
+    use parquet::file::properties::WriterProperties;
+
+    assert!(rows_per_batch > 0, "Expected non-zero rows per batch");
+    let properties = WriterProperties::builder()
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            ROOT_METADATA_KEY.to_string(),
+            format!("0x{}", hex::encode(tree.root())),
+        )]))
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, Arc::new(proofs_schema()), Some(properties))?;
+    let leaves = tree.leaf_count();
+    for start in (0..leaves).step_by(rows_per_batch) {
+        let end = leaves.min(start + rows_per_batch);
+        writer.write(&proofs_batch(tree, start..end)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! Append-only audit log: the entries of a transparency log with the queries its clients make,
+//! namely an entry, its inclusion proof under any earlier tree head, and the consistency proof
+//! between two heads. Leaves are `hash_leaf(entry)` in the RFC 6962 shape, so heads are roots of
+//! [`crate::consistency::log_root`] and a [`crate::monitor::LogMonitor`] can follow them.
+//!
+//! Signing tree heads is up to the log: sign the [`TreeHead`] it publishes with its own scheme.
+//! The empty log's head has the all-zero root.
+
+use crate::consistency::{get_consistency_proof, log_root, split_point, subtree_root};
+use crate::hashes::Hasher;
+use crate::incremental::IncrementalMerkleTree;
+use crate::merkle::{
+    positional_proof_sides, verify_positional_proof, Bytes, MerkleError, Side,
+    MAX_POSITIONAL_LEAVES,
+};
+use crate::monitor::TreeHead;
+use crate::tree::{check_pair_ordering, EmptyRoot, PairOrdering};
+
+#[derive(Clone, Debug)]
+pub struct AuditLog<H: Hasher> {
+    /// Frontier of all leaves, for the current head in `O(log n)`.
+    tree: IncrementalMerkleTree<H>,
+    entries: Vec<Bytes>,
+    leaves: Vec<Bytes>,
+}
+
+impl<H: Hasher> AuditLog<H> {
+    /// Empty log. Panics on a sorted-pair hasher, which cannot bind entries to positions.
+    pub fn new(hasher: H) -> Self {
+        Self::try_new(hasher).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(hasher: H) -> Result<Self, MerkleError> {
+        check_pair_ordering(&hasher, PairOrdering::Positional)?;
+        Ok(Self {
+            tree: IncrementalMerkleTree::with_empty_root(hasher, EmptyRoot::Zero),
+            entries: Vec::new(),
+            leaves: Vec::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn hasher(&self) -> &H {
+        self.tree.hasher()
+    }
+
+    /// Append `entry` and return its index.
+    pub fn append(&mut self, entry: impl Into<Bytes>) -> usize {
+        let entry = entry.into();
+        let leaf = self.hasher().hash_leaf(&entry);
+        self.tree.push(&leaf);
+        self.leaves.push(leaf.to_vec());
+        self.entries.push(entry);
+        self.entries.len() - 1
+    }
+
+    pub fn entry(&self, index: usize) -> Option<&[u8]> {
+        self.entries.get(index).map(|e| e.as_slice())
+    }
+
+    /// Head over all entries so far.
+    pub fn head(&self) -> TreeHead {
+        let root = self.tree.root().expect("Empty root is defined");
+        TreeHead::new(self.len(), root)
+    }
+
+    /// Head the log had at `size` entries, or `None` past its end.
+    pub fn head_at(&self, size: usize) -> Option<TreeHead> {
+        match size {
+            0 => Some(TreeHead::new(0, EmptyRoot::Zero.root(self.hasher()).ok()?)),
+            _ if size <= self.len() => Some(TreeHead::new(
+                size,
+                log_root(&self.leaves[..size], self.hasher()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Inclusion proof of entry `index` under the head of `at_size` entries, checked with
+    /// [`verify_entry`]. Panics unless `index < at_size <= self.len()`.
+    pub fn prove_entry(&self, index: usize, at_size: usize) -> Vec<(Side, Bytes)> {
+        self.try_prove_entry(index, at_size)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_prove_entry(
+        &self,
+        index: usize,
+        at_size: usize,
+    ) -> Result<Vec<(Side, Bytes)>, MerkleError> {
+        self.check_size(at_size)?;
+        if index >= at_size {
+            return Err(MerkleError::LeafOutOfRange {
+                position: index,
+                leaves: at_size,
+            });
+        }
+        Ok(inclusion_path(
+            index,
+            &self.leaves[..at_size],
+            self.hasher(),
+        ))
+    }
+
+    /// Consistency proof from the head of `old_size` entries to that of `new_size`, checked
+    /// with [`crate::consistency::verify_consistency`]. The proof from the empty log is empty.
+    /// Panics unless `old_size <= new_size <= self.len()`.
+    pub fn prove_consistency(&self, old_size: usize, new_size: usize) -> Vec<Bytes> {
+        self.try_prove_consistency(old_size, new_size)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_prove_consistency(
+        &self,
+        old_size: usize,
+        new_size: usize,
+    ) -> Result<Vec<Bytes>, MerkleError> {
+        self.check_size(new_size)?;
+        if old_size > new_size {
+            return Err(MerkleError::LeafOutOfRange {
+                position: old_size - 1,
+                leaves: new_size,
+            });
+        }
+        if old_size == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(get_consistency_proof(
+            &self.leaves[..new_size],
+            old_size,
+            self.hasher(),
+        ))
+    }
+
+    /// A head of `size` entries needs its last entry in the log.
+    fn check_size(&self, size: usize) -> Result<(), MerkleError> {
+        if size > self.len() {
+            return Err(MerkleError::LeafOutOfRange {
+                position: size - 1,
+                leaves: self.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// RFC 6962 audit path of leaf `index`, leaf first, with the side of each sibling.
+fn inclusion_path<H: Hasher>(index: usize, leaves: &[Bytes], hasher: &H) -> Vec<(Side, Bytes)> {
+    if leaves.len() == 1 {
+        return Vec::new();
+    }
+    let k = split_point(leaves.len());
+    if index < k {
+        let mut path = inclusion_path(index, &leaves[..k], hasher);
+        path.push((Side::Right, subtree_root(&leaves[k..], hasher).to_vec()));
+        path
+    } else {
+        let mut path = inclusion_path(index - k, &leaves[k..], hasher);
+        path.push((Side::Left, subtree_root(&leaves[..k], hasher).to_vec()));
+        path
+    }
+}
+
+/// Check an [`AuditLog::prove_entry`] proof that `entry` is at `index` under `head`. The sides
+/// must be those of `index` in a log of `head.size` entries, so the proof also fixes the index.
+pub fn verify_entry<H: Hasher>(
+    head: &TreeHead,
+    index: usize,
+    entry: &[u8],
+    proof: &[(Side, Bytes)],
+    hasher: &H,
+) -> bool {
+    if index >= head.size || head.size > MAX_POSITIONAL_LEAVES {
+        return false;
+    }
+    let sides = positional_proof_sides(head.size, index);
+    sides.len() == proof.len()
+        && sides.iter().zip(proof).all(|(side, (s, _))| side == s)
+        && verify_positional_proof(&head.root, &hasher.hash_leaf(entry), proof, |a, b| {
+            hasher.hash_node(a, b).to_vec()
+        })
+}
@@ -0,0 +1,133 @@
+//! Content-defined chunking (FastCDC) for file merkleization. Chunk boundaries are chosen by a
+//! rolling gear hash over the content rather than at fixed offsets, so an insertion or deletion
+//! only changes the chunks around it and the rest re-align. Each [`Chunk`] records its byte
+//! range so a range of the file can later be mapped to the leaves that cover it.
+
+use std::ops::Range;
+
+use crate::hashes::keccak256;
+use crate::merkle::{Bytes, Hash};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkerConfig {
+    pub min_size: usize,
+    /// Target chunk size; must be a power of two.
+    pub avg_size: usize,
+    pub max_size: usize,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 2 * 1024,
+            avg_size: 8 * 1024,
+            max_size: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    /// Byte offset of the chunk in the input.
+    pub offset: u64,
+    pub len: usize,
+    /// `keccak256` of the chunk contents; used as the tree leaf.
+    pub hash: Hash,
+}
+
+pub struct FastCdc {
+    config: ChunkerConfig,
+    gear: [u64; 256],
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl FastCdc {
+    pub fn new(config: ChunkerConfig) -> Self {
+        assert!(
+            config.avg_size.is_power_of_two() && config.avg_size >= 64,
+            "Expected a power-of-two average chunk size of at least 64, got {}",
+            config.avg_size
+        );
+        assert!(
+            config.min_size <= config.avg_size && config.avg_size <= config.max_size,
+            "Expected min_size <= avg_size <= max_size, got {} / {} / {}",
+            config.min_size,
+            config.avg_size,
+            config.max_size
+        );
+
+        // deterministic gear table so every implementation cuts at the same offsets
+        let mut gear = [0u64; 256];
+        for (i, g) in gear.iter_mut().enumerate() {
+            let h = keccak256(&[i as u8]);
+            *g = u64::from_be_bytes(h[..8].try_into().unwrap());
+        }
+
+        // normalized chunking: a stricter mask before the target size, a looser one after.
+        // The gear hash shifts left, so the high bits depend on the most bytes.
+        let bits = config.avg_size.trailing_zeros();
+        Self {
+            config,
+            gear,
+            mask_small: !0u64 << (64 - (bits + 1)),
+            mask_large: !0u64 << (64 - (bits - 1)),
+        }
+    }
+
+    pub fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    /// Length of the first chunk of `data`.
+    pub fn cut(&self, data: &[u8]) -> usize {
+        let min = self.config.min_size;
+        if data.len() <= min {
+            return data.len();
+        }
+        let end = data.len().min(self.config.max_size);
+        let normal = end.min(self.config.avg_size);
+
+        let mut fp: u64 = 0;
+        for (i, &b) in data.iter().enumerate().take(end).skip(min) {
+            fp = (fp << 1).wrapping_add(self.gear[b as usize]);
+            let mask = if i < normal {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if fp & mask == 0 {
+                return i + 1;
+            }
+        }
+        end
+    }
+
+    pub fn chunks(&self, data: &[u8]) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let len = self.cut(&data[offset..]);
+            chunks.push(Chunk {
+                offset: offset as u64,
+                len,
+                hash: keccak256(&data[offset..offset + len]),
+            });
+            offset += len;
+        }
+        chunks
+    }
+}
+
+/// Chunk hashes as tree leaves, in file order.
+pub fn chunk_leaves(chunks: &[Chunk]) -> Vec<Bytes> {
+    chunks.iter().map(|c| c.hash.to_vec()).collect()
+}
+
+/// Indices of the chunks overlapping the byte range `[offset, offset + len)`.
+pub fn chunks_covering(chunks: &[Chunk], offset: u64, len: u64) -> Range<usize> {
+    let end = offset.saturating_add(len);
+    let first = chunks.partition_point(|c| c.offset + c.len as u64 <= offset);
+    let last = chunks.partition_point(|c| c.offset < end);
+    first..last.max(first)
+}
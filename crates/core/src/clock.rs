@@ -0,0 +1,151 @@
+//! Merkle clock: a DAG of events where each event commits to its parents' hashes, so the set
+//! of current heads summarizes a replica's whole causal history. Useful for CRDT / replica
+//! sync protocols that compare heads and ship only missing events.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::hashes::keccak256;
+use crate::merkle::{Bytes, Hash};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// Parent event hashes, sorted ascending.
+    pub parents: Vec<Hash>,
+    pub payload: Bytes,
+}
+
+impl Event {
+    pub fn new(mut parents: Vec<Hash>, payload: Bytes) -> Self {
+        parents.sort();
+        parents.dedup();
+        Self { parents, payload }
+    }
+
+    /// `keccak256(u32_be(parent count) ‖ parents ‖ payload)`.
+    pub fn hash(&self) -> Hash {
+        let mut data = Vec::with_capacity(4 + 32 * self.parents.len() + self.payload.len());
+        data.extend_from_slice(&(self.parents.len() as u32).to_be_bytes());
+        for p in self.parents.iter() {
+            data.extend_from_slice(p);
+        }
+        data.extend_from_slice(&self.payload);
+        keccak256(&data)
+    }
+}
+
+/// Causal relation between two clocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockOrdering {
+    Equal,
+    /// `self` has seen a strict subset of the other clock's history.
+    Before,
+    /// `self` has seen a strict superset of the other clock's history.
+    After,
+    Concurrent,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ClockError {
+    #[error("Unknown parent event 0x{}", hex::encode(.0))]
+    UnknownParent(Hash),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MerkleClock {
+    events: HashMap<Hash, Event>,
+    heads: BTreeSet<Hash>,
+}
+
+impl MerkleClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current heads, sorted ascending.
+    pub fn heads(&self) -> Vec<Hash> {
+        self.heads.iter().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.events.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<&Event> {
+        self.events.get(hash)
+    }
+
+    /// Record a local event on top of the current heads and return its hash.
+    pub fn append(&mut self, payload: Bytes) -> Hash {
+        self.insert(Event::new(self.heads(), payload))
+            .expect("heads are known events")
+    }
+
+    /// Insert an event received from another replica. All its parents must already be known;
+    /// otherwise the clock is left unchanged and [`ClockError::UnknownParent`] is returned.
+    pub fn insert(&mut self, event: Event) -> Result<Hash, ClockError> {
+        let hash = event.hash();
+        if self.events.contains_key(&hash) {
+            return Ok(hash);
+        }
+        if let Some(p) = event.parents.iter().find(|p| !self.events.contains_key(*p)) {
+            return Err(ClockError::UnknownParent(*p));
+        }
+        for p in event.parents.iter() {
+            self.heads.remove(p);
+        }
+        self.heads.insert(hash);
+        self.events.insert(hash, event);
+        Ok(hash)
+    }
+
+    pub fn compare(&self, other: &MerkleClock) -> ClockOrdering {
+        let self_has_other = other.heads.iter().all(|h| self.contains(h));
+        let other_has_self = self.heads.iter().all(|h| other.contains(h));
+        match (self_has_other, other_has_self) {
+            (true, true) => ClockOrdering::Equal,
+            (true, false) => ClockOrdering::After,
+            (false, true) => ClockOrdering::Before,
+            (false, false) => ClockOrdering::Concurrent,
+        }
+    }
+
+    /// Events known to `self` but not to `other`, parents before children, so inserting them
+    /// into `other` in order always succeeds.
+    pub fn diff(&self, other: &MerkleClock) -> Vec<Event> {
+        let mut missing: Vec<Hash> = Vec::new();
+        let mut seen: BTreeSet<Hash> = BTreeSet::new();
+        let mut stack: Vec<Hash> = self.heads().into_iter().rev().collect();
+
+        // iterative post-order walk that stops at events the other side already has
+        let mut expanded: BTreeSet<Hash> = BTreeSet::new();
+        while let Some(h) = stack.pop() {
+            if other.contains(&h) || seen.contains(&h) {
+                continue;
+            }
+            if expanded.insert(h) {
+                stack.push(h);
+                for p in self.events[&h].parents.iter().rev() {
+                    if !other.contains(p) && !seen.contains(p) {
+                        stack.push(*p);
+                    }
+                }
+            } else {
+                seen.insert(h);
+                missing.push(h);
+            }
+        }
+
+        missing
+            .into_iter()
+            .map(|h| self.events[&h].clone())
+            .collect()
+    }
+}
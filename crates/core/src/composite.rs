@@ -0,0 +1,244 @@
+//! One commitment to two positional trees over the same leaves: one in insertion order and
+//! one in ascending byte order. A [`CompositeProof`] proves a leaf in both, which fixes its
+//! insertion position and its rank among all leaves under a single root. A [`RankProof`] proves
+//! the leaf at rank `k` of the sorted tree together with its neighbours at ranks `k - 1` and
+//! `k + 1`, so a verifier also checks that the leaf is ordered against them.
+//!
+//! Use a positional hasher such as [`crate::hashes::PositionalKeccak256Hasher`]. A sorted-pair
+//! hasher does not bind a proof to a position, so neither the position nor the rank would be
+//! proven; [`CompositeTree::try_new`] rejects one.
+
+use crate::consts::COMPOSITE_DOMAIN;
+use crate::hashes::{keccak256, Hasher};
+use crate::merkle::{
+    constant_time_eq, positional_proof_sides, try_process_positional_proof, Bytes, Hash,
+    MerkleError, Side, MAX_POSITIONAL_LEAVES,
+};
+use crate::tree::MerkleTree;
+
+/// `keccak256(domain ‖ u64_be(leaf_count) ‖ ordered_root ‖ sorted_root)`.
+fn composite_root(leaf_count: usize, ordered_root: &[u8], sorted_root: &[u8]) -> Hash {
+    keccak256(
+        &[
+            COMPOSITE_DOMAIN,
+            &(leaf_count as u64).to_be_bytes(),
+            ordered_root,
+            sorted_root,
+        ]
+        .concat(),
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompositeTree<H: Hasher> {
+    ordered: MerkleTree<H>,
+    sorted: MerkleTree<H>,
+    /// Rank of the leaf at each insertion position; equal leaves keep their insertion order.
+    ranks: Vec<usize>,
+}
+
+impl<H: Hasher + Clone> CompositeTree<H> {
+    /// Panics on an empty leaf list, a leaf that is not 32 bytes or a sorted-pair hasher.
+    pub fn new(leaves: Vec<Bytes>, hasher: H) -> Self {
+        Self::try_new(leaves, hasher).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(leaves: Vec<Bytes>, hasher: H) -> Result<Self, MerkleError> {
+        let mut order: Vec<usize> = (0..leaves.len()).collect();
+        order.sort_by(|&a, &b| leaves[a].cmp(&leaves[b]));
+        let mut ranks = vec![0; leaves.len()];
+        for (rank, &position) in order.iter().enumerate() {
+            ranks[position] = rank;
+        }
+        let sorted_leaves = order.iter().map(|&i| leaves[i].clone()).collect();
+        let ordered = MerkleTree::try_new_positional(leaves, hasher.clone())?;
+        let sorted = MerkleTree::try_new_positional(sorted_leaves, hasher)?;
+        Ok(Self {
+            ordered,
+            sorted,
+            ranks,
+        })
+    }
+}
+
+impl<H: Hasher> CompositeTree<H> {
+    /// The commitment to both trees and the leaf count.
+    pub fn root(&self) -> Hash {
+        composite_root(self.len(), self.ordered.root(), self.sorted.root())
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    /// The tree in insertion order.
+    pub fn ordered(&self) -> &MerkleTree<H> {
+        &self.ordered
+    }
+
+    /// The tree in ascending leaf order.
+    pub fn sorted(&self) -> &MerkleTree<H> {
+        &self.sorted
+    }
+
+    /// Rank of the leaf inserted at `position`. Panics on an out-of-range position.
+    pub fn rank(&self, position: usize) -> usize {
+        self.ranks[position]
+    }
+
+    /// Proof of the leaf inserted at `position` in both trees. Panics on an out-of-range
+    /// position.
+    pub fn proof(&self, position: usize) -> CompositeProof {
+        self.try_proof(position).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_proof(&self, position: usize) -> Result<CompositeProof, MerkleError> {
+        self.ordered.try_leaf(position)?;
+        let rank = self.ranks[position];
+        Ok(CompositeProof {
+            leaf_count: self.len(),
+            position,
+            rank,
+            ordered: self.ordered.positional_proof(position),
+            sorted: self.sorted.positional_proof(rank),
+        })
+    }
+
+    /// Proof of the leaf at `rank` in sorted order and of its neighbours. Panics on an
+    /// out-of-range rank.
+    pub fn rank_proof(&self, rank: usize) -> RankProof {
+        self.try_rank_proof(rank)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_rank_proof(&self, rank: usize) -> Result<RankProof, MerkleError> {
+        self.sorted.try_leaf(rank)?;
+        let neighbour = |rank: usize| {
+            self.sorted.try_leaf(rank).ok().map(|leaf| RankNeighbour {
+                leaf: leaf.to_vec(),
+                proof: self.sorted.positional_proof(rank),
+            })
+        };
+        Ok(RankProof {
+            leaf_count: self.len(),
+            rank,
+            ordered_root: self.ordered.root().to_vec(),
+            proof: self.sorted.positional_proof(rank),
+            previous: rank.checked_sub(1).and_then(neighbour),
+            next: neighbour(rank + 1),
+        })
+    }
+}
+
+/// Proof that a leaf sits at `position` in insertion order and at `rank` in sorted order of a
+/// [`CompositeTree`] with `leaf_count` leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompositeProof {
+    pub leaf_count: usize,
+    pub position: usize,
+    pub rank: usize,
+    pub ordered: Vec<(Side, Bytes)>,
+    pub sorted: Vec<(Side, Bytes)>,
+}
+
+impl CompositeProof {
+    /// Check both proofs against the composite `root`, including that their sides match
+    /// `position` and `rank`. Malformed proofs verify as `false`.
+    pub fn verify<H: Hasher>(&self, root: &[u8], leaf: &[u8], hasher: &H) -> bool {
+        if self.position >= self.leaf_count
+            || self.rank >= self.leaf_count
+            || self.leaf_count > MAX_POSITIONAL_LEAVES
+        {
+            return false;
+        }
+        let sides =
+            |proof: &[(Side, Bytes)]| proof.iter().map(|(side, _)| *side).collect::<Vec<_>>();
+        let node_hash = |a: &[u8], b: &[u8]| hasher.hash_node(a, b).to_vec();
+        if sides(&self.ordered) != positional_proof_sides(self.leaf_count, self.position)
+            || sides(&self.sorted) != positional_proof_sides(self.leaf_count, self.rank)
+        {
+            return false;
+        }
+        let (Ok(ordered_root), Ok(sorted_root)) = (
+            try_process_positional_proof(leaf, &self.ordered, node_hash),
+            try_process_positional_proof(leaf, &self.sorted, node_hash),
+        ) else {
+            return false;
+        };
+        constant_time_eq(
+            &composite_root(self.leaf_count, &ordered_root, &sorted_root),
+            root,
+        )
+    }
+}
+
+/// A leaf next to the proven one in sorted order, with its proof in the sorted tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RankNeighbour {
+    pub leaf: Bytes,
+    pub proof: Vec<(Side, Bytes)>,
+}
+
+/// Proof that a leaf sits at `rank` in sorted order of a [`CompositeTree`] with `leaf_count`
+/// leaves, between its neighbours at `rank - 1` and `rank + 1`. The neighbours show the leaf
+/// is ordered against them; they cannot show that the builder sorted the rest of the tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RankProof {
+    pub leaf_count: usize,
+    pub rank: usize,
+    /// Root of the insertion-order tree, needed to rebuild the composite root.
+    pub ordered_root: Bytes,
+    pub proof: Vec<(Side, Bytes)>,
+    /// The leaf at `rank - 1`; `None` only for rank 0.
+    pub previous: Option<RankNeighbour>,
+    /// The leaf at `rank + 1`; `None` only for the last rank.
+    pub next: Option<RankNeighbour>,
+}
+
+impl RankProof {
+    /// Check the leaf and both neighbours against the composite `root`, including that their
+    /// sides match their ranks and that `previous <= leaf <= next`. Malformed proofs verify
+    /// as `false`.
+    pub fn verify<H: Hasher>(&self, root: &[u8], leaf: &[u8], hasher: &H) -> bool {
+        if self.rank >= self.leaf_count
+            || self.leaf_count > MAX_POSITIONAL_LEAVES
+            || self.previous.is_some() != (self.rank > 0)
+            || self.next.is_some() != (self.rank + 1 < self.leaf_count)
+        {
+            return false;
+        }
+        let node_hash = |a: &[u8], b: &[u8]| hasher.hash_node(a, b).to_vec();
+        let sorted_root = |rank: usize, leaf: &[u8], proof: &[(Side, Bytes)]| {
+            let sides = proof.iter().map(|(side, _)| *side).collect::<Vec<_>>();
+            if sides != positional_proof_sides(self.leaf_count, rank) {
+                return None;
+            }
+            try_process_positional_proof(leaf, proof, node_hash).ok()
+        };
+        let Some(expected) = sorted_root(self.rank, leaf, &self.proof) else {
+            return false;
+        };
+        let previous = self.previous.as_ref().map(|n| (self.rank - 1, n));
+        let next = self.next.as_ref().map(|n| (self.rank + 1, n));
+        for (rank, neighbour) in previous.into_iter().chain(next) {
+            let ordered = if rank < self.rank {
+                neighbour.leaf.as_slice() <= leaf
+            } else {
+                leaf <= neighbour.leaf.as_slice()
+            };
+            if !ordered
+                || sorted_root(rank, &neighbour.leaf, &neighbour.proof).as_ref() != Some(&expected)
+            {
+                return false;
+            }
+        }
+        constant_time_eq(
+            &composite_root(self.leaf_count, &self.ordered_root, &expected),
+            root,
+        )
+    }
+}
@@ -0,0 +1,198 @@
+//! RFC 6962 consistency proofs for append-only logs. These use the RFC's tree shape, where a
+//! tree of `n` leaves splits at the largest power of two below `n`, so a tree is always a
+//! prefix of every larger one. Heap trees built by [`crate::merkle::make_merkle_tree_bytes`]
+//! rearrange their leaves as the tree grows and have no such proofs.
+//!
+//! Leaves are already-hashed 32-byte values; pairs are combined positionally with
+//! [`Hasher::hash_node`], e.g. [`crate::hashes::Rfc6962Hasher`] for Certificate Transparency.
+//! The root of a one-leaf log is the leaf, as in the RFC; the `_with_single_leaf` variants
+//! take a [`SingleLeafRoot`] for logs that hash it instead.
+
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, Bytes, Hash};
+use crate::tree::SingleLeafRoot;
+
+/// Largest power of two strictly below `n` (for `n >= 2`).
+pub(crate) fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+fn check_leaves(leaves: &[Bytes]) {
+    assert!(!leaves.is_empty(), "Expected non-zero number of leaves");
+    for l in leaves.iter() {
+        assert!(
+            l.len() == 32,
+            "Expected valid merkle node, got length {}",
+            l.len()
+        );
+    }
+}
+
+pub(crate) fn subtree_root<H: Hasher>(leaves: &[Bytes], hasher: &H) -> Hash {
+    if leaves.len() == 1 {
+        return leaves[0].as_slice().try_into().unwrap();
+    }
+    let k = split_point(leaves.len());
+    hasher.hash_node(
+        &subtree_root(&leaves[..k], hasher),
+        &subtree_root(&leaves[k..], hasher),
+    )
+}
+
+/// The RFC 6962 Merkle tree hash (`MTH`) of `leaves`.
+pub fn log_root<H: Hasher>(leaves: &[Bytes], hasher: &H) -> Bytes {
+    log_root_with_single_leaf(leaves, hasher, SingleLeafRoot::Leaf)
+}
+
+/// [`log_root`] with the root of a one-leaf log set by `single_leaf`.
+pub fn log_root_with_single_leaf<H: Hasher>(
+    leaves: &[Bytes],
+    hasher: &H,
+    single_leaf: SingleLeafRoot,
+) -> Bytes {
+    check_leaves(leaves);
+    if let [leaf] = leaves {
+        return single_leaf.root(leaf, hasher);
+    }
+    subtree_root(leaves, hasher).to_vec()
+}
+
+fn subproof<H: Hasher>(
+    old_size: usize,
+    leaves: &[Bytes],
+    complete: bool,
+    hasher: &H,
+) -> Vec<Bytes> {
+    let n = leaves.len();
+    if old_size == n {
+        return if complete {
+            Vec::new()
+        } else {
+            vec![subtree_root(leaves, hasher).to_vec()]
+        };
+    }
+    let k = split_point(n);
+    if old_size <= k {
+        let mut proof = subproof(old_size, &leaves[..k], complete, hasher);
+        proof.push(subtree_root(&leaves[k..], hasher).to_vec());
+        proof
+    } else {
+        let mut proof = subproof(old_size - k, &leaves[k..], false, hasher);
+        proof.push(subtree_root(&leaves[..k], hasher).to_vec());
+        proof
+    }
+}
+
+/// Proof that the log over the first `old_size` of `leaves` is a prefix of the log over all
+/// `leaves` (RFC 6962 section 2.1.2). Panics unless `0 < old_size <= leaves.len()`.
+pub fn get_consistency_proof<H: Hasher>(
+    leaves: &[Bytes],
+    old_size: usize,
+    hasher: &H,
+) -> Vec<Bytes> {
+    get_consistency_proof_with_single_leaf(leaves, old_size, hasher, SingleLeafRoot::Leaf)
+}
+
+/// [`get_consistency_proof`] between roots of [`log_root_with_single_leaf`]. A hashed one-leaf
+/// old root is not a node of the new tree, so a proof from it starts with the leaf.
+pub fn get_consistency_proof_with_single_leaf<H: Hasher>(
+    leaves: &[Bytes],
+    old_size: usize,
+    hasher: &H,
+    single_leaf: SingleLeafRoot,
+) -> Vec<Bytes> {
+    check_leaves(leaves);
+    assert!(
+        old_size > 0 && old_size <= leaves.len(),
+        "Old size {} out of range for {} leaves",
+        old_size,
+        leaves.len()
+    );
+    let complete = old_size > 1 || single_leaf == SingleLeafRoot::Leaf;
+    subproof(old_size, leaves, complete, hasher)
+}
+
+/// Check a [`get_consistency_proof`] proof between two log roots, following the verification
+/// algorithm of RFC 9162 section 2.1.4.2. Sizes out of range or malformed proofs verify as
+/// `false`.
+pub fn verify_consistency<H: Hasher>(
+    old_size: usize,
+    new_size: usize,
+    old_root: &[u8],
+    new_root: &[u8],
+    proof: &[Bytes],
+    hasher: &H,
+) -> bool {
+    verify_consistency_with_single_leaf(
+        old_size,
+        new_size,
+        old_root,
+        new_root,
+        proof,
+        hasher,
+        SingleLeafRoot::Leaf,
+    )
+}
+
+/// Check a [`get_consistency_proof_with_single_leaf`] proof made with the same `single_leaf`.
+pub fn verify_consistency_with_single_leaf<H: Hasher>(
+    old_size: usize,
+    new_size: usize,
+    old_root: &[u8],
+    new_root: &[u8],
+    proof: &[Bytes],
+    hasher: &H,
+    single_leaf: SingleLeafRoot,
+) -> bool {
+    if old_size == 0 || old_size > new_size || proof.iter().any(|p| p.len() != 32) {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && constant_time_eq(old_root, new_root);
+    }
+    if old_root.len() != 32 {
+        return false;
+    }
+
+    // a hashed one-leaf old root is checked against the leaf the proof starts with
+    let hashed_leaf = old_size == 1 && single_leaf == SingleLeafRoot::Hashed;
+    let mut path: Vec<&[u8]> = proof.iter().map(|p| p.as_slice()).collect();
+    if old_size.is_power_of_two() && !hashed_leaf {
+        path.insert(0, old_root);
+    }
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+    if hashed_leaf && !single_leaf.matches(old_root, first, hasher) {
+        return false;
+    }
+    let old_node = if hashed_leaf { *first } else { old_root };
+
+    let mut old_n = old_size - 1;
+    let mut new_n = new_size - 1;
+    while old_n & 1 == 1 {
+        old_n >>= 1;
+        new_n >>= 1;
+    }
+    let mut old_r: Hash = (*first).try_into().unwrap();
+    let mut new_r = old_r;
+    for c in rest {
+        if new_n == 0 {
+            return false;
+        }
+        if old_n & 1 == 1 || old_n == new_n {
+            old_r = hasher.hash_node(c, &old_r);
+            new_r = hasher.hash_node(c, &new_r);
+            while old_n & 1 == 0 && old_n != 0 {
+                old_n >>= 1;
+                new_n >>= 1;
+            }
+        } else {
+            new_r = hasher.hash_node(&new_r, c);
+        }
+        old_n >>= 1;
+        new_n >>= 1;
+    }
+
+    new_n == 0 && constant_time_eq(&old_r, old_node) && constant_time_eq(&new_r, new_root)
+}
@@ -0,0 +1,44 @@
+//! Protocol constants: node size, hash prefixes, zero hashes, empty roots and format tags.
+//! The core modules and the bindings use these instead of their own literals, so a binding
+//! cannot drift from the core definitions.
+
+use crate::merkle::Hash;
+
+/// Length of every leaf and node hash.
+pub const HASH_LEN: usize = 32;
+
+/// The all-zero hash: empty sparse-tree leaves and [`crate::tree::EmptyRoot::Zero`].
+pub const ZERO_HASH: Hash = [0u8; HASH_LEN];
+
+/// RFC 6962 prefix of a hashed leaf, see [`crate::hashes::Rfc6962Hasher`].
+pub const RFC6962_LEAF_PREFIX: u8 = 0x00;
+
+/// RFC 6962 prefix of a hashed node pair.
+pub const RFC6962_NODE_PREFIX: u8 = 0x01;
+
+/// `SHA-256("")`, the RFC 6962 root of an empty log.
+pub const EMPTY_SHA256: Hash = [
+    0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24,
+    0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+];
+
+/// `keccak256("")`.
+pub const EMPTY_KECCAK256: Hash = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// `format` of a [`crate::standard::StandardMerkleTree`] dump, as in OpenZeppelin's library.
+pub const STANDARD_FORMAT: &str = "standard-v1";
+
+/// `format` of a [`crate::simple::SimpleMerkleTree`] dump.
+pub const SIMPLE_FORMAT: &str = "simple-v1";
+
+/// `format` of a run-length encoded [`crate::simple::SimpleMerkleTree`] dump.
+pub const SIMPLE_RLE_FORMAT: &str = "simple-rle-v1";
+
+/// Domain tag of [`crate::tree::TreeConfig::fingerprint`]; the suffix is its version.
+pub const CONFIG_DOMAIN: &[u8] = b"merklerust-config-v1";
+
+/// Domain tag of [`crate::composite::CompositeTree::root`].
+pub const COMPOSITE_DOMAIN: &[u8] = b"merklerust-composite-v1";
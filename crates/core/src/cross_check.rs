@@ -0,0 +1,321 @@
+//! Check a root computed elsewhere (a JS builder, a contract, another service) against a tree
+//! dump, and explain a mismatch. The most common integration failures are a different hasher
+//! or pair ordering, leaves hashed differently, and leaves fed in another order; when the
+//! external root matches the dump's leaves under one of those changes, the [`Report`] says
+//! which.
+
+use std::fmt;
+
+use crate::abi::try_encode;
+use crate::consts::{SIMPLE_FORMAT, SIMPLE_RLE_FORMAT, STANDARD_FORMAT};
+use crate::dump::{invalid_dump, DumpError, Json};
+use crate::hashes::{keccak256, Hasher};
+use crate::merkle::{leaf_count, Bytes, ZeroLeafPolicy};
+use crate::simple::SimpleMerkleTree;
+use crate::standard::StandardMerkleTree;
+use crate::tree::{MerkleTree, MultiProofAlgorithm, PairOrdering, TreeConfig};
+
+/// Hashers tried when looking for config drift.
+const HASHER_NAMES: &[&str] = &[
+    "keccak256",
+    "sha256",
+    "sha3-256",
+    "rfc6962",
+    #[cfg(feature = "blake3")]
+    "blake3",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingDrift {
+    /// The external builder hashed each leaf once more with its hasher's `hash_leaf`.
+    Rehashed,
+    /// `keccak256(abi.encode(value))` instead of the double hash of a standard tree, as
+    /// merkletreejs users often build.
+    SingleHashed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderingDrift {
+    /// Leaves sorted ascending before building.
+    Sorted,
+    /// Leaves in reverse order.
+    Reversed,
+}
+
+/// One difference between the dump and the external builder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Drift {
+    /// The external root is [`TreeConfig::commit_root`] of the dump's root, not the bare root.
+    ConfigCommitment,
+    /// Another hasher or pair ordering than the recorded config.
+    Config {
+        hasher: String,
+        pair_ordering: PairOrdering,
+    },
+    Encoding(EncodingDrift),
+    Ordering(OrderingDrift),
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::ConfigCommitment => write!(f, "it is the config commitment of the root"),
+            Drift::Config {
+                hasher,
+                pair_ordering,
+            } => write!(
+                f,
+                "it was built with {:?} pairs of {}",
+                pair_ordering, hasher
+            ),
+            Drift::Encoding(EncodingDrift::Rehashed) => write!(f, "its leaves were hashed again"),
+            Drift::Encoding(EncodingDrift::SingleHashed) => {
+                write!(f, "its leaves are single-hashed ABI encodings")
+            }
+            Drift::Ordering(OrderingDrift::Sorted) => write!(f, "its leaves were sorted"),
+            Drift::Ordering(OrderingDrift::Reversed) => write!(f, "its leaves were reversed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Match,
+    /// The external root matches the dump's leaves once all of these are applied.
+    Explained(Vec<Drift>),
+    /// No combination of the supported drifts reproduces the external root.
+    Unexplained,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// Settings recorded in (or implied by) the dump.
+    pub config: TreeConfig,
+    /// Root rebuilt from the dump's leaves under `config`.
+    pub root: Bytes,
+    pub external_root: Bytes,
+    pub verdict: Verdict,
+}
+
+impl Report {
+    pub fn is_match(&self) -> bool {
+        self.verdict == Verdict::Match
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (root, external) = (hex::encode(&self.root), hex::encode(&self.external_root));
+        match &self.verdict {
+            Verdict::Match => write!(f, "roots match: 0x{}", root),
+            Verdict::Explained(drifts) => {
+                write!(f, "external root 0x{} differs from 0x{}: ", external, root)?;
+                for (i, drift) in drifts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    write!(f, "{}", drift)?;
+                }
+                Ok(())
+            }
+            Verdict::Unexplained => write!(
+                f,
+                "external root 0x{} differs from 0x{} and no hasher, encoding or ordering change \
+                 reproduces it",
+                external, root
+            ),
+        }
+    }
+}
+
+/// Leaves of a loaded dump in leaf order, and the single-hashed ABI leaves of a standard dump.
+struct Loaded {
+    config: TreeConfig,
+    leaves: Vec<Bytes>,
+    single_hashed: Option<Vec<Bytes>>,
+}
+
+/// Leaves of a heap-layout tree in leaf order.
+fn heap_leaves(tree: &[Bytes]) -> Vec<Bytes> {
+    tree.iter()
+        .rev()
+        .take(leaf_count(tree.len()))
+        .cloned()
+        .collect()
+}
+
+fn load(dump: &str) -> Result<Loaded, DumpError> {
+    let json = Json::parse(dump)?;
+    let format = match json.get("format") {
+        Some(format) => Some(format.as_str()?),
+        None => None,
+    };
+    match format {
+        Some(STANDARD_FORMAT) => {
+            let tree = StandardMerkleTree::load(dump)?;
+            let len = tree.tree().len();
+            let mut single_hashed = vec![Vec::new(); tree.len()];
+            for v in tree.values() {
+                let encoded = try_encode(tree.leaf_encoding(), &v.value)
+                    .map_err(|e| invalid_dump(e.to_string()))?;
+                single_hashed[len - 1 - v.tree_index] = keccak256(&encoded).to_vec();
+            }
+            Ok(Loaded {
+                config: tree.config(),
+                leaves: heap_leaves(tree.tree()),
+                single_hashed: Some(single_hashed),
+            })
+        }
+        Some(SIMPLE_FORMAT | SIMPLE_RLE_FORMAT) => {
+            let tree = SimpleMerkleTree::load(dump)?;
+            Ok(Loaded {
+                config: TreeConfig::default(),
+                leaves: heap_leaves(tree.tree()),
+                single_hashed: None,
+            })
+        }
+        Some(other) => Err(DumpError::UnknownFormat(other.to_string())),
+        None => {
+            let config = TreeConfig::from_json(json.field("config")?)?;
+            let hasher = config
+                .build_hasher()
+                .map_err(|e| invalid_dump(e.to_string()))?;
+            let tree = MerkleTree::load(dump, hasher)?;
+            Ok(Loaded {
+                config,
+                leaves: tree.leaves().map(|l| l.to_vec()).collect(),
+                single_hashed: None,
+            })
+        }
+    }
+}
+
+/// Root of `leaves` under `config` after applying the given drifts, if the config has a
+/// built-in hasher.
+fn drifted_root(
+    loaded: &Loaded,
+    config: &TreeConfig,
+    encoding: Option<EncodingDrift>,
+    ordering: Option<OrderingDrift>,
+) -> Option<Bytes> {
+    let hasher = config.build_hasher().ok()?;
+    let mut leaves = match encoding {
+        None => loaded.leaves.clone(),
+        Some(EncodingDrift::Rehashed) => loaded
+            .leaves
+            .iter()
+            .map(|l| hasher.hash_leaf(l).to_vec())
+            .collect(),
+        Some(EncodingDrift::SingleHashed) => loaded.single_hashed.clone()?,
+    };
+    match ordering {
+        None => {}
+        Some(OrderingDrift::Sorted) => leaves.sort(),
+        Some(OrderingDrift::Reversed) => leaves.reverse(),
+    }
+    let config = TreeConfig {
+        leaf_policy: ZeroLeafPolicy::Allow,
+        ..config.clone()
+    };
+    MerkleTree::try_with_config(leaves, hasher, config)
+        .ok()
+        .map(|tree| tree.root().to_vec())
+}
+
+/// Reload `tree_dump` (a [`MerkleTree::dump`], `standard-v1` or `simple-v1` dump), rebuild
+/// its root under the recorded config and compare it with `external_root_hex` (with or
+/// without `0x`). On a mismatch every combination of one hasher or ordering change, one leaf
+/// encoding change and one leaf ordering change is tried, fewest changes first.
+pub fn cross_check(tree_dump: &str, external_root_hex: &str) -> Result<Report, DumpError> {
+    let external_root = hex::decode(
+        external_root_hex
+            .strip_prefix("0x")
+            .unwrap_or(external_root_hex),
+    )
+    .map_err(|_| invalid_dump(format!("invalid root {:?}", external_root_hex)))?;
+    let loaded = load(tree_dump)?;
+    let root = drifted_root(&loaded, &loaded.config, None, None).ok_or_else(|| {
+        invalid_dump(format!(
+            "no built-in hasher named {:?}",
+            loaded.config.hasher
+        ))
+    })?;
+    let verdict = explain(&loaded, &root, &external_root);
+    Ok(Report {
+        config: loaded.config,
+        root,
+        external_root,
+        verdict,
+    })
+}
+
+fn explain(loaded: &Loaded, root: &[u8], external_root: &[u8]) -> Verdict {
+    if root == external_root {
+        return Verdict::Match;
+    }
+    let hasher = loaded.config.build_hasher().expect("Config was rebuilt");
+    if loaded.config.commit_root(root, &hasher)[..] == *external_root {
+        return Verdict::Explained(vec![Drift::ConfigCommitment]);
+    }
+
+    let mut configs = vec![None];
+    for name in HASHER_NAMES {
+        for pair_ordering in [PairOrdering::Sorted, PairOrdering::Positional] {
+            // combinations without a built-in hasher are skipped by `drifted_root`
+            if (*name, pair_ordering)
+                != (loaded.config.hasher.as_str(), loaded.config.pair_ordering)
+            {
+                configs.push(Some((name.to_string(), pair_ordering)));
+            }
+        }
+    }
+    let encodings = [
+        None,
+        Some(EncodingDrift::Rehashed),
+        Some(EncodingDrift::SingleHashed),
+    ];
+    let orderings = [
+        None,
+        Some(OrderingDrift::Sorted),
+        Some(OrderingDrift::Reversed),
+    ];
+    let mut candidates = Vec::new();
+    for config in configs.iter() {
+        for encoding in encodings {
+            for ordering in orderings {
+                let changes = config.is_some() as usize
+                    + encoding.is_some() as usize
+                    + ordering.is_some() as usize;
+                if changes > 0 {
+                    candidates.push((changes, config, encoding, ordering));
+                }
+            }
+        }
+    }
+    candidates.sort_by_key(|(changes, ..)| *changes);
+
+    for (_, config, encoding, ordering) in candidates {
+        let drifted = match config {
+            Some((hasher, pair_ordering)) => TreeConfig {
+                hasher: hasher.clone(),
+                pair_ordering: *pair_ordering,
+                multi_proof: MultiProofAlgorithm::for_ordering(*pair_ordering),
+                ..loaded.config.clone()
+            },
+            None => loaded.config.clone(),
+        };
+        if drifted_root(loaded, &drifted, encoding, ordering).as_deref() == Some(external_root) {
+            let mut drifts = Vec::new();
+            if let Some((hasher, pair_ordering)) = config {
+                drifts.push(Drift::Config {
+                    hasher: hasher.clone(),
+                    pair_ordering: *pair_ordering,
+                });
+            }
+            drifts.extend(encoding.map(Drift::Encoding));
+            drifts.extend(ordering.map(Drift::Ordering));
+            return Verdict::Explained(drifts);
+        }
+    }
+    Verdict::Unexplained
+}
@@ -0,0 +1,78 @@
+use crate::merkle::{try_get_multi_proof, verify_multi_proof, Bytes, MerkleError, MultiProof};
+
+/// Proof that two trees of equal shape differ only at a given set of leaves.
+///
+/// `old` and `new` are multiproofs for the changed leaves under the old and new root. Since
+/// each changed leaf's path is covered by the proofs, every sibling hash outside those paths
+/// must be identical in both, which is what ties the two roots together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeProof {
+    /// Tree indices of the changed leaves, in the order used by the multiproofs (descending).
+    pub indices: Vec<usize>,
+    pub old: MultiProof,
+    pub new: MultiProof,
+}
+
+/// Build a [`ChangeProof`] covering every leaf that differs between `old_tree` and `new_tree`.
+/// Panics on trees of different shapes or invalid nodes, see [`try_get_change_proof`].
+pub fn get_change_proof(old_tree: &[Bytes], new_tree: &[Bytes]) -> ChangeProof {
+    try_get_change_proof(old_tree, new_tree).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_get_change_proof(
+    old_tree: &[Bytes],
+    new_tree: &[Bytes],
+) -> Result<ChangeProof, MerkleError> {
+    if old_tree.len() != new_tree.len() {
+        return Err(MerkleError::ShapeMismatch {
+            old: old_tree.len(),
+            new: new_tree.len(),
+        });
+    }
+
+    let first_leaf = old_tree.len() / 2;
+    let mut indices: Vec<usize> = (first_leaf..old_tree.len())
+        .filter(|&i| old_tree[i] != new_tree[i])
+        .collect();
+    indices.sort_by(|a, b| b.cmp(a));
+
+    let old = try_get_multi_proof(old_tree, indices.clone())?;
+    let new = try_get_multi_proof(new_tree, indices.clone())?;
+
+    Ok(ChangeProof { indices, old, new })
+}
+
+/// Check that `cp` transforms `old_root` into `new_root` by changing only the leaves it lists.
+///
+/// Note that with sorted-pair node hashing the proofs commit to the set of changed leaves and
+/// the untouched remainder of the tree, but not to the positions listed in `indices`.
+/// Malformed proofs verify as `false`.
+pub fn verify_change_proof<F>(
+    old_root: &[u8],
+    new_root: &[u8],
+    cp: &ChangeProof,
+    node_hash: F,
+) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    if cp.old.leaves.len() != cp.indices.len()
+        || cp.new.leaves.len() != cp.indices.len()
+        || cp.old.proof != cp.new.proof
+        || cp.old.proof_flags != cp.new.proof_flags
+    {
+        return false;
+    }
+    if cp
+        .old
+        .leaves
+        .iter()
+        .zip(cp.new.leaves.iter())
+        .any(|(a, b)| a == b)
+    {
+        return false;
+    }
+
+    verify_multi_proof(old_root, &cp.old, &node_hash)
+        && verify_multi_proof(new_root, &cp.new, &node_hash)
+}
@@ -0,0 +1,324 @@
+//! JSON tree dumps shared by the OpenZeppelin-compatible tree types. Only the small subset of
+//! JSON used by the dump formats is handled, so the core crate needs no JSON dependency.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DumpError {
+    InvalidJson(String),
+    UnknownFormat(String),
+    /// Well-formed JSON that does not describe a valid tree.
+    InvalidDump(String),
+}
+
+impl std::fmt::Display for DumpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpError::InvalidJson(msg) => write!(f, "Invalid JSON: {}", msg),
+            DumpError::UnknownFormat(format) => write!(f, "Unknown format {:?}", format),
+            DumpError::InvalidDump(msg) => write!(f, "Invalid tree dump: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+pub(crate) fn invalid_dump(msg: impl Into<String>) -> DumpError {
+    DumpError::InvalidDump(msg.into())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    /// Kept as written; dumps only contain small non-negative integers.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn parse(input: &str) -> Result<Json, DumpError> {
+        let mut parser = Parser {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_ws();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Field `key` of an object, or an error naming it.
+    pub(crate) fn field(&self, key: &str) -> Result<&Json, DumpError> {
+        self.get(key)
+            .ok_or_else(|| invalid_dump(format!("missing field {:?}", key)))
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str, DumpError> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err(invalid_dump("expected string")),
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Result<&[Json], DumpError> {
+        match self {
+            Json::Array(items) => Ok(items),
+            _ => Err(invalid_dump("expected array")),
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Result<usize, DumpError> {
+        match self {
+            Json::Number(n) => n
+                .parse()
+                .map_err(|_| invalid_dump(format!("expected index, got {}", n))),
+            _ => Err(invalid_dump("expected number")),
+        }
+    }
+
+    pub(crate) fn write(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) => out.push_str(n),
+            Json::String(s) => write_string(s, out),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    pub(crate) fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out);
+        out
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// `0x`-prefixed lowercase hex, the byte format used in dumps.
+pub(crate) fn hex_string(bytes: &[u8]) -> Json {
+    Json::String(format!("0x{}", hex::encode(bytes)))
+}
+
+pub(crate) fn parse_hex32(value: &Json) -> Result<Vec<u8>, DumpError> {
+    let s = value.as_str()?;
+    let bytes = s
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .ok_or_else(|| invalid_dump(format!("invalid hex value {:?}", s)))?;
+    if bytes.len() != 32 {
+        return Err(invalid_dump(format!("expected 32-byte value, got {:?}", s)));
+    }
+    Ok(bytes)
+}
+
+/// Deepest array/object nesting [`Json::parse`] accepts; dumps nest at most three levels, and
+/// the parser recurses once per level.
+const MAX_JSON_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> DumpError {
+        DumpError::InvalidJson(format!("{} at byte {}", msg, self.pos))
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.bytes[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), DumpError> {
+        self.skip_ws();
+        if self.bytes.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", c as char)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, DumpError> {
+        self.skip_ws();
+        match self.bytes.get(self.pos) {
+            None => Err(self.error("unexpected end")),
+            Some(&open @ (b'{' | b'[')) => {
+                if self.depth == MAX_JSON_DEPTH {
+                    return Err(self.error("nesting too deep"));
+                }
+                self.depth += 1;
+                let value = if open == b'{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => Ok(self.number()),
+            _ if self.eat("null") => Ok(Json::Null),
+            _ if self.eat("true") => Ok(Json::Bool(true)),
+            _ if self.eat("false") => Ok(Json::Bool(false)),
+            _ => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn number(&mut self) -> Json {
+        let start = self.pos;
+        while matches!(
+            self.bytes.get(self.pos),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.pos += 1;
+        }
+        Json::Number(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned())
+    }
+
+    fn string(&mut self) -> Result<String, DumpError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let c = self.input[self.pos..]
+                .chars()
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let esc = *self
+                        .bytes
+                        .get(self.pos)
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match esc {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let hex = self
+                                .bytes
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|h| std::str::from_utf8(h).ok())
+                                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.pos += 4;
+                            // surrogate pairs are not needed for dump contents
+                            out.push(
+                                char::from_u32(hex)
+                                    .ok_or_else(|| self.error("invalid unicode escape"))?,
+                            );
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, DumpError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.eat("]") {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            if self.eat("]") {
+                return Ok(Json::Array(items));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, DumpError> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_ws();
+        if self.eat("}") {
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_ws();
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            self.skip_ws();
+            if self.eat("}") {
+                return Ok(Json::Object(fields));
+            }
+            self.expect(b',')?;
+        }
+    }
+}
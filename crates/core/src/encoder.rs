@@ -0,0 +1,459 @@
+//! Leaf encoders: how a typed value becomes the bytes a leaf commits to. [`TypedMerkleTree`]
+//! generalizes [`crate::standard::StandardMerkleTree`] from Solidity tuples to any type with a
+//! [`LeafEncoder`], and records the encoder's id so proofs are checked with the same encoding.
+
+use std::collections::HashMap;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::hashes::Hasher;
+use crate::merkle::{verify_proof, Bytes, Hash, MerkleError, MultiProof};
+use crate::standard::TreeOptions;
+use crate::tree::{MerkleTree, TreeConfig};
+
+/// A value its encoder cannot encode, with the encoder's reason.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("Cannot encode leaf value: {0}")]
+pub struct EncodeError(pub String);
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TypedTreeError {
+    #[error("Value {index}: {source}")]
+    Value { index: usize, source: EncodeError },
+    #[error(transparent)]
+    Merkle(#[from] MerkleError),
+}
+
+pub trait LeafEncoder<T: ?Sized> {
+    /// Stable name of the encoding, e.g. `"raw"` or `"abi(address,uint256)"`.
+    fn id(&self) -> String;
+    /// Panics on a value the encoding cannot represent; see [`Self::try_encode`].
+    fn encode(&self, value: &T) -> Bytes;
+    /// [`Self::encode`] returning an error instead. Encoders that can fail override it.
+    fn try_encode(&self, value: &T) -> Result<Bytes, EncodeError> {
+        Ok(self.encode(value))
+    }
+}
+
+impl<T: ?Sized, E: LeafEncoder<T> + ?Sized> LeafEncoder<T> for &E {
+    fn id(&self) -> String {
+        (**self).id()
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        (**self).encode(value)
+    }
+    fn try_encode(&self, value: &T) -> Result<Bytes, EncodeError> {
+        (**self).try_encode(value)
+    }
+}
+
+/// The value's bytes as-is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RawEncoder;
+
+impl<T: AsRef<[u8]> + ?Sized> LeafEncoder<T> for RawEncoder {
+    fn id(&self) -> String {
+        "raw".to_string()
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        value.as_ref().to_vec()
+    }
+}
+
+/// Solidity `abi.encode` of a tuple of `types`, see [`crate::abi::encode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiEncoder {
+    types: Vec<String>,
+}
+
+impl AbiEncoder {
+    pub fn new<T: AsRef<str>>(types: &[T]) -> Self {
+        Self {
+            types: types.iter().map(|t| t.as_ref().to_string()).collect(),
+        }
+    }
+
+    pub fn types(&self) -> &[String] {
+        &self.types
+    }
+}
+
+impl<V: AsRef<str>> LeafEncoder<[V]> for AbiEncoder {
+    fn id(&self) -> String {
+        format!("abi({})", self.types.join(","))
+    }
+    fn encode(&self, value: &[V]) -> Bytes {
+        crate::abi::encode(&self.types, value)
+    }
+    fn try_encode(&self, value: &[V]) -> Result<Bytes, EncodeError> {
+        crate::abi::try_encode(&self.types, value).map_err(|e| EncodeError(e.to_string()))
+    }
+}
+
+impl<V: AsRef<str>> LeafEncoder<Vec<V>> for AbiEncoder {
+    fn id(&self) -> String {
+        LeafEncoder::<[V]>::id(self)
+    }
+    fn encode(&self, value: &Vec<V>) -> Bytes {
+        crate::abi::encode(&self.types, value)
+    }
+    fn try_encode(&self, value: &Vec<V>) -> Result<Bytes, EncodeError> {
+        LeafEncoder::<[V]>::try_encode(self, value)
+    }
+}
+
+/// Unicode normalization form applied by [`StringEncoder`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// The string as given.
+    None,
+    #[default]
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+impl Normalization {
+    pub fn name(self) -> &'static str {
+        match self {
+            Normalization::None => "none",
+            Normalization::Nfc => "nfc",
+            Normalization::Nfd => "nfd",
+            Normalization::Nfkc => "nfkc",
+            Normalization::Nfkd => "nfkd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Normalization::None,
+            Normalization::Nfc,
+            Normalization::Nfd,
+            Normalization::Nfkc,
+            Normalization::Nfkd,
+        ]
+        .into_iter()
+        .find(|n| n.name() == name)
+    }
+
+    pub fn apply(self, value: &str) -> String {
+        match self {
+            Normalization::None => value.to_string(),
+            Normalization::Nfc => value.nfc().collect(),
+            Normalization::Nfd => value.nfd().collect(),
+            Normalization::Nfkc => value.nfkc().collect(),
+            Normalization::Nfkd => value.nfkd().collect(),
+        }
+    }
+}
+
+/// Byte encoding of a normalized string in [`StringEncoder`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+}
+
+impl TextEncoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf8",
+            TextEncoding::Utf16Le => "utf16le",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [TextEncoding::Utf8, TextEncoding::Utf16Le]
+            .into_iter()
+            .find(|e| e.name() == name)
+    }
+}
+
+/// Strings normalized and encoded by an explicit policy, so the same text gives the same
+/// leaf on every platform. The id, e.g. `"string(nfc,utf8)"`, records the policy in
+/// [`TreeConfig::leaf_encoding`]; recover it with [`StringEncoder::from_id`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StringEncoder {
+    pub normalization: Normalization,
+    pub encoding: TextEncoding,
+}
+
+impl StringEncoder {
+    pub fn new(normalization: Normalization, encoding: TextEncoding) -> Self {
+        Self {
+            normalization,
+            encoding,
+        }
+    }
+
+    /// The encoder whose [`LeafEncoder::id`] is `id`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        let (normalization, encoding) = id
+            .strip_prefix("string(")?
+            .strip_suffix(')')?
+            .split_once(',')?;
+        Some(Self::new(
+            Normalization::from_name(normalization)?,
+            TextEncoding::from_name(encoding)?,
+        ))
+    }
+}
+
+impl<T: AsRef<str> + ?Sized> LeafEncoder<T> for StringEncoder {
+    fn id(&self) -> String {
+        format!(
+            "string({},{})",
+            self.normalization.name(),
+            self.encoding.name()
+        )
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        let normalized = self.normalization.apply(value.as_ref());
+        match self.encoding {
+            TextEncoding::Utf8 => normalized.into_bytes(),
+            TextEncoding::Utf16Le => normalized
+                .encode_utf16()
+                .flat_map(|u| u.to_le_bytes())
+                .collect(),
+        }
+    }
+}
+
+/// Borsh serialization (feature `borsh`).
+#[cfg(feature = "borsh")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BorshEncoder;
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshSerialize + ?Sized> LeafEncoder<T> for BorshEncoder {
+    fn id(&self) -> String {
+        "borsh".to_string()
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        borsh::to_vec(value).expect("Borsh serialization into a Vec cannot fail")
+    }
+}
+
+/// Bincode 1.x serialization with its default fixed-width little-endian options (feature
+/// `bincode`). Panics on values bincode cannot encode, such as sequences of unknown length.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BincodeEncoder;
+
+#[cfg(feature = "bincode")]
+impl<T: serde::Serialize + ?Sized> LeafEncoder<T> for BincodeEncoder {
+    fn id(&self) -> String {
+        "bincode".to_string()
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        bincode::serialize(value).expect("Value is not bincode-serializable")
+    }
+    fn try_encode(&self, value: &T) -> Result<Bytes, EncodeError> {
+        bincode::serialize(value).map_err(|e| EncodeError(e.to_string()))
+    }
+}
+
+/// Postcard serialization, varint-compact (feature `postcard`). Panics on values postcard
+/// cannot encode.
+#[cfg(feature = "postcard")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PostcardEncoder;
+
+#[cfg(feature = "postcard")]
+impl<T: serde::Serialize + ?Sized> LeafEncoder<T> for PostcardEncoder {
+    fn id(&self) -> String {
+        "postcard".to_string()
+    }
+    fn encode(&self, value: &T) -> Bytes {
+        postcard::to_allocvec(value).expect("Value is not postcard-serializable")
+    }
+    fn try_encode(&self, value: &T) -> Result<Bytes, EncodeError> {
+        postcard::to_allocvec(value).map_err(|e| EncodeError(e.to_string()))
+    }
+}
+
+/// `hash_leaf(hash_leaf(encode(value)))`. As in OpenZeppelin's standard tree, the double hash
+/// keeps a 64-byte encoding from being mistaken for an internal node.
+pub fn typed_leaf_hash<T, E, H>(encoder: &E, hasher: &H, value: &T) -> Hash
+where
+    T: ?Sized,
+    E: LeafEncoder<T>,
+    H: Hasher,
+{
+    hasher.hash_leaf(&hasher.hash_leaf(&encoder.encode(value)))
+}
+
+/// [`typed_leaf_hash`] returning an error on a value the encoder rejects.
+pub fn try_typed_leaf_hash<T, E, H>(encoder: &E, hasher: &H, value: &T) -> Result<Hash, EncodeError>
+where
+    T: ?Sized,
+    E: LeafEncoder<T>,
+    H: Hasher,
+{
+    Ok(hasher.hash_leaf(&hasher.hash_leaf(&encoder.try_encode(value)?)))
+}
+
+/// Tree over typed values; with [`AbiEncoder`] and [`crate::hashes::Keccak256Hasher`] it is
+/// the same tree as [`crate::standard::StandardMerkleTree`].
+#[derive(Clone, Debug)]
+pub struct TypedMerkleTree<T, E: LeafEncoder<T>, H: Hasher> {
+    tree: MerkleTree<H>,
+    encoder: E,
+    values: Vec<T>,
+    /// Leaf position of each value, in input order.
+    positions: Vec<usize>,
+    hash_lookup: HashMap<Hash, usize>,
+    options: TreeOptions,
+}
+
+impl<T, E: LeafEncoder<T>, H: Hasher> TypedMerkleTree<T, E, H> {
+    /// Tree over `values` with leaves sorted by hash. Panics on an empty value list or a value
+    /// the encoder rejects.
+    pub fn of(values: Vec<T>, encoder: E, hasher: H) -> Self {
+        Self::of_with_options(values, encoder, hasher, TreeOptions::default())
+    }
+
+    pub fn try_of(values: Vec<T>, encoder: E, hasher: H) -> Result<Self, TypedTreeError> {
+        Self::try_of_with_options(values, encoder, hasher, TreeOptions::default())
+    }
+
+    pub fn of_with_options(values: Vec<T>, encoder: E, hasher: H, options: TreeOptions) -> Self {
+        Self::try_of_with_options(values, encoder, hasher, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_of_with_options(
+        values: Vec<T>,
+        encoder: E,
+        hasher: H,
+        options: TreeOptions,
+    ) -> Result<Self, TypedTreeError> {
+        let hashes = values
+            .iter()
+            .enumerate()
+            .map(|(index, v)| {
+                try_typed_leaf_hash(&encoder, &hasher, v)
+                    .map_err(|source| TypedTreeError::Value { index, source })
+            })
+            .collect::<Result<Vec<Hash>, _>>()?;
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        if options.sort_leaves {
+            order.sort_by_key(|&i| hashes[i]);
+        }
+        let mut positions = vec![0; values.len()];
+        for (position, &value_index) in order.iter().enumerate() {
+            positions[value_index] = position;
+        }
+        let tree =
+            MerkleTree::try_new(order.iter().map(|&i| hashes[i].to_vec()).collect(), hasher)?;
+        let hash_lookup = hashes
+            .into_iter()
+            .enumerate()
+            .map(|(i, h)| (h, i))
+            .collect();
+        Ok(Self {
+            tree,
+            encoder,
+            values,
+            positions,
+            hash_lookup,
+            options,
+        })
+    }
+
+    /// Check a proof against `root` without building a tree. A value the encoder rejects
+    /// verifies as `false`.
+    pub fn verify(root: &[u8], encoder: &E, hasher: &H, value: &T, proof: &[Bytes]) -> bool {
+        try_typed_leaf_hash(encoder, hasher, value).is_ok_and(|leaf| {
+            verify_proof(root, &leaf, proof, |a, b| hasher.hash_node(a, b).to_vec())
+        })
+    }
+
+    pub fn root(&self) -> &[u8] {
+        self.tree.root()
+    }
+
+    /// Id of the encoder the leaves were built with, see [`LeafEncoder::id`].
+    pub fn encoder_id(&self) -> String {
+        self.encoder.id()
+    }
+
+    pub fn config(&self) -> TreeConfig {
+        TreeConfig {
+            leaf_encoding: self.encoder.id(),
+            ..self.tree.config()
+        }
+    }
+
+    /// The root bound to [`Self::config`], see [`TreeConfig::commit_root`].
+    pub fn root_with_config_commitment(&self) -> Hash {
+        self.config().commit_root(self.root(), self.tree.hasher())
+    }
+
+    /// Options the tree was built with.
+    pub fn options(&self) -> TreeOptions {
+        self.options
+    }
+
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    pub fn tree(&self) -> &MerkleTree<H> {
+        &self.tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Values in input order.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn leaf_hash(&self, value: &T) -> Hash {
+        typed_leaf_hash(&self.encoder, self.tree.hasher(), value)
+    }
+
+    /// Index (in input order) of `value`, if it is in the tree; `None` also for a value the
+    /// encoder rejects.
+    pub fn leaf_lookup(&self, value: &T) -> Option<usize> {
+        let leaf = try_typed_leaf_hash(&self.encoder, self.tree.hasher(), value).ok()?;
+        self.hash_lookup.get(&leaf).copied()
+    }
+
+    fn position(&self, value_index: usize) -> usize {
+        *self.positions.get(value_index).unwrap_or_else(|| {
+            panic!(
+                "Index {} out of range for {} values",
+                value_index,
+                self.values.len()
+            )
+        })
+    }
+
+    pub fn get_proof(&self, value_index: usize) -> Vec<Bytes> {
+        self.tree.proof(self.position(value_index))
+    }
+
+    pub fn get_proof_for_value(&self, value: &T) -> Vec<Bytes> {
+        let index = self.leaf_lookup(value).expect("Leaf is not in tree");
+        self.get_proof(index)
+    }
+
+    pub fn get_multi_proof(&self, value_indices: &[usize]) -> MultiProof {
+        let positions: Vec<usize> = value_indices.iter().map(|&i| self.position(i)).collect();
+        self.tree.multi_proof(&positions)
+    }
+
+    pub fn verify_proof(&self, value: &T, proof: &[Bytes]) -> bool {
+        Self::verify(self.root(), &self.encoder, self.tree.hasher(), value, proof)
+    }
+}
@@ -0,0 +1,170 @@
+//! Per-epoch trees chained under one root. An [`EpochSchedule`] cuts a timeline (block
+//! numbers, unix seconds, ...) into fixed-length epochs; an [`EpochManager`] asks a data source
+//! for each finished epoch's leaves, builds that epoch's [`MerkleTree`] and appends its root to
+//! an [`Mmr`] of epoch roots. A [`ChainedProof`] then proves a leaf against the latest root
+//! through its epoch root, so older proofs only need the epoch part refreshed.
+//!
+//! Everything is a function of the schedule and source, so replaying the same data always
+//! gives the same roots.
+
+use std::ops::Range;
+
+use crate::consts::ZERO_HASH;
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, verify_proof, Bytes, Hash};
+use crate::mmr::{Mmr, MmrProof};
+use crate::tree::MerkleTree;
+
+/// Epoch `e` covers `start + e * length .. start + (e + 1) * length`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EpochSchedule {
+    pub start: u64,
+    pub length: u64,
+}
+
+impl EpochSchedule {
+    /// Panics on a zero `length`.
+    pub fn new(start: u64, length: u64) -> Self {
+        assert!(length > 0, "Expected non-zero epoch length");
+        Self { start, length }
+    }
+
+    /// Epoch containing `time`, or `None` before the schedule starts.
+    pub fn epoch_of(&self, time: u64) -> Option<u64> {
+        time.checked_sub(self.start).map(|t| t / self.length)
+    }
+
+    pub fn bounds(&self, epoch: u64) -> Range<u64> {
+        let start = self.start + epoch * self.length;
+        start..start + self.length
+    }
+
+    /// Number of epochs that have fully ended by `time`.
+    pub fn ended_by(&self, time: u64) -> u64 {
+        time.saturating_sub(self.start) / self.length
+    }
+}
+
+/// Proof that `leaf` is in epoch `epoch`'s tree and that the epoch's root is in the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainedProof {
+    pub epoch: u64,
+    pub epoch_root: Hash,
+    /// Proof of the leaf against `epoch_root`, as from [`MerkleTree::proof`].
+    pub leaf_proof: Vec<Bytes>,
+    /// Proof of `epoch_root` as leaf `epoch` of the epoch-root MMR.
+    pub epoch_proof: MmrProof,
+}
+
+impl ChainedProof {
+    /// Check both links against the chain root `root`.
+    pub fn verify<H: Hasher>(&self, root: &[u8], leaf: &[u8], hasher: &H) -> bool {
+        self.epoch_proof.leaf_index == self.epoch
+            && verify_proof(&self.epoch_root, leaf, &self.leaf_proof, |a, b| {
+                hasher.hash_node(a, b).to_vec()
+            })
+            && self.epoch_proof.verify(root, &self.epoch_root, hasher)
+    }
+}
+
+/// Builds epoch trees in order from `source(epoch, bounds)`, which returns the epoch's 32-byte
+/// leaves. An epoch with no leaves has no tree and contributes [`ZERO_HASH`] to the chain.
+pub struct EpochManager<H, S>
+where
+    H: Hasher + Clone,
+    S: FnMut(u64, Range<u64>) -> Vec<Bytes>,
+{
+    schedule: EpochSchedule,
+    source: S,
+    trees: Vec<Option<MerkleTree<H>>>,
+    roots: Mmr<H>,
+}
+
+impl<H, S> EpochManager<H, S>
+where
+    H: Hasher + Clone,
+    S: FnMut(u64, Range<u64>) -> Vec<Bytes>,
+{
+    pub fn new(schedule: EpochSchedule, source: S, hasher: H) -> Self {
+        Self {
+            schedule,
+            source,
+            trees: Vec::new(),
+            roots: Mmr::new(hasher),
+        }
+    }
+
+    pub fn schedule(&self) -> &EpochSchedule {
+        &self.schedule
+    }
+
+    /// Number of epochs built so far; the next one built is this epoch.
+    pub fn epoch_count(&self) -> u64 {
+        self.trees.len() as u64
+    }
+
+    /// Build the next epoch whether or not it has ended, returning its root. Panics if the
+    /// source returns a leaf that is not 32 bytes.
+    pub fn build_next(&mut self) -> Hash {
+        let epoch = self.epoch_count();
+        let leaves = (self.source)(epoch, self.schedule.bounds(epoch));
+        let tree =
+            (!leaves.is_empty()).then(|| MerkleTree::new(leaves, self.roots.hasher().clone()));
+        let root = tree
+            .as_ref()
+            .map_or(ZERO_HASH, |t| t.root().try_into().unwrap());
+        self.roots.push(&root);
+        self.trees.push(tree);
+        root
+    }
+
+    /// Build every epoch that has ended by `time` and return how many were built.
+    pub fn advance_to(&mut self, time: u64) -> u64 {
+        let ended = self.schedule.ended_by(time);
+        let built = ended.saturating_sub(self.epoch_count());
+        for _ in 0..built {
+            self.build_next();
+        }
+        built
+    }
+
+    /// Root over all epoch roots, or `None` before the first epoch is built.
+    pub fn root(&self) -> Option<Hash> {
+        self.roots.root()
+    }
+
+    pub fn epoch_root(&self, epoch: u64) -> Option<Hash> {
+        self.trees.get(epoch as usize).map(|t| {
+            t.as_ref()
+                .map_or(ZERO_HASH, |t| t.root().try_into().unwrap())
+        })
+    }
+
+    /// The tree of a built epoch, or `None` if it is not built or had no leaves.
+    pub fn epoch_tree(&self, epoch: u64) -> Option<&MerkleTree<H>> {
+        self.trees.get(epoch as usize)?.as_ref()
+    }
+
+    /// Proof for leaf `leaf_position` of `epoch` against [`Self::root`]. Panics if the epoch
+    /// is not built or has no such leaf.
+    pub fn chained_proof(&self, epoch: u64, leaf_position: usize) -> ChainedProof {
+        let tree = self
+            .epoch_tree(epoch)
+            .unwrap_or_else(|| panic!("Epoch {} has no tree", epoch));
+        ChainedProof {
+            epoch,
+            epoch_root: tree.root().try_into().unwrap(),
+            leaf_proof: tree.proof(leaf_position),
+            epoch_proof: self.roots.proof(epoch),
+        }
+    }
+
+    /// Re-prove `proof`'s epoch root against the current [`Self::root`], keeping its leaf proof.
+    pub fn refresh(&self, proof: &ChainedProof) -> Option<ChainedProof> {
+        let root = self.epoch_root(proof.epoch)?;
+        constant_time_eq(&root, &proof.epoch_root).then(|| ChainedProof {
+            epoch_proof: self.roots.proof(proof.epoch),
+            ..proof.clone()
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! Heap-layout tree stored as one contiguous `Vec<[u8; 32]>` instead of a `Vec<Vec<u8>>`, so
+//! building it takes one allocation and walking it stays in cache. Node indices, proofs and
+//! validity are the same as for the `&[Bytes]` functions in [`crate::merkle`].
+
+use crate::merkle::{
+    check_tree_len, is_valid_tree, leaf_count, make_hash_tree, proof_of, try_leaf_tree_index,
+    Bytes, Hash, MerkleError,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlatMerkleTree {
+    nodes: Vec<Hash>,
+}
+
+impl FlatMerkleTree {
+    /// Build a tree over `leaves`, like [`crate::merkle::make_merkle_tree_bytes`]. Panics on an
+    /// empty leaf list or a leaf that is not 32 bytes.
+    pub fn new<F>(leaves: Vec<Bytes>, node_hash: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        Self::try_new(leaves, node_hash).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new<F>(leaves: Vec<Bytes>, node_hash: F) -> Result<Self, MerkleError>
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        Ok(Self {
+            nodes: make_hash_tree(&leaves, node_hash)?,
+        })
+    }
+
+    /// Copy a `Vec<Bytes>` tree, failing on an even node count. Node hashes are not checked,
+    /// see [`Self::is_valid`].
+    pub fn from_tree(tree: &[Bytes]) -> Result<Self, MerkleError> {
+        check_tree_len(tree.len())?;
+        let nodes = tree
+            .iter()
+            .map(|n| {
+                n.as_slice()
+                    .try_into()
+                    .map_err(|_| MerkleError::InvalidNode(n.len()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { nodes })
+    }
+
+    /// Read nodes from `32 * n` concatenated bytes, as written by [`Self::as_bytes`], failing
+    /// on an even `n`. Node hashes are not checked, see [`Self::is_valid`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        let (nodes, rest) = bytes.as_chunks::<32>();
+        if !rest.is_empty() {
+            return Err(MerkleError::InvalidNode(rest.len()));
+        }
+        check_tree_len(nodes.len())?;
+        Ok(Self {
+            nodes: nodes.to_vec(),
+        })
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false`: a tree has at least one node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        leaf_count(self.nodes.len())
+    }
+
+    pub fn root(&self) -> &Hash {
+        &self.nodes[0]
+    }
+
+    /// Node at tree `index`. Panics if it is out of range.
+    pub fn node(&self, index: usize) -> &Hash {
+        &self.nodes[index]
+    }
+
+    /// Leaf at 0-based `leaf_position`, in input order.
+    pub fn leaf(&self, leaf_position: usize) -> &Hash {
+        self.try_leaf(leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_leaf(&self, leaf_position: usize) -> Result<&Hash, MerkleError> {
+        try_leaf_tree_index(self.nodes.len(), leaf_position).map(|i| &self.nodes[i])
+    }
+
+    pub fn nodes(&self) -> &[Hash] {
+        &self.nodes
+    }
+
+    /// All nodes back to back, `32 * len` bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.nodes.as_flattened()
+    }
+
+    /// The tree as the `Vec<Bytes>` the [`crate::merkle`] functions take.
+    pub fn to_tree(&self) -> Vec<Bytes> {
+        self.nodes.iter().map(|n| n.to_vec()).collect()
+    }
+
+    /// Proof for the leaf at tree index `leaf_index`, like [`crate::merkle::get_proof`].
+    pub fn get_proof(&self, leaf_index: usize) -> Vec<Bytes> {
+        self.try_get_proof(leaf_index)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_proof(&self, leaf_index: usize) -> Result<Vec<Bytes>, MerkleError> {
+        if leaf_index >= self.nodes.len() || 2 * leaf_index + 1 < self.nodes.len() {
+            return Err(MerkleError::NotALeaf(leaf_index));
+        }
+        Ok(proof_of(&self.nodes, leaf_index))
+    }
+
+    /// Proof for the leaf at 0-based `leaf_position`.
+    pub fn get_proof_for_leaf(&self, leaf_position: usize) -> Vec<Bytes> {
+        let index = try_leaf_tree_index(self.nodes.len(), leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e));
+        proof_of(&self.nodes, index)
+    }
+
+    /// Check every internal node against its children, like
+    /// [`crate::merkle::is_valid_merkle_tree`].
+    pub fn is_valid<F>(&self, node_hash: F) -> bool
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        is_valid_tree(&self.nodes, node_hash)
+    }
+}
@@ -1,4 +1,9 @@
-use sha3::{Digest, Keccak256};
+use sha2::digest::FixedOutputReset;
+use sha2::Sha256;
+use sha3::{Digest, Keccak256, Sha3_256};
+
+use crate::consts::{RFC6962_LEAF_PREFIX, RFC6962_NODE_PREFIX};
+use crate::tree::PairOrdering;
 
 // import { encode } from '@metamask/abi-utils';
 // import { keccak256 as _keccak256 } from 'ethereum-cryptography/keccak';
@@ -19,7 +24,6 @@ use sha3::{Digest, Keccak256};
 //   return keccak256(concat([a, b].sort(compare)));
 // }
 
-// TODO: implement hashes
 pub fn keccak256(input: &[u8]) -> [u8; 32] {
     let mut hasher = Keccak256::new();
     hasher.update(input);
@@ -28,3 +32,389 @@ pub fn keccak256(input: &[u8]) -> [u8; 32] {
     hash.copy_from_slice(&result);
     hash
 }
+
+pub fn sha256(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(input);
+    let result = hasher.finalize();
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&result);
+    hash
+}
+
+fn hash_pairs_with<D: Digest + FixedOutputReset>(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    // one hasher instance is reused across the whole batch
+    let mut hasher = D::new();
+    pairs
+        .iter()
+        .map(|(left, right)| {
+            Digest::update(&mut hasher, left);
+            Digest::update(&mut hasher, right);
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&hasher.finalize_reset());
+            hash
+        })
+        .collect()
+}
+
+/// Hash every `(left, right)` pair as `keccak256(left ‖ right)` on the calling thread, reusing
+/// one hasher. Pairs are hashed as given; callers wanting sorted-pair semantics must order
+/// each pair themselves. See [`hash_pairs_batch_parallel`] for large batches.
+pub fn hash_pairs_batch(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    hash_pairs_with::<Keccak256>(pairs)
+}
+
+/// SHA-256 counterpart of [`hash_pairs_batch`].
+pub fn sha256_pairs_batch(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    hash_pairs_with::<Sha256>(pairs)
+}
+
+/// Fewest pairs worth handing to another thread; smaller batches are hashed in place.
+const MIN_PARALLEL_PAIRS: usize = 4096;
+
+fn hash_pairs_parallel_with<D: Digest + FixedOutputReset>(
+    pairs: &[([u8; 32], [u8; 32])],
+) -> Vec<[u8; 32]> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = pairs.len().div_ceil(threads).max(MIN_PARALLEL_PAIRS);
+    if chunk >= pairs.len() {
+        return hash_pairs_with::<D>(pairs);
+    }
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = pairs
+            .chunks(chunk)
+            .map(|pairs| scope.spawn(move || hash_pairs_with::<D>(pairs)))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("Hash worker panicked"))
+            .collect()
+    })
+}
+
+/// Like [`hash_pairs_batch`], splitting batches of more than a few thousand pairs across the
+/// available cores. The output is identical. The keccak256 hashers' [`Hasher::hash_pairs`]
+/// uses it, so tree builds over them share this kernel.
+pub fn hash_pairs_batch_parallel(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    hash_pairs_parallel_with::<Keccak256>(pairs)
+}
+
+/// SHA-256 counterpart of [`hash_pairs_batch_parallel`], used by the sha256 hashers'
+/// [`Hasher::hash_pairs`].
+pub fn sha256_pairs_batch_parallel(pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+    hash_pairs_parallel_with::<Sha256>(pairs)
+}
+
+/// `keccak256(keccak256(abi.encode(types, values)))`, the leaf hash of OpenZeppelin's
+/// `StandardMerkleTree`. See [`crate::abi::encode`] for the accepted value formats.
+pub fn standard_leaf_hash<T, V>(types: &[T], values: &[V]) -> [u8; 32]
+where
+    T: AsRef<str>,
+    V: AsRef<str>,
+{
+    keccak256(&keccak256(&crate::abi::encode(types, values)))
+}
+
+/// [`standard_leaf_hash`] returning an error on values that do not fit their types.
+pub fn try_standard_leaf_hash<T, V>(
+    types: &[T],
+    values: &[V],
+) -> Result<[u8; 32], crate::abi::AbiError>
+where
+    T: AsRef<str>,
+    V: AsRef<str>,
+{
+    Ok(keccak256(&keccak256(&crate::abi::try_encode(
+        types, values,
+    )?)))
+}
+
+/// `keccak256(u32_be(context length) ‖ context ‖ leaf)`: `leaf` bound to a verification
+/// context such as a session id, see [`crate::merkle::verify_proof_in_context`].
+pub fn context_leaf_hash(context: &[u8], leaf: &[u8]) -> [u8; 32] {
+    let mut data = Vec::with_capacity(4 + context.len() + leaf.len());
+    data.extend_from_slice(&(context.len() as u32).to_be_bytes());
+    data.extend_from_slice(context);
+    data.extend_from_slice(leaf);
+    keccak256(&data)
+}
+
+/// `keccak256` of the two nodes concatenated in ascending order.
+pub fn standard_node_hash(a: &[u8], b: &[u8]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak256(&[left, right].concat())
+}
+
+/// Leaf and node hashing used by [`crate::tree::MerkleTree`]. Sorted-pair proof processing
+/// orders each pair before hashing, so there `hash_node` must be commutative in its two
+/// arguments, as the sorted built-in hashers are. The positional hashers combine
+/// `left ‖ right` as given and are used with positional proofs instead.
+pub trait Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32];
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32];
+    /// Name of the hash function recorded in [`crate::tree::TreeConfig::hasher`], as accepted
+    /// by [`hasher_by_name`]; sorted and positional variants share it. Empty for custom
+    /// hashers.
+    fn name(&self) -> &str {
+        ""
+    }
+    /// How `hash_node` orders its arguments, which tells apart the sorted and positional
+    /// variants of one name. `None` for custom hashers, whose ordering is not known.
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        None
+    }
+    /// `hash_node` of every `(left, right)` pair, in order. Builders hash a whole tree level
+    /// through it; the built-in keccak256 and sha256 hashers use the batch kernels
+    /// [`hash_pairs_batch_parallel`] and [`sha256_pairs_batch_parallel`].
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        pairs.iter().map(|(l, r)| self.hash_node(l, r)).collect()
+    }
+}
+
+impl<H: Hasher + ?Sized> Hasher for &H {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        (**self).hash_node(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        (**self).hash_leaf(data)
+    }
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        (**self).pair_ordering()
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        (**self).hash_pairs(pairs)
+    }
+}
+
+impl<H: Hasher + ?Sized> Hasher for Box<H> {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        (**self).hash_node(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        (**self).hash_leaf(data)
+    }
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        (**self).pair_ordering()
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        (**self).hash_pairs(pairs)
+    }
+}
+
+fn digest_pair<D: Digest>(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let mut hasher = D::new();
+    hasher.update(left);
+    hasher.update(right);
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+fn digest_sorted_pair<D: Digest>(a: &[u8], b: &[u8]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    digest_pair::<D>(left, right)
+}
+
+/// Each pair in ascending order, for batch hashing with sorted-pair semantics.
+fn sorted_pairs(pairs: &[([u8; 32], [u8; 32])]) -> Vec<([u8; 32], [u8; 32])> {
+    pairs
+        .iter()
+        .map(|&(a, b)| if a <= b { (a, b) } else { (b, a) })
+        .collect()
+}
+
+fn digest_once<D: Digest>(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&D::digest(data));
+    hash
+}
+
+/// Sorted-pair keccak256, see [`standard_node_hash`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keccak256Hasher;
+
+impl Hasher for Keccak256Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        standard_node_hash(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        keccak256(data)
+    }
+    fn name(&self) -> &str {
+        "keccak256"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Sorted)
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        hash_pairs_batch_parallel(&sorted_pairs(pairs))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha256Hasher;
+
+impl Hasher for Sha256Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        digest_sorted_pair::<Sha256>(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        sha256(data)
+    }
+    fn name(&self) -> &str {
+        "sha256"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Sorted)
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        sha256_pairs_batch_parallel(&sorted_pairs(pairs))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha3_256Hasher;
+
+impl Hasher for Sha3_256Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        digest_sorted_pair::<Sha3_256>(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        digest_once::<Sha3_256>(data)
+    }
+    fn name(&self) -> &str {
+        "sha3-256"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Sorted)
+    }
+}
+
+#[cfg(feature = "blake3")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "blake3")]
+impl Hasher for Blake3Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        let (left, right) = if left <= right {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        blake3::hash(data).into()
+    }
+    fn name(&self) -> &str {
+        "blake3"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Sorted)
+    }
+}
+
+/// `keccak256(left ‖ right)` without sorting, for
+/// [`crate::merkle::make_positional_merkle_tree_bytes`].
+pub fn positional_node_hash(left: &[u8], right: &[u8]) -> [u8; 32] {
+    digest_pair::<Keccak256>(left, right)
+}
+
+/// Keccak256 with positional (unsorted) pairs, see [`positional_node_hash`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionalKeccak256Hasher;
+
+impl Hasher for PositionalKeccak256Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        positional_node_hash(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        keccak256(data)
+    }
+    fn name(&self) -> &str {
+        "keccak256"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Positional)
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        hash_pairs_batch_parallel(pairs)
+    }
+}
+
+/// SHA-256 with positional (unsorted) pairs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionalSha256Hasher;
+
+impl Hasher for PositionalSha256Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        digest_pair::<Sha256>(left, right)
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        sha256(data)
+    }
+    fn name(&self) -> &str {
+        "sha256"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Positional)
+    }
+    fn hash_pairs(&self, pairs: &[([u8; 32], [u8; 32])]) -> Vec<[u8; 32]> {
+        sha256_pairs_batch_parallel(pairs)
+    }
+}
+
+/// Certificate Transparency (RFC 6962) hashing: `SHA-256(0x00 ‖ data)` for leaves and
+/// `SHA-256(0x01 ‖ left ‖ right)` for nodes. The prefixes keep a leaf from ever hashing like an
+/// internal node. Pairs are positional and need the RFC 6962 tree shape, which
+/// [`crate::consistency::log_root`], [`crate::incremental::IncrementalMerkleTree`] and
+/// [`crate::tree::MerkleTree::new_positional`] all build.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rfc6962Hasher;
+
+impl Hasher for Rfc6962Hasher {
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([RFC6962_NODE_PREFIX]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+    fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([RFC6962_LEAF_PREFIX]);
+        hasher.update(data);
+        hasher.finalize().into()
+    }
+    fn name(&self) -> &str {
+        "rfc6962"
+    }
+    fn pair_ordering(&self) -> Option<PairOrdering> {
+        Some(PairOrdering::Positional)
+    }
+}
+
+/// Built-in hasher for `name` (`"keccak256"`, `"sha256"`, `"sha3-256"`, `"rfc6962"` and, with
+/// the `blake3` feature, `"blake3"`), so bindings can select the algorithm at runtime. All but
+/// [`Rfc6962Hasher`] sort pairs.
+pub fn hasher_by_name(name: &str) -> Option<Box<dyn Hasher + Send + Sync>> {
+    match name {
+        "keccak256" => Some(Box::new(Keccak256Hasher)),
+        "sha256" => Some(Box::new(Sha256Hasher)),
+        "sha3-256" => Some(Box::new(Sha3_256Hasher)),
+        "rfc6962" => Some(Box::new(Rfc6962Hasher)),
+        #[cfg(feature = "blake3")]
+        "blake3" => Some(Box::new(Blake3Hasher)),
+        _ => None,
+    }
+}
@@ -0,0 +1,383 @@
+//! Append-only Merkle tree for logs and deposit-style trees that receive leaves over time.
+//! Only the roots of the perfect subtrees covering the leaves so far (the "frontier") are
+//! kept, so a push rehashes at most one path of `log2(n)` nodes and memory stays `O(log n)`.
+//!
+//! The tree has the RFC 6962 shape, so [`IncrementalMerkleTree::root`] equals
+//! [`crate::consistency::log_root`] over the same leaves and successive roots can be linked
+//! with consistency proofs. It differs from the heap layout of
+//! [`crate::merkle::make_merkle_tree_bytes`] unless the leaf count is a power of two.
+//!
+//! [`FixedDepthTree`] is the on-chain variant used by the Eth2 deposit contract, Tornado Cash
+//! and Semaphore: a tree of fixed depth whose unfilled leaves hold a zero value, so its root
+//! always covers `2^depth` leaves.
+
+use std::collections::VecDeque;
+
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, Bytes, Hash, MerkleError};
+use crate::tree::{
+    check_pair_ordering, EmptyRoot, MultiProofAlgorithm, Padding, PairOrdering, SingleLeafRoot,
+    TreeConfig,
+};
+
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree<H: Hasher> {
+    hasher: H,
+    empty_root: EmptyRoot,
+    single_leaf: SingleLeafRoot,
+    /// Roots of the perfect subtrees, largest (leftmost) first; subtree `i` has as many leaves
+    /// as the `i`-th highest set bit of `len`.
+    frontier: Vec<Hash>,
+    len: usize,
+    /// Up to `root_history` latest roots, oldest first.
+    roots: VecDeque<Bytes>,
+    root_history: usize,
+}
+
+impl<H: Hasher> IncrementalMerkleTree<H> {
+    /// Empty tree whose [`Self::root`] fails until the first push.
+    pub fn new(hasher: H) -> Self {
+        Self::with_empty_root(hasher, EmptyRoot::Reject)
+    }
+
+    pub fn with_empty_root(hasher: H, empty_root: EmptyRoot) -> Self {
+        Self::with_root_history(hasher, empty_root, 0)
+    }
+
+    /// Tree that remembers its last `size` roots, including the empty root if `empty_root`
+    /// defines one, for [`Self::is_known_root`]. Each push then also computes the new root.
+    pub fn with_root_history(hasher: H, empty_root: EmptyRoot, size: usize) -> Self {
+        Self::with_single_leaf_root(hasher, empty_root, size, SingleLeafRoot::Leaf)
+    }
+
+    /// Like [`Self::with_root_history`], with the root of a one-leaf tree set by `single_leaf`.
+    pub fn with_single_leaf_root(
+        hasher: H,
+        empty_root: EmptyRoot,
+        size: usize,
+        single_leaf: SingleLeafRoot,
+    ) -> Self {
+        let mut tree = Self {
+            hasher,
+            empty_root,
+            single_leaf,
+            frontier: Vec::new(),
+            len: 0,
+            roots: VecDeque::with_capacity(size),
+            root_history: size,
+        };
+        tree.record_root();
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Roots of the perfect subtrees covering the leaves, largest first.
+    pub fn frontier(&self) -> &[Hash] {
+        &self.frontier
+    }
+
+    /// Append a 32-byte leaf. Panics on any other length, like
+    /// [`crate::merkle::make_merkle_tree_bytes`].
+    pub fn push(&mut self, leaf: &[u8]) {
+        assert!(
+            leaf.len() == 32,
+            "Expected valid merkle node, got length {}",
+            leaf.len()
+        );
+        let mut carry: Hash = leaf.try_into().unwrap();
+        // each trailing one bit of `len` is a subtree of the new leaf's size to merge with
+        let mut n = self.len;
+        while n & 1 == 1 {
+            let left = self.frontier.pop().unwrap();
+            carry = self.hasher.hash_node(&left, &carry);
+            n >>= 1;
+        }
+        self.frontier.push(carry);
+        self.len += 1;
+        self.record_root();
+    }
+
+    fn record_root(&mut self) {
+        if self.root_history == 0 {
+            return;
+        }
+        if let Ok(root) = self.root() {
+            if self.roots.len() == self.root_history {
+                self.roots.pop_front();
+            }
+            self.roots.push_back(root);
+        }
+    }
+
+    /// The remembered roots, oldest first; empty without a root history.
+    pub fn recent_roots(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+        self.roots.iter().map(|r| r.as_slice())
+    }
+
+    /// Whether `root` is the current root or one of the remembered ones, so proofs built
+    /// against a slightly stale root are still accepted. Roots are compared in constant time.
+    pub fn is_known_root(&self, root: &[u8]) -> bool {
+        let current = self.root().is_ok_and(|r| constant_time_eq(&r, root));
+        // every remembered root is compared so the time does not depend on which matched
+        self.roots
+            .iter()
+            .fold(current, |known, r| constant_time_eq(r, root) | known)
+    }
+
+    pub fn extend<I>(&mut self, leaves: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        for leaf in leaves {
+            self.push(leaf.as_ref());
+        }
+    }
+
+    /// Append `leaves`, like [`Self::extend`], and return a proof that they are the newest
+    /// leaves of the resulting tree. The proof is the frontier before the batch, so it costs
+    /// `O(log n)` hashes whatever the batch size and needs none of the older leaves.
+    pub fn extend_with_proof(&mut self, leaves: &[Bytes]) -> NewestLeavesProof {
+        let proof = NewestLeavesProof {
+            start: self.len,
+            frontier: self.frontier.clone(),
+        };
+        self.extend(leaves);
+        proof
+    }
+
+    /// Root over all leaves pushed so far, or the configured empty or single-leaf root.
+    pub fn root(&self) -> Result<Bytes, MerkleError> {
+        let mut peaks = self.frontier.iter().rev();
+        let Some(&last) = peaks.next() else {
+            return self.empty_root.root(&self.hasher).map(|h| h.to_vec());
+        };
+        if self.len == 1 {
+            return Ok(self.single_leaf.root(&last, &self.hasher));
+        }
+        let root = peaks.fold(last, |acc, peak| self.hasher.hash_node(peak, &acc));
+        Ok(root.to_vec())
+    }
+}
+
+/// Proof from [`IncrementalMerkleTree::extend_with_proof`] that a batch of leaves occupies
+/// positions `start..` and ends the tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewestLeavesProof {
+    /// Number of leaves before the batch.
+    pub start: usize,
+    /// Frontier of the tree before the batch, largest subtree first.
+    pub frontier: Vec<Hash>,
+}
+
+impl NewestLeavesProof {
+    /// Check that appending `leaves` to the recorded frontier gives `root`, the root of a tree
+    /// of `start + leaves.len()` leaves. Malformed input verifies as `false`.
+    pub fn verify<H: Hasher>(&self, root: &[u8], leaves: &[Bytes], hasher: &H) -> bool {
+        self.verify_with_single_leaf_root(root, leaves, hasher, SingleLeafRoot::Leaf)
+    }
+
+    /// Like [`Self::verify`] for a tree whose one-leaf root is set by `single_leaf`.
+    pub fn verify_with_single_leaf_root<H: Hasher>(
+        &self,
+        root: &[u8],
+        leaves: &[Bytes],
+        hasher: &H,
+        single_leaf: SingleLeafRoot,
+    ) -> bool {
+        if leaves.is_empty()
+            || leaves.iter().any(|l| l.len() != 32)
+            || self.frontier.len() != self.start.count_ones() as usize
+            || self.start.checked_add(leaves.len()).is_none()
+        {
+            return false;
+        }
+        let mut tree = IncrementalMerkleTree {
+            hasher,
+            empty_root: EmptyRoot::Reject,
+            single_leaf,
+            frontier: self.frontier.clone(),
+            len: self.start,
+            roots: VecDeque::new(),
+            root_history: 0,
+        };
+        tree.extend(leaves);
+        tree.root().is_ok_and(|r| constant_time_eq(&r, root))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FixedDepthTree<H: Hasher> {
+    hasher: H,
+    /// Root of an all-zero subtree per height, `depth + 1` entries.
+    zeros: Vec<Hash>,
+    /// Nodes per height covering the leaves so far; the rest of each level is zero subtrees.
+    levels: Vec<Vec<Hash>>,
+}
+
+impl<H: Hasher> FixedDepthTree<H> {
+    /// Empty tree of `2^depth` leaves set to `zero_value`. Panics unless
+    /// `depth < usize::BITS` or on a sorted-pair hasher, see [`Self::try_new`].
+    pub fn new(depth: usize, zero_value: Hash, hasher: H) -> Self {
+        Self::try_new(depth, zero_value, hasher).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like [`Self::new`], failing on a depth of `usize::BITS` or more and on a hasher whose
+    /// [`Hasher::pair_ordering`] is not positional: the proofs fix the leaf position, which a
+    /// sorted-pair hasher would not, and [`Self::config`] records positional pairs.
+    pub fn try_new(depth: usize, zero_value: Hash, hasher: H) -> Result<Self, MerkleError> {
+        if depth >= usize::BITS as usize {
+            return Err(MerkleError::InvalidConfig(format!(
+                "depth {} exceeds maximum of {}",
+                depth,
+                usize::BITS - 1
+            )));
+        }
+        check_pair_ordering(&hasher, PairOrdering::Positional)?;
+        let mut zeros = Vec::with_capacity(depth + 1);
+        zeros.push(zero_value);
+        for height in 0..depth {
+            let z = zeros[height];
+            zeros.push(hasher.hash_node(&z, &z));
+        }
+        Ok(Self {
+            hasher,
+            zeros,
+            levels: vec![Vec::new(); depth + 1],
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.zeros.len() - 1
+    }
+
+    /// Number of leaves the tree can hold, `2^depth`.
+    pub fn capacity(&self) -> usize {
+        1 << self.depth()
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Positional pairs padded with zero subtrees up to the capacity.
+    pub fn config(&self) -> TreeConfig {
+        TreeConfig {
+            hasher: self.hasher.name().to_string(),
+            pair_ordering: PairOrdering::Positional,
+            multi_proof: MultiProofAlgorithm::Positional,
+            padding: Padding::ZeroSubtrees {
+                depth: self.depth(),
+                zero_value: self.zeros[0],
+            },
+            ..TreeConfig::default()
+        }
+    }
+
+    /// Root of an all-zero subtree per height, leaves first.
+    pub fn zeros(&self) -> &[Hash] {
+        &self.zeros
+    }
+
+    fn node(&self, height: usize, index: usize) -> Hash {
+        self.levels[height]
+            .get(index)
+            .copied()
+            .unwrap_or(self.zeros[height])
+    }
+
+    /// Append a 32-byte leaf and return its index. Panics on any other length or when the
+    /// tree is full.
+    pub fn insert(&mut self, leaf: &[u8]) -> usize {
+        assert!(
+            leaf.len() == 32,
+            "Expected valid merkle node, got length {}",
+            leaf.len()
+        );
+        let index = self.len();
+        assert!(
+            index < self.capacity(),
+            "Tree of depth {} is full",
+            self.depth()
+        );
+        self.levels[0].push(leaf.try_into().unwrap());
+        let mut i = index;
+        for height in 0..self.depth() {
+            i /= 2;
+            let parent = self
+                .hasher
+                .hash_node(&self.node(height, 2 * i), &self.node(height, 2 * i + 1));
+            let level = &mut self.levels[height + 1];
+            if i < level.len() {
+                level[i] = parent;
+            } else {
+                level.push(parent);
+            }
+        }
+        index
+    }
+
+    pub fn root(&self) -> Hash {
+        self.node(self.depth(), 0)
+    }
+
+    /// Siblings from the leaf at `index` up to the root, `depth` entries. Indices past
+    /// [`Self::len`] prove the zero value. Panics if `index` is not below the capacity.
+    pub fn proof(&self, index: usize) -> Vec<Bytes> {
+        assert!(
+            index < self.capacity(),
+            "Index {} out of range for depth {}",
+            index,
+            self.depth()
+        );
+        (0..self.depth())
+            .map(|height| self.node(height, (index >> height) ^ 1).to_vec())
+            .collect()
+    }
+}
+
+/// Check a [`FixedDepthTree::proof`]: the bits of `index` choose the side of each sibling, so
+/// the proof also fixes the leaf's position.
+pub fn verify_fixed_depth_proof<H: Hasher>(
+    root: &[u8],
+    leaf: &[u8],
+    index: usize,
+    proof: &[Bytes],
+    hasher: &H,
+) -> bool {
+    if leaf.len() != 32
+        || proof.iter().any(|p| p.len() != 32)
+        || proof.len() >= usize::BITS as usize
+        || index >> proof.len() != 0
+    {
+        return false;
+    }
+    let mut current: Hash = leaf.try_into().unwrap();
+    for (height, sibling) in proof.iter().enumerate() {
+        current = if (index >> height) & 1 == 1 {
+            hasher.hash_node(sibling, &current)
+        } else {
+            hasher.hash_node(&current, sibling)
+        };
+    }
+    constant_time_eq(&current, root)
+}
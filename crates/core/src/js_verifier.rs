@@ -0,0 +1,332 @@
+//! Self-contained JavaScript verifier for the proofs of one [`TreeConfig`], so browsers and
+//! other runtimes without the native addon can check proofs. The emitted ES module has no
+//! imports: it carries its own SHA-256 or Keccak implementation and only the verify functions
+//! the config's pair ordering and multiproof algorithm need.
+//!
+//! Leaves are passed already hashed, as 32-byte `Uint8Array`s, byte arrays or hex strings;
+//! [`TreeConfig::leaf_encoding`] is recorded in the module but not applied.
+
+use crate::merkle::MerkleError;
+use crate::tree::{PairOrdering, SingleLeafRoot, TreeConfig};
+
+const PRELUDE: &str = r#"
+function toBytes(value) {
+    if (typeof value === "string") {
+        const hex = value.startsWith("0x") ? value.slice(2) : value;
+        if (hex.length % 2 !== 0 || /[^0-9a-fA-F]/.test(hex)) {
+            throw new Error("Invalid hex string");
+        }
+        return Uint8Array.from(hex.match(/../g) ?? [], (byte) => parseInt(byte, 16));
+    }
+    return Uint8Array.from(value);
+}
+
+function toNode(value) {
+    const node = toBytes(value);
+    if (node.length !== 32) {
+        throw new Error(`Expected valid merkle node, got length ${node.length}`);
+    }
+    return node;
+}
+
+function compare(a, b) {
+    for (let i = 0; i < a.length && i < b.length; i++) {
+        if (a[i] !== b[i]) return a[i] - b[i];
+    }
+    return a.length - b.length;
+}
+
+function concat(...parts) {
+    const out = new Uint8Array(parts.reduce((n, part) => n + part.length, 0));
+    let offset = 0;
+    for (const part of parts) {
+        out.set(part, offset);
+        offset += part.length;
+    }
+    return out;
+}
+
+function equal(a, b) {
+    let diff = a.length ^ b.length;
+    for (let i = 0; i < a.length && i < b.length; i++) diff |= a[i] ^ b[i];
+    return diff === 0;
+}
+
+// malformed input verifies as false instead of throwing
+function check(root, compute) {
+    try {
+        return equal(toNode(root), compute());
+    } catch {
+        return false;
+    }
+}
+"#;
+
+const SHA256: &str = r#"
+const SHA256_K = new Uint32Array([
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+]);
+
+function sha256(data) {
+    const ror = (x, n) => (x >>> n) | (x << (32 - n));
+    const length = Math.ceil((data.length + 9) / 64) * 64;
+    const message = new Uint8Array(length);
+    message.set(data);
+    message[data.length] = 0x80;
+    const view = new DataView(message.buffer);
+    view.setUint32(length - 8, Math.floor(data.length / 0x20000000));
+    view.setUint32(length - 4, data.length * 8);
+    const h = new Uint32Array([
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ]);
+    const w = new Uint32Array(64);
+    for (let offset = 0; offset < length; offset += 64) {
+        for (let i = 0; i < 16; i++) w[i] = view.getUint32(offset + 4 * i);
+        for (let i = 16; i < 64; i++) {
+            const s0 = ror(w[i - 15], 7) ^ ror(w[i - 15], 18) ^ (w[i - 15] >>> 3);
+            const s1 = ror(w[i - 2], 17) ^ ror(w[i - 2], 19) ^ (w[i - 2] >>> 10);
+            w[i] = w[i - 16] + s0 + w[i - 7] + s1;
+        }
+        let [a, b, c, d, e, f, g, hh] = h;
+        for (let i = 0; i < 64; i++) {
+            const t1 = (hh + (ror(e, 6) ^ ror(e, 11) ^ ror(e, 25)) + ((e & f) ^ (~e & g)) + SHA256_K[i] + w[i]) | 0;
+            const t2 = ((ror(a, 2) ^ ror(a, 13) ^ ror(a, 22)) + ((a & b) ^ (a & c) ^ (b & c))) | 0;
+            [hh, g, f, e, d, c, b, a] = [g, f, e, (d + t1) | 0, c, b, a, (t1 + t2) | 0];
+        }
+        [a, b, c, d, e, f, g, hh].forEach((x, i) => (h[i] += x));
+    }
+    const out = new Uint8Array(32);
+    h.forEach((x, i) => new DataView(out.buffer).setUint32(4 * i, x));
+    return out;
+}
+"#;
+
+const KECCAK: &str = r#"
+const KECCAK_MASK = (1n << 64n) - 1n;
+const KECCAK_RC = [
+    0x0000000000000001n, 0x0000000000008082n, 0x800000000000808an, 0x8000000080008000n,
+    0x000000000000808bn, 0x0000000080000001n, 0x8000000080008081n, 0x8000000000008009n,
+    0x000000000000008an, 0x0000000000000088n, 0x0000000080008009n, 0x000000008000000an,
+    0x000000008000808bn, 0x800000000000008bn, 0x8000000000008089n, 0x8000000000008003n,
+    0x8000000000008002n, 0x8000000000000080n, 0x000000000000800an, 0x800000008000000an,
+    0x8000000080008081n, 0x8000000000008080n, 0x0000000080000001n, 0x8000000080008008n,
+];
+// rotation of lane x + 5y
+const KECCAK_ROT = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+function keccakF(s) {
+    const rotl = (x, n) => (n === 0 ? x : ((x << BigInt(n)) | (x >> BigInt(64 - n))) & KECCAK_MASK);
+    for (let round = 0; round < 24; round++) {
+        const c = [0, 1, 2, 3, 4].map((x) => s[x] ^ s[x + 5] ^ s[x + 10] ^ s[x + 15] ^ s[x + 20]);
+        for (let x = 0; x < 5; x++) {
+            const d = c[(x + 4) % 5] ^ rotl(c[(x + 1) % 5], 1);
+            for (let y = 0; y < 25; y += 5) s[x + y] ^= d;
+        }
+        const b = new Array(25);
+        for (let x = 0; x < 5; x++) {
+            for (let y = 0; y < 5; y++) {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = rotl(s[x + 5 * y], KECCAK_ROT[x + 5 * y]);
+            }
+        }
+        for (let x = 0; x < 5; x++) {
+            for (let y = 0; y < 25; y += 5) {
+                s[x + y] = b[x + y] ^ (~b[((x + 1) % 5) + y] & KECCAK_MASK & b[((x + 2) % 5) + y]);
+            }
+        }
+        s[0] ^= KECCAK_RC[round];
+    }
+}
+
+// `pad` is 0x01 for keccak256 and 0x06 for SHA3-256
+function keccak(data, pad) {
+    const rate = 136;
+    const length = (Math.floor(data.length / rate) + 1) * rate;
+    const message = new Uint8Array(length);
+    message.set(data);
+    message[data.length] ^= pad;
+    message[length - 1] ^= 0x80;
+    const s = new Array(25).fill(0n);
+    for (let offset = 0; offset < length; offset += rate) {
+        for (let i = 0; i < rate / 8; i++) {
+            let lane = 0n;
+            for (let j = 7; j >= 0; j--) lane = (lane << 8n) | BigInt(message[offset + 8 * i + j]);
+            s[i] ^= lane;
+        }
+        keccakF(s);
+    }
+    return Uint8Array.from({ length: 32 }, (_, i) => Number((s[i >> 3] >> BigInt(8 * (i & 7))) & 0xffn));
+}
+"#;
+
+const SORTED: &str = r#"
+function hashNode(a, b) {
+    return hash(compare(a, b) <= 0 ? concat(a, b) : concat(b, a));
+}
+
+/** Root computed from a leaf and its `MerkleTree.proof`; throws on malformed input. */
+export function processProof(leaf, proof) {
+    const nodes = proof.map(toNode);
+    if (nodes.length === 0) return singleLeafRoot(toNode(leaf));
+    return nodes.reduce((computed, node) => hashNode(computed, node), toNode(leaf));
+}
+
+export function verifyProof(root, leaf, proof) {
+    return check(root, () => processProof(leaf, proof));
+}
+"#;
+
+const POSITIONAL: &str = r#"
+function hashNode(left, right) {
+    return hash(concat(NODE_PREFIX, left, right));
+}
+
+/**
+ * Root computed from a leaf and its `MerkleTree.positionalProof`, given as
+ * `{ side: "left" | "right", node }` entries; throws on malformed input.
+ */
+export function processPositionalProof(leaf, proof) {
+    const steps = proof.map(({ side, node }) => {
+        if (side !== "left" && side !== "right") throw new Error(`Invalid side ${side}`);
+        return [side, toNode(node)];
+    });
+    if (steps.length === 0) return singleLeafRoot(toNode(leaf));
+    return steps.reduce(
+        (computed, [side, node]) => (side === "left" ? hashNode(node, computed) : hashNode(computed, node)),
+        toNode(leaf),
+    );
+}
+
+export function verifyPositionalProof(root, leaf, proof) {
+    return check(root, () => processPositionalProof(leaf, proof));
+}
+"#;
+
+const OZ_MULTI_PROOF: &str = r#"
+/** Root computed from a `{ leaves, proof, proofFlags }` multiproof; throws on malformed input. */
+export function processMultiProof({ leaves, proof, proofFlags }) {
+    const stack = leaves.map(toNode);
+    const nodes = proof.map(toNode);
+    if (nodes.length < proofFlags.filter((flag) => !flag).length || stack.length + nodes.length !== proofFlags.length + 1) {
+        throw new Error("Invalid multiproof");
+    }
+    if (stack.length === 1 && nodes.length === 0) return singleLeafRoot(stack[0]);
+    for (const flag of proofFlags) {
+        const a = stack.shift();
+        const b = flag ? stack.shift() : nodes.shift();
+        if (a === undefined || b === undefined) throw new Error("Invalid multiproof");
+        stack.push(hashNode(a, b));
+    }
+    if (stack.length + nodes.length !== 1) throw new Error("Invalid multiproof");
+    return stack.length === 1 ? stack[0] : nodes[0];
+}
+
+export function verifyMultiProof(root, multiProof) {
+    return check(root, () => processMultiProof(multiProof));
+}
+"#;
+
+const POSITIONAL_MULTI_PROOF: &str = r#"
+/**
+ * Root computed from a `{ leafCount, positions, leaves, proof }` positional multiproof;
+ * throws on malformed input.
+ */
+export function processPositionalMultiProof({ leafCount, positions, leaves, proof }) {
+    const nodes = proof.map(toNode);
+    if (
+        !Number.isSafeInteger(leafCount) || leafCount < 1 || positions.length !== leaves.length ||
+        positions.some((p, i) => !Number.isSafeInteger(p) || p < 0 || p >= leafCount || (i > 0 && positions[i - 1] >= p))
+    ) {
+        throw new Error("Invalid multiproof");
+    }
+    if (positions.length === 0) {
+        if (nodes.length !== 1) throw new Error("Invalid multiproof");
+        return nodes[0];
+    }
+    let known = positions.map((p, i) => [p, toNode(leaves[i])]);
+    if (leafCount === 1 && nodes.length === 0) return singleLeafRoot(known[0][1]);
+    let next = 0;
+    const sibling = () => {
+        if (next === nodes.length) throw new Error("Invalid multiproof");
+        return nodes[next++];
+    };
+    // the last node of an odd layer is carried up unpaired
+    for (let width = leafCount; width > 1; width = Math.ceil(width / 2)) {
+        const up = [];
+        for (let i = 0; i < known.length; i++) {
+            const [p, value] = known[i];
+            let parent = value;
+            if (p % 2 === 1) {
+                parent = hashNode(sibling(), value);
+            } else if (p + 1 < width) {
+                parent = i + 1 < known.length && known[i + 1][0] === p + 1
+                    ? hashNode(value, known[++i][1])
+                    : hashNode(value, sibling());
+            }
+            up.push([Math.floor(p / 2), parent]);
+        }
+        known = up;
+    }
+    if (next !== nodes.length) throw new Error("Invalid multiproof");
+    return known[0][1];
+}
+
+export function verifyPositionalMultiProof(root, multiProof) {
+    return check(root, () => processPositionalMultiProof(multiProof));
+}
+"#;
+
+/// ES module source verifying the proofs of trees built with `config`. Fails with
+/// [`MerkleError::InvalidConfig`] on settings [`TreeConfig::check_supported`] rejects for the
+/// config's built-in hasher, and on hashers without a JS implementation (BLAKE3).
+pub fn emit_js_verifier(config: &TreeConfig) -> Result<String, MerkleError> {
+    config.check_supported(&config.build_hasher()?)?;
+    let (digest, hash, leaf_prefix, node_prefix) = match config.hasher.as_str() {
+        "keccak256" => (KECCAK, "keccak(data, 0x01)", "", ""),
+        "sha3-256" => (KECCAK, "keccak(data, 0x06)", "", ""),
+        "sha256" => (SHA256, "sha256(data)", "", ""),
+        "rfc6962" => (SHA256, "sha256(data)", "0", "1"),
+        other => {
+            return Err(MerkleError::InvalidConfig(format!(
+                "no JS implementation of hasher {:?}",
+                other
+            )))
+        }
+    };
+    let single_leaf = match config.single_leaf {
+        SingleLeafRoot::Leaf => "leaf",
+        SingleLeafRoot::Hashed => "hash(concat(LEAF_PREFIX, leaf))",
+    };
+    let mut out = format!(
+        "// Generated by merklerust: verifies proofs of trees built with the config below.\n\
+         export const config = {};\n",
+        config.to_json().to_json_string()
+    );
+    out.push_str(PRELUDE);
+    out.push_str(digest);
+    out.push_str(&format!(
+        "\nconst LEAF_PREFIX = Uint8Array.of({leaf_prefix});\n\
+         const NODE_PREFIX = Uint8Array.of({node_prefix});\n\n\
+         function hash(data) {{\n    return {hash};\n}}\n\n\
+         function singleLeafRoot(leaf) {{\n    return {single_leaf};\n}}\n",
+    ));
+    match config.pair_ordering {
+        PairOrdering::Sorted => {
+            out.push_str(SORTED);
+            out.push_str(OZ_MULTI_PROOF);
+        }
+        PairOrdering::Positional => {
+            out.push_str(POSITIONAL);
+            out.push_str(POSITIONAL_MULTI_PROOF);
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,584 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::dump::{DumpError, Json};
+use crate::hashes::Hasher;
+use crate::merkle::{multi_proof_indices, try_leaf_tree_index, Bytes, Hash, MultiProof};
+use crate::profile::ResourceProfile;
+
+/// Iterator over consecutive 32-byte leaf records read from `reader`.
+pub struct LeafReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> LeafReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+}
+
+impl<R: Read> Iterator for LeafReader<R> {
+    type Item = io::Result<Hash>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = [0u8; 32];
+        let mut filled = 0;
+        while filled < record.len() {
+            match self.reader.read(&mut record[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("Truncated leaf record: got {} of 32 bytes", filled),
+                    )))
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(record))
+    }
+}
+
+/// K-way merge of several individually sorted leaf files (e.g. external-sort runs) into one
+/// ascending, deduplicated leaf stream. Only one pending record per input is held in memory.
+pub struct SortedLeafMerge<R: Read> {
+    inputs: Vec<LeafReader<R>>,
+    last_from_input: Vec<Option<Hash>>,
+    heap: BinaryHeap<Reverse<(Hash, usize)>>,
+    last_emitted: Option<Hash>,
+    primed: bool,
+}
+
+impl<R: Read> SortedLeafMerge<R> {
+    pub fn new(readers: Vec<R>) -> Self {
+        let n = readers.len();
+        Self {
+            inputs: readers.into_iter().map(LeafReader::new).collect(),
+            last_from_input: vec![None; n],
+            heap: BinaryHeap::new(),
+            last_emitted: None,
+            primed: false,
+        }
+    }
+
+    fn pull(&mut self, input: usize) -> io::Result<()> {
+        if let Some(record) = self.inputs[input].next() {
+            let record = record?;
+            if let Some(prev) = self.last_from_input[input] {
+                if record < prev {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Leaf file {} is not sorted", input),
+                    ));
+                }
+            }
+            self.last_from_input[input] = Some(record);
+            self.heap.push(Reverse((record, input)));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for SortedLeafMerge<R> {
+    type Item = io::Result<Hash>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.primed {
+            self.primed = true;
+            for i in 0..self.inputs.len() {
+                if let Err(e) = self.pull(i) {
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        while let Some(Reverse((record, input))) = self.heap.pop() {
+            if let Err(e) = self.pull(input) {
+                return Some(Err(e));
+            }
+            if self.last_emitted == Some(record) {
+                continue;
+            }
+            self.last_emitted = Some(record);
+            return Some(Ok(record));
+        }
+        None
+    }
+}
+
+/// Merge sorted leaf files and feed the resulting ascending, deduplicated leaves to `builder`,
+/// so neither the leaves nor the tree are held in memory.
+pub fn make_merkle_tree_from_sorted_files<R, H>(
+    readers: Vec<R>,
+    mut builder: TreeBuilder<H>,
+) -> io::Result<LevelFileTree<H>>
+where
+    R: Read,
+    H: Hasher,
+{
+    for leaf in SortedLeafMerge::new(readers) {
+        builder.feed(&leaf?)?;
+    }
+    builder.finish()
+}
+
+/// Builds a tree too large for memory from leaves fed one at a time or read as 32-byte records
+/// from any [`Read`]. Leaves are spooled to a file in `dir` and [`TreeBuilder::finish`] then
+/// hashes one level file per tree level, streaming each pass, so memory use does not grow with
+/// the leaf count. The result is the tree [`crate::tree::MerkleTree::new`] would build.
+pub struct TreeBuilder<H: Hasher> {
+    hasher: H,
+    buffer: usize,
+    leaves: BufWriter<File>,
+    count: usize,
+    // declared after `leaves` so the spool file is closed before the directory is removed
+    dir: BuildDir,
+}
+
+/// Build directory. One created by [`TreeBuilder::in_temp_dir`] is deleted if the builder is
+/// dropped or [`TreeBuilder::finish`] fails, and handed to the [`LevelFileTree`] otherwise.
+struct BuildDir {
+    path: PathBuf,
+    owned: bool,
+}
+
+impl BuildDir {
+    /// Hand the directory over without deleting it; returns it and whether it is owned.
+    fn keep(mut self) -> (PathBuf, bool) {
+        let owned = std::mem::replace(&mut self.owned, false);
+        (std::mem::take(&mut self.path), owned)
+    }
+}
+
+impl Drop for BuildDir {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Memory used for file buffers by [`TreeBuilder::new`].
+pub const DEFAULT_BUILD_BUFFER: usize = 1 << 20;
+
+/// At most this many files are open at once during a build.
+const OPEN_FILES: usize = 4;
+
+fn leaves_path(dir: &Path) -> PathBuf {
+    dir.join("leaves.bin")
+}
+
+fn level_path(dir: &Path, level: usize) -> PathBuf {
+    dir.join(format!("level-{}.bin", level))
+}
+
+fn header_path(dir: &Path) -> PathBuf {
+    dir.join("tree.json")
+}
+
+/// Number of nodes on each heap level of a tree of `leaf_count` leaves, root level first.
+fn level_lengths(leaf_count: usize) -> Vec<usize> {
+    let depth = (usize::BITS - 1 - leaf_count.leading_zeros()) as usize;
+    let k = leaf_count - (1 << depth);
+    let mut lengths: Vec<usize> = (0..=depth).map(|level| 1 << level).collect();
+    if k > 0 {
+        lengths.push(2 * k);
+    }
+    lengths
+}
+
+/// Nodes in the heap tree over `leaf_count` leaves, `None` if there are none or too many.
+fn tree_len(leaf_count: usize) -> Option<usize> {
+    leaf_count.checked_mul(2)?.checked_sub(1)
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn writer(path: &Path, buffer: usize) -> io::Result<BufWriter<File>> {
+    File::create(path).map(|f| BufWriter::with_capacity(buffer, f))
+}
+
+fn reader(path: &Path, buffer: usize) -> io::Result<LeafReader<BufReader<File>>> {
+    File::open(path).map(|f| LeafReader::new(BufReader::with_capacity(buffer, f)))
+}
+
+/// Hash consecutive pairs of `nodes` into `out`. Level files list nodes from the right of the
+/// heap level, so each pair is `(right child, left child)`.
+fn hash_pairs<H, I, W>(hasher: &H, nodes: I, out: &mut W) -> io::Result<usize>
+where
+    H: Hasher,
+    I: Iterator<Item = io::Result<Hash>>,
+    W: Write,
+{
+    let mut nodes = nodes.peekable();
+    let mut written = 0;
+    while let Some(right) = nodes.next() {
+        let left = nodes
+            .next()
+            .unwrap_or_else(|| Err(io::Error::other("Level has an odd number of nodes")))?;
+        out.write_all(&hasher.hash_node(&left, &right?))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+impl<H: Hasher> TreeBuilder<H> {
+    /// Start a build whose level files go in `dir`, which is created if needed. Existing
+    /// level files there are overwritten.
+    pub fn new(dir: impl Into<PathBuf>, hasher: H) -> io::Result<Self> {
+        Self::with_memory_budget(dir, hasher, DEFAULT_BUILD_BUFFER)
+    }
+
+    /// Like [`Self::new`], reading and writing level files in chunks so that all file buffers
+    /// together stay within `memory_budget` bytes. Hashing itself needs only constant memory.
+    pub fn with_memory_budget(
+        dir: impl Into<PathBuf>,
+        hasher: H,
+        memory_budget: usize,
+    ) -> io::Result<Self> {
+        Self::create(dir.into(), false, hasher, memory_budget)
+    }
+
+    /// Like [`Self::with_memory_budget`] with the budget of `profile`.
+    pub fn with_profile(
+        dir: impl Into<PathBuf>,
+        hasher: H,
+        profile: ResourceProfile,
+    ) -> io::Result<Self> {
+        Self::with_memory_budget(dir, hasher, profile.build_buffer())
+    }
+
+    /// Like [`Self::with_memory_budget`] in a fresh directory under [`std::env::temp_dir`],
+    /// deleted by [`LevelFileTree::remove`], or when the builder is dropped or fails to finish.
+    pub fn in_temp_dir(hasher: H, memory_budget: usize) -> io::Result<Self> {
+        static BUILDS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "merklerust-build-{}-{}",
+            std::process::id(),
+            BUILDS.fetch_add(1, Ordering::Relaxed)
+        ));
+        Self::create(dir, true, hasher, memory_budget)
+    }
+
+    fn create(path: PathBuf, owned: bool, hasher: H, memory_budget: usize) -> io::Result<Self> {
+        fs::create_dir_all(&path)?;
+        let dir = BuildDir { path, owned };
+        let buffer = (memory_budget / OPEN_FILES / 32).max(1) * 32;
+        let leaves = writer(&leaves_path(&dir.path), buffer)?;
+        Ok(Self {
+            hasher,
+            buffer,
+            leaves,
+            count: 0,
+            dir,
+        })
+    }
+
+    /// Directory holding the level files.
+    pub fn dir(&self) -> &Path {
+        &self.dir.path
+    }
+
+    /// Number of leaves fed so far.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Append one 32-byte leaf; other lengths fail with [`io::ErrorKind::InvalidInput`].
+    pub fn feed(&mut self, leaf: &[u8]) -> io::Result<()> {
+        if leaf.len() != 32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expected valid merkle node, got length {}", leaf.len()),
+            ));
+        }
+        self.leaves.write_all(leaf)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Append every 32-byte record of `reader` and return how many were read.
+    pub fn feed_reader<R: Read>(&mut self, reader: R) -> io::Result<usize> {
+        let mut read = 0;
+        for leaf in LeafReader::new(reader) {
+            self.feed(&leaf?)?;
+            read += 1;
+        }
+        Ok(read)
+    }
+
+    /// Hash all levels. Fails with [`io::ErrorKind::InvalidInput`] if no leaf was fed.
+    pub fn finish(self) -> io::Result<LevelFileTree<H>> {
+        self.leaves.into_inner().map_err(|e| e.into_error())?;
+        let n = self.count;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Expected non-zero number of leaves",
+            ));
+        }
+        // The first 2k leaves sit one level below the others, as in `compute_root`; the k
+        // parents of those pairs follow the other leaves on the first full level.
+        let depth = (usize::BITS - 1 - n.leading_zeros()) as usize;
+        let k = n - (1 << depth);
+        let dir = &self.dir.path;
+        let mut leaves = reader(&leaves_path(dir), self.buffer)?;
+        let parents_path = dir.join("parents.bin");
+        let mut full = writer(&level_path(dir, depth), self.buffer)?;
+        if k > 0 {
+            let mut bottom = writer(&level_path(dir, depth + 1), self.buffer)?;
+            let mut parents = writer(&parents_path, self.buffer)?;
+            let pairs = leaves.by_ref().take(2 * k).map(|leaf| {
+                let leaf = leaf?;
+                bottom.write_all(&leaf)?;
+                Ok(leaf)
+            });
+            hash_pairs(&self.hasher, pairs, &mut parents)?;
+            bottom.flush()?;
+            parents.flush()?;
+        }
+        for leaf in leaves {
+            full.write_all(&leaf?)?;
+        }
+        if k > 0 {
+            io::copy(&mut File::open(&parents_path)?, &mut full)?;
+            fs::remove_file(&parents_path)?;
+        }
+        full.flush()?;
+        drop(full);
+        fs::remove_file(leaves_path(dir))?;
+
+        for level in (0..depth).rev() {
+            let mut out = writer(&level_path(dir, level), self.buffer)?;
+            hash_pairs(
+                &self.hasher,
+                reader(&level_path(dir, level + 1), self.buffer)?,
+                &mut out,
+            )?;
+            out.flush()?;
+        }
+        let lengths = level_lengths(n);
+        let header = Json::Object(vec![
+            (
+                "hasher".to_string(),
+                Json::String(self.hasher.name().to_string()),
+            ),
+            ("leafCount".to_string(), Json::Number(n.to_string())),
+            (
+                "levels".to_string(),
+                Json::Array(
+                    lengths
+                        .iter()
+                        .map(|len| Json::Number(len.to_string()))
+                        .collect(),
+                ),
+            ),
+        ]);
+        fs::write(header_path(dir), header.to_json_string())?;
+        let (dir, owns_dir) = self.dir.keep();
+        LevelFileTree::load(dir, owns_dir, self.hasher, n, lengths)
+    }
+}
+
+/// Tree finished by [`TreeBuilder`], kept as one file of nodes per level next to a `tree.json`
+/// header recording the hasher name, leaf count and level lengths. The level files stay open
+/// and proofs are read with one seek per level.
+pub struct LevelFileTree<H: Hasher> {
+    hasher: H,
+    dir: PathBuf,
+    owns_dir: bool,
+    leaf_count: usize,
+    tree_len: usize,
+    level_lengths: Vec<usize>,
+    files: Vec<Mutex<File>>,
+    root: Hash,
+}
+
+impl<H: Hasher> LevelFileTree<H> {
+    /// Reopen a tree that [`TreeBuilder`] finished in `dir`. Fails with
+    /// [`io::ErrorKind::InvalidInput`] if it was built with a hasher of another name, and with
+    /// [`io::ErrorKind::InvalidData`] if the header or a level file does not match the tree.
+    /// The directory is never deleted by [`Self::remove`].
+    pub fn open(dir: impl Into<PathBuf>, hasher: H) -> io::Result<Self> {
+        let dir = dir.into();
+        let header = fs::read_to_string(header_path(&dir))?;
+        let dump_error = |e: DumpError| invalid_data(e.to_string());
+        let header = Json::parse(&header).map_err(dump_error)?;
+        let name = header
+            .field("hasher")
+            .and_then(Json::as_str)
+            .map_err(dump_error)?;
+        if name != hasher.name() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Tree was built with hasher {:?}, got {:?}",
+                    name,
+                    hasher.name()
+                ),
+            ));
+        }
+        let leaf_count = header
+            .field("leafCount")
+            .and_then(Json::as_usize)
+            .map_err(dump_error)?;
+        let lengths = header
+            .field("levels")
+            .and_then(Json::as_array)
+            .and_then(|levels| levels.iter().map(Json::as_usize).collect())
+            .map_err(dump_error)?;
+        // a hostile header must not overflow the level lengths
+        if tree_len(leaf_count).is_none() || lengths != level_lengths(leaf_count) {
+            return Err(invalid_data(format!(
+                "Level lengths {:?} do not match {} leaves",
+                lengths, leaf_count
+            )));
+        }
+        Self::load(dir, false, hasher, leaf_count, lengths)
+    }
+
+    /// Open the level files, checking each holds `level_lengths` nodes, and read the root.
+    fn load(
+        dir: PathBuf,
+        owns_dir: bool,
+        hasher: H,
+        leaf_count: usize,
+        level_lengths: Vec<usize>,
+    ) -> io::Result<Self> {
+        let tree_len = tree_len(leaf_count)
+            .ok_or_else(|| invalid_data(format!("Invalid leaf count {}", leaf_count)))?;
+        let mut files = Vec::with_capacity(level_lengths.len());
+        for (level, &nodes) in level_lengths.iter().enumerate() {
+            let file = File::open(level_path(&dir, level))?;
+            let len = file.metadata()?.len();
+            if Some(len) != (nodes as u64).checked_mul(32) {
+                return Err(invalid_data(format!(
+                    "Level {} file has {} bytes, expected {} nodes",
+                    level, len, nodes
+                )));
+            }
+            files.push(Mutex::new(file));
+        }
+        let mut tree = Self {
+            hasher,
+            dir,
+            owns_dir,
+            leaf_count,
+            tree_len,
+            level_lengths,
+            files,
+            root: [0u8; 32],
+        };
+        tree.root = tree.read_node(0)?;
+        Ok(tree)
+    }
+
+    pub fn root(&self) -> &Hash {
+        &self.root
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Number of levels, including the root level.
+    pub fn levels(&self) -> usize {
+        self.files.len()
+    }
+
+    /// The level file for heap `level` (0 = root), holding the level's nodes right to left.
+    pub fn level_path(&self, level: usize) -> PathBuf {
+        level_path(&self.dir, level)
+    }
+
+    fn read_node(&self, tree_index: usize) -> io::Result<Hash> {
+        let level = (usize::BITS - 1 - (tree_index + 1).leading_zeros()) as usize;
+        let offset = self.level_lengths[level] - 1 - (tree_index + 1 - (1 << level));
+        let mut file = self.files[level]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        file.seek(SeekFrom::Start(offset as u64 * 32))?;
+        let mut node = [0u8; 32];
+        file.read_exact(&mut node)?;
+        Ok(node)
+    }
+
+    /// Proof for the leaf at 0-based `leaf_position`, the same as
+    /// [`crate::merkle::get_proof_for_leaf`] on the in-memory tree. Fails with
+    /// [`io::ErrorKind::InvalidInput`] if it is out of range.
+    pub fn proof(&self, leaf_position: usize) -> io::Result<Vec<Bytes>> {
+        let mut index = try_leaf_tree_index(self.tree_len, leaf_position)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut proof = Vec::new();
+        while index > 0 {
+            let sibling = if index.is_multiple_of(2) {
+                index - 1
+            } else {
+                index + 1
+            };
+            proof.push(self.read_node(sibling)?.to_vec());
+            index = (index - 1) / 2;
+        }
+        Ok(proof)
+    }
+
+    /// Multi-proof for the leaves at 0-based `leaf_positions`, the same as
+    /// [`crate::merkle::get_multi_proof_for_leaves`] on the in-memory tree. Only the leaves
+    /// and proof nodes are read. Fails with [`io::ErrorKind::InvalidInput`] on an
+    /// out-of-range or repeated position.
+    pub fn multi_proof(&self, leaf_positions: &[usize]) -> io::Result<MultiProof> {
+        let len = self.tree_len;
+        let invalid_input = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+        let indices = leaf_positions
+            .iter()
+            .map(|&p| try_leaf_tree_index(len, p))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid_input)?;
+        multi_proof_indices(len, indices)
+            .map_err(invalid_input)?
+            .fetch(|i| Ok(self.read_node(i)?.to_vec()))
+    }
+
+    /// Load the whole tree in the heap layout of [`crate::merkle::make_merkle_tree_bytes`].
+    pub fn to_tree(&self) -> io::Result<Vec<Bytes>> {
+        let mut tree = Vec::with_capacity(self.tree_len);
+        for level in 0..self.levels() {
+            let mut nodes = reader(&self.level_path(level), DEFAULT_BUILD_BUFFER)?
+                .collect::<io::Result<Vec<Hash>>>()?;
+            nodes.reverse();
+            tree.extend(nodes.iter().map(|n| n.to_vec()));
+        }
+        Ok(tree)
+    }
+
+    /// Delete the level files and header, and the directory if the builder created it.
+    pub fn remove(mut self) -> io::Result<()> {
+        let levels = self.levels();
+        self.files.clear();
+        for level in 0..levels {
+            fs::remove_file(self.level_path(level))?;
+        }
+        fs::remove_file(header_path(&self.dir))?;
+        if self.owns_dir {
+            fs::remove_dir(&self.dir)?;
+        }
+        Ok(())
+    }
+}
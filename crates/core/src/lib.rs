@@ -1,2 +1,43 @@
+pub mod abi;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod audit;
+#[cfg(feature = "cdc")]
+pub mod cdc;
+pub mod clock;
+pub mod composite;
+pub mod consistency;
+pub mod consts;
+pub mod cross_check;
+pub mod diff;
+pub mod dump;
+pub mod encoder;
+pub mod epoch;
+pub mod flat;
 pub mod hashes;
+pub mod incremental;
+pub mod js_verifier;
+pub mod leaf_io;
+pub mod manifest;
 pub mod merkle;
+pub mod migration;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod mmr;
+pub mod monitor;
+pub mod observe;
+pub mod profile;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+pub mod reconcile;
+pub mod rle;
+#[cfg(feature = "rlp")]
+pub mod rlp;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod simple;
+pub mod smt;
+pub mod standard;
+pub mod store;
+pub mod timestamp;
+pub mod tree;
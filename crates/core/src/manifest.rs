@@ -0,0 +1,487 @@
+//! Manifest trees over sets of named files (archive members or a directory tree, e.g. CI
+//! artifacts to attest), so members can be verified one at a time. Each leaf commits to a
+//! member's kind, path and content hash; members are sorted by path so the root does not
+//! depend on archive order. The first leaf is a header committing to the [`PathPolicy`], so
+//! the root also fixes how paths were canonicalized.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use sha3::{Digest, Keccak256};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::hashes::keccak256;
+use crate::merkle::{get_proof, make_merkle_tree_bytes, verify_proof, Bytes, Hash};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    File,
+    /// Content hash is the hash of the link target, which is not followed.
+    Symlink,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub path: String,
+    pub kind: EntryKind,
+    pub content_hash: Hash,
+}
+
+impl Entry {
+    pub fn file(path: impl Into<String>, contents: &[u8]) -> Self {
+        Self {
+            path: path.into(),
+            kind: EntryKind::File,
+            content_hash: keccak256(contents),
+        }
+    }
+
+    pub fn symlink(path: impl Into<String>, target: &str) -> Self {
+        Self {
+            path: path.into(),
+            kind: EntryKind::Symlink,
+            content_hash: keccak256(target.as_bytes()),
+        }
+    }
+
+    /// `keccak256(kind ‖ u32_be(path length) ‖ path ‖ content hash)`, with kind `0x00` for
+    /// files and `0x01` for symlinks.
+    pub fn leaf_hash(&self) -> Hash {
+        let tag = match self.kind {
+            EntryKind::File => 0u8,
+            EntryKind::Symlink => 1u8,
+        };
+        let mut data = Vec::with_capacity(1 + 4 + self.path.len() + 32);
+        data.push(tag);
+        data.extend_from_slice(&(self.path.len() as u32).to_be_bytes());
+        data.extend_from_slice(self.path.as_bytes());
+        data.extend_from_slice(&self.content_hash);
+        keccak256(&data)
+    }
+}
+
+/// Canonicalization applied to member paths before hashing, so that manifests built on
+/// different operating systems agree. The default leaves paths untouched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathPolicy {
+    /// Treat `\` as a separator and drop empty and `.` components.
+    pub normalize_separators: bool,
+    /// Unicode NFC, so precomposed and decomposed names (macOS vs. Linux) match.
+    pub unicode_nfc: bool,
+    /// Lowercase paths, for case-insensitive filesystems.
+    pub case_fold: bool,
+}
+
+impl PathPolicy {
+    /// All canonicalizations enabled.
+    pub fn portable() -> Self {
+        Self {
+            normalize_separators: true,
+            unicode_nfc: true,
+            case_fold: true,
+        }
+    }
+
+    pub fn apply(&self, path: &str) -> String {
+        let mut path = if self.normalize_separators {
+            path.replace('\\', "/")
+                .split('/')
+                .filter(|c| !c.is_empty() && *c != ".")
+                .collect::<Vec<_>>()
+                .join("/")
+        } else {
+            path.to_string()
+        };
+        if self.unicode_nfc {
+            path = path.nfc().collect();
+        }
+        if self.case_fold {
+            path = path.to_lowercase();
+        }
+        path
+    }
+
+    /// Flags byte recorded alongside the root: bit 0 separators, bit 1 NFC, bit 2 case folding.
+    pub fn to_byte(&self) -> u8 {
+        self.normalize_separators as u8
+            | (self.unicode_nfc as u8) << 1
+            | (self.case_fold as u8) << 2
+    }
+
+    /// Inverse of [`Self::to_byte`], or `None` if unknown bits are set.
+    pub fn from_byte(flags: u8) -> Option<Self> {
+        (flags < 8).then_some(Self {
+            normalize_separators: flags & 1 != 0,
+            unicode_nfc: flags & 2 != 0,
+            case_fold: flags & 4 != 0,
+        })
+    }
+
+    /// `keccak256(0x02 ‖ flags byte)`: the manifest's first leaf. The tag keeps it distinct
+    /// from entry leaves.
+    pub fn leaf_hash(&self) -> Hash {
+        keccak256(&[2u8, self.to_byte()])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ManifestError {
+    #[error("Expected at least one manifest entry")]
+    NoEntries,
+    /// Two entries have the same path after canonicalization.
+    #[error("Duplicate manifest path {0:?}")]
+    DuplicatePath(String),
+}
+
+pub struct Manifest {
+    entries: Vec<Entry>,
+    tree: Vec<Bytes>,
+    policy: PathPolicy,
+}
+
+impl Manifest {
+    pub fn new<F>(entries: Vec<Entry>, node_hash: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        Self::with_policy(entries, PathPolicy::default(), node_hash)
+    }
+
+    /// Like [`Manifest::new`], canonicalizing every path with `policy` first. Panics on no
+    /// entries or duplicate paths; see [`Manifest::try_with_policy`].
+    pub fn with_policy<F>(entries: Vec<Entry>, policy: PathPolicy, node_hash: F) -> Self
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        Self::try_with_policy(entries, policy, node_hash).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_policy<F>(
+        mut entries: Vec<Entry>,
+        policy: PathPolicy,
+        node_hash: F,
+    ) -> Result<Self, ManifestError>
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        if entries.is_empty() {
+            return Err(ManifestError::NoEntries);
+        }
+        for e in entries.iter_mut() {
+            e.path = policy.apply(&e.path);
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        if let Some(w) = entries.windows(2).find(|w| w[0].path == w[1].path) {
+            return Err(ManifestError::DuplicatePath(w[0].path.clone()));
+        }
+        let leaves = std::iter::once(policy.leaf_hash().to_vec())
+            .chain(entries.iter().map(|e| e.leaf_hash().to_vec()))
+            .collect();
+        let tree = make_merkle_tree_bytes(leaves, node_hash);
+        Ok(Self {
+            entries,
+            tree,
+            policy,
+        })
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.tree[0]
+    }
+
+    /// Entries sorted by path.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn tree(&self) -> &[Bytes] {
+        &self.tree
+    }
+
+    /// Path policy the manifest was built with; verifiers must canonicalize paths the same way.
+    pub fn policy(&self) -> PathPolicy {
+        self.policy
+    }
+
+    /// Proof of the policy header leaf, for [`verify_policy`].
+    pub fn policy_proof(&self) -> Vec<Bytes> {
+        get_proof(&self.tree, self.tree.len() - 1)
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Entry> {
+        self.position(path).map(|i| &self.entries[i])
+    }
+
+    /// Index of `path`, which is canonicalized with the manifest's policy first.
+    fn position(&self, path: &str) -> Option<usize> {
+        let path = self.policy.apply(path);
+        self.entries
+            .binary_search_by(|e| e.path.as_str().cmp(&path))
+            .ok()
+    }
+
+    pub fn proof(&self, path: &str) -> Option<Vec<Bytes>> {
+        self.position(path)
+            .map(|i| get_proof(&self.tree, self.tree.len() - 2 - i))
+    }
+}
+
+/// Manifest over the files and symlinks below `dir`; see [`read_dir_entries`].
+pub fn merkleize_dir<F>(dir: &Path, policy: PathPolicy, node_hash: F) -> io::Result<Manifest>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    let entries = read_dir_entries(dir)?;
+    if entries.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No files below {}", dir.display()),
+        ));
+    }
+    Manifest::try_with_policy(entries, policy, node_hash)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Walk `dir` recursively and return one entry per regular file and symlink, with paths
+/// relative to `dir` and `/`-separated. Symlinks are recorded by target and never followed;
+/// empty directories contribute nothing.
+pub fn read_dir_entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    walk_dir(dir, "", &mut entries)?;
+    Ok(entries)
+}
+
+fn walk_dir(dir: &Path, prefix: &str, entries: &mut Vec<Entry>) -> io::Result<()> {
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let name = item.file_name().into_string().map_err(|name| {
+            invalid(format!(
+                "Non-UTF-8 file name {:?} in {}",
+                name,
+                dir.display()
+            ))
+        })?;
+        let path = format!("{}{}", prefix, name);
+        let file_type = item.file_type()?;
+
+        if file_type.is_symlink() {
+            let target = fs::read_link(item.path())?;
+            let target = target
+                .to_str()
+                .ok_or_else(|| invalid(format!("Non-UTF-8 symlink target {:?}", target)))?;
+            entries.push(Entry::symlink(path, target));
+        } else if file_type.is_dir() {
+            walk_dir(&item.path(), &format!("{}/", path), entries)?;
+        } else if file_type.is_file() {
+            let mut hasher = Keccak256::new();
+            io::copy(&mut fs::File::open(item.path())?, &mut hasher)?;
+            entries.push(Entry {
+                path,
+                kind: EntryKind::File,
+                content_hash: hasher.finalize().into(),
+            });
+        } else {
+            return Err(invalid(format!("Unsupported file type at {}", path)));
+        }
+    }
+    Ok(())
+}
+
+/// Check that `entry` is a member of the manifest with the given root.
+pub fn verify_entry<F>(root: &[u8], entry: &Entry, proof: &[Bytes], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    verify_proof(root, &entry.leaf_hash(), proof, node_hash)
+}
+
+/// Check that the manifest with the given root was built with `policy`.
+pub fn verify_policy<F>(root: &[u8], policy: PathPolicy, proof: &[Bytes], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    verify_proof(root, &policy.leaf_hash(), proof, node_hash)
+}
+
+const BLOCK: usize = 512;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn cstr(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+fn utf8(bytes: &[u8]) -> io::Result<String> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| invalid(format!("Non-UTF-8 tar path {:?}", bytes)))
+}
+
+fn parse_size(field: &[u8]) -> io::Result<u64> {
+    if field[0] & 0x80 != 0 {
+        // GNU base-256 encoding; negative sizes and values past 64 bits are rejected
+        let digits = &field[1..];
+        let (high, low) = digits.split_at(digits.len().saturating_sub(8));
+        if field[0] != 0x80 || high.iter().any(|&b| b != 0) {
+            return Err(invalid("Tar size out of range".to_string()));
+        }
+        return Ok(low.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64));
+    }
+    let digits = cstr(field);
+    let digits = std::str::from_utf8(digits)
+        .map_err(|_| invalid("Invalid tar size field".to_string()))?
+        .trim();
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| invalid(format!("Invalid tar size {:?}", digits)))
+}
+
+/// Read a member's data (padded to the block size), feeding it to `sink`.
+fn read_data<R: Read>(reader: &mut R, size: u64, mut sink: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut remaining = size
+        .div_ceil(BLOCK as u64)
+        .checked_mul(BLOCK as u64)
+        .ok_or_else(|| invalid(format!("Tar size {} out of range", size)))?;
+    let mut unpadded = size;
+    let mut buf = [0u8; 8 * BLOCK];
+    while remaining > 0 {
+        let n = remaining.min(buf.len() as u64) as usize;
+        reader.read_exact(&mut buf[..n])?;
+        let take = unpadded.min(n as u64) as usize;
+        sink(&buf[..take]);
+        unpadded -= take as u64;
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+/// Parse `"<len> <key>=<value>\n"` records, where `len` counts the whole record.
+fn pax_records(data: &[u8]) -> io::Result<BTreeMap<String, String>> {
+    let mut records = BTreeMap::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let len: usize = match std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            Some(len) if len >= space + 2 && len <= rest.len() && rest[len - 1] == b'\n' => len,
+            _ => return Err(invalid("Malformed PAX record".to_string())),
+        };
+        let record = &rest[space + 1..len - 1];
+        if let Some(eq) = record.iter().position(|&b| b == b'=') {
+            records.insert(
+                String::from_utf8_lossy(&record[..eq]).into_owned(),
+                String::from_utf8_lossy(&record[eq + 1..]).into_owned(),
+            );
+        }
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+/// Read the regular files and symlinks of a tar archive (ustar, with GNU long names and PAX
+/// path overrides). Directories are skipped; hard links take the content of their target.
+pub fn read_tar<R: Read>(mut reader: R) -> io::Result<Vec<Entry>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut long_name: Option<String> = None;
+    let mut long_link: Option<String> = None;
+    let mut header = [0u8; BLOCK];
+
+    loop {
+        reader.read_exact(&mut header)?;
+        if header.iter().all(|&b| b == 0) {
+            return Ok(entries);
+        }
+
+        let expected = parse_size(&header[148..156])?;
+        let checksum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { b' ' } else { b } as u64)
+            .sum();
+        if checksum != expected {
+            return Err(invalid("Tar header checksum mismatch".to_string()));
+        }
+
+        let size = parse_size(&header[124..136])?;
+        let typeflag = header[156];
+
+        if matches!(typeflag, b'L' | b'K' | b'x' | b'g') {
+            let mut data = Vec::new();
+            read_data(&mut reader, size, |chunk| data.extend_from_slice(chunk))?;
+            match typeflag {
+                b'L' => long_name = Some(utf8(cstr(&data))?),
+                b'K' => long_link = Some(utf8(cstr(&data))?),
+                b'x' => {
+                    let records = pax_records(&data)?;
+                    if let Some(p) = records.get("path") {
+                        long_name = Some(p.clone());
+                    }
+                    if let Some(l) = records.get("linkpath") {
+                        long_link = Some(l.clone());
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let path = match long_name.take() {
+            Some(p) => p,
+            None => {
+                let name = cstr(&header[0..100]);
+                let prefix = cstr(&header[345..500]);
+                if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                    utf8(&[prefix, b"/", name].concat())?
+                } else {
+                    utf8(name)?
+                }
+            }
+        };
+        let link = match long_link.take() {
+            Some(l) => l,
+            None => utf8(cstr(&header[157..257]))?,
+        };
+
+        match typeflag {
+            b'0' | 0 | b'7' => {
+                let mut hasher = Keccak256::new();
+                read_data(&mut reader, size, |chunk| hasher.update(chunk))?;
+                entries.push(Entry {
+                    path,
+                    kind: EntryKind::File,
+                    content_hash: hasher.finalize().into(),
+                });
+            }
+            b'1' => {
+                read_data(&mut reader, size, |_| {})?;
+                let target = entries
+                    .iter()
+                    .rev()
+                    .find(|e| e.path == link)
+                    .ok_or_else(|| invalid(format!("Hard link to unknown member {:?}", link)))?;
+                entries.push(Entry {
+                    path,
+                    kind: target.kind,
+                    content_hash: target.content_hash,
+                });
+            }
+            b'2' => {
+                read_data(&mut reader, size, |_| {})?;
+                entries.push(Entry::symlink(path, &link));
+            }
+            b'5' => read_data(&mut reader, size, |_| {})?,
+            other => {
+                return Err(invalid(format!(
+                    "Unsupported tar entry type {:?} for {:?}",
+                    other as char, path
+                )))
+            }
+        }
+    }
+}
@@ -1,11 +1,21 @@
+use crate::consts::ZERO_HASH;
+
 /// Binary data (owned)
 pub type Bytes = Vec<u8>;
 
 /// Internal fixed-size hash (keccak-256 or SHA-256-sized)
 pub type Hash = [u8; 32];
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub struct MultiProof {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::nodes"))]
     pub leaves: Vec<Bytes>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::nodes"))]
     pub proof: Vec<Bytes>,
     pub proof_flags: Vec<bool>,
 }
@@ -20,6 +30,79 @@ impl MultiProof {
     }
 }
 
+/// Errors returned by the `try_*` functions. The panicking functions report the same
+/// conditions with the same messages.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MerkleError {
+    #[error("Expected non-zero number of leaves")]
+    EmptyTree,
+    #[error("Expected valid merkle node, got length {0}")]
+    InvalidNode(usize),
+    #[error("Expected leaf node at index {0}")]
+    NotALeaf(usize),
+    #[error("Cannot prove duplicated index")]
+    DuplicateIndex,
+    #[error("Invariant error")]
+    InvalidMultiProof,
+    #[error("node_hash must produce 32-byte hash")]
+    InvalidNodeHash,
+    #[error("Leaf position {position} out of range for {leaves} leaves")]
+    LeafOutOfRange { position: usize, leaves: usize },
+    #[error("No node at level {level}, position {position}")]
+    NodeOutOfRange { level: usize, position: usize },
+    #[error("Leaf equals an internal node hash of the tree (possible second-preimage forgery)")]
+    LeafIsInternalNode,
+    #[error("Leaf at position {0} is the zero hash")]
+    ZeroLeaf(usize),
+    #[error("Unsupported tree config: {0}")]
+    InvalidConfig(String),
+    #[error("Last proof node equals the proof padding")]
+    PaddingCollision,
+    #[error("Expected an odd number of tree nodes, got {0}")]
+    InvalidTreeLength(usize),
+    #[error("Expected trees of equal shape, got {old} and {new} nodes")]
+    ShapeMismatch { old: usize, new: usize },
+    #[error(transparent)]
+    TreeSize(#[from] TreeSizeError),
+}
+
+fn unwrap_or_panic<T>(result: Result<T, MerkleError>) -> T {
+    result.unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// A heap-layout tree has `2n - 1` nodes for `n >= 1` leaves.
+pub(crate) fn check_tree_len(tree_len: usize) -> Result<(), MerkleError> {
+    match tree_len {
+        0 => Err(MerkleError::EmptyTree),
+        n if n.is_multiple_of(2) => Err(MerkleError::InvalidTreeLength(n)),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_leaf_node(tree_len: usize, index: usize) -> Result<(), MerkleError> {
+    if is_leaf_node(index, tree_len) {
+        Ok(())
+    } else {
+        Err(MerkleError::NotALeaf(index))
+    }
+}
+
+fn check_merkle_node(node: &[u8]) -> Result<(), MerkleError> {
+    if is_valid_merkle_node(node) {
+        Ok(())
+    } else {
+        Err(MerkleError::InvalidNode(node.len()))
+    }
+}
+
+fn check_node_hash(parent: Bytes) -> Result<Hash, MerkleError> {
+    if parent.len() == 32 {
+        Ok(slice_to_hash(&parent))
+    } else {
+        Err(MerkleError::InvalidNodeHash)
+    }
+}
+
 pub fn is_valid_merkle_node(data: &[u8]) -> bool {
     data.len() == 32
 }
@@ -44,7 +127,7 @@ fn parent_index(index: usize) -> usize {
     (index - 1) / 2
 }
 fn sibling_index(index: usize) -> usize {
-    if index % 2 == 0 {
+    if index.is_multiple_of(2) {
         index - 1
     } else {
         index + 1
@@ -61,21 +144,6 @@ fn is_leaf_node(index: usize, tree_len: usize) -> bool {
     !is_internal_node(index, tree_len) && is_tree_node(index, tree_len)
 }
 
-fn assert_leaf_node(tree_len: usize, index: usize) {
-    assert!(
-        is_leaf_node(index, tree_len),
-        "Expected leaf node at index {}",
-        index
-    );
-}
-fn assert_merkle_node(node: &[u8]) {
-    assert!(
-        is_valid_merkle_node(node),
-        "Expected valid merkle node, got length {}",
-        node.len(),
-    );
-}
-
 /// Generic Merkle-tree builder: takes an iterator of leaves (owned values `T`) and a node-hash
 /// function that combines two children into a parent. Returns the flat vector representing the
 /// tree stored as a binary heap (root at index 0, leaves at the end).
@@ -101,25 +169,158 @@ where
     tree
 }
 
-pub fn get_proof(tree: &Vec<Bytes>, leaf_index: usize) -> Vec<Bytes> {
-    assert_leaf_node(tree.len(), leaf_index);
+pub fn get_proof(tree: &[Bytes], leaf_index: usize) -> Vec<Bytes> {
+    unwrap_or_panic(try_get_proof(tree, leaf_index))
+}
 
-    // Convert tree to fixed-size hashes for internal processing
-    let hash_tree: Vec<Hash> = tree.iter().map(|n| slice_to_hash(n.as_slice())).collect();
+/// Only the leaf and the siblings on its path are checked, so this is `O(log n)`.
+pub fn try_get_proof(tree: &[Bytes], leaf_index: usize) -> Result<Vec<Bytes>, MerkleError> {
+    check_leaf_node(tree.len(), leaf_index)?;
+    check_merkle_node(&tree[leaf_index])?;
+    let proof = proof_of(tree, leaf_index);
+    for n in proof.iter() {
+        check_merkle_node(n)?;
+    }
+    Ok(proof)
+}
 
-    let mut proof_hashes: Vec<Hash> = Vec::new();
-    let mut index = leaf_index;
+/// Proofs for every tree index in `leaf_indices`, in the same order. The proof paths are
+/// validated in one walk that stops where a path joins one already checked, so nodes shared
+/// by several proofs are checked once for the whole batch.
+pub fn get_proofs(tree: &[Bytes], leaf_indices: &[usize]) -> Vec<Vec<Bytes>> {
+    unwrap_or_panic(try_get_proofs(tree, leaf_indices))
+}
 
+pub fn try_get_proofs(
+    tree: &[Bytes],
+    leaf_indices: &[usize],
+) -> Result<Vec<Vec<Bytes>>, MerkleError> {
+    check_proof_batch(tree, leaf_indices)?;
+    Ok(leaf_indices.iter().map(|&i| proof_of(tree, i)).collect())
+}
+
+/// Like [`get_proofs`], splitting the batch across the available cores.
+pub fn get_proofs_parallel(tree: &[Bytes], leaf_indices: &[usize]) -> Vec<Vec<Bytes>> {
+    unwrap_or_panic(try_get_proofs_parallel(tree, leaf_indices))
+}
+
+pub fn try_get_proofs_parallel(
+    tree: &[Bytes],
+    leaf_indices: &[usize],
+) -> Result<Vec<Vec<Bytes>>, MerkleError> {
+    check_proof_batch(tree, leaf_indices)?;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = leaf_indices.len().div_ceil(threads).max(1);
+    Ok(std::thread::scope(|scope| {
+        let workers: Vec<_> = leaf_indices
+            .chunks(chunk)
+            .map(|indices| {
+                scope.spawn(move || {
+                    indices
+                        .iter()
+                        .map(|&i| proof_of(tree, i))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("Proof worker panicked"))
+            .collect()
+    }))
+}
+
+fn check_proof_batch(tree: &[Bytes], leaf_indices: &[usize]) -> Result<(), MerkleError> {
+    let mut walked = HashSet::new();
+    for &i in leaf_indices.iter() {
+        check_leaf_node(tree.len(), i)?;
+        check_merkle_node(&tree[i])?;
+        let mut index = i;
+        while index > 0 && walked.insert(index) {
+            let s = sibling_index(index);
+            if s < tree.len() {
+                check_merkle_node(&tree[s])?;
+            }
+            index = parent_index(index);
+        }
+    }
+    Ok(())
+}
+
+/// Siblings of `leaf_index` up to the root, read straight from the tree's nodes. The caller
+/// has checked the index and node lengths.
+pub(crate) fn proof_of<N: AsRef<[u8]>>(tree: &[N], leaf_index: usize) -> Vec<Bytes> {
+    let mut proof = Vec::new();
+    let mut index = leaf_index;
     while index > 0 {
         let s = sibling_index(index);
-        if s < hash_tree.len() {
-            proof_hashes.push(hash_tree[s]);
+        if s < tree.len() {
+            proof.push(tree[s].as_ref().to_vec());
         }
-
         index = parent_index(index);
     }
+    proof
+}
+
+/// Number of leaves of a tree with `tree_len` nodes.
+pub fn leaf_count(tree_len: usize) -> usize {
+    tree_len.div_ceil(2)
+}
+
+/// Flat tree index of the 0-based `leaf_position` (leaves are stored in reverse at the end).
+pub fn leaf_tree_index(tree_len: usize, leaf_position: usize) -> usize {
+    unwrap_or_panic(try_leaf_tree_index(tree_len, leaf_position))
+}
+
+pub fn try_leaf_tree_index(tree_len: usize, leaf_position: usize) -> Result<usize, MerkleError> {
+    let leaves = leaf_count(tree_len);
+    if leaf_position < leaves {
+        Ok(tree_len - 1 - leaf_position)
+    } else {
+        Err(MerkleError::LeafOutOfRange {
+            position: leaf_position,
+            leaves,
+        })
+    }
+}
+
+/// Flat tree index of the node at `position` within heap `level` (level 0 is the root), as
+/// laid out by [`crate::tree::MerkleTree::levels`].
+pub fn try_node_tree_index(
+    tree_len: usize,
+    level: usize,
+    position: usize,
+) -> Result<usize, MerkleError> {
+    if level < usize::BITS as usize - 1 {
+        let start = (1usize << level) - 1;
+        // a level holds at most 2^level nodes
+        if position <= start && start + position < tree_len {
+            return Ok(start + position);
+        }
+    }
+    Err(MerkleError::NodeOutOfRange { level, position })
+}
+
+/// Inverse of [`leaf_tree_index`].
+pub fn leaf_position(tree_len: usize, tree_index: usize) -> usize {
+    unwrap_or_panic(try_leaf_position(tree_len, tree_index))
+}
+
+pub fn try_leaf_position(tree_len: usize, tree_index: usize) -> Result<usize, MerkleError> {
+    check_leaf_node(tree_len, tree_index)?;
+    Ok(tree_len - 1 - tree_index)
+}
+
+/// Like [`get_proof`], addressed by 0-based leaf position instead of flat tree index.
+pub fn get_proof_for_leaf(tree: &[Bytes], leaf_position: usize) -> Vec<Bytes> {
+    unwrap_or_panic(try_get_proof_for_leaf(tree, leaf_position))
+}
 
-    proof_hashes.iter().map(|h| hash_to_vec(h)).collect()
+pub fn try_get_proof_for_leaf(
+    tree: &[Bytes],
+    leaf_position: usize,
+) -> Result<Vec<Bytes>, MerkleError> {
+    try_get_proof(tree, try_leaf_tree_index(tree.len(), leaf_position)?)
 }
 
 /// Process a standard single-proof: start from `leaf` and apply the `node_hash` reductions
@@ -129,10 +330,20 @@ pub fn process_proof<F>(leaf: &[u8], proof: &[Bytes], node_hash: F) -> Bytes
 where
     F: Fn(&[u8], &[u8]) -> Bytes,
 {
-    // assert valid merkle node for leaf and each proof
-    assert_merkle_node(leaf);
+    unwrap_or_panic(try_process_proof(leaf, proof, node_hash))
+}
+
+pub fn try_process_proof<F>(
+    leaf: &[u8],
+    proof: &[Bytes],
+    node_hash: F,
+) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    check_merkle_node(leaf)?;
     for p in proof.iter() {
-        assert_merkle_node(&p);
+        check_merkle_node(p)?;
     }
     // Work with fixed-size `Hash` internally to avoid heap allocations per node
     let mut computed: Hash = slice_to_hash(leaf);
@@ -144,241 +355,1579 @@ where
         } else {
             node_hash(&p_hash[..], &computed[..])
         };
-        assert!(
-            parent_bytes.len() == 32,
-            "node_hash must produce 32-byte hash"
-        );
-        computed = slice_to_hash(&parent_bytes);
+        computed = check_node_hash(parent_bytes)?;
     }
 
-    hash_to_vec(&computed)
+    Ok(hash_to_vec(&computed))
 }
 
-pub fn get_multi_proof(tree: &Vec<Bytes>, mut indices: Vec<usize>) -> MultiProof {
-    for &i in indices.iter() {
-        assert_leaf_node(tree.len(), i);
-    }
-    indices.sort_by(|a, b| b.cmp(a));
-
-    for i in 1..indices.len() {
-        assert!(
-            indices[i] != indices[i - 1],
-            "Cannot prove duplicated index"
-        );
-    }
+/// Side of the path a proof sibling is combined on, for positional (unsorted) pair hashing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
 
-    // Convert tree to fixed-size hashes for internal processing
-    let hash_tree: Vec<Hash> = tree.iter().map(|n| slice_to_hash(n.as_slice())).collect();
+/// Most leaves a [`PositionalLayout`] indexes without overflowing its `2n - 1` node indices.
+/// Leaf counts read from proofs are checked against it before building a layout.
+pub(crate) const MAX_POSITIONAL_LEAVES: usize = usize::MAX / 2;
 
-    let mut stack = indices.clone(); // copy
-    let mut proof_hashes: Vec<Hash> = Vec::new();
-    let mut proof_flags: Vec<bool> = Vec::new();
+/// Shape of a positional tree: leaves are paired left to right and the last node of an odd
+/// layer is carried up unchanged, which gives the RFC 6962 split at the largest power of two
+/// below the leaf count. Only the nodes formed at each layer are stored, root layer first.
+#[derive(Clone, Debug)]
+pub(crate) struct PositionalLayout {
+    /// Nodes per layer including carried ones, leaves first.
+    widths: Vec<usize>,
+    /// Flat index of the first node formed at each layer.
+    offsets: Vec<usize>,
+}
 
-    while !stack.is_empty() && stack[0] > 0 {
-        let j = stack.remove(0); // take from the beginning
-        let s = sibling_index(j);
-        let p = parent_index(j);
+impl PositionalLayout {
+    pub(crate) fn new(leaf_count: usize) -> Self {
+        let mut widths = vec![leaf_count];
+        while let Some(&w) = widths.last().filter(|&&w| w > 1) {
+            widths.push(w.div_ceil(2));
+        }
+        let mut offsets = vec![0; widths.len()];
+        let mut offset = 0;
+        for layer in (0..widths.len()).rev() {
+            offsets[layer] = offset;
+            offset += Self::formed_at(&widths, layer);
+        }
+        Self { widths, offsets }
+    }
 
-        if !stack.is_empty() && s == stack[0] {
-            proof_flags.push(true);
-            stack.remove(0); // consume from the stack
+    fn formed_at(widths: &[usize], layer: usize) -> usize {
+        if layer == 0 {
+            widths[0]
         } else {
-            proof_flags.push(false);
-            proof_hashes.push(hash_tree[s]);
+            widths[layer - 1] / 2
         }
-        stack.push(p);
     }
 
-    if indices.is_empty() {
-        proof_hashes.push(hash_tree[0]);
+    /// Index of the root layer; 0 for a single leaf.
+    pub(crate) fn depth(&self) -> usize {
+        self.widths.len() - 1
+    }
+
+    /// Number of nodes hashed at `layer` (the leaves for layer 0).
+    pub(crate) fn formed(&self, layer: usize) -> usize {
+        Self::formed_at(&self.widths, layer)
     }
 
-    let leaves_hashes: Vec<Hash> = indices.iter().map(|&i| hash_tree[i]).collect();
+    /// Flat index of the first node formed at `layer`.
+    pub(crate) fn offset(&self, layer: usize) -> usize {
+        self.offsets[layer]
+    }
 
-    let leaves: Vec<Bytes> = leaves_hashes.iter().map(|h| hash_to_vec(h)).collect();
-    let proof: Vec<Bytes> = proof_hashes.iter().map(|h| hash_to_vec(h)).collect();
+    /// Flat index of node `position` of `layer`, following carried nodes down to where they
+    /// were formed.
+    pub(crate) fn index(&self, mut layer: usize, mut position: usize) -> usize {
+        while position >= self.formed(layer) {
+            layer -= 1;
+            position = self.widths[layer] - 1;
+        }
+        self.offsets[layer] + position
+    }
 
-    MultiProof::new(leaves, proof, proof_flags)
+    /// Sibling of node `position` of `layer` and its side, or `None` if the node is carried
+    /// up unpaired.
+    pub(crate) fn sibling(&self, layer: usize, position: usize) -> Option<(Side, usize)> {
+        if !position.is_multiple_of(2) {
+            Some((Side::Left, position - 1))
+        } else if position + 1 < self.widths[layer] {
+            Some((Side::Right, position + 1))
+        } else {
+            None
+        }
+    }
 }
 
-use std::collections::VecDeque;
+/// Like [`make_merkle_tree_bytes`], but for positional (unsorted) pair hashing: `node_hash`
+/// receives each pair in leaf order. Leaves are paired left to right and the last node of an
+/// odd level is carried up unhashed, so the root is the RFC 6962 Merkle tree hash
+/// ([`crate::consistency::log_root`]) and matches merkletreejs without `duplicateOdd`:
+/// `H(H(l0 ‖ l1) ‖ l2)` for three leaves.
+///
+/// The result holds the `2n - 1` nodes level by level from the root down, each level in leaf
+/// order and without the carried nodes, so the leaves are the last `n` entries in order.
+pub fn make_positional_merkle_tree_bytes<F>(leaves: Vec<Bytes>, node_hash: F) -> Vec<Bytes>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_make_positional_merkle_tree_bytes(leaves, node_hash))
+}
 
-/// Reconstruct the Merkle root from a multi-proof. Panics with an "Invariant error" message
-/// if the provided proof is malformed.
-pub fn process_multi_proof<F>(mp: &MultiProof, node_hash: F) -> Bytes
+pub fn try_make_positional_merkle_tree_bytes<F>(
+    leaves: Vec<Bytes>,
+    node_hash: F,
+) -> Result<Vec<Bytes>, MerkleError>
 where
     F: Fn(&[u8], &[u8]) -> Bytes,
 {
-    // Validate basic compatibility similar to the TypeScript implementation
-    let required_proofs = mp.proof_flags.iter().filter(|&&b| !b).count();
-    if mp.proof.len() < required_proofs
-        || (mp.leaves.len() + mp.proof.len()) != (mp.proof_flags.len() + 1)
-    {
-        panic!("Invariant error");
+    if leaves.is_empty() {
+        return Err(MerkleError::EmptyTree);
     }
-    // Convert MultiProof buffers to fixed-size hashes for internal processing
-    let mut stack: VecDeque<Hash> = VecDeque::from(
-        mp.leaves
-            .iter()
-            .map(|l| slice_to_hash(l))
-            .collect::<Vec<_>>(),
-    );
-    let mut proof: VecDeque<Hash> = VecDeque::from(
-        mp.proof
-            .iter()
-            .map(|p| slice_to_hash(p))
-            .collect::<Vec<_>>(),
-    );
-
-    for &flag in mp.proof_flags.iter() {
-        let a = stack
-            .pop_front()
-            .unwrap_or_else(|| panic!("Invariant error"));
-        let b = if flag {
-            stack
-                .pop_front()
-                .unwrap_or_else(|| panic!("Invariant error"))
-        } else {
-            proof
-                .pop_front()
-                .unwrap_or_else(|| panic!("Invariant error"))
-        };
-
-        let parent_bytes = node_hash(&a[..], &b[..]);
-        assert!(
-            parent_bytes.len() == 32,
-            "node_hash must produce 32-byte hash"
-        );
-        stack.push_back(slice_to_hash(&parent_bytes));
+    for l in leaves.iter() {
+        check_merkle_node(l)?;
     }
-
-    if stack.len() + proof.len() != 1 {
-        panic!("Invariant error");
+    let layout = PositionalLayout::new(leaves.len());
+    let mut tree = vec![Vec::new(); 2 * leaves.len() - 1];
+    let first_leaf = layout.offset(0);
+    for (i, leaf) in leaves.into_iter().enumerate() {
+        tree[first_leaf + i] = leaf;
     }
-
-    if !stack.is_empty() {
-        hash_to_vec(&stack.pop_front().unwrap())
-    } else {
-        hash_to_vec(&proof.pop_front().unwrap())
+    for layer in 1..=layout.depth() {
+        for position in 0..layout.formed(layer) {
+            let left = &tree[layout.index(layer - 1, 2 * position)];
+            let right = &tree[layout.index(layer - 1, 2 * position + 1)];
+            let parent = check_node_hash(node_hash(left, right))?;
+            tree[layout.offset(layer) + position] = hash_to_vec(&parent);
+        }
     }
+    Ok(tree)
 }
 
-/// Convenience helper for byte-oriented trees: validates leaf size and delegates to generic constructor.
-pub fn make_merkle_tree_bytes<F>(leaves: Vec<Bytes>, node_hash: F) -> Vec<Bytes>
+/// Check every formed node of a [`make_positional_merkle_tree_bytes`] tree against its
+/// children.
+pub fn is_valid_positional_merkle_tree<F>(tree: &[Bytes], node_hash: F) -> bool
 where
     F: Fn(&[u8], &[u8]) -> Bytes,
 {
-    assert!(!leaves.is_empty(), "Expected non-zero number of leaves");
-    for l in leaves.iter() {
-        assert_merkle_node(l);
+    if tree.len().is_multiple_of(2) || !tree.iter().all(|n| is_valid_merkle_node(n)) {
+        return false;
     }
+    let layout = PositionalLayout::new(leaf_count(tree.len()));
+    (1..=layout.depth()).all(|layer| {
+        (0..layout.formed(layer)).all(|position| {
+            let left = &tree[layout.index(layer - 1, 2 * position)];
+            let right = &tree[layout.index(layer - 1, 2 * position + 1)];
+            node_hash(left, right) == tree[layout.offset(layer) + position]
+        })
+    })
+}
 
-    // Convert input leaves to fixed-size `Hash` arrays to avoid per-node heap allocations
-    let hash_leaves: Vec<Hash> = leaves.iter().map(|l| slice_to_hash(l.as_slice())).collect();
-    // internal builder that works with `Hash`
-    fn build_hash_tree<F2>(leaves: Vec<Hash>, node_hash: F2) -> Vec<Hash>
-    where
-        F2: Fn(&[u8], &[u8]) -> Bytes,
-    {
-        assert!(!leaves.is_empty(), "Expected non-zero number of leaves");
-
-        let mut tree = vec![leaves[0]; 2 * leaves.len() - 1];
-        let tree_len = tree.len();
+/// Proof for the 0-based `leaf_position` of a [`make_positional_merkle_tree_bytes`] tree,
+/// recording the side of each sibling. Levels where the node is carried up add no entry.
+pub fn get_positional_proof(tree: &[Bytes], leaf_position: usize) -> Vec<(Side, Bytes)> {
+    unwrap_or_panic(try_get_positional_proof(tree, leaf_position))
+}
 
-        for (i, leaf) in leaves.iter().enumerate() {
-            tree[tree_len - 1 - i] = *leaf;
+pub fn try_get_positional_proof(
+    tree: &[Bytes],
+    leaf_position: usize,
+) -> Result<Vec<(Side, Bytes)>, MerkleError> {
+    let leaves = leaf_count(tree.len());
+    if leaf_position >= leaves {
+        return Err(MerkleError::LeafOutOfRange {
+            position: leaf_position,
+            leaves,
+        });
+    }
+    let layout = PositionalLayout::new(leaves);
+    check_merkle_node(&tree[layout.offset(0) + leaf_position])?;
+    let mut proof = Vec::with_capacity(layout.depth());
+    let mut position = leaf_position;
+    for layer in 0..layout.depth() {
+        if let Some((side, sibling)) = layout.sibling(layer, position) {
+            let node = &tree[layout.index(layer, sibling)];
+            check_merkle_node(node)?;
+            proof.push((side, node.clone()));
         }
-        for i in (0..(tree_len - leaves.len())).rev() {
-            let left = tree[left_child_index(i)];
-            let right = tree[right_child_index(i)];
-            let parent_bytes = node_hash(&left[..], &right[..]);
-            assert!(
-                parent_bytes.len() == 32,
-                "node_hash must produce 32-byte hash"
-            );
-            tree[i] = slice_to_hash(&parent_bytes);
+        position /= 2;
+    }
+    Ok(proof)
+}
+
+/// Sibling sides of a [`get_positional_proof`] proof of `leaf_position` in a tree of
+/// `leaf_count` leaves. The sides encode the position, so a verifier that knows the leaf count
+/// checks a claimed position by comparing them with the proof's. Panics on an out-of-range
+/// position.
+pub fn positional_proof_sides(leaf_count: usize, leaf_position: usize) -> Vec<Side> {
+    assert!(
+        leaf_position < leaf_count,
+        "Leaf position {} out of range for {} leaves",
+        leaf_position,
+        leaf_count
+    );
+    let layout = PositionalLayout::new(leaf_count);
+    let mut position = leaf_position;
+    let mut sides = Vec::with_capacity(layout.depth());
+    for layer in 0..layout.depth() {
+        if let Some((side, _)) = layout.sibling(layer, position) {
+            sides.push(side);
         }
+        position /= 2;
+    }
+    sides
+}
+
+/// Process a [`get_positional_proof`] proof, passing each pair to `node_hash` in leaf order.
+pub fn process_positional_proof<F>(leaf: &[u8], proof: &[(Side, Bytes)], node_hash: F) -> Bytes
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_process_positional_proof(leaf, proof, node_hash))
+}
+
+pub fn try_process_positional_proof<F>(
+    leaf: &[u8],
+    proof: &[(Side, Bytes)],
+    node_hash: F,
+) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    check_merkle_node(leaf)?;
+    for (_, p) in proof.iter() {
+        check_merkle_node(p)?;
+    }
+    let mut computed: Hash = slice_to_hash(leaf);
 
-        tree
+    for (side, p) in proof.iter() {
+        let parent_bytes = match side {
+            Side::Left => node_hash(p, &computed[..]),
+            Side::Right => node_hash(&computed[..], p),
+        };
+        computed = check_node_hash(parent_bytes)?;
     }
 
-    let built: Vec<Hash> = build_hash_tree(hash_leaves, node_hash);
-    // Convert back to Vec<Bytes> for existing public API
-    built.iter().map(|h| hash_to_vec(h)).collect()
+    Ok(hash_to_vec(&computed))
 }
 
-pub fn is_valid_merkle_tree<F>(tree: &Vec<Bytes>, node_hash: F) -> bool
+/// Positional counterpart of [`verify_proof`].
+pub fn verify_positional_proof<F>(
+    root: &[u8],
+    leaf: &[u8],
+    proof: &[(Side, Bytes)],
+    node_hash: F,
+) -> bool
 where
     F: Fn(&[u8], &[u8]) -> Bytes,
 {
-    // Convert to fixed-size hashes for internal checks
-    for n in tree.iter() {
-        if !is_valid_merkle_node(n) {
-            return false;
+    try_process_positional_proof(leaf, proof, node_hash)
+        .is_ok_and(|computed| constant_time_eq(&computed, root))
+}
+
+/// Multiproof of a positional tree: the leaves at ascending `positions`, and the siblings that
+/// cannot be computed from them layer by layer from the leaves up, left to right. The sides
+/// follow from the positions and `leaf_count`, so there are no flags.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct PositionalMultiProof {
+    pub leaf_count: usize,
+    pub positions: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::nodes"))]
+    pub leaves: Vec<Bytes>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::nodes"))]
+    pub proof: Vec<Bytes>,
+}
+
+/// Fold the ascending known nodes `(position, value)` of the leaf layer up to the root.
+/// `sibling(layer, position)` supplies, in proof order, each sibling that is not known and
+/// `join(left, right)` each parent. `None` if nothing is known.
+fn walk_positional_multi_proof<T, E>(
+    layout: &PositionalLayout,
+    mut known: Vec<(usize, T)>,
+    mut sibling: impl FnMut(usize, usize) -> Result<T, E>,
+    mut join: impl FnMut(T, T) -> T,
+) -> Result<Option<T>, E> {
+    for layer in 0..layout.depth() {
+        let mut next = Vec::with_capacity(known.len());
+        let mut nodes = known.into_iter().peekable();
+        while let Some((position, value)) = nodes.next() {
+            let parent = match layout.sibling(layer, position) {
+                None => value,
+                Some((Side::Right, s)) if nodes.peek().is_some_and(|&(p, _)| p == s) => {
+                    let (_, right) = nodes.next().expect("Peeked node");
+                    join(value, right)
+                }
+                Some((Side::Right, s)) => join(value, sibling(layer, s)?),
+                Some((Side::Left, s)) => join(sibling(layer, s)?, value),
+            };
+            next.push((position / 2, parent));
         }
+        known = next;
     }
-    let hash_tree: Vec<Hash> = tree.iter().map(|n| slice_to_hash(n)).collect();
+    Ok(known.pop().map(|(_, value)| value))
+}
 
-    for (i, node_hash_val) in hash_tree.iter().enumerate() {
-        let l = left_child_index(i);
-        let r = right_child_index(i);
+/// Multiproof of the leaves at `leaf_positions` of a positional tree, in any order. With no
+/// positions the proof is just the root, as for [`get_multi_proof`].
+pub fn get_positional_multi_proof(
+    tree: &[Bytes],
+    leaf_positions: &[usize],
+) -> PositionalMultiProof {
+    unwrap_or_panic(try_get_positional_multi_proof(tree, leaf_positions))
+}
 
-        if r >= hash_tree.len() {
-            if l < hash_tree.len() {
-                return false;
-            }
-        } else {
-            let expected_node_bytes = node_hash(&hash_tree[l][..], &hash_tree[r][..]);
-            if expected_node_bytes.len() != 32 {
-                return false;
-            }
-            let expected_hash = slice_to_hash(&expected_node_bytes);
-            if *node_hash_val != expected_hash {
-                return false;
-            }
-        }
+pub fn try_get_positional_multi_proof(
+    tree: &[Bytes],
+    leaf_positions: &[usize],
+) -> Result<PositionalMultiProof, MerkleError> {
+    if tree.is_empty() {
+        return Err(MerkleError::EmptyTree);
+    }
+    let leaves = leaf_count(tree.len());
+    let mut positions = leaf_positions.to_vec();
+    positions.sort_unstable();
+    if positions.windows(2).any(|w| w[0] == w[1]) {
+        return Err(MerkleError::DuplicateIndex);
+    }
+    if let Some(&position) = positions.last().filter(|&&p| p >= leaves) {
+        return Err(MerkleError::LeafOutOfRange { position, leaves });
+    }
+    let layout = PositionalLayout::new(leaves);
+    let node = |layer: usize, position: usize| -> Result<Bytes, MerkleError> {
+        let node = &tree[layout.index(layer, position)];
+        check_merkle_node(node)?;
+        Ok(node.clone())
+    };
+    let mut proof = Vec::new();
+    walk_positional_multi_proof(
+        &layout,
+        positions.iter().map(|&p| (p, ())).collect(),
+        |layer, s| node(layer, s).map(|n| proof.push(n)),
+        |_, _| (),
+    )?;
+    if positions.is_empty() {
+        proof.push(node(layout.depth(), 0)?);
+    }
+    Ok(PositionalMultiProof {
+        leaf_count: leaves,
+        leaves: positions
+            .iter()
+            .map(|&p| node(0, p))
+            .collect::<Result<_, _>>()?,
+        positions,
+        proof,
+    })
+}
+
+/// Reconstruct the root from a [`get_positional_multi_proof`] proof, passing each pair to
+/// `node_hash` in leaf order. Panics if the proof is malformed.
+pub fn process_positional_multi_proof<F>(mp: &PositionalMultiProof, node_hash: F) -> Bytes
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_process_positional_multi_proof(mp, node_hash))
+}
+
+/// Fails with [`MerkleError::InvalidMultiProof`] unless the positions are ascending and in
+/// range, there is one leaf per position, the proof has at most one node per position and
+/// level and every proof node is used.
+pub fn try_process_positional_multi_proof<F>(
+    mp: &PositionalMultiProof,
+    node_hash: F,
+) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    for n in mp.leaves.iter().chain(mp.proof.iter()) {
+        check_merkle_node(n)?;
     }
+    if mp.leaf_count == 0
+        || mp.leaf_count > MAX_POSITIONAL_LEAVES
+        || mp.positions.len() != mp.leaves.len()
+        || mp.positions.windows(2).any(|w| w[0] >= w[1])
+        || mp.positions.last().is_some_and(|&p| p >= mp.leaf_count)
+    {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+    if mp.positions.is_empty() {
+        return match &mp.proof[..] {
+            [root] => Ok(root.clone()),
+            _ => Err(MerkleError::InvalidMultiProof),
+        };
+    }
+    let depth = mp.leaf_count.next_power_of_two().ilog2() as usize;
+    if mp.proof.len() > mp.positions.len().saturating_mul(depth) {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+    let layout = PositionalLayout::new(mp.leaf_count);
+    let mut proof = mp.proof.iter();
+    let root = walk_positional_multi_proof(
+        &layout,
+        mp.positions
+            .iter()
+            .copied()
+            .zip(mp.leaves.iter().cloned())
+            .collect(),
+        |_, _| proof.next().cloned().ok_or(MerkleError::InvalidMultiProof),
+        |left, right| node_hash(&left, &right),
+    )?
+    .expect("Positions are not empty");
+    if proof.next().is_some() {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+    Ok(root)
+}
 
-    hash_tree.len() > 0
+/// Whether `mp` recomputes `root`; malformed proofs verify as `false`.
+pub fn verify_positional_multi_proof<F>(
+    root: &[u8],
+    mp: &PositionalMultiProof,
+    node_hash: F,
+) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    try_process_positional_multi_proof(mp, node_hash)
+        .is_ok_and(|computed| constant_time_eq(&computed, root))
 }
 
-pub fn render_merkle_tree(tree: &Vec<Bytes>) -> String {
+/// Filler node used by [`get_padded_proof`], `keccak256("merklerust-proof-padding-v1")`.
+/// [`process_padded_proof`] drops it from the end of a proof, so padded proofs carry no length.
+pub const PROOF_PADDING: Hash = [
+    0x02, 0x4c, 0xa2, 0x48, 0x91, 0x43, 0xa6, 0x35, 0xfe, 0x52, 0x48, 0x21, 0x40, 0x53, 0xc0, 0xb7,
+    0xda, 0x19, 0xc2, 0x96, 0x42, 0x6b, 0xf3, 0x23, 0x00, 0x43, 0x7b, 0x1e, 0xf5, 0xc7, 0xb7, 0x32,
+];
+
+/// Depth of the deepest leaf of a tree with `tree_len` nodes, i.e. the longest proof length.
+pub fn max_proof_len(tree_len: usize) -> usize {
     assert!(
-        !tree.is_empty(),
+        tree_len > 0,
         "Expected non-zero number of nodes in merkle tree"
     );
+    tree_len.ilog2() as usize
+}
 
-    let mut stack: Vec<(usize, Vec<usize>)> = vec![(0, vec![])];
+/// Unpadded proof length of the node at tree index `index`, i.e. its depth.
+pub fn proof_len(index: usize) -> usize {
+    (index + 1).ilog2() as usize
+}
 
-    let mut lines: Vec<String> = Vec::new();
+/// Like [`get_proof`], but pads the proof with [`PROOF_PADDING`] up to [`max_proof_len`], so
+/// every proof of the tree has the same length and does not reveal the leaf's depth. Panics
+/// if the last sibling is [`PROOF_PADDING`] itself, which a verifier could not tell apart
+/// from padding.
+pub fn get_padded_proof(tree: &[Bytes], leaf_index: usize) -> Vec<Bytes> {
+    unwrap_or_panic(try_get_padded_proof(tree, leaf_index))
+}
 
-    while let Some((i, path)) = stack.pop() {
-        let mut line = String::new();
+pub fn try_get_padded_proof(tree: &[Bytes], leaf_index: usize) -> Result<Vec<Bytes>, MerkleError> {
+    let mut proof = try_get_proof(tree, leaf_index)?;
+    if proof.last().is_some_and(|node| node[..] == PROOF_PADDING) {
+        return Err(MerkleError::PaddingCollision);
+    }
+    proof.resize(max_proof_len(tree.len()), hash_to_vec(&PROOF_PADDING));
+    Ok(proof)
+}
 
-        for p in path.iter().take(path.len().saturating_sub(1)) {
-            line.push_str(if *p == 0 { "   " } else { "│  " });
-        }
+/// Like [`process_proof`] over a [`get_padded_proof`] proof, with the trailing
+/// [`PROOF_PADDING`] nodes dropped first.
+pub fn process_padded_proof<F>(leaf: &[u8], proof: &[Bytes], node_hash: F) -> Bytes
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_process_padded_proof(leaf, proof, node_hash))
+}
 
-        if let Some(last) = path.last() {
-            line.push_str(if *last == 0 { "└─ " } else { "├─ " });
-        }
+pub fn try_process_padded_proof<F>(
+    leaf: &[u8],
+    proof: &[Bytes],
+    node_hash: F,
+) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    for node in proof.iter() {
+        check_merkle_node(node)?;
+    }
+    let len = proof
+        .iter()
+        .rposition(|node| node.as_slice() != PROOF_PADDING)
+        .map_or(0, |last| last + 1);
+    try_process_proof(leaf, &proof[..len], node_hash)
+}
 
-        line.push_str(&format!("{}) {:?}", i, tree[i]));
-        lines.push(line);
+/// Check a [`get_padded_proof`] proof of `leaf` against `root`. Malformed proofs verify as
+/// `false`.
+pub fn verify_padded_proof<F>(root: &[u8], leaf: &[u8], proof: &[Bytes], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    try_process_padded_proof(leaf, proof, node_hash)
+        .is_ok_and(|computed| constant_time_eq(&computed, root))
+}
 
-        if right_child_index(i) < tree.len() {
-            stack.push((right_child_index(i), {
-                let mut new_path = path.clone();
-                new_path.push(0);
-                new_path
-            }));
-            stack.push((left_child_index(i), {
-                let mut new_path = path.clone();
-                new_path.push(1);
-                new_path
-            }));
-        }
+/// Compare two byte strings in time that depends only on their lengths, for checking a
+/// computed root against an expected one.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
     }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
 
-    lines.join("\n")
+/// Check that `proof` proves `leaf` under `root`, comparing roots in constant time. Malformed
+/// input (nodes that are not 32 bytes) verifies as `false` instead of panicking.
+pub fn verify_proof<F>(root: &[u8], leaf: &[u8], proof: &[Bytes], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    try_process_proof(leaf, proof, node_hash)
+        .is_ok_and(|computed| constant_time_eq(&computed, root))
+}
+
+/// [`verify_proof`] for each `(leaf, proof)` against the same `root`, in order.
+pub fn verify_proofs_batch<L, P, F>(root: &[u8], items: &[(L, P)], node_hash: F) -> Vec<bool>
+where
+    L: AsRef<[u8]>,
+    P: AsRef<[Bytes]>,
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    items
+        .iter()
+        .map(|(leaf, proof)| verify_proof(root, leaf.as_ref(), proof.as_ref(), &node_hash))
+        .collect()
+}
+
+/// Whether every item of the batch verifies, stopping at the first that does not.
+pub fn verify_all_proofs<L, P, F>(root: &[u8], items: &[(L, P)], node_hash: F) -> bool
+where
+    L: AsRef<[u8]>,
+    P: AsRef<[Bytes]>,
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    items
+        .iter()
+        .all(|(leaf, proof)| verify_proof(root, leaf.as_ref(), proof.as_ref(), &node_hash))
+}
+
+/// Like [`verify_proofs_batch`], splitting the batch across the available cores.
+pub fn verify_proofs_batch_parallel<L, P, F>(
+    root: &[u8],
+    items: &[(L, P)],
+    node_hash: F,
+) -> Vec<bool>
+where
+    L: AsRef<[u8]> + Sync,
+    P: AsRef<[Bytes]> + Sync,
+    F: Fn(&[u8], &[u8]) -> Bytes + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = items.len().div_ceil(threads).max(1);
+    let node_hash = &node_hash;
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = items
+            .chunks(chunk)
+            .map(|items| scope.spawn(move || verify_proofs_batch(root, items, node_hash)))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("Verification worker panicked"))
+            .collect()
+    })
+}
+
+/// Bind every leaf to `context` with [`crate::hashes::context_leaf_hash`] before building a
+/// tree, so its proofs only verify through [`verify_proof_in_context`] with the same context
+/// and cannot be replayed in another one.
+pub fn bind_leaves_to_context(leaves: &[Bytes], context: &[u8]) -> Vec<Bytes> {
+    leaves
+        .iter()
+        .map(|l| crate::hashes::context_leaf_hash(context, l).to_vec())
+        .collect()
+}
+
+/// Like [`verify_proof`] for a tree built over [`bind_leaves_to_context`] leaves: `leaf` is the
+/// unbound leaf and is mixed with `context` before the proof is processed.
+pub fn verify_proof_in_context<F>(
+    root: &[u8],
+    leaf: &[u8],
+    context: &[u8],
+    proof: &[Bytes],
+    node_hash: F,
+) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    let bound = crate::hashes::context_leaf_hash(context, leaf);
+    verify_proof(root, &bound, proof, node_hash)
+}
+
+/// Multiproof counterpart of [`verify_proof`]; malformed multiproofs verify as `false`.
+pub fn verify_multi_proof<F>(root: &[u8], mp: &MultiProof, node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    try_process_multi_proof(mp, node_hash).is_ok_and(|computed| constant_time_eq(&computed, root))
+}
+
+/// Returns true if `value` equals one of the internal (non-leaf) nodes of `tree`.
+///
+/// Without domain separation between leaves and nodes, a "leaf" equal to an internal node
+/// lets an attacker present a shorter proof for a value that was never inserted (the classic
+/// second-preimage forgery).
+pub fn is_internal_node_hash(tree: &[Bytes], value: &[u8]) -> bool {
+    let internal_count = tree.len() / 2;
+    tree[..internal_count].iter().any(|n| n.as_slice() == value)
+}
+
+/// Fails with [`MerkleError::LeafIsInternalNode`] if `value` is an internal node of `tree`,
+/// unless `domain_separated` says leaves are hashed apart from nodes, where such a collision
+/// cannot be turned into a forged proof.
+fn check_not_internal_node_hash(
+    tree: &[Bytes],
+    value: &[u8],
+    domain_separated: bool,
+) -> Result<(), MerkleError> {
+    if !domain_separated && is_internal_node_hash(tree, value) {
+        return Err(MerkleError::LeafIsInternalNode);
+    }
+    Ok(())
+}
+
+/// Like [`try_get_proof`], but refuses to produce a proof for a leaf whose value equals an
+/// internal node of the tree. Pass `domain_separated = true` for trees built with domain
+/// separation, which allows such leaves.
+pub fn get_proof_guarded(
+    tree: &[Bytes],
+    leaf_index: usize,
+    domain_separated: bool,
+) -> Result<Vec<Bytes>, MerkleError> {
+    check_leaf_node(tree.len(), leaf_index)?;
+    check_not_internal_node_hash(tree, &tree[leaf_index], domain_separated)?;
+    try_get_proof(tree, leaf_index)
+}
+
+/// Like [`try_process_proof`], but refuses to accept a `leaf` that equals an internal node of
+/// the known `tree`; `domain_separated` as for [`get_proof_guarded`].
+pub fn process_proof_guarded<F>(
+    tree: &[Bytes],
+    leaf: &[u8],
+    proof: &[Bytes],
+    node_hash: F,
+    domain_separated: bool,
+) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    check_not_internal_node_hash(tree, leaf, domain_separated)?;
+    try_process_proof(leaf, proof, node_hash)
+}
+
+pub fn get_multi_proof(tree: &[Bytes], indices: Vec<usize>) -> MultiProof {
+    unwrap_or_panic(try_get_multi_proof(tree, indices))
+}
+
+pub fn try_get_multi_proof(tree: &[Bytes], indices: Vec<usize>) -> Result<MultiProof, MerkleError> {
+    for &i in indices.iter() {
+        check_leaf_node(tree.len(), i)?;
+    }
+    for n in tree.iter() {
+        check_merkle_node(n)?;
+    }
+    multi_proof_indices(tree.len(), indices)?.fetch(|i| Ok(tree[i].clone()))
+}
+
+/// Shape of a multi-proof: tree indices of its leaves (descending) and proof nodes, and its
+/// flags. Disk-backed trees read just these O(k log n) nodes.
+pub(crate) struct MultiProofIndices {
+    pub leaves: Vec<usize>,
+    pub proof: Vec<usize>,
+    pub proof_flags: Vec<bool>,
+}
+
+impl MultiProofIndices {
+    /// Flags and proof nodes of the `steps` of a walk over `leaves`.
+    fn from_steps(leaves: Vec<usize>, steps: &[WalkStep]) -> Self {
+        let mut proof: Vec<usize> = steps.iter().filter_map(|&(_, s)| s).collect();
+        if leaves.is_empty() {
+            proof.push(0);
+        }
+        Self {
+            proof_flags: steps.iter().map(|&(_, s)| s.is_none()).collect(),
+            leaves,
+            proof,
+        }
+    }
+
+    /// The multi-proof with each index replaced by the node `node` returns for it.
+    pub fn fetch<E>(
+        self,
+        mut node: impl FnMut(usize) -> Result<Bytes, E>,
+    ) -> Result<MultiProof, E> {
+        let leaves = self
+            .leaves
+            .iter()
+            .map(|&i| node(i))
+            .collect::<Result<_, _>>()?;
+        let proof = self
+            .proof
+            .iter()
+            .map(|&i| node(i))
+            .collect::<Result<_, _>>()?;
+        Ok(MultiProof::new(leaves, proof, self.proof_flags))
+    }
+}
+
+/// One step of the multi-proof walk: the parent reached and the sibling taken from the tree,
+/// or `None` when the sibling is proven too (a `true` flag).
+type WalkStep = (usize, Option<usize>);
+
+/// `indices` checked to be distinct leaves of a tree of `tree_len` nodes, in descending order.
+fn sorted_leaf_indices(
+    tree_len: usize,
+    mut indices: Vec<usize>,
+) -> Result<Vec<usize>, MerkleError> {
+    check_tree_len(tree_len)?;
+    for &i in indices.iter() {
+        check_leaf_node(tree_len, i)?;
+    }
+    indices.sort_by(|a, b| b.cmp(a));
+
+    if indices.windows(2).any(|w| w[0] == w[1]) {
+        return Err(MerkleError::DuplicateIndex);
+    }
+    Ok(indices)
+}
+
+/// Walk the descending `queue` of descendants of `root` up to `root`, appending one step per
+/// parent reached. Parents are reached in descending order.
+fn walk_multi_proof(mut queue: VecDeque<usize>, root: usize, steps: &mut Vec<WalkStep>) {
+    // indices are taken from the front and parents queued at the back, so the queue stays in
+    // descending order and each step is O(1)
+    while let Some(j) = queue.pop_front().filter(|&j| j != root) {
+        let s = sibling_index(j);
+        if queue.front() == Some(&s) {
+            queue.pop_front();
+            steps.push((parent_index(j), None));
+        } else {
+            steps.push((parent_index(j), Some(s)));
+        }
+        queue.push_back(parent_index(j));
+    }
+}
+
+/// Indices of the multi-proof of `indices` in a tree of `tree_len` nodes.
+pub(crate) fn multi_proof_indices(
+    tree_len: usize,
+    indices: Vec<usize>,
+) -> Result<MultiProofIndices, MerkleError> {
+    let indices = sorted_leaf_indices(tree_len, indices)?;
+    let mut steps = Vec::new();
+    walk_multi_proof(indices.iter().copied().collect(), 0, &mut steps);
+    Ok(MultiProofIndices::from_steps(indices, &steps))
+}
+
+/// Like [`get_multi_proof`], addressed by 0-based leaf positions.
+pub fn get_multi_proof_for_leaves(tree: &[Bytes], leaf_positions: &[usize]) -> MultiProof {
+    unwrap_or_panic(try_get_multi_proof_for_leaves(tree, leaf_positions))
+}
+
+pub fn try_get_multi_proof_for_leaves(
+    tree: &[Bytes],
+    leaf_positions: &[usize],
+) -> Result<MultiProof, MerkleError> {
+    let indices = leaf_positions
+        .iter()
+        .map(|&p| try_leaf_tree_index(tree.len(), p))
+        .collect::<Result<Vec<usize>, MerkleError>>()?;
+    try_get_multi_proof(tree, indices)
+}
+
+/// Like [`get_multi_proof`], walking the subtrees below the top levels on all available
+/// cores. Each subtree reaches its parents in descending order and no two subtrees share a
+/// parent, so sorting the merged steps by parent gives exactly the flags and proof of
+/// [`get_multi_proof`]. Only the leaves and proof nodes are validated, as in
+/// [`get_proof`].
+pub fn get_multi_proof_parallel(tree: &[Bytes], indices: Vec<usize>) -> MultiProof {
+    unwrap_or_panic(try_get_multi_proof_parallel(tree, indices))
+}
+
+pub fn try_get_multi_proof_parallel(
+    tree: &[Bytes],
+    indices: Vec<usize>,
+) -> Result<MultiProof, MerkleError> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    try_get_multi_proof_with_threads(tree, indices, threads)
+}
+
+/// Like [`get_multi_proof_parallel`], on up to about `threads` threads.
+pub fn get_multi_proof_with_threads(
+    tree: &[Bytes],
+    indices: Vec<usize>,
+    threads: usize,
+) -> MultiProof {
+    unwrap_or_panic(try_get_multi_proof_with_threads(tree, indices, threads))
+}
+
+pub fn try_get_multi_proof_with_threads(
+    tree: &[Bytes],
+    indices: Vec<usize>,
+    threads: usize,
+) -> Result<MultiProof, MerkleError> {
+    let indices = sorted_leaf_indices(tree.len(), indices)?;
+    let level = |i: usize| usize::BITS - 1 - (i + 1).leading_zeros();
+    // at least one subtree per thread, rooted above the shallowest leaf
+    let split = threads
+        .next_power_of_two()
+        .trailing_zeros()
+        .min(level(tree.len().saturating_sub(1) / 2));
+
+    let mut subtrees: BTreeMap<usize, VecDeque<usize>> = BTreeMap::new();
+    for &i in indices.iter() {
+        let root = ((i + 1) >> (level(i) - split)) - 1;
+        subtrees.entry(root).or_default().push_back(i);
+    }
+    let mut steps: Vec<WalkStep> = std::thread::scope(|scope| {
+        let workers: Vec<_> = subtrees
+            .iter()
+            .map(|(&root, queue)| {
+                scope.spawn(move || {
+                    let mut steps = Vec::new();
+                    walk_multi_proof(queue.clone(), root, &mut steps);
+                    steps
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("Multi-proof worker panicked"))
+            .collect()
+    });
+    steps.sort_unstable_by_key(|&(parent, _)| std::cmp::Reverse(parent));
+    walk_multi_proof(subtrees.keys().rev().copied().collect(), 0, &mut steps);
+
+    MultiProofIndices::from_steps(indices, &steps).fetch(|i| {
+        check_merkle_node(&tree[i])?;
+        Ok(tree[i].clone())
+    })
+}
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Reconstruct the Merkle root from a multi-proof. Panics with an "Invariant error" message
+/// if the provided proof is malformed.
+pub fn process_multi_proof<F>(mp: &MultiProof, node_hash: F) -> Bytes
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_process_multi_proof(mp, node_hash))
+}
+
+pub fn try_process_multi_proof<F>(mp: &MultiProof, node_hash: F) -> Result<Bytes, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    // Validate basic compatibility similar to the TypeScript implementation
+    let required_proofs = mp.proof_flags.iter().filter(|&&b| !b).count();
+    if mp.proof.len() < required_proofs
+        || (mp.leaves.len() + mp.proof.len()) != (mp.proof_flags.len() + 1)
+    {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+    for n in mp.leaves.iter().chain(mp.proof.iter()) {
+        check_merkle_node(n)?;
+    }
+    // Convert MultiProof buffers to fixed-size hashes for internal processing
+    let mut stack: VecDeque<Hash> = mp.leaves.iter().map(|l| slice_to_hash(l)).collect();
+    let mut proof: VecDeque<Hash> = mp.proof.iter().map(|p| slice_to_hash(p)).collect();
+
+    for &flag in mp.proof_flags.iter() {
+        let a = stack.pop_front().ok_or(MerkleError::InvalidMultiProof)?;
+        let b = if flag {
+            stack.pop_front()
+        } else {
+            proof.pop_front()
+        }
+        .ok_or(MerkleError::InvalidMultiProof)?;
+
+        stack.push_back(check_node_hash(node_hash(&a[..], &b[..]))?);
+    }
+
+    if stack.len() + proof.len() != 1 {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+
+    Ok(hash_to_vec(
+        &stack.pop_front().or_else(|| proof.pop_front()).unwrap(),
+    ))
+}
+
+/// Streaming variant of [`process_multi_proof`] for very large multiproofs: leaves, proof
+/// nodes and flags are pulled from iterators, and only the queue of computed parents is kept in
+/// memory. Panics with "Invariant error" on malformed input, like the non-streaming version;
+/// see [`try_process_multi_proof_iter`].
+pub fn process_multi_proof_iter<L, P, G, F>(
+    leaves: L,
+    proof: P,
+    proof_flags: G,
+    node_hash: F,
+) -> Bytes
+where
+    L: IntoIterator,
+    L::Item: AsRef<[u8]>,
+    P: IntoIterator,
+    P::Item: AsRef<[u8]>,
+    G: IntoIterator<Item = bool>,
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_process_multi_proof_iter(
+        leaves,
+        proof,
+        proof_flags,
+        node_hash,
+    ))
+}
+
+pub fn try_process_multi_proof_iter<L, P, G, F>(
+    leaves: L,
+    proof: P,
+    proof_flags: G,
+    node_hash: F,
+) -> Result<Bytes, MerkleError>
+where
+    L: IntoIterator,
+    L::Item: AsRef<[u8]>,
+    P: IntoIterator,
+    P::Item: AsRef<[u8]>,
+    G: IntoIterator<Item = bool>,
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    let to_hash = |node: &[u8]| check_merkle_node(node).map(|_| slice_to_hash(node));
+    let mut leaves = leaves.into_iter().map(|l| to_hash(l.as_ref()));
+    let mut proof = proof.into_iter().map(|p| to_hash(p.as_ref()));
+    // leaves are consumed before any computed parent, matching the queue order of
+    // `process_multi_proof`
+    let mut computed: VecDeque<Hash> = VecDeque::new();
+
+    for flag in proof_flags {
+        let a = match leaves.next().transpose()? {
+            Some(a) => a,
+            None => computed.pop_front().ok_or(MerkleError::InvalidMultiProof)?,
+        };
+        let b = if flag {
+            match leaves.next().transpose()? {
+                Some(b) => Some(b),
+                None => computed.pop_front(),
+            }
+        } else {
+            proof.next().transpose()?
+        }
+        .ok_or(MerkleError::InvalidMultiProof)?;
+
+        computed.push_back(check_node_hash(node_hash(&a[..], &b[..]))?);
+    }
+
+    let mut remaining = leaves.chain(computed.into_iter().map(Ok)).chain(proof);
+    let root = remaining
+        .next()
+        .transpose()?
+        .ok_or(MerkleError::InvalidMultiProof)?;
+    if remaining.next().is_some() {
+        return Err(MerkleError::InvalidMultiProof);
+    }
+
+    Ok(hash_to_vec(&root))
+}
+
+/// Convenience helper for byte-oriented trees: validates leaf size and delegates to generic constructor.
+///
+/// A single-leaf tree is just `[leaf]`: its root is the leaf itself (no extra hashing), its
+/// single proof is empty and its multiproof for index 0 carries the leaf with no proof nodes.
+pub fn make_merkle_tree_bytes<F>(leaves: Vec<Bytes>, node_hash: F) -> Vec<Bytes>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    unwrap_or_panic(try_make_merkle_tree_bytes(leaves, node_hash))
+}
+
+pub fn try_make_merkle_tree_bytes<F>(
+    leaves: Vec<Bytes>,
+    node_hash: F,
+) -> Result<Vec<Bytes>, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    let tree = make_hash_tree(&leaves, node_hash)?;
+    // Convert back to Vec<Bytes> for existing public API
+    Ok(tree.iter().map(hash_to_vec).collect())
+}
+
+/// Heap-layout tree as fixed-size hashes, the storage of [`crate::flat::FlatMerkleTree`].
+pub(crate) fn make_hash_tree<F>(leaves: &[Bytes], node_hash: F) -> Result<Vec<Hash>, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    if leaves.is_empty() {
+        return Err(MerkleError::EmptyTree);
+    }
+    for l in leaves.iter() {
+        check_merkle_node(l)?;
+    }
+
+    // Work with fixed-size `Hash` arrays internally to avoid per-node heap allocations
+    let mut tree = vec![[0u8; 32]; 2 * leaves.len() - 1];
+    let tree_len = tree.len();
+
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree[tree_len - 1 - i] = slice_to_hash(leaf);
+    }
+    for i in (0..(tree_len - leaves.len())).rev() {
+        let left = tree[left_child_index(i)];
+        let right = tree[right_child_index(i)];
+        tree[i] = check_node_hash(node_hash(&left[..], &right[..]))?;
+    }
+    Ok(tree)
+}
+
+/// Like [`try_make_merkle_tree_bytes`], hashing one tree level at a time: `hash_pairs` gets
+/// every `(left, right)` child pair of a level and returns their parents in order. Pass
+/// [`crate::hashes::Hasher::hash_pairs`] to build with a hasher's batch kernel.
+pub fn try_make_merkle_tree_by_levels<F>(
+    leaves: Vec<Bytes>,
+    hash_pairs: F,
+) -> Result<Vec<Bytes>, MerkleError>
+where
+    F: Fn(&[(Hash, Hash)]) -> Vec<Hash>,
+{
+    if leaves.is_empty() {
+        return Err(MerkleError::EmptyTree);
+    }
+    for l in leaves.iter() {
+        check_merkle_node(l)?;
+    }
+
+    let mut tree = vec![[0u8; 32]; 2 * leaves.len() - 1];
+    let tree_len = tree.len();
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree[tree_len - 1 - i] = slice_to_hash(leaf);
+    }
+    // internal nodes are 0..internal; depth d of the heap starts at 2^d - 1, deepest first
+    let internal = leaves.len() - 1;
+    let mut width = if internal == 0 {
+        0
+    } else {
+        1 << internal.ilog2()
+    };
+    while width > 0 {
+        let level = width - 1..(2 * width - 1).min(internal);
+        let pairs: Vec<(Hash, Hash)> = level
+            .clone()
+            .map(|i| (tree[left_child_index(i)], tree[right_child_index(i)]))
+            .collect();
+        let parents = hash_pairs(&pairs);
+        if parents.len() != pairs.len() {
+            return Err(MerkleError::InvalidNodeHash);
+        }
+        tree[level].copy_from_slice(&parents);
+        width /= 2;
+    }
+    Ok(tree.iter().map(hash_to_vec).collect())
+}
+
+/// Like [`make_merkle_tree_bytes`] over fixed-size hashes, with no length checks or per-node
+/// allocations. Panics on an empty leaf list.
+pub fn make_merkle_tree_hashes<F>(leaves: &[Hash], node_hash: F) -> Vec<Hash>
+where
+    F: Fn(&[u8], &[u8]) -> Hash,
+{
+    assert!(!leaves.is_empty(), "Expected non-zero number of leaves");
+    let mut tree = vec![[0u8; 32]; 2 * leaves.len() - 1];
+    let tree_len = tree.len();
+    for (i, leaf) in leaves.iter().enumerate() {
+        tree[tree_len - 1 - i] = *leaf;
+    }
+    for i in (0..(tree_len - leaves.len())).rev() {
+        tree[i] = node_hash(&tree[left_child_index(i)], &tree[right_child_index(i)]);
+    }
+    tree
+}
+
+/// Like [`get_proof`] over a [`make_merkle_tree_hashes`] tree.
+pub fn get_proof_hashes(tree: &[Hash], leaf_index: usize) -> Vec<Hash> {
+    unwrap_or_panic(try_get_proof_hashes(tree, leaf_index))
+}
+
+pub fn try_get_proof_hashes(tree: &[Hash], leaf_index: usize) -> Result<Vec<Hash>, MerkleError> {
+    check_leaf_node(tree.len(), leaf_index)?;
+    let mut proof = Vec::new();
+    let mut index = leaf_index;
+    while index > 0 {
+        let s = sibling_index(index);
+        if s < tree.len() {
+            proof.push(tree[s]);
+        }
+        index = parent_index(index);
+    }
+    Ok(proof)
+}
+
+/// Like [`process_proof`] over fixed-size hashes; pairs are sorted before hashing.
+pub fn process_proof_hashes<F>(leaf: &Hash, proof: &[Hash], node_hash: F) -> Hash
+where
+    F: Fn(&[u8], &[u8]) -> Hash,
+{
+    proof.iter().fold(*leaf, |computed, p| {
+        if computed <= *p {
+            node_hash(&computed, p)
+        } else {
+            node_hash(p, &computed)
+        }
+    })
+}
+
+/// Like [`verify_proof`] over fixed-size hashes, comparing roots in constant time.
+pub fn verify_proof_hashes<F>(root: &Hash, leaf: &Hash, proof: &[Hash], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Hash,
+{
+    constant_time_eq(&process_proof_hashes(leaf, proof, node_hash), root)
+}
+
+/// Like [`is_valid_merkle_tree`] over a [`make_merkle_tree_hashes`] tree.
+pub fn is_valid_merkle_tree_hashes<F>(tree: &[Hash], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Hash,
+{
+    is_valid_tree(tree, node_hash)
+}
+
+/// What to do when a leaf is the all-zero 32-byte value, which many on-chain verifiers treat
+/// as an empty slot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum ZeroLeafPolicy {
+    #[default]
+    Allow,
+    /// Report zero leaves without failing.
+    Warn,
+    Reject,
+}
+
+/// Apply `policy` to `leaves`, which must all be 32 bytes. Returns the positions of zero
+/// leaves under [`ZeroLeafPolicy::Warn`] (always empty under `Allow`) and fails with
+/// [`MerkleError::ZeroLeaf`] under `Reject`.
+pub fn check_zero_leaves(
+    leaves: &[Bytes],
+    policy: ZeroLeafPolicy,
+) -> Result<Vec<usize>, MerkleError> {
+    for l in leaves.iter() {
+        check_merkle_node(l)?;
+    }
+    if policy == ZeroLeafPolicy::Allow {
+        return Ok(Vec::new());
+    }
+    let positions: Vec<usize> = leaves
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.as_slice() == ZERO_HASH)
+        .map(|(i, _)| i)
+        .collect();
+    match positions.first() {
+        Some(&i) if policy == ZeroLeafPolicy::Reject => Err(MerkleError::ZeroLeaf(i)),
+        _ => Ok(positions),
+    }
+}
+
+/// Memo of parent hashes keyed by their `(left, right)` children. Keeping one across periodic
+/// rebuilds lets [`make_merkle_tree_bytes_cached`] skip re-hashing subtrees that did not
+/// change. Only valid for a single `node_hash` function.
+///
+/// Each rebuild starts a new generation and evicts the entries it did not use, so the cache
+/// never outgrows the latest tree; [`Self::with_max_entries`] also caps it within a build.
+/// [`Self::to_bytes`] and [`Self::from_bytes`] carry it across restarts.
+pub struct NodeHashCache {
+    /// Parent hash and the generation that last used it.
+    entries: HashMap<(Hash, Hash), (Hash, u64)>,
+    generation: u64,
+    max_entries: usize,
+}
+
+impl Default for NodeHashCache {
+    fn default() -> Self {
+        Self::with_max_entries(usize::MAX)
+    }
+}
+
+impl NodeHashCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache that stops memoizing new parents once it holds `max_entries`.
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            generation: 0,
+            max_entries,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Entries as concatenated 96-byte `left || right || parent` records.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * 96);
+        for ((left, right), (parent, _)) in &self.entries {
+            bytes.extend_from_slice(left);
+            bytes.extend_from_slice(right);
+            bytes.extend_from_slice(parent);
+        }
+        bytes
+    }
+
+    /// Read records written by [`Self::to_bytes`], keeping at most `max_entries` of them.
+    pub fn from_bytes(bytes: &[u8], max_entries: usize) -> Result<Self, MerkleError> {
+        let (records, rest) = bytes.as_chunks::<96>();
+        if !rest.is_empty() {
+            return Err(MerkleError::InvalidNode(rest.len()));
+        }
+        let mut cache = Self::with_max_entries(max_entries);
+        for record in records.iter().take(max_entries) {
+            let (key, parent) = record.split_at(64);
+            cache.entries.insert(
+                (slice_to_hash(&key[..32]), slice_to_hash(&key[32..])),
+                (slice_to_hash(parent), 0),
+            );
+        }
+        Ok(cache)
+    }
+
+    fn get(&mut self, key: &(Hash, Hash)) -> Option<Hash> {
+        let generation = self.generation;
+        self.entries.get_mut(key).map(|(parent, used)| {
+            *used = generation;
+            *parent
+        })
+    }
+
+    fn insert(&mut self, key: (Hash, Hash), parent: Hash) {
+        if self.entries.len() < self.max_entries {
+            self.entries.insert(key, (parent, self.generation));
+        }
+    }
+}
+
+/// Like [`make_merkle_tree_bytes`], but consults and fills `cache` instead of calling
+/// `node_hash` for every internal node. Entries the new tree does not use are evicted.
+pub fn make_merkle_tree_bytes_cached<F>(
+    leaves: Vec<Bytes>,
+    cache: &mut NodeHashCache,
+    node_hash: F,
+) -> Vec<Bytes>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    cache.generation += 1;
+    let generation = cache.generation;
+    let tree = {
+        let cache = RefCell::new(&mut *cache);
+        make_merkle_tree_bytes(leaves, |a, b| {
+            let key = (slice_to_hash(a), slice_to_hash(b));
+            if let Some(h) = cache.borrow_mut().get(&key) {
+                return hash_to_vec(&h);
+            }
+            let parent = node_hash(a, b);
+            if is_valid_merkle_node(&parent) {
+                cache.borrow_mut().insert(key, slice_to_hash(&parent));
+            }
+            parent
+        })
+    };
+    cache.entries.retain(|_, (_, used)| *used == generation);
+    tree
+}
+
+/// Upper bounds checked before a tree is allocated, so absurd leaf counts fail with a
+/// [`TreeSizeError`] instead of aborting the process on allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeLimits {
+    pub max_leaves: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for TreeLimits {
+    fn default() -> Self {
+        Self {
+            max_leaves: u32::MAX as usize,
+            max_bytes: 8 << 30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TreeSizeError {
+    #[error("Tree of {leaves} leaves exceeds limit of {max} leaves")]
+    TooManyLeaves { leaves: usize, max: usize },
+    #[error("Tree needs an estimated {estimated} bytes, exceeding limit of {max} bytes")]
+    TooManyBytes { estimated: u64, max: u64 },
+}
+
+/// Estimated peak memory, in bytes, of building a byte tree over `leaf_count` leaves: the
+/// internal `Hash` tree plus the returned `Vec<Bytes>` with one heap buffer per node.
+pub fn estimated_tree_bytes(leaf_count: usize) -> u64 {
+    let nodes = (leaf_count as u64).saturating_mul(2).saturating_sub(1);
+    let per_node = (32 + std::mem::size_of::<Bytes>() + 32) as u64;
+    nodes.saturating_mul(per_node)
+}
+
+pub fn check_tree_size(leaf_count: usize, limits: &TreeLimits) -> Result<(), TreeSizeError> {
+    if leaf_count > limits.max_leaves {
+        return Err(TreeSizeError::TooManyLeaves {
+            leaves: leaf_count,
+            max: limits.max_leaves,
+        });
+    }
+    let estimated = estimated_tree_bytes(leaf_count);
+    if estimated > limits.max_bytes {
+        return Err(TreeSizeError::TooManyBytes {
+            estimated,
+            max: limits.max_bytes,
+        });
+    }
+    Ok(())
+}
+
+/// Like [`try_make_merkle_tree_bytes`], but checks `limits` before allocating the tree and
+/// reports a violation as [`MerkleError::TreeSize`].
+pub fn make_merkle_tree_bytes_with_limits<F>(
+    leaves: Vec<Bytes>,
+    limits: &TreeLimits,
+    node_hash: F,
+) -> Result<Vec<Bytes>, MerkleError>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    check_tree_size(leaves.len(), limits)?;
+    try_make_merkle_tree_bytes(leaves, node_hash)
+}
+
+pub fn is_valid_merkle_tree<F>(tree: &[Bytes], node_hash: F) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    tree.iter().all(|n| is_valid_merkle_node(n)) && is_valid_tree(tree, node_hash)
+}
+
+/// Check every internal node against its children, comparing in place. The caller has checked
+/// node lengths.
+pub(crate) fn is_valid_tree<N, R, F>(tree: &[N], node_hash: F) -> bool
+where
+    N: AsRef<[u8]>,
+    R: AsRef<[u8]>,
+    F: Fn(&[u8], &[u8]) -> R,
+{
+    for (i, node) in tree.iter().enumerate() {
+        let l = left_child_index(i);
+        let r = right_child_index(i);
+
+        if r >= tree.len() {
+            if l < tree.len() {
+                return false;
+            }
+        } else if node_hash(tree[l].as_ref(), tree[r].as_ref()).as_ref() != node.as_ref() {
+            return false;
+        }
+    }
+
+    !tree.is_empty()
+}
+
+/// Outcome of [`sample_verify`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleReport {
+    /// Number of internal nodes checked (with repetition).
+    pub checked: usize,
+    pub internal_nodes: usize,
+    /// Tree indices of sampled internal nodes that do not match their children.
+    pub failed: Vec<usize>,
+}
+
+impl SampleReport {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Chance that the sample would have caught at least one bad node if `corrupt_fraction` of
+    /// the internal nodes were bad.
+    pub fn detection_probability(&self, corrupt_fraction: f64) -> f64 {
+        1.0 - (1.0 - corrupt_fraction.clamp(0.0, 1.0)).powf(self.checked as f64)
+    }
+
+    /// Largest fraction of bad internal nodes that a passing sample still leaves plausible at
+    /// `confidence` (e.g. `0.95`); 1.0 when nothing was checked.
+    pub fn max_corrupt_fraction(&self, confidence: f64) -> f64 {
+        if self.checked == 0 {
+            return 1.0;
+        }
+        1.0 - (1.0 - confidence.clamp(0.0, 1.0)).powf(1.0 / self.checked as f64)
+    }
+}
+
+/// Re-hash `k` internal nodes picked at random (with replacement) and compare them with their
+/// children, as a cheap spot check for trees too large for [`is_valid_merkle_tree`]. `rng`
+/// supplies uniformly random `u64`s, so callers choose the randomness source.
+pub fn sample_verify<R, F>(tree: &[Bytes], mut rng: R, k: usize, node_hash: F) -> SampleReport
+where
+    R: FnMut() -> u64,
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    assert!(
+        !tree.is_empty(),
+        "Expected non-zero number of nodes in merkle tree"
+    );
+    let internal_nodes = tree.len() / 2;
+    let mut failed = Vec::new();
+    if internal_nodes == 0 {
+        return SampleReport {
+            checked: 0,
+            internal_nodes,
+            failed,
+        };
+    }
+    for _ in 0..k {
+        let i = (rng() % internal_nodes as u64) as usize;
+        // a tree of even length is malformed and its last internal node lacks a right child
+        let ok = right_child_index(i) < tree.len() && {
+            let (l, r) = (&tree[left_child_index(i)], &tree[right_child_index(i)]);
+            [&tree[i], l, r].iter().all(|n| is_valid_merkle_node(n)) && node_hash(l, r) == tree[i]
+        };
+        if !ok && !failed.contains(&i) {
+            failed.push(i);
+        }
+    }
+    SampleReport {
+        checked: k,
+        internal_nodes,
+        failed,
+    }
+}
+
+pub fn render_merkle_tree(tree: &[Bytes]) -> String {
+    assert!(
+        !tree.is_empty(),
+        "Expected non-zero number of nodes in merkle tree"
+    );
+
+    let mut stack: Vec<(usize, Vec<usize>)> = vec![(0, vec![])];
+
+    let mut lines: Vec<String> = Vec::new();
+
+    while let Some((i, path)) = stack.pop() {
+        let mut line = String::new();
+
+        for p in path.iter().take(path.len().saturating_sub(1)) {
+            line.push_str(if *p == 0 { "   " } else { "│  " });
+        }
+
+        if let Some(last) = path.last() {
+            line.push_str(if *last == 0 { "└─ " } else { "├─ " });
+        }
+
+        line.push_str(&format!("{}) {:?}", i, tree[i]));
+        lines.push(line);
+
+        if right_child_index(i) < tree.len() {
+            stack.push((right_child_index(i), {
+                let mut new_path = path.clone();
+                new_path.push(0);
+                new_path
+            }));
+            stack.push((left_child_index(i), {
+                let mut new_path = path.clone();
+                new_path.push(1);
+                new_path
+            }));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Render the tree as a Graphviz DOT digraph. Nodes are named by their heap index and
+/// labelled with the hex-encoded hash; edges point from each parent to its children.
+pub fn render_merkle_tree_dot(tree: &[Bytes]) -> String {
+    assert!(
+        !tree.is_empty(),
+        "Expected non-zero number of nodes in merkle tree"
+    );
+
+    let mut lines: Vec<String> = vec!["digraph merkle {".to_string()];
+
+    for (i, node) in tree.iter().enumerate() {
+        lines.push(format!(
+            "  n{} [label=\"{}) 0x{}\"];",
+            i,
+            i,
+            hex::encode(node)
+        ));
+    }
+    for i in 0..tree.len() {
+        if right_child_index(i) < tree.len() {
+            lines.push(format!("  n{} -> n{};", i, left_child_index(i)));
+            lines.push(format!("  n{} -> n{};", i, right_child_index(i)));
+        }
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+/// Render the tree as a JSON document of the form
+/// `{"root":"0x..","nodes":[{"index":0,"hash":"0x..","left":1,"right":2},...]}`.
+/// Leaves have `null` children.
+pub fn render_merkle_tree_json(tree: &[Bytes]) -> String {
+    assert!(
+        !tree.is_empty(),
+        "Expected non-zero number of nodes in merkle tree"
+    );
+
+    let nodes: Vec<String> = tree
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let (left, right) = if right_child_index(i) < tree.len() {
+                (
+                    left_child_index(i).to_string(),
+                    right_child_index(i).to_string(),
+                )
+            } else {
+                ("null".to_string(), "null".to_string())
+            };
+            format!(
+                "{{\"index\":{},\"hash\":\"0x{}\",\"left\":{},\"right\":{}}}",
+                i,
+                hex::encode(node),
+                left,
+                right
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"root\":\"0x{}\",\"nodes\":[{}]}}",
+        hex::encode(&tree[0]),
+        nodes.join(",")
+    )
 }
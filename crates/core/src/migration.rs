@@ -0,0 +1,134 @@
+//! Moving a tree to a new hash function without losing auditability. [`rehash_tree`] rebuilds
+//! a [`TypedMerkleTree`] over the same values with another [`Hasher`], and a
+//! [`TransitionProof`] shows one value is committed under both the old and the new root, so
+//! claims made against the old root can be honoured under the new one.
+
+use crate::encoder::{typed_leaf_hash, LeafEncoder, TypedMerkleTree};
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, verify_proof, Bytes, Hash};
+use crate::standard::TreeOptions;
+
+/// The same values, encoder and options as `old`, re-hashed with `hasher`. Leaf order changes
+/// when the leaves are sorted, since it follows the new hashes.
+pub fn rehash_tree<T, E, H, H2>(
+    old: &TypedMerkleTree<T, E, H>,
+    hasher: H2,
+) -> TypedMerkleTree<T, E, H2>
+where
+    T: Clone,
+    E: LeafEncoder<T> + Clone,
+    H: Hasher,
+    H2: Hasher,
+{
+    TypedMerkleTree::of_with_options(
+        old.values().to_vec(),
+        old.encoder().clone(),
+        hasher,
+        old.options(),
+    )
+}
+
+/// One value's proofs under both roots of a migration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransitionProof {
+    pub old_root: Hash,
+    pub new_root: Hash,
+    pub old_proof: Vec<Bytes>,
+    pub new_proof: Vec<Bytes>,
+}
+
+impl TransitionProof {
+    /// Proofs for `value_index` (in input order) in `old` and its rehashed `new`. Panics if the
+    /// index is out of range or the trees hold different values at it.
+    pub fn new<T, E, H, H2>(
+        old: &TypedMerkleTree<T, E, H>,
+        new: &TypedMerkleTree<T, E, H2>,
+        value_index: usize,
+    ) -> Self
+    where
+        T: PartialEq,
+        E: LeafEncoder<T>,
+        H: Hasher,
+        H2: Hasher,
+    {
+        assert!(
+            new.values().get(value_index) == old.values().get(value_index),
+            "Trees hold different values at index {}",
+            value_index
+        );
+        Self {
+            old_root: old.root().try_into().unwrap(),
+            new_root: new.root().try_into().unwrap(),
+            old_proof: old.get_proof(value_index),
+            new_proof: new.get_proof(value_index),
+        }
+    }
+
+    /// Check that `value` is in both the old and new trees, and that those are the trees with
+    /// roots `old_root` and `new_root`.
+    pub fn verify<T, E, H, H2>(
+        &self,
+        old_root: &[u8],
+        new_root: &[u8],
+        encoder: &E,
+        old_hasher: &H,
+        new_hasher: &H2,
+        value: &T,
+    ) -> bool
+    where
+        T: ?Sized,
+        E: LeafEncoder<T>,
+        H: Hasher,
+        H2: Hasher,
+    {
+        constant_time_eq(&self.old_root, old_root)
+            && constant_time_eq(&self.new_root, new_root)
+            && verify_proof(
+                old_root,
+                &typed_leaf_hash(encoder, old_hasher, value),
+                &self.old_proof,
+                |a, b| old_hasher.hash_node(a, b).to_vec(),
+            )
+            && verify_proof(
+                new_root,
+                &typed_leaf_hash(encoder, new_hasher, value),
+                &self.new_proof,
+                |a, b| new_hasher.hash_node(a, b).to_vec(),
+            )
+    }
+}
+
+/// Check that `old_root` and `new_root` both commit to exactly `values`, built with `options`
+/// under the old and new hasher respectively. Rebuilds both trees.
+pub fn verify_transition<T, E, H, H2>(
+    values: &[T],
+    encoder: &E,
+    options: TreeOptions,
+    old_hasher: H,
+    old_root: &[u8],
+    new_hasher: H2,
+    new_root: &[u8],
+) -> bool
+where
+    T: Clone,
+    E: LeafEncoder<T> + Clone,
+    H: Hasher,
+    H2: Hasher,
+{
+    if values.is_empty() {
+        return false;
+    }
+    constant_time_eq(&typed_root(values, encoder, options, old_hasher), old_root)
+        && constant_time_eq(&typed_root(values, encoder, options, new_hasher), new_root)
+}
+
+fn typed_root<T, E, H>(values: &[T], encoder: &E, options: TreeOptions, hasher: H) -> Bytes
+where
+    T: Clone,
+    E: LeafEncoder<T> + Clone,
+    H: Hasher,
+{
+    TypedMerkleTree::of_with_options(values.to_vec(), encoder.clone(), hasher, options)
+        .root()
+        .to_vec()
+}
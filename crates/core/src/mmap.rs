@@ -0,0 +1,121 @@
+//! Trees served straight from a memory-mapped file (feature `mmap`). The file is the
+//! contiguous layout written by [`crate::flat::FlatMerkleTree::as_bytes`], so opening it only
+//! maps it and checks its length; nodes are paged in as proofs touch them.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::merkle::{
+    check_tree_len, is_valid_tree, leaf_count, multi_proof_indices, proof_of, try_leaf_tree_index,
+    Bytes, Hash, MerkleError, MultiProof,
+};
+
+pub struct MappedMerkleTree {
+    map: Mmap,
+}
+
+fn invalid(e: MerkleError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl MappedMerkleTree {
+    /// Map the tree file at `path`. Fails with [`io::ErrorKind::InvalidData`] unless it is an
+    /// odd number of 32-byte nodes. The file must not be modified while mapped.
+    pub fn open_mmap(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only and callers must not change the file while it is
+        // open, as documented above.
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() % 32 != 0 {
+            return Err(invalid(MerkleError::InvalidNode(map.len() % 32)));
+        }
+        check_tree_len(map.len() / 32).map_err(invalid)?;
+        Ok(Self { map })
+    }
+
+    /// All nodes, read from the mapping.
+    pub fn nodes(&self) -> &[Hash] {
+        self.map.as_chunks::<32>().0
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes().len()
+    }
+
+    /// Always `false`: an open tree has at least one node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes().is_empty()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        leaf_count(self.len())
+    }
+
+    pub fn root(&self) -> &Hash {
+        &self.nodes()[0]
+    }
+
+    /// Leaf at 0-based `leaf_position`, in input order.
+    pub fn leaf(&self, leaf_position: usize) -> &Hash {
+        self.try_leaf(leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_leaf(&self, leaf_position: usize) -> Result<&Hash, MerkleError> {
+        try_leaf_tree_index(self.len(), leaf_position).map(|i| &self.nodes()[i])
+    }
+
+    /// Proof for the leaf at tree index `leaf_index`, like [`crate::merkle::get_proof`].
+    pub fn get_proof(&self, leaf_index: usize) -> Vec<Bytes> {
+        self.try_get_proof(leaf_index)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_proof(&self, leaf_index: usize) -> Result<Vec<Bytes>, MerkleError> {
+        let nodes = self.nodes();
+        if leaf_index >= nodes.len() || 2 * leaf_index + 1 < nodes.len() {
+            return Err(MerkleError::NotALeaf(leaf_index));
+        }
+        Ok(proof_of(nodes, leaf_index))
+    }
+
+    /// Proof for the leaf at 0-based `leaf_position`.
+    pub fn get_proof_for_leaf(&self, leaf_position: usize) -> Vec<Bytes> {
+        let index =
+            try_leaf_tree_index(self.len(), leaf_position).unwrap_or_else(|e| panic!("{}", e));
+        proof_of(self.nodes(), index)
+    }
+
+    /// Multi-proof for the leaves at tree indices `indices`, like
+    /// [`crate::merkle::get_multi_proof`]. Only the leaves and proof nodes are read.
+    pub fn get_multi_proof(&self, indices: Vec<usize>) -> MultiProof {
+        self.try_get_multi_proof(indices)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_get_multi_proof(&self, indices: Vec<usize>) -> Result<MultiProof, MerkleError> {
+        let nodes = self.nodes();
+        multi_proof_indices(nodes.len(), indices)?.fetch(|i| Ok(nodes[i].to_vec()))
+    }
+
+    /// Like [`Self::get_multi_proof`], addressed by 0-based leaf positions.
+    pub fn get_multi_proof_for_leaves(&self, leaf_positions: &[usize]) -> MultiProof {
+        let indices = leaf_positions
+            .iter()
+            .map(|&p| try_leaf_tree_index(self.len(), p).unwrap_or_else(|e| panic!("{}", e)))
+            .collect();
+        self.get_multi_proof(indices)
+    }
+
+    /// Check every internal node against its children. Reads the whole file.
+    pub fn is_valid<F>(&self, node_hash: F) -> bool
+    where
+        F: Fn(&[u8], &[u8]) -> Bytes,
+    {
+        is_valid_tree(self.nodes(), node_hash)
+    }
+}
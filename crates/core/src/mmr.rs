@@ -0,0 +1,322 @@
+//! Merkle Mountain Range: an append-only list of perfect binary trees ("peaks") of strictly
+//! decreasing height, as used by blockchain light clients. Nodes are numbered in post-order
+//! from 0, so appending only ever adds nodes at the end and existing positions never move.
+//! The root is the [`bag_peaks`] of all peaks, which is the same value as
+//! [`crate::incremental::IncrementalMerkleTree::root`] over the same leaves; unlike that tree,
+//! an [`Mmr`] keeps its nodes and can prove any leaf until it is pruned.
+//!
+//! Leaves are 32-byte hashes and pairs are combined positionally with [`Hasher::hash_node`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, Hash, MerkleError};
+
+fn bit_length(n: u64) -> u32 {
+    u64::BITS - n.leading_zeros()
+}
+
+/// Height of the node at `pos` (0 for leaves).
+fn pos_height(pos: u64) -> u32 {
+    // in 1-based post-order numbering the left-most node of each height is all ones
+    let mut p = pos + 1;
+    while p.count_ones() != bit_length(p) {
+        p -= (1 << (bit_length(p) - 1)) - 1;
+    }
+    bit_length(p) - 1
+}
+
+/// Most leaves an MMR can have with every node position fitting in a `u64`.
+pub const MAX_LEAVES: u64 = u64::MAX / 2;
+
+/// Node position of the 0-based `leaf_index`. Panics if `leaf_index` exceeds [`MAX_LEAVES`].
+pub fn leaf_index_to_pos(leaf_index: u64) -> u64 {
+    assert!(
+        leaf_index <= MAX_LEAVES,
+        "Leaf index {} exceeds maximum of {}",
+        leaf_index,
+        MAX_LEAVES
+    );
+    2 * leaf_index - leaf_index.count_ones() as u64
+}
+
+/// Number of nodes of an MMR over `leaf_count` leaves. Panics if `leaf_count` exceeds
+/// [`MAX_LEAVES`].
+pub fn mmr_size(leaf_count: u64) -> u64 {
+    assert!(
+        leaf_count <= MAX_LEAVES,
+        "Leaf count {} exceeds maximum of {}",
+        leaf_count,
+        MAX_LEAVES
+    );
+    2 * leaf_count - leaf_count.count_ones() as u64
+}
+
+/// Positions of the peaks of an MMR with `size` nodes, left to right, or `None` if no MMR has
+/// that many nodes.
+pub fn peak_positions(size: u64) -> Option<Vec<u64>> {
+    let mut peaks = Vec::new();
+    let mut offset = 0;
+    let mut remaining = size;
+    for height in (0..bit_length(size)).rev() {
+        // `2^(height + 1) - 1`, which for height 63 is `u64::MAX`
+        let tree_size = u64::MAX >> (u64::BITS - 1 - height);
+        if tree_size <= remaining {
+            offset += tree_size;
+            remaining -= tree_size;
+            peaks.push(offset - 1);
+        }
+    }
+    (remaining == 0).then_some(peaks)
+}
+
+/// Fold the peaks right to left into one root: `H(p0, H(p1, ... H(pn-1, pn)))`.
+pub fn bag_peaks<H: Hasher>(peaks: &[Hash], hasher: &H) -> Option<Hash> {
+    let (last, rest) = peaks.split_last()?;
+    Some(
+        rest.iter()
+            .rev()
+            .fold(*last, |acc, p| hasher.hash_node(p, &acc)),
+    )
+}
+
+/// Inclusion proof for one leaf: the siblings from the leaf up to its peak and all peaks of
+/// the MMR at the time of proving.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrProof {
+    pub mmr_size: u64,
+    pub leaf_index: u64,
+    pub siblings: Vec<Hash>,
+    pub peaks: Vec<Hash>,
+}
+
+impl MmrProof {
+    /// `mmr_size || leaf_index` (u64, big-endian), the number of siblings (u32, big-endian),
+    /// then the siblings and peaks as concatenated 32-byte hashes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let siblings =
+            u32::try_from(self.siblings.len()).expect("More than u32::MAX proof siblings");
+        [
+            &self.mmr_size.to_be_bytes()[..],
+            &self.leaf_index.to_be_bytes(),
+            &siblings.to_be_bytes(),
+            self.siblings.as_flattened(),
+            self.peaks.as_flattened(),
+        ]
+        .concat()
+    }
+
+    /// Read a proof written by [`Self::to_bytes`]. Whether it verifies is not checked.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        let Some((header, rest)) = bytes.split_first_chunk::<20>() else {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        };
+        let (hashes, tail) = rest.as_chunks::<32>();
+        if !tail.is_empty() {
+            return Err(MerkleError::InvalidNode(tail.len()));
+        }
+        let siblings = u32::from_be_bytes(header[16..].try_into().unwrap()) as usize;
+        if siblings > hashes.len() {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        }
+        let (siblings, peaks) = hashes.split_at(siblings);
+        Ok(Self {
+            mmr_size: u64::from_be_bytes(header[..8].try_into().unwrap()),
+            leaf_index: u64::from_be_bytes(header[8..16].try_into().unwrap()),
+            siblings: siblings.to_vec(),
+            peaks: peaks.to_vec(),
+        })
+    }
+
+    /// Check that `leaf` is the proven leaf of the MMR with root `root`. Sizes past those of
+    /// [`MAX_LEAVES`] leaves verify as `false`.
+    pub fn verify<H: Hasher>(&self, root: &[u8], leaf: &[u8], hasher: &H) -> bool {
+        if self.mmr_size > mmr_size(MAX_LEAVES)
+            || self.leaf_index > MAX_LEAVES
+            || self.siblings.len() >= u64::BITS as usize - 1
+        {
+            return false;
+        }
+        let Some(peak_pos) = peak_positions(self.mmr_size) else {
+            return false;
+        };
+        let Ok(leaf) = <Hash>::try_from(leaf) else {
+            return false;
+        };
+        if peak_pos.len() != self.peaks.len() || self.leaf_index >= self.mmr_size {
+            return false;
+        }
+        let mut pos = leaf_index_to_pos(self.leaf_index);
+        if pos >= self.mmr_size {
+            return false;
+        }
+        let mut current = leaf;
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            let step = 2u64 << height;
+            if pos_height(pos + 1) > height as u32 {
+                // `pos` is a right child; its parent follows it
+                current = hasher.hash_node(sibling, &current);
+                pos += 1;
+            } else {
+                current = hasher.hash_node(&current, sibling);
+                pos += step;
+            }
+            if pos >= self.mmr_size {
+                return false;
+            }
+        }
+        let Some(k) = peak_pos.iter().position(|&p| p == pos) else {
+            return false;
+        };
+        if !constant_time_eq(&current, &self.peaks[k]) {
+            return false;
+        }
+        bag_peaks(&self.peaks, hasher).is_some_and(|r| constant_time_eq(&r, root))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Mmr<H: Hasher> {
+    hasher: H,
+    /// Node hashes by position. Pruned nodes are removed, so only retained nodes use memory.
+    nodes: BTreeMap<u64, Hash>,
+    size: u64,
+    leaf_count: u64,
+    /// Leaves below this index have been pruned and can no longer be proven.
+    pruned_before: u64,
+}
+
+impl<H: Hasher> Mmr<H> {
+    pub fn new(hasher: H) -> Self {
+        Self {
+            hasher,
+            nodes: BTreeMap::new(),
+            size: 0,
+            leaf_count: 0,
+            pruned_before: 0,
+        }
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// Number of nodes, including pruned ones.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// First leaf that can still be proven; earlier ones have been pruned.
+    pub fn pruned_before(&self) -> u64 {
+        self.pruned_before
+    }
+
+    /// Number of node hashes still held in memory.
+    pub fn retained_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node(&self, pos: u64) -> Hash {
+        *self.nodes.get(&pos).expect("MMR node has been pruned")
+    }
+
+    /// Append a 32-byte leaf and return its leaf index. Panics on any other length.
+    pub fn push(&mut self, leaf: &[u8]) -> u64 {
+        assert!(
+            leaf.len() == 32,
+            "Expected valid merkle node, got length {}",
+            leaf.len()
+        );
+        let mut pos = self.size();
+        let mut current: Hash = leaf.try_into().unwrap();
+        self.nodes.insert(pos, current);
+        let mut height = 0;
+        // merge with the equal-height peak on the left while the next slot is a parent
+        while pos_height(pos + 1) > height {
+            let left = self.node(pos + 1 - (2 << height));
+            current = self.hasher.hash_node(&left, &current);
+            pos += 1;
+            self.nodes.insert(pos, current);
+            height += 1;
+        }
+        self.size = pos + 1;
+        self.leaf_count += 1;
+        self.leaf_count - 1
+    }
+
+    pub fn peaks(&self) -> Vec<Hash> {
+        peak_positions(self.size())
+            .unwrap()
+            .into_iter()
+            .map(|p| self.node(p))
+            .collect()
+    }
+
+    /// Bagged peaks, or `None` for an empty MMR.
+    pub fn root(&self) -> Option<Hash> {
+        bag_peaks(&self.peaks(), &self.hasher)
+    }
+
+    /// Sibling positions from `leaf_index` up to its peak.
+    fn path(&self, leaf_index: u64) -> Vec<u64> {
+        let size = self.size();
+        let mut pos = leaf_index_to_pos(leaf_index);
+        let mut siblings = Vec::new();
+        for height in 0.. {
+            let step = 2u64 << height;
+            let (sibling, parent) = if pos_height(pos + 1) > height {
+                (pos + 1 - step, pos + 1)
+            } else {
+                (pos + step - 1, pos + step)
+            };
+            if parent >= size {
+                break;
+            }
+            siblings.push(sibling);
+            pos = parent;
+        }
+        siblings
+    }
+
+    /// Inclusion proof for `leaf_index`. Panics if it is out of range or pruned.
+    pub fn proof(&self, leaf_index: u64) -> MmrProof {
+        assert!(
+            leaf_index < self.leaf_count,
+            "Leaf index {} out of range for {} leaves",
+            leaf_index,
+            self.leaf_count
+        );
+        assert!(
+            leaf_index >= self.pruned_before,
+            "Leaf {} has been pruned",
+            leaf_index
+        );
+        MmrProof {
+            mmr_size: self.size(),
+            leaf_index,
+            siblings: self
+                .path(leaf_index)
+                .into_iter()
+                .map(|p| self.node(p))
+                .collect(),
+            peaks: self.peaks(),
+        }
+    }
+
+    /// Drop every node that is only needed to prove leaves below `leaf_index`. Peaks are
+    /// always kept, so appending and the root are unaffected.
+    pub fn prune(&mut self, leaf_index: u64) {
+        let before = leaf_index.min(self.leaf_count).max(self.pruned_before);
+        let mut keep: BTreeSet<u64> = peak_positions(self.size()).unwrap().into_iter().collect();
+        for i in before..self.leaf_count {
+            keep.insert(leaf_index_to_pos(i));
+            keep.extend(self.path(i));
+        }
+        self.nodes.retain(|pos, _| keep.contains(pos));
+        self.pruned_before = before;
+    }
+}
@@ -0,0 +1,193 @@
+//! Watching an append-only log. A [`LogMonitor`] follows the tree heads a log publishes and
+//! only moves its verified head forward when an RFC 6962 consistency proof links the two, so
+//! a log that rewrites its history is caught at the first head that shows it. A [`ForkProof`]
+//! packs the conflicting heads so that anyone else can check the fork.
+//!
+//! Heads are the `(size, root)` part of a signed tree head; checking the log's signature is up
+//! to the caller, before a head reaches the monitor. Roots are those of
+//! [`crate::consistency::log_root`].
+
+use crate::consistency::verify_consistency;
+use crate::hashes::Hasher;
+use crate::merkle::{constant_time_eq, Bytes};
+
+/// Size and root of a log at one point in its history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeHead {
+    pub size: usize,
+    pub root: Bytes,
+}
+
+impl TreeHead {
+    pub fn new(size: usize, root: impl Into<Bytes>) -> Self {
+        Self {
+            size,
+            root: root.into(),
+        }
+    }
+}
+
+/// How an accepted head relates to the verified one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadStatus {
+    /// A larger head, now the verified one.
+    Advanced,
+    /// The verified head again.
+    Unchanged,
+    /// An older head that is a prefix of the verified one.
+    Stale,
+}
+
+/// Evidence that a log showed two histories: `first`, and a head `second` of at least its size
+/// whose log starts with a different `first.size`-leaf prefix. `prefix_root` is the root of
+/// that prefix in `second`'s log and `proof` the consistency proof from it to `second`. For
+/// equal sizes the prefix is `second` itself and the proof is empty. Together with the log's
+/// signatures on both heads this convinces a third party without trusting the monitor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForkProof {
+    pub first: TreeHead,
+    pub second: TreeHead,
+    pub prefix_root: Bytes,
+    pub proof: Vec<Bytes>,
+}
+
+impl ForkProof {
+    /// Fork proof of two heads of one size, or `None` unless their roots differ.
+    pub fn conflicting(first: TreeHead, second: TreeHead) -> Option<Self> {
+        let proof = Self {
+            prefix_root: second.root.clone(),
+            first,
+            second,
+            proof: Vec::new(),
+        };
+        (proof.first.size == proof.second.size && proof.is_fork()).then_some(proof)
+    }
+
+    /// Fork proof of `first` and a larger `second`, where `prefix_root` and `proof` come from
+    /// `second`'s log, e.g. [`crate::consistency::get_consistency_proof`] over its leaves.
+    /// `None` unless it verifies.
+    pub fn new<H: Hasher>(
+        first: TreeHead,
+        second: TreeHead,
+        prefix_root: Bytes,
+        proof: Vec<Bytes>,
+        hasher: &H,
+    ) -> Option<Self> {
+        let proof = Self {
+            first,
+            second,
+            prefix_root,
+            proof,
+        };
+        proof.verify(hasher).then_some(proof)
+    }
+
+    fn is_fork(&self) -> bool {
+        self.first.size > 0
+            && self.first.root.len() == 32
+            && self.prefix_root.len() == 32
+            && !constant_time_eq(&self.first.root, &self.prefix_root)
+    }
+
+    /// Check that `second`'s log has a prefix of `first.size` leaves with a root other than
+    /// `first.root`. The empty log cannot fork; malformed proofs verify as `false`.
+    pub fn verify<H: Hasher>(&self, hasher: &H) -> bool {
+        self.is_fork()
+            && verify_consistency(
+                self.first.size,
+                self.second.size,
+                &self.prefix_root,
+                &self.second.root,
+                &self.proof,
+                hasher,
+            )
+    }
+}
+
+/// Evidence that a log misbehaved. The verified head is left as it was.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MonitorAlert {
+    /// Two different roots for the same size, with the verified head first.
+    #[error("Log published two roots for size {}", .0.first.size)]
+    Fork(ForkProof),
+    /// The consistency proof does not link the two heads.
+    #[error("No valid consistency proof between sizes {} and {}", verified.size, head.size)]
+    Inconsistent { verified: TreeHead, head: TreeHead },
+}
+
+/// Follows one log's heads from a trusted starting head.
+#[derive(Clone, Debug)]
+pub struct LogMonitor<H: Hasher> {
+    hasher: H,
+    verified: TreeHead,
+}
+
+impl<H: Hasher> LogMonitor<H> {
+    /// Start from `head`, trusted as is, e.g. the empty log or a head checked out of band.
+    pub fn new(hasher: H, head: TreeHead) -> Self {
+        Self {
+            hasher,
+            verified: head,
+        }
+    }
+
+    /// Latest head proven consistent with every head accepted before it.
+    pub fn verified(&self) -> &TreeHead {
+        &self.verified
+    }
+
+    /// Check `head` against the verified head. `proof` is the consistency proof from the
+    /// smaller of the two heads to the larger one and is empty for equal sizes or a verified
+    /// empty log. A larger head becomes the verified head.
+    pub fn observe(&mut self, head: TreeHead, proof: &[Bytes]) -> Result<HeadStatus, MonitorAlert> {
+        let verified = &self.verified;
+        let (status, consistent) = if head.size == verified.size {
+            let same = proof.is_empty() && constant_time_eq(&head.root, &verified.root);
+            (HeadStatus::Unchanged, same)
+        } else if verified.size == 0 {
+            // every log extends the empty one
+            (HeadStatus::Advanced, proof.is_empty())
+        } else if head.size > verified.size {
+            let consistent = verify_consistency(
+                verified.size,
+                head.size,
+                &verified.root,
+                &head.root,
+                proof,
+                &self.hasher,
+            );
+            (HeadStatus::Advanced, consistent)
+        } else {
+            // the empty log is a prefix of every log
+            let consistent = if head.size == 0 {
+                proof.is_empty()
+            } else {
+                verify_consistency(
+                    head.size,
+                    verified.size,
+                    &head.root,
+                    &verified.root,
+                    proof,
+                    &self.hasher,
+                )
+            };
+            (HeadStatus::Stale, consistent)
+        };
+        if !consistent {
+            let verified = verified.clone();
+            // malformed or empty-log heads of one size are not a provable fork
+            let fork = proof
+                .is_empty()
+                .then(|| ForkProof::conflicting(verified.clone(), head.clone()))
+                .flatten();
+            return Err(match fork {
+                Some(fork) => MonitorAlert::Fork(fork),
+                None => MonitorAlert::Inconsistent { verified, head },
+            });
+        }
+        if status == HeadStatus::Advanced {
+            self.verified = head;
+        }
+        Ok(status)
+    }
+}
@@ -0,0 +1,122 @@
+//! Update hooks for [`MerkleTree`]: an [`ObservedTree`] reports every leaf change together
+//! with the new root, so applications can publish roots (to a chain, a message bus, ...)
+//! without polling the tree.
+//!
+//! Hooks run synchronously inside the update. A consumer that should not block the writer,
+//! such as an async task, gets the events through a channel: an `mpsc::Sender<UpdateEvent>`
+//! is itself a hook that queues every event for the receiving side.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+
+use crate::hashes::Hasher;
+use crate::merkle::{Bytes, MerkleError};
+use crate::tree::MerkleTree;
+
+/// One leaf change reported to an [`UpdateHook`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdateEvent {
+    pub position: usize,
+    pub old: Bytes,
+    pub new: Bytes,
+    /// Root after the whole update, shared by all events of one batch.
+    pub new_root: Bytes,
+}
+
+pub trait UpdateHook {
+    fn on_update(&self, position: usize, old: &[u8], new: &[u8], new_root: &[u8]);
+}
+
+impl<F: Fn(usize, &[u8], &[u8], &[u8])> UpdateHook for F {
+    fn on_update(&self, position: usize, old: &[u8], new: &[u8], new_root: &[u8]) {
+        self(position, old, new, new_root)
+    }
+}
+
+/// Queues each event for the receiver; events are dropped once the receiver is gone.
+impl UpdateHook for Sender<UpdateEvent> {
+    fn on_update(&self, position: usize, old: &[u8], new: &[u8], new_root: &[u8]) {
+        let _ = self.send(UpdateEvent {
+            position,
+            old: old.to_vec(),
+            new: new.to_vec(),
+            new_root: new_root.to_vec(),
+        });
+    }
+}
+
+/// A [`MerkleTree`] whose updates are reported to the registered hooks, in registration
+/// order, once the tree is rehashed. Failed updates change nothing and report nothing.
+pub struct ObservedTree<H: Hasher> {
+    tree: MerkleTree<H>,
+    hooks: Vec<Box<dyn UpdateHook + Send>>,
+}
+
+impl<H: Hasher> ObservedTree<H> {
+    pub fn new(tree: MerkleTree<H>) -> Self {
+        Self {
+            tree,
+            hooks: Vec::new(),
+        }
+    }
+
+    pub fn on_update<K: UpdateHook + Send + 'static>(&mut self, hook: K) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    pub fn tree(&self) -> &MerkleTree<H> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> MerkleTree<H> {
+        self.tree
+    }
+
+    /// [`MerkleTree::update_leaf`], then report the change.
+    pub fn update_leaf(&mut self, leaf_position: usize, new_leaf: &[u8]) {
+        self.try_update_leaf(leaf_position, new_leaf)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_update_leaf(
+        &mut self,
+        leaf_position: usize,
+        new_leaf: &[u8],
+    ) -> Result<(), MerkleError> {
+        self.try_batch_update(&[(leaf_position, new_leaf)])
+    }
+
+    /// [`MerkleTree::batch_update`], then report one event per update in the given order.
+    /// An update of a position changed earlier in the batch reports the earlier value as
+    /// `old`.
+    pub fn batch_update<L: AsRef<[u8]>>(&mut self, updates: &[(usize, L)]) {
+        self.try_batch_update(updates)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_batch_update<L: AsRef<[u8]>>(
+        &mut self,
+        updates: &[(usize, L)],
+    ) -> Result<(), MerkleError> {
+        // value of each position before the update, following earlier updates of the batch
+        let mut current: HashMap<usize, &[u8]> = HashMap::new();
+        let mut olds = Vec::with_capacity(updates.len());
+        for (position, leaf) in updates.iter() {
+            let old = match current.get(position) {
+                Some(value) => value.to_vec(),
+                None => self.tree.try_leaf(*position)?.to_vec(),
+            };
+            olds.push(old);
+            current.insert(*position, leaf.as_ref());
+        }
+        self.tree.try_batch_update(updates)?;
+
+        let root = self.tree.root();
+        for ((position, leaf), old) in updates.iter().zip(olds.iter()) {
+            for hook in self.hooks.iter() {
+                hook.on_update(*position, old, leaf.as_ref(), root);
+            }
+        }
+        Ok(())
+    }
+}
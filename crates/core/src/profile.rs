@@ -0,0 +1,69 @@
+//! Resource profiles: one switch for the size limits, buffer sizes and thread counts used by
+//! hosts that cannot tune each call, such as the napi bindings on small containers.
+
+use crate::leaf_io::DEFAULT_BUILD_BUFFER;
+use crate::merkle::TreeLimits;
+
+/// Environment variable read by [`ResourceProfile::from_env`].
+pub const PROFILE_ENV: &str = "MERKLERUST_PROFILE";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResourceProfile {
+    #[default]
+    Standard,
+    /// Single-threaded, small I/O buffers and a 256 MiB cap on in-memory trees.
+    LowMemory,
+}
+
+impl ResourceProfile {
+    /// `"standard"` or `"low-memory"`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Self::Standard),
+            "low-memory" => Some(Self::LowMemory),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::LowMemory => "low-memory",
+        }
+    }
+
+    /// The profile named by [`PROFILE_ENV`], [`Self::Standard`] if it is unset or unknown.
+    pub fn from_env() -> Self {
+        std::env::var(PROFILE_ENV)
+            .ok()
+            .and_then(|name| Self::from_name(&name))
+            .unwrap_or_default()
+    }
+
+    /// Limits to check before building an in-memory tree.
+    pub fn tree_limits(self) -> TreeLimits {
+        match self {
+            Self::Standard => TreeLimits::default(),
+            Self::LowMemory => TreeLimits {
+                max_bytes: 256 << 20,
+                ..TreeLimits::default()
+            },
+        }
+    }
+
+    /// Worker threads for batch operations.
+    pub fn threads(self) -> usize {
+        match self {
+            Self::Standard => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            Self::LowMemory => 1,
+        }
+    }
+
+    /// Memory budget used by [`crate::leaf_io::TreeBuilder::with_profile`].
+    pub fn build_buffer(self) -> usize {
+        match self {
+            Self::Standard => DEFAULT_BUILD_BUFFER,
+            Self::LowMemory => 64 << 10,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Prost message types for `proto/merklerust.proto` (package `merklerust.v1`). The structs are
+//! kept in sync with the schema by hand so the crate builds without `protoc`; field tags must
+//! match the `.proto` file.
+
+use crate::merkle::{self, Bytes, Hash, MerkleError};
+use crate::smt::{self, key_path_from_id};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TreeMetadata {
+    #[prost(bytes = "vec", tag = "1")]
+    pub root: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub leaf_count: u64,
+    #[prost(uint64, tag = "3")]
+    pub node_count: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Tree {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub nodes: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Proof {
+    #[prost(bytes = "vec", tag = "1")]
+    pub leaf: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub siblings: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MultiProof {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub leaves: Vec<Vec<u8>>,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub proof: Vec<Vec<u8>>,
+    #[prost(bool, repeated, tag = "3")]
+    pub proof_flags: Vec<bool>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SmtProof {
+    #[prost(uint32, tag = "1")]
+    pub key_path: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub bitmap: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub siblings: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SmtMultiProof {
+    #[prost(uint32, tag = "1")]
+    pub key_path: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub bitmap: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub siblings: Vec<Vec<u8>>,
+}
+
+impl TreeMetadata {
+    pub fn from_tree(tree: &[Bytes]) -> Self {
+        assert!(
+            !tree.is_empty(),
+            "Expected non-zero number of nodes in merkle tree"
+        );
+        Self {
+            root: tree[0].clone(),
+            leaf_count: tree.len().div_ceil(2) as u64,
+            node_count: tree.len() as u64,
+        }
+    }
+}
+
+impl From<&[Bytes]> for Tree {
+    fn from(tree: &[Bytes]) -> Self {
+        Self {
+            nodes: tree.to_vec(),
+        }
+    }
+}
+
+impl From<&merkle::MultiProof> for MultiProof {
+    fn from(mp: &merkle::MultiProof) -> Self {
+        Self {
+            leaves: mp.leaves.clone(),
+            proof: mp.proof.clone(),
+            proof_flags: mp.proof_flags.clone(),
+        }
+    }
+}
+
+impl From<MultiProof> for merkle::MultiProof {
+    fn from(mp: MultiProof) -> Self {
+        merkle::MultiProof::new(mp.leaves, mp.proof, mp.proof_flags)
+    }
+}
+
+fn key_path(id: u32) -> Result<smt::KeyPath, MerkleError> {
+    let id = u8::try_from(id)
+        .map_err(|_| MerkleError::InvalidConfig(format!("Unknown SMT key path id {}", id)))?;
+    key_path_from_id(id)
+}
+
+fn hashes(nodes: Vec<Vec<u8>>) -> Result<Vec<Hash>, MerkleError> {
+    nodes
+        .into_iter()
+        .map(|n| Hash::try_from(n.as_slice()).map_err(|_| MerkleError::InvalidNode(n.len())))
+        .collect()
+}
+
+impl From<&smt::SmtProof> for SmtProof {
+    fn from(proof: &smt::SmtProof) -> Self {
+        Self {
+            key_path: proof.key_path.id().into(),
+            bitmap: proof.bitmap.to_vec(),
+            siblings: proof.siblings.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+}
+
+impl TryFrom<SmtProof> for smt::SmtProof {
+    type Error = MerkleError;
+
+    fn try_from(proof: SmtProof) -> Result<Self, MerkleError> {
+        let bitmap = proof.bitmap.as_slice().try_into();
+        Ok(Self {
+            key_path: key_path(proof.key_path)?,
+            bitmap: bitmap.map_err(|_| MerkleError::InvalidNode(proof.bitmap.len()))?,
+            siblings: hashes(proof.siblings)?,
+        })
+    }
+}
+
+impl From<&smt::SmtMultiProof> for SmtMultiProof {
+    fn from(proof: &smt::SmtMultiProof) -> Self {
+        Self {
+            key_path: proof.key_path.id().into(),
+            bitmap: proof.bitmap.clone(),
+            siblings: proof.siblings.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+}
+
+impl TryFrom<SmtMultiProof> for smt::SmtMultiProof {
+    type Error = MerkleError;
+
+    fn try_from(proof: SmtMultiProof) -> Result<Self, MerkleError> {
+        Ok(Self {
+            key_path: key_path(proof.key_path)?,
+            bitmap: proof.bitmap,
+            siblings: hashes(proof.siblings)?,
+        })
+    }
+}
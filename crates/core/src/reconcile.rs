@@ -0,0 +1,265 @@
+//! Anti-entropy set reconciliation (the Dynamo/Cassandra use of Merkle trees). Each replica
+//! partitions its 32-byte key hashes by their first `depth` bits into buckets and builds a
+//! binary digest tree over the buckets. Peers exchange digests top-down, descend only into
+//! mismatching prefixes, and finally swap the keys of mismatching buckets.
+//!
+//! Digests are kept in a flat heap (root at index 0, children of `i` at `2i+1` and `2i+2`) and
+//! updated incrementally on every write: a bucket digest is the XOR of its keys, so an insert or
+//! remove costs one XOR plus `depth` node hashes, independent of how many keys a bucket holds.
+//!
+//! The protocol is transport-agnostic: [`SetReconciler::start`] and
+//! [`SetReconciler::respond`] produce [`ReconcileMessage`]s which the caller ships to the peer
+//! and feeds into the peer's `respond`, until neither side has anything left to send.
+
+use std::collections::BTreeSet;
+
+use crate::consts::ZERO_HASH;
+use crate::hashes::keccak256;
+use crate::merkle::Hash;
+
+/// Digest of an empty bucket or subtree; lets both sides skip empty regions cheaply. Note that
+/// XOR also yields this value for (astronomically unlikely) key sets that cancel out.
+const EMPTY_DIGEST: Hash = ZERO_HASH;
+
+/// Deepest supported partitioning (2^24 buckets).
+pub const MAX_DEPTH: u8 = 24;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReconcileMessage {
+    /// Digests of the listed prefixes at `level` (a prefix at level `l` has `l` bits).
+    Digests {
+        level: u8,
+        entries: Vec<(u32, Hash)>,
+    },
+    /// All keys of the bucket `prefix`; the receiver answers with its own keys for that
+    /// bucket if `reply` is set.
+    Keys {
+        prefix: u32,
+        keys: Vec<Hash>,
+        reply: bool,
+    },
+}
+
+/// A peer message that does not fit this replica's partitioning, e.g. from a peer built with
+/// a different depth.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ReconcileError {
+    #[error("Expected level of at most {depth}, got {level}")]
+    LevelOutOfRange { level: u8, depth: u8 },
+    #[error("Prefix {prefix} out of range for level {level}")]
+    PrefixOutOfRange { prefix: u32, level: u8 },
+}
+
+/// Output of one [`SetReconciler::respond`] step.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReconcileStep {
+    /// Messages to send to the peer.
+    pub messages: Vec<ReconcileMessage>,
+    /// Keys the peer has that this replica lacks.
+    pub missing: Vec<Hash>,
+}
+
+pub struct SetReconciler {
+    depth: u8,
+    buckets: Vec<BTreeSet<Hash>>,
+    digests: Vec<Hash>,
+    len: usize,
+}
+
+fn xor_into(acc: &mut Hash, key: &Hash) {
+    for (a, k) in acc.iter_mut().zip(key.iter()) {
+        *a ^= k;
+    }
+}
+
+fn heap_index(level: u8, prefix: u32) -> usize {
+    (1usize << level) - 1 + prefix as usize
+}
+
+impl SetReconciler {
+    pub fn new(depth: u8) -> Self {
+        assert!(
+            depth <= MAX_DEPTH,
+            "Expected depth of at most {}, got {}",
+            MAX_DEPTH,
+            depth
+        );
+        Self {
+            depth,
+            buckets: vec![BTreeSet::new(); 1 << depth],
+            digests: vec![EMPTY_DIGEST; (1 << (depth as usize + 1)) - 1],
+            len: 0,
+        }
+    }
+
+    /// Reconciler with `bucket_count` buckets, which must be a power of two.
+    pub fn with_buckets(bucket_count: usize) -> Self {
+        assert!(
+            bucket_count.is_power_of_two(),
+            "Expected a power-of-two bucket count, got {}",
+            bucket_count
+        );
+        Self::new(bucket_count.trailing_zeros() as u8)
+    }
+
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bucket_of(&self, key: &Hash) -> usize {
+        if self.depth == 0 {
+            return 0;
+        }
+        let top = u32::from_be_bytes([key[0], key[1], key[2], key[3]]);
+        (top >> (32 - self.depth as u32)) as usize
+    }
+
+    pub fn insert(&mut self, key: Hash) -> bool {
+        let b = self.bucket_of(&key);
+        let inserted = self.buckets[b].insert(key);
+        if inserted {
+            self.len += 1;
+            self.toggle(b, &key);
+        }
+        inserted
+    }
+
+    pub fn remove(&mut self, key: &Hash) -> bool {
+        let b = self.bucket_of(key);
+        let removed = self.buckets[b].remove(key);
+        if removed {
+            self.len -= 1;
+            self.toggle(b, key);
+        }
+        removed
+    }
+
+    /// XOR `key` into bucket `bucket` and re-hash its ancestors up to the root.
+    fn toggle(&mut self, bucket: usize, key: &Hash) {
+        let mut i = heap_index(self.depth, bucket as u32);
+        xor_into(&mut self.digests[i], key);
+        while i > 0 {
+            i = (i - 1) / 2;
+            let (left, right) = (self.digests[2 * i + 1], self.digests[2 * i + 2]);
+            self.digests[i] = if left == EMPTY_DIGEST && right == EMPTY_DIGEST {
+                EMPTY_DIGEST
+            } else {
+                keccak256(&[&left[..], &right[..]].concat())
+            };
+        }
+    }
+
+    pub fn contains(&self, key: &Hash) -> bool {
+        self.buckets[self.bucket_of(key)].contains(key)
+    }
+
+    /// Digest of the subtree covering `prefix` at `level`. Panics if either is out of range.
+    pub fn digest(&self, level: u8, prefix: u32) -> Hash {
+        self.try_digest(level, prefix)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_digest(&self, level: u8, prefix: u32) -> Result<Hash, ReconcileError> {
+        self.check_prefix(level, prefix)?;
+        Ok(self.digests[heap_index(level, prefix)])
+    }
+
+    fn check_prefix(&self, level: u8, prefix: u32) -> Result<(), ReconcileError> {
+        if level > self.depth {
+            return Err(ReconcileError::LevelOutOfRange {
+                level,
+                depth: self.depth,
+            });
+        }
+        if (prefix as u64) >= 1u64 << level {
+            return Err(ReconcileError::PrefixOutOfRange { prefix, level });
+        }
+        Ok(())
+    }
+
+    pub fn root(&self) -> Hash {
+        self.digest(0, 0)
+    }
+
+    /// First message of a reconciliation round.
+    pub fn start(&self) -> ReconcileMessage {
+        ReconcileMessage::Digests {
+            level: 0,
+            entries: vec![(0, self.root())],
+        }
+    }
+
+    fn keys_message(&self, prefix: u32, reply: bool) -> ReconcileMessage {
+        ReconcileMessage::Keys {
+            prefix,
+            keys: self.buckets[prefix as usize].iter().copied().collect(),
+            reply,
+        }
+    }
+
+    /// Process a message from the peer. Both replicas must use the same depth; a level or
+    /// prefix outside this replica's partitioning is rejected before anything is looked up.
+    pub fn respond(&self, msg: &ReconcileMessage) -> Result<ReconcileStep, ReconcileError> {
+        let mut step = ReconcileStep::default();
+        match msg {
+            ReconcileMessage::Digests { level, entries } => {
+                let mut mismatched: Vec<u32> = Vec::new();
+                for (prefix, digest) in entries.iter() {
+                    if self.try_digest(*level, *prefix)? != *digest {
+                        mismatched.push(*prefix);
+                    }
+                }
+                if mismatched.is_empty() {
+                    return Ok(step);
+                }
+                if *level == self.depth {
+                    step.messages.extend(
+                        mismatched
+                            .into_iter()
+                            .map(|prefix| self.keys_message(prefix, true)),
+                    );
+                } else {
+                    let child_level = level + 1;
+                    let entries = mismatched
+                        .into_iter()
+                        .flat_map(|p| [p << 1, (p << 1) | 1])
+                        .map(|p| (p, self.digest(child_level, p)))
+                        .collect();
+                    step.messages.push(ReconcileMessage::Digests {
+                        level: child_level,
+                        entries,
+                    });
+                }
+            }
+            ReconcileMessage::Keys {
+                prefix,
+                keys,
+                reply,
+            } => {
+                self.check_prefix(self.depth, *prefix)?;
+                let ours = &self.buckets[*prefix as usize];
+                step.missing = keys
+                    .iter()
+                    .filter(|k| !ours.contains(*k))
+                    .copied()
+                    .collect();
+                if *reply {
+                    step.messages.push(self.keys_message(*prefix, false));
+                }
+            }
+        }
+        Ok(step)
+    }
+}
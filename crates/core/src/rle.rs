@@ -0,0 +1,77 @@
+//! Run-length encoding of node lists. Trees padded with a repeated leaf, such as sparse
+//! airdrops padded to a power of two, repeat the same leaf and the same padding subtree
+//! hashes level after level, so multiproofs over them and their dumps shrink to one entry per
+//! run. See [`CompressedMultiProof`] and [`crate::simple::SimpleMerkleTree::dump_compressed`].
+
+use crate::merkle::{Bytes, MultiProof};
+
+/// `count` consecutive copies of `value`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    pub value: Bytes,
+    pub count: usize,
+}
+
+pub fn compress_runs<N: AsRef<[u8]>>(nodes: &[N]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for node in nodes {
+        match runs.last_mut() {
+            Some(run) if run.value == node.as_ref() => run.count += 1,
+            _ => runs.push(Run {
+                value: node.as_ref().to_vec(),
+                count: 1,
+            }),
+        }
+    }
+    runs
+}
+
+/// Total count of `runs`, or `None` on overflow.
+pub fn expanded_len(runs: &[Run]) -> Option<usize> {
+    runs.iter().try_fold(0usize, |n, r| n.checked_add(r.count))
+}
+
+/// Inverse of [`compress_runs`]. Callers taking runs from untrusted input should bound
+/// [`expanded_len`] first.
+pub fn expand_runs(runs: &[Run]) -> Vec<Bytes> {
+    runs.iter()
+        .flat_map(|r| std::iter::repeat_n(&r.value, r.count).cloned())
+        .collect()
+}
+
+/// [`MultiProof`] with its leaves and proof nodes run-length encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedMultiProof {
+    pub leaves: Vec<Run>,
+    pub proof: Vec<Run>,
+    pub proof_flags: Vec<bool>,
+}
+
+impl CompressedMultiProof {
+    pub fn compress(mp: &MultiProof) -> Self {
+        Self {
+            leaves: compress_runs(&mp.leaves),
+            proof: compress_runs(&mp.proof),
+            proof_flags: mp.proof_flags.clone(),
+        }
+    }
+
+    /// The plain multiproof, or `None` if the runs expand to more nodes than the flags allow
+    /// (a valid multiproof has at most `proof_flags.len() + 1` leaves and proof nodes).
+    pub fn expand(&self) -> Option<MultiProof> {
+        let max = self.proof_flags.len() + 1;
+        if expanded_len(&self.leaves)? > max || expanded_len(&self.proof)? > max {
+            return None;
+        }
+        Some(MultiProof::new(
+            expand_runs(&self.leaves),
+            expand_runs(&self.proof),
+            self.proof_flags.clone(),
+        ))
+    }
+
+    /// Number of node values stored, against `leaves.len() + proof.len()` uncompressed.
+    pub fn stored_nodes(&self) -> usize {
+        self.leaves.len() + self.proof.len()
+    }
+}
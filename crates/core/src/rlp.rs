@@ -0,0 +1,198 @@
+//! RLP encoding of proofs and multiproofs, for protocols exchanging witness data the way
+//! Ethereum does. A proof is a list of byte strings; a multiproof is the list
+//! `[leaves, proof, proof_flags]` with flags encoded as `0x01` (true) or the empty string
+//! (false).
+
+use crate::merkle::{Bytes, MultiProof};
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RlpError {
+    #[error("unexpected end of RLP input")]
+    UnexpectedEnd,
+    #[error("non-canonical RLP encoding")]
+    NonCanonical,
+    #[error("trailing bytes after RLP item")]
+    TrailingBytes,
+    #[error("expected RLP list")]
+    ExpectedList,
+    #[error("expected RLP byte string")]
+    ExpectedBytes,
+    #[error("invalid proof flag")]
+    InvalidFlag,
+    #[error("RLP lists nested deeper than {MAX_DEPTH}")]
+    TooDeep,
+}
+
+/// Deepest list nesting a proof format uses: the multiproof's list of lists. Decoding stops
+/// there, so hostile input cannot recurse without bound.
+const MAX_DEPTH: usize = 2;
+
+enum Item<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<Item<'a>>),
+}
+
+fn encode_length(out: &mut Vec<u8>, len: usize, offset: u8) {
+    if len < 56 {
+        out.push(offset + len as u8);
+    } else {
+        let be = len.to_be_bytes();
+        let skip = be.iter().position(|&b| b != 0).unwrap_or(be.len() - 1);
+        out.push(offset + 55 + (be.len() - skip) as u8);
+        out.extend_from_slice(&be[skip..]);
+    }
+}
+
+fn encode_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        out.push(bytes[0]);
+    } else {
+        encode_length(out, bytes.len(), 0x80);
+        out.extend_from_slice(bytes);
+    }
+}
+
+fn encode_list(out: &mut Vec<u8>, payload: &[u8]) {
+    encode_length(out, payload.len(), 0xc0);
+    out.extend_from_slice(payload);
+}
+
+fn encode_bytes_list(items: &[Bytes]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for item in items.iter() {
+        encode_bytes(&mut payload, item);
+    }
+    let mut out = Vec::new();
+    encode_list(&mut out, &payload);
+    out
+}
+
+fn read_long_len(rest: &[u8], len_of_len: usize) -> Result<usize, RlpError> {
+    let bytes = rest.get(..len_of_len).ok_or(RlpError::UnexpectedEnd)?;
+    if bytes[0] == 0 || len_of_len > std::mem::size_of::<usize>() {
+        return Err(RlpError::NonCanonical);
+    }
+    let len = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+    if len < 56 {
+        return Err(RlpError::NonCanonical);
+    }
+    Ok(len)
+}
+
+fn split_body(rest: &[u8], start: usize, len: usize) -> Result<(&[u8], &[u8]), RlpError> {
+    let end = start.checked_add(len).ok_or(RlpError::UnexpectedEnd)?;
+    let body = rest.get(start..end).ok_or(RlpError::UnexpectedEnd)?;
+    Ok((body, &rest[end..]))
+}
+
+/// Decode one item from the front of `data`, returning it and the remaining input. `depth` is
+/// how many more levels of lists may be opened.
+fn decode_item(data: &[u8], depth: usize) -> Result<(Item<'_>, &[u8]), RlpError> {
+    let (&prefix, rest) = data.split_first().ok_or(RlpError::UnexpectedEnd)?;
+
+    match prefix {
+        0x00..=0x7f => Ok((Item::Bytes(&data[..1]), rest)),
+        0x80..=0xb7 => {
+            let (body, rest) = split_body(rest, 0, (prefix - 0x80) as usize)?;
+            if body.len() == 1 && body[0] < 0x80 {
+                return Err(RlpError::NonCanonical);
+            }
+            Ok((Item::Bytes(body), rest))
+        }
+        0xb8..=0xbf => {
+            let len_of_len = (prefix - 0xb7) as usize;
+            let len = read_long_len(rest, len_of_len)?;
+            let (body, rest) = split_body(rest, len_of_len, len)?;
+            Ok((Item::Bytes(body), rest))
+        }
+        0xc0..=0xf7 => {
+            let (body, rest) = split_body(rest, 0, (prefix - 0xc0) as usize)?;
+            Ok((Item::List(decode_list_payload(body, depth)?), rest))
+        }
+        0xf8..=0xff => {
+            let len_of_len = (prefix - 0xf7) as usize;
+            let len = read_long_len(rest, len_of_len)?;
+            let (body, rest) = split_body(rest, len_of_len, len)?;
+            Ok((Item::List(decode_list_payload(body, depth)?), rest))
+        }
+    }
+}
+
+fn decode_list_payload(mut payload: &[u8], depth: usize) -> Result<Vec<Item<'_>>, RlpError> {
+    let depth = depth.checked_sub(1).ok_or(RlpError::TooDeep)?;
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        let (item, rest) = decode_item(payload, depth)?;
+        items.push(item);
+        payload = rest;
+    }
+    Ok(items)
+}
+
+fn decode_exact(data: &[u8]) -> Result<Item<'_>, RlpError> {
+    let (item, rest) = decode_item(data, MAX_DEPTH)?;
+    if !rest.is_empty() {
+        return Err(RlpError::TrailingBytes);
+    }
+    Ok(item)
+}
+
+fn into_list(item: Item<'_>) -> Result<Vec<Item<'_>>, RlpError> {
+    match item {
+        Item::List(items) => Ok(items),
+        Item::Bytes(_) => Err(RlpError::ExpectedList),
+    }
+}
+
+fn into_bytes_list(item: Item<'_>) -> Result<Vec<Bytes>, RlpError> {
+    into_list(item)?
+        .into_iter()
+        .map(|i| match i {
+            Item::Bytes(b) => Ok(b.to_vec()),
+            Item::List(_) => Err(RlpError::ExpectedBytes),
+        })
+        .collect()
+}
+
+pub fn encode_proof(proof: &[Bytes]) -> Bytes {
+    encode_bytes_list(proof)
+}
+
+pub fn decode_proof(data: &[u8]) -> Result<Vec<Bytes>, RlpError> {
+    into_bytes_list(decode_exact(data)?)
+}
+
+pub fn encode_multi_proof(mp: &MultiProof) -> Bytes {
+    let flags: Vec<Bytes> = mp
+        .proof_flags
+        .iter()
+        .map(|&f| if f { vec![1u8] } else { Vec::new() })
+        .collect();
+
+    let mut payload = encode_bytes_list(&mp.leaves);
+    payload.extend(encode_bytes_list(&mp.proof));
+    payload.extend(encode_bytes_list(&flags));
+    let mut out = Vec::new();
+    encode_list(&mut out, &payload);
+    out
+}
+
+pub fn decode_multi_proof(data: &[u8]) -> Result<MultiProof, RlpError> {
+    let mut fields = into_list(decode_exact(data)?)?.into_iter();
+    let mut next = || fields.next().ok_or(RlpError::UnexpectedEnd);
+    let leaves = into_bytes_list(next()?)?;
+    let proof = into_bytes_list(next()?)?;
+    let proof_flags = into_bytes_list(next()?)?
+        .into_iter()
+        .map(|f| match f.as_slice() {
+            [1] => Ok(true),
+            [] => Ok(false),
+            _ => Err(RlpError::InvalidFlag),
+        })
+        .collect::<Result<Vec<bool>, RlpError>>()?;
+    if fields.next().is_some() {
+        return Err(RlpError::TrailingBytes);
+    }
+
+    Ok(MultiProof::new(leaves, proof, proof_flags))
+}
@@ -0,0 +1,240 @@
+//! Serde support (feature `serde`). Byte values are written as `0x`-prefixed hex strings for
+//! human-readable formats such as JSON and as raw bytes for binary formats such as bincode.
+//! Tree types serialize to the same shapes as their JSON dumps and are validated when
+//! deserialized. The [`node`], [`nodes`] and [`hash`] modules can be used with
+//! `#[serde(with = "...")]` on caller-defined fields.
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::consts::{SIMPLE_FORMAT, STANDARD_FORMAT};
+use crate::dump::DumpError;
+use crate::hashes::Hasher;
+use crate::merkle::{Bytes, Hash};
+use crate::simple::{SimpleMerkleTree, SimpleValue};
+use crate::standard::{StandardMerkleTree, StandardValue};
+use crate::tree::{MerkleTree, TreeConfig};
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte string or 0x-prefixed hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Bytes, E> {
+        v.strip_prefix("0x")
+            .and_then(|digits| hex::decode(digits).ok())
+            .ok_or_else(|| E::custom(format!("invalid hex value {:?}", v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Bytes, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+        let mut out = Vec::new();
+        while let Some(b) = seq.next_element()? {
+            out.push(b);
+        }
+        Ok(out)
+    }
+}
+
+/// A single byte string.
+pub mod node {
+    use super::*;
+
+    pub fn serialize<T: AsRef<[u8]>, S: Serializer>(node: &T, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            s.serialize_str(&format!("0x{}", hex::encode(node.as_ref())))
+        } else {
+            s.serialize_bytes(node.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Bytes, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_str(BytesVisitor)
+        } else {
+            d.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+}
+
+/// A 32-byte [`Hash`].
+pub mod hash {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(hash: &Hash, s: S) -> Result<S::Ok, S::Error> {
+        node::serialize(hash, s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Hash, D::Error> {
+        let bytes = node::deserialize(d)?;
+        let len = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| de::Error::invalid_length(len, &"32 bytes"))
+    }
+}
+
+struct NodeRef<'a>(&'a [u8]);
+
+impl Serialize for NodeRef<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        node::serialize(&self.0, s)
+    }
+}
+
+struct Node(Bytes);
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        node::deserialize(d).map(Node)
+    }
+}
+
+/// A list of byte strings, such as a flat tree or a proof.
+pub mod nodes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(nodes: &[Bytes], s: S) -> Result<S::Ok, S::Error> {
+        let mut seq = s.serialize_seq(Some(nodes.len()))?;
+        for n in nodes {
+            seq.serialize_element(&NodeRef(n))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Bytes>, D::Error> {
+        let nodes = Vec::<Node>::deserialize(d)?;
+        Ok(nodes.into_iter().map(|n| n.0).collect())
+    }
+}
+
+fn check_format<E: de::Error>(format: &str, expected: &str) -> Result<(), E> {
+    if format == expected {
+        Ok(())
+    } else {
+        Err(E::custom(DumpError::UnknownFormat(format.to_string())))
+    }
+}
+
+#[derive(Serialize)]
+struct TreeRef<'a> {
+    #[serde(with = "nodes")]
+    tree: &'a [Bytes],
+    config: &'a TreeConfig,
+}
+
+#[derive(Deserialize)]
+struct TreeOwned {
+    #[serde(with = "nodes")]
+    tree: Vec<Bytes>,
+    config: TreeConfig,
+}
+
+impl<H: Hasher> Serialize for MerkleTree<H> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        TreeRef {
+            tree: self.as_slice(),
+            config: &self.config(),
+        }
+        .serialize(s)
+    }
+}
+
+/// The hasher is not serialized; the tree is checked against `H::default()`, which must carry
+/// the hasher name the recorded config names.
+impl<'de, H: Hasher + Default> Deserialize<'de> for MerkleTree<H> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let TreeOwned { tree, config } = TreeOwned::deserialize(d)?;
+        MerkleTree::from_dump_parts(tree, H::default(), config).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StandardRef<'a> {
+    format: &'a str,
+    leaf_encoding: &'a [String],
+    #[serde(with = "nodes")]
+    tree: &'a [Bytes],
+    values: &'a [StandardValue],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StandardOwned {
+    format: String,
+    leaf_encoding: Vec<String>,
+    #[serde(with = "nodes")]
+    tree: Vec<Bytes>,
+    values: Vec<StandardValue>,
+}
+
+impl Serialize for StandardMerkleTree {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        StandardRef {
+            format: STANDARD_FORMAT,
+            tree: self.tree(),
+            values: self.values(),
+            leaf_encoding: self.leaf_encoding(),
+        }
+        .serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for StandardMerkleTree {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let r = StandardOwned::deserialize(d)?;
+        check_format(&r.format, STANDARD_FORMAT)?;
+        StandardMerkleTree::from_dump_parts(r.tree, r.values, r.leaf_encoding)
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+struct SimpleRef<'a> {
+    format: &'a str,
+    #[serde(with = "nodes")]
+    tree: &'a [Bytes],
+    values: &'a [SimpleValue],
+}
+
+#[derive(Deserialize)]
+struct SimpleOwned {
+    format: String,
+    #[serde(with = "nodes")]
+    tree: Vec<Bytes>,
+    values: Vec<SimpleValue>,
+}
+
+impl Serialize for SimpleMerkleTree {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        SimpleRef {
+            format: SIMPLE_FORMAT,
+            tree: self.tree(),
+            values: self.values(),
+        }
+        .serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for SimpleMerkleTree {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let r = SimpleOwned::deserialize(d)?;
+        check_format(&r.format, SIMPLE_FORMAT)?;
+        SimpleMerkleTree::from_dump_parts(r.tree, r.values).map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,316 @@
+//! Port of OpenZeppelin's `SimpleMerkleTree`: leaves are pre-hashed 32-byte values used as-is,
+//! combined with the sorted-pair keccak256 node hash. Trees can be dumped to and loaded from
+//! the library's `simple-v1` JSON format.
+
+use std::collections::HashMap;
+
+use crate::consts::{SIMPLE_FORMAT, SIMPLE_RLE_FORMAT};
+use crate::dump::{hex_string, invalid_dump, parse_hex32, DumpError, Json};
+use crate::hashes::standard_node_hash;
+use crate::merkle::{
+    check_leaf_node, check_tree_size, get_multi_proof, get_proof, is_valid_merkle_tree,
+    make_merkle_tree_bytes, verify_proof, Bytes, Hash, MultiProof, TreeLimits,
+};
+use crate::rle::{compress_runs, expand_runs, expanded_len, Run};
+use crate::standard::TreeOptions;
+
+fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
+    standard_node_hash(a, b).to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SimpleValue {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::hash"))]
+    pub value: Hash,
+    /// Index of the value's leaf in the flat tree.
+    pub tree_index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct SimpleMerkleTree {
+    tree: Vec<Bytes>,
+    values: Vec<SimpleValue>,
+    hash_lookup: HashMap<Hash, usize>,
+}
+
+impl SimpleMerkleTree {
+    /// Tree over `values` with leaves sorted, as the JS library does by default.
+    pub fn of(values: Vec<Hash>) -> Self {
+        Self::of_with_options(values, TreeOptions::default())
+    }
+
+    pub fn of_with_options(values: Vec<Hash>, options: TreeOptions) -> Self {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        if options.sort_leaves {
+            order.sort_by_key(|&i| values[i]);
+        }
+        let tree = make_merkle_tree_bytes(
+            order.iter().map(|&i| values[i].to_vec()).collect(),
+            node_hash,
+        );
+
+        let mut tree_indices = vec![0; values.len()];
+        for (leaf_index, &value_index) in order.iter().enumerate() {
+            tree_indices[value_index] = tree.len() - 1 - leaf_index;
+        }
+        let values = values
+            .into_iter()
+            .zip(tree_indices)
+            .map(|(value, tree_index)| SimpleValue { value, tree_index })
+            .collect();
+        Self::from_parts(tree, values)
+    }
+
+    fn from_parts(tree: Vec<Bytes>, values: Vec<SimpleValue>) -> Self {
+        let hash_lookup = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.value, i))
+            .collect();
+        Self {
+            tree,
+            values,
+            hash_lookup,
+        }
+    }
+
+    /// Check a proof against `root` without building a tree.
+    pub fn verify(root: &[u8], value: &Hash, proof: &[Bytes]) -> bool {
+        verify_proof(root, value, proof, node_hash)
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.tree[0]
+    }
+
+    pub fn tree(&self) -> &[Bytes] {
+        &self.tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Values in input order.
+    pub fn values(&self) -> &[SimpleValue] {
+        &self.values
+    }
+
+    /// Index (in input order) of `value`, if it is in the tree.
+    pub fn leaf_lookup(&self, value: &Hash) -> Option<usize> {
+        self.hash_lookup.get(value).copied()
+    }
+
+    fn value_at(&self, value_index: usize) -> &SimpleValue {
+        self.values.get(value_index).unwrap_or_else(|| {
+            panic!(
+                "Index {} out of range for {} values",
+                value_index,
+                self.values.len()
+            )
+        })
+    }
+
+    pub fn get_proof(&self, value_index: usize) -> Vec<Bytes> {
+        get_proof(&self.tree, self.value_at(value_index).tree_index)
+    }
+
+    pub fn get_proof_for_value(&self, value: &Hash) -> Vec<Bytes> {
+        let index = self.leaf_lookup(value).expect("Leaf is not in tree");
+        self.get_proof(index)
+    }
+
+    pub fn get_multi_proof(&self, value_indices: &[usize]) -> MultiProof {
+        let indices = value_indices
+            .iter()
+            .map(|&i| self.value_at(i).tree_index)
+            .collect();
+        get_multi_proof(&self.tree, indices)
+    }
+
+    pub fn verify_proof(&self, value: &Hash, proof: &[Bytes]) -> bool {
+        Self::verify(self.root(), value, proof)
+    }
+
+    /// `{"format":"simple-v1","tree":[...],"values":[{"value":...,"treeIndex":...}]}`.
+    pub fn dump(&self) -> String {
+        Json::Object(vec![
+            (
+                "format".to_string(),
+                Json::String(SIMPLE_FORMAT.to_string()),
+            ),
+            (
+                "tree".to_string(),
+                Json::Array(self.tree.iter().map(|n| hex_string(n)).collect()),
+            ),
+            (
+                "values".to_string(),
+                Json::Array(
+                    self.values
+                        .iter()
+                        .map(|v| {
+                            Json::Object(vec![
+                                ("value".to_string(), hex_string(&v.value)),
+                                (
+                                    "treeIndex".to_string(),
+                                    Json::Number(v.tree_index.to_string()),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+        .to_json_string()
+    }
+
+    /// Like [`Self::dump`] in the `simple-rle-v1` format: runs of equal tree nodes become
+    /// `{"node":...,"count":n}` and runs of equal values at consecutive leaves become
+    /// `{"value":...,"treeIndex":i,"count":n}` for tree indices `i, i-1, ..., i-n+1`.
+    pub fn dump_compressed(&self) -> String {
+        let mut values: Vec<(Hash, usize, usize)> = Vec::new();
+        for v in self.values.iter() {
+            match values.last_mut() {
+                Some((value, index, count))
+                    if *value == v.value && index.checked_sub(*count) == Some(v.tree_index) =>
+                {
+                    *count += 1
+                }
+                _ => values.push((v.value, v.tree_index, 1)),
+            }
+        }
+        let count = |n: usize| ("count".to_string(), Json::Number(n.to_string()));
+        Json::Object(vec![
+            (
+                "format".to_string(),
+                Json::String(SIMPLE_RLE_FORMAT.to_string()),
+            ),
+            (
+                "tree".to_string(),
+                Json::Array(
+                    compress_runs(&self.tree)
+                        .iter()
+                        .map(|r| {
+                            Json::Object(vec![
+                                ("node".to_string(), hex_string(&r.value)),
+                                count(r.count),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "values".to_string(),
+                Json::Array(
+                    values
+                        .iter()
+                        .map(|&(value, tree_index, n)| {
+                            Json::Object(vec![
+                                ("value".to_string(), hex_string(&value)),
+                                (
+                                    "treeIndex".to_string(),
+                                    Json::Number(tree_index.to_string()),
+                                ),
+                                count(n),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+        .to_json_string()
+    }
+
+    /// Load a `simple-v1` or `simple-rle-v1` dump, checking that the tree is consistent and
+    /// every value sits at its leaf.
+    pub fn load(dump: &str) -> Result<Self, DumpError> {
+        let json = Json::parse(dump)?;
+        let format = json.field("format")?.as_str()?;
+        if format == SIMPLE_RLE_FORMAT {
+            return Self::load_compressed(&json);
+        }
+        if format != SIMPLE_FORMAT {
+            return Err(DumpError::UnknownFormat(format.to_string()));
+        }
+
+        let tree = json
+            .field("tree")?
+            .as_array()?
+            .iter()
+            .map(parse_hex32)
+            .collect::<Result<Vec<Bytes>, DumpError>>()?;
+        let mut values = Vec::new();
+        for v in json.field("values")?.as_array()? {
+            values.push(SimpleValue {
+                value: parse_hex32(v.field("value")?)?.try_into().unwrap(),
+                tree_index: v.field("treeIndex")?.as_usize()?,
+            });
+        }
+
+        Self::from_dump_parts(tree, values)
+    }
+
+    fn load_compressed(json: &Json) -> Result<Self, DumpError> {
+        let mut runs = Vec::new();
+        for r in json.field("tree")?.as_array()? {
+            runs.push(Run {
+                value: parse_hex32(r.field("node")?)?,
+                count: r.field("count")?.as_usize()?,
+            });
+        }
+        // bound the expansion before allocating: a tree of n nodes has (n + 1) / 2 leaves
+        let nodes = expanded_len(&runs).ok_or_else(|| invalid_dump("tree is too large"))?;
+        check_tree_size(nodes.div_ceil(2), &TreeLimits::default())
+            .map_err(|e| invalid_dump(e.to_string()))?;
+        let tree = expand_runs(&runs);
+
+        let mut values = Vec::new();
+        for v in json.field("values")?.as_array()? {
+            let value: Hash = parse_hex32(v.field("value")?)?.try_into().unwrap();
+            let tree_index = v.field("treeIndex")?.as_usize()?;
+            let count = v.field("count")?.as_usize()?;
+            if count > tree_index.saturating_add(1) || values.len() + count > nodes {
+                return Err(invalid_dump(format!(
+                    "value run at {} is out of range",
+                    tree_index
+                )));
+            }
+            values.extend((0..count).map(|i| SimpleValue {
+                value,
+                tree_index: tree_index - i,
+            }));
+        }
+
+        Self::from_dump_parts(tree, values)
+    }
+
+    /// Rebuild a tree from dumped parts, checking that the tree is consistent and every value
+    /// sits at its leaf.
+    pub(crate) fn from_dump_parts(
+        tree: Vec<Bytes>,
+        values: Vec<SimpleValue>,
+    ) -> Result<Self, DumpError> {
+        if !is_valid_merkle_tree(&tree, node_hash) {
+            return Err(invalid_dump("tree is not a valid merkle tree"));
+        }
+        for v in values.iter() {
+            if check_leaf_node(tree.len(), v.tree_index).is_err() || tree[v.tree_index] != v.value {
+                return Err(invalid_dump(format!(
+                    "value 0x{} does not match leaf {}",
+                    hex::encode(v.value),
+                    v.tree_index
+                )));
+            }
+        }
+        Ok(Self::from_parts(tree, values))
+    }
+}
@@ -0,0 +1,691 @@
+//! Sparse Merkle tree over the full 256-bit key space: every possible key has a leaf, empty
+//! leaves are the zero hash and empty subtrees at each height have a precomputed default hash,
+//! so only the paths of inserted keys are stored. Proofs show either the value stored at a key
+//! (membership) or that the key's leaf is empty (non-membership).
+//!
+//! A [`KeyPath`] maps each key to a 256-bit path, whose bits, most significant first, choose
+//! the leaf from the root; the occupied leaf is `hash_leaf(path ‖ value)`. The tree records its
+//! strategy and so do its proofs, which only verify under the strategy the verifier expects. An [`SmtMultiProof`] covers many keys at once, sending
+//! each sibling shared by their paths once, and an [`SmtWitness`] carries one with the values
+//! it proves so that [`apply_updates_with_witness`] computes the root after a batch of writes
+//! without the tree. Use a positional hasher such as
+//! [`crate::hashes::PositionalKeccak256Hasher`]: sorted pairs lose the left/right order, which
+//! lets an empty leaf elsewhere pass as a non-membership proof for any key, so the tree
+//! rejects them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::consts::ZERO_HASH;
+use crate::hashes::{sha256, Hasher};
+use crate::merkle::{constant_time_eq, Bytes, Hash, MerkleError};
+use crate::tree::{check_pair_ordering, PairOrdering};
+
+/// Number of levels below the root.
+pub const DEPTH: usize = 256;
+
+const EMPTY_LEAF: Hash = ZERO_HASH;
+
+/// How a key becomes the path of its leaf, to match systems that use raw 256-bit keys or
+/// hash theirs first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyPath {
+    /// The key is the path and must be 32 bytes.
+    #[default]
+    Raw,
+    /// SHA-256 of the key.
+    Sha256,
+    /// BLAKE3 of the key.
+    #[cfg(feature = "blake3")]
+    Blake3,
+    /// The first 32 bytes of a key of at least 32 bytes.
+    Truncated,
+}
+
+impl KeyPath {
+    /// Id carried by proofs and their byte encodings.
+    pub fn id(&self) -> u8 {
+        match self {
+            KeyPath::Raw => 0,
+            KeyPath::Sha256 => 1,
+            #[cfg(feature = "blake3")]
+            KeyPath::Blake3 => 2,
+            KeyPath::Truncated => 3,
+        }
+    }
+
+    /// Strategy with `id`, or `None` for an unknown one (or BLAKE3 without its feature).
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(KeyPath::Raw),
+            1 => Some(KeyPath::Sha256),
+            #[cfg(feature = "blake3")]
+            2 => Some(KeyPath::Blake3),
+            3 => Some(KeyPath::Truncated),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyPath::Raw => "raw",
+            KeyPath::Sha256 => "sha256",
+            #[cfg(feature = "blake3")]
+            KeyPath::Blake3 => "blake3",
+            KeyPath::Truncated => "truncated",
+        }
+    }
+
+    /// Strategy named `name`, as returned by [`Self::name`].
+    pub fn by_name(name: &str) -> Option<Self> {
+        (0..=3).filter_map(Self::from_id).find(|k| k.name() == name)
+    }
+
+    /// Path of `key`; [`MerkleError::InvalidNode`] for a key too short for `Raw` or
+    /// `Truncated`, or a `Raw` key longer than 32 bytes.
+    pub fn path(&self, key: &[u8]) -> Result<Hash, MerkleError> {
+        match self {
+            KeyPath::Raw => key.try_into().ok(),
+            KeyPath::Sha256 => Some(sha256(key)),
+            #[cfg(feature = "blake3")]
+            KeyPath::Blake3 => Some(blake3::hash(key).into()),
+            KeyPath::Truncated => key.first_chunk::<32>().copied(),
+        }
+        .ok_or(MerkleError::InvalidNode(key.len()))
+    }
+}
+
+pub(crate) fn key_path_from_id(id: u8) -> Result<KeyPath, MerkleError> {
+    KeyPath::from_id(id)
+        .ok_or_else(|| MerkleError::InvalidConfig(format!("Unknown SMT key path id {}", id)))
+}
+
+/// Bit of `key` deciding whether the node at `height` (0 = leaf) on its path is a right child.
+fn is_right(key: &Hash, height: usize) -> bool {
+    let bit = DEPTH - 1 - height;
+    key[bit / 8] & (0x80 >> (bit % 8)) != 0
+}
+
+/// `key` with the bits below `height` cleared, naming the subtree at `height` that holds it.
+fn prefix(key: &Hash, height: usize) -> Hash {
+    let mut out = *key;
+    for bit in DEPTH - height..DEPTH {
+        out[bit / 8] &= !(0x80 >> (bit % 8));
+    }
+    out
+}
+
+fn sibling_prefix(key: &Hash, height: usize) -> Hash {
+    let mut out = prefix(key, height);
+    let bit = DEPTH - 1 - height;
+    out[bit / 8] ^= 0x80 >> (bit % 8);
+    out
+}
+
+fn leaf_hash<H: Hasher>(hasher: &H, key: &Hash, value: &[u8]) -> Hash {
+    hasher.hash_leaf(&[&key[..], value].concat())
+}
+
+/// Siblings along a key's path, bottom-up. Siblings equal to the default hash of their height
+/// are left out and marked by a clear bit in `bitmap` (bit `h` is `bitmap[h / 8] >> (h % 8)`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmtProof {
+    pub key_path: KeyPath,
+    pub bitmap: [u8; 32],
+    pub siblings: Vec<Hash>,
+}
+
+/// Split concatenated 32-byte hashes.
+fn hashes_from_bytes(bytes: &[u8]) -> Result<Vec<Hash>, MerkleError> {
+    let (hashes, rest) = bytes.as_chunks::<32>();
+    if !rest.is_empty() {
+        return Err(MerkleError::InvalidNode(rest.len()));
+    }
+    Ok(hashes.to_vec())
+}
+
+impl SmtProof {
+    /// `key path id (1 byte) || bitmap || siblings`, the siblings as concatenated 32-byte
+    /// hashes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            &[self.key_path.id()][..],
+            &self.bitmap,
+            self.siblings.as_flattened(),
+        ]
+        .concat()
+    }
+
+    /// Read a proof written by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        let Some(([id], rest)) = bytes.split_first_chunk::<1>() else {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        };
+        let Some((bitmap, siblings)) = rest.split_first_chunk::<32>() else {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        };
+        Ok(Self {
+            key_path: key_path_from_id(*id)?,
+            bitmap: *bitmap,
+            siblings: hashes_from_bytes(siblings)?,
+        })
+    }
+
+    fn has_sibling(&self, height: usize) -> bool {
+        self.bitmap[height / 8] & (1 << (height % 8)) != 0
+    }
+
+    /// Root implied by `leaf` at `key`, or `None` if the proof is malformed.
+    fn compute_root<H: Hasher>(&self, key: &Hash, leaf: Hash, hasher: &H) -> Option<Hash> {
+        let expected: u32 = self.bitmap.iter().map(|b| b.count_ones()).sum();
+        if self.siblings.len() != expected as usize {
+            return None;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut default = EMPTY_LEAF;
+        let mut current = leaf;
+        for height in 0..DEPTH {
+            let sibling = if self.has_sibling(height) {
+                *siblings.next()?
+            } else {
+                default
+            };
+            current = if is_right(key, height) {
+                hasher.hash_node(&sibling, &current)
+            } else {
+                hasher.hash_node(&current, &sibling)
+            };
+            default = hasher.hash_node(&default, &default);
+        }
+        Some(current)
+    }
+
+    /// Check that `key` maps to `value` under `root` in a tree of [`KeyPath::Raw`] keys.
+    pub fn verify_membership<H: Hasher>(
+        &self,
+        root: &[u8],
+        key: &[u8],
+        value: &[u8],
+        hasher: &H,
+    ) -> bool {
+        self.verify_membership_with_key_path(root, key, value, hasher, KeyPath::Raw)
+    }
+
+    /// [`Self::verify_membership`] in a tree of `key_path` keys; `false` for a proof made
+    /// with another strategy.
+    pub fn verify_membership_with_key_path<H: Hasher>(
+        &self,
+        root: &[u8],
+        key: &[u8],
+        value: &[u8],
+        hasher: &H,
+        key_path: KeyPath,
+    ) -> bool {
+        self.verify_leaf(root, key, Some(value), hasher, key_path)
+    }
+
+    /// Check that `key` has no value under `root` in a tree of [`KeyPath::Raw`] keys.
+    pub fn verify_non_membership<H: Hasher>(&self, root: &[u8], key: &[u8], hasher: &H) -> bool {
+        self.verify_non_membership_with_key_path(root, key, hasher, KeyPath::Raw)
+    }
+
+    /// [`Self::verify_non_membership`] in a tree of `key_path` keys; `false` for a proof made
+    /// with another strategy.
+    pub fn verify_non_membership_with_key_path<H: Hasher>(
+        &self,
+        root: &[u8],
+        key: &[u8],
+        hasher: &H,
+        key_path: KeyPath,
+    ) -> bool {
+        self.verify_leaf(root, key, None, hasher, key_path)
+    }
+
+    fn verify_leaf<H: Hasher>(
+        &self,
+        root: &[u8],
+        key: &[u8],
+        value: Option<&[u8]>,
+        hasher: &H,
+        key_path: KeyPath,
+    ) -> bool {
+        let Ok(path) = key_path.path(key) else {
+            return false;
+        };
+        let leaf = value.map_or(EMPTY_LEAF, |v| leaf_hash(hasher, &path, v));
+        self.key_path == key_path
+            && self
+                .compute_root(&path, leaf, hasher)
+                .is_some_and(|r| constant_time_eq(&r, root))
+    }
+}
+
+/// Fold the leaves of `leaves`, keyed by their keys, up to the root. Each sibling that is not
+/// on another key's path comes from `sibling(height, prefix, default)`, bottom-up and in key
+/// order within a height, where `default` is the empty-subtree hash of `height`.
+fn walk_paths<H, E>(
+    hasher: &H,
+    leaves: BTreeMap<Hash, Hash>,
+    mut sibling: impl FnMut(usize, &Hash, &Hash) -> Result<Hash, E>,
+) -> Result<Hash, E>
+where
+    H: Hasher,
+{
+    let mut known = leaves;
+    let mut default = EMPTY_LEAF;
+    for height in 0..DEPTH {
+        let mut parents = BTreeMap::new();
+        let mut nodes = known.into_iter().peekable();
+        while let Some((key, current)) = nodes.next() {
+            let sibling_key = sibling_prefix(&key, height);
+            let parent = if is_right(&key, height) {
+                hasher.hash_node(&sibling(height, &sibling_key, &default)?, &current)
+            } else if nodes
+                .peek()
+                .is_some_and(|(k, _)| prefix(k, height) == sibling_key)
+            {
+                // the right sibling is on another key's path
+                let (_, right) = nodes.next().expect("Peeked node");
+                hasher.hash_node(&current, &right)
+            } else {
+                hasher.hash_node(&current, &sibling(height, &sibling_key, &default)?)
+            };
+            parents.insert(prefix(&key, height + 1), parent);
+        }
+        known = parents;
+        default = hasher.hash_node(&default, &default);
+    }
+    Ok(known.into_values().next().unwrap_or(default))
+}
+
+/// Proof for several keys at once, from [`SparseMerkleTree::prove_many`]. Siblings are listed
+/// once even when several paths need them and not at all when another proven path supplies
+/// them, so a batch costs far less than one [`SmtProof`] per key. Bit `i` of `bitmap`
+/// (`bitmap[i / 8] >> (i % 8)`) is set if the `i`-th sibling the verifier needs is in
+/// `siblings` rather than the default hash of its height.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmtMultiProof {
+    pub key_path: KeyPath,
+    pub bitmap: Vec<u8>,
+    pub siblings: Vec<Hash>,
+}
+
+impl SmtMultiProof {
+    /// `key path id (1 byte) || bitmap length (u32, big-endian) || bitmap || siblings`, the
+    /// siblings as concatenated 32-byte hashes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len = u32::try_from(self.bitmap.len()).expect("Bitmap longer than u32::MAX bytes");
+        [
+            &[self.key_path.id()][..],
+            &len.to_be_bytes(),
+            &self.bitmap,
+            self.siblings.as_flattened(),
+        ]
+        .concat()
+    }
+
+    /// Read a proof written by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleError> {
+        let Some(([id], rest)) = bytes.split_first_chunk::<1>() else {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        };
+        let Some((len, rest)) = rest.split_first_chunk::<4>() else {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        };
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(MerkleError::InvalidNode(bytes.len()));
+        }
+        let (bitmap, siblings) = rest.split_at(len);
+        Ok(Self {
+            key_path: key_path_from_id(*id)?,
+            bitmap: bitmap.to_vec(),
+            siblings: hashes_from_bytes(siblings)?,
+        })
+    }
+
+    /// Check that each `(key, value)` of `entries` holds under `root` in a tree of
+    /// [`KeyPath::Raw`] keys, where a `None` value proves that the key is empty. Duplicate
+    /// keys, an empty batch or a malformed proof verify as `false`.
+    pub fn verify<H: Hasher>(
+        &self,
+        root: &[u8],
+        entries: &[(Hash, Option<&[u8]>)],
+        hasher: &H,
+    ) -> bool {
+        let entries: Vec<_> = entries.iter().map(|(k, v)| (&k[..], *v)).collect();
+        self.verify_with_key_path(root, &entries, hasher, KeyPath::Raw)
+    }
+
+    /// [`Self::verify`] in a tree of `key_path` keys; `false` for a proof made with another
+    /// strategy or a key it rejects.
+    pub fn verify_with_key_path<H: Hasher>(
+        &self,
+        root: &[u8],
+        entries: &[(&[u8], Option<&[u8]>)],
+        hasher: &H,
+        key_path: KeyPath,
+    ) -> bool {
+        let paths: Result<Vec<_>, _> = entries
+            .iter()
+            .map(|(key, value)| key_path.path(key).map(|path| (path, *value)))
+            .collect();
+        self.key_path == key_path
+            && paths.is_ok_and(|paths| self.verify_paths(root, &paths, hasher))
+    }
+
+    fn verify_paths<H: Hasher>(
+        &self,
+        root: &[u8],
+        entries: &[(Hash, Option<&[u8]>)],
+        hasher: &H,
+    ) -> bool {
+        let mut leaves = BTreeMap::new();
+        for (path, value) in entries {
+            let leaf = value.map_or(EMPTY_LEAF, |v| leaf_hash(hasher, path, v));
+            if leaves.insert(*path, leaf).is_some() {
+                return false;
+            }
+        }
+        !leaves.is_empty()
+            && self
+                .compute_root(leaves, hasher)
+                .is_some_and(|r| constant_time_eq(&r, root))
+    }
+
+    /// Root implied by `leaves` with this proof's siblings, or `None` if the proof does not fit
+    /// their keys. The siblings depend only on the keys, so one proof gives the root both
+    /// before and after their leaves change.
+    fn compute_root<H: Hasher>(&self, leaves: BTreeMap<Hash, Hash>, hasher: &H) -> Option<Hash> {
+        let mut index = 0;
+        let mut siblings = self.siblings.iter();
+        let computed = walk_paths(hasher, leaves, |_, _, default| {
+            let present = self
+                .bitmap
+                .get(index / 8)
+                .is_some_and(|b| b & (1 << (index % 8)) != 0);
+            index += 1;
+            if present {
+                siblings.next().copied().ok_or(())
+            } else {
+                Ok(*default)
+            }
+        });
+        // every sibling is used and no bit is set past the last one the walk asked for
+        let unused_bits = self.bitmap.len() != index.div_ceil(8)
+            || (index % 8 != 0 && self.bitmap[index / 8] >> (index % 8) != 0);
+        computed
+            .ok()
+            .filter(|_| siblings.next().is_none() && !unused_bits)
+    }
+}
+
+/// Values of a set of keys before a batch of writes, `None` for an empty key, with one
+/// [`SmtMultiProof`] for all of them. Built by [`SparseMerkleTree::witness`]; keys are listed
+/// by their [`KeyPath::path`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmtWitness {
+    pub values: Vec<(Hash, Option<Bytes>)>,
+    pub proof: SmtMultiProof,
+}
+
+/// Root after applying `updates` to the tree with root `old_root`, computed from `witness`
+/// alone. Each update sets the key at a path to a value, or empties it for `None`, and every
+/// updated path must be in the witness. `None` unless the witness verifies under `old_root`, or if it or
+/// `updates` repeats a key.
+pub fn apply_updates_with_witness<H: Hasher>(
+    old_root: &[u8],
+    updates: &[(Hash, Option<&[u8]>)],
+    witness: &SmtWitness,
+    hasher: &H,
+) -> Option<Hash> {
+    let entries: Vec<_> = witness
+        .values
+        .iter()
+        .map(|(key, value)| (*key, value.as_deref()))
+        .collect();
+    if !witness.proof.verify_paths(old_root, &entries, hasher) {
+        return None;
+    }
+    let mut leaves: BTreeMap<Hash, Hash> = entries
+        .iter()
+        .map(|(key, value)| {
+            (
+                *key,
+                value.map_or(EMPTY_LEAF, |v| leaf_hash(hasher, key, v)),
+            )
+        })
+        .collect();
+    let mut updated = HashSet::new();
+    for (key, value) in updates {
+        let leaf = leaves.get_mut(key)?;
+        if !updated.insert(*key) {
+            return None;
+        }
+        *leaf = value.map_or(EMPTY_LEAF, |v| leaf_hash(hasher, key, v));
+    }
+    witness.proof.compute_root(leaves, hasher)
+}
+
+#[derive(Clone, Debug)]
+pub struct SparseMerkleTree<H: Hasher> {
+    hasher: H,
+    /// Default (empty subtree) hash per height, `DEPTH + 1` entries.
+    defaults: Vec<Hash>,
+    /// Non-default nodes keyed by height and [`prefix`]; the root is at height `DEPTH`.
+    nodes: HashMap<(usize, Hash), Hash>,
+    /// Values keyed by path.
+    values: BTreeMap<Hash, Bytes>,
+    key_path: KeyPath,
+}
+
+impl<H: Hasher> SparseMerkleTree<H> {
+    /// Empty tree of [`KeyPath::Raw`] keys. Panics on a sorted-pair hasher, see
+    /// [`Self::try_new`].
+    pub fn new(hasher: H) -> Self {
+        Self::with_key_path(hasher, KeyPath::Raw)
+    }
+
+    /// Like [`Self::new`], failing on a hasher whose [`Hasher::pair_ordering`] is not
+    /// positional: sorted pairs would let an empty leaf elsewhere prove any key absent.
+    pub fn try_new(hasher: H) -> Result<Self, MerkleError> {
+        Self::try_with_key_path(hasher, KeyPath::Raw)
+    }
+
+    /// Empty tree mapping keys to paths with `key_path`. Panics on a sorted-pair hasher.
+    pub fn with_key_path(hasher: H, key_path: KeyPath) -> Self {
+        Self::try_with_key_path(hasher, key_path).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_key_path(hasher: H, key_path: KeyPath) -> Result<Self, MerkleError> {
+        check_pair_ordering(&hasher, PairOrdering::Positional)?;
+        let mut defaults = Vec::with_capacity(DEPTH + 1);
+        defaults.push(EMPTY_LEAF);
+        for height in 0..DEPTH {
+            let d = defaults[height];
+            defaults.push(hasher.hash_node(&d, &d));
+        }
+        Ok(Self {
+            hasher,
+            defaults,
+            nodes: HashMap::new(),
+            values: BTreeMap::new(),
+            key_path,
+        })
+    }
+
+    pub fn key_path(&self) -> KeyPath {
+        self.key_path
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Root of the empty tree.
+    pub fn empty_root(&self) -> Hash {
+        self.defaults[DEPTH]
+    }
+
+    pub fn root(&self) -> Hash {
+        self.node(DEPTH, &[0u8; 32])
+    }
+
+    /// Value of `key`; `None` also for a key the tree's [`KeyPath`] rejects.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = self.key_path.path(key).ok()?;
+        self.values.get(&path).map(|v| v.as_slice())
+    }
+
+    /// Stored paths and values, in ascending path order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Hash, &[u8])> + '_ {
+        self.values.iter().map(|(k, v)| (k, v.as_slice()))
+    }
+
+    fn node(&self, height: usize, prefix: &Hash) -> Hash {
+        self.nodes
+            .get(&(height, *prefix))
+            .copied()
+            .unwrap_or(self.defaults[height])
+    }
+
+    fn set_node(&mut self, height: usize, prefix: Hash, hash: Hash) {
+        if hash == self.defaults[height] {
+            self.nodes.remove(&(height, prefix));
+        } else {
+            self.nodes.insert((height, prefix), hash);
+        }
+    }
+
+    fn update_path(&mut self, key: &Hash, leaf: Hash) {
+        let mut current = leaf;
+        for height in 0..DEPTH {
+            self.set_node(height, prefix(key, height), current);
+            let sibling = self.node(height, &sibling_prefix(key, height));
+            current = if is_right(key, height) {
+                self.hasher.hash_node(&sibling, &current)
+            } else {
+                self.hasher.hash_node(&current, &sibling)
+            };
+        }
+        self.set_node(DEPTH, [0u8; 32], current);
+    }
+
+    /// Set `key` to `value`, returning the previous value. Rehashes the key's path of
+    /// [`DEPTH`] nodes. Panics on a key the tree's [`KeyPath`] rejects.
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, value: Bytes) -> Option<Bytes> {
+        self.try_insert(key, value)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_insert(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: Bytes,
+    ) -> Result<Option<Bytes>, MerkleError> {
+        let path = self.key_path.path(key.as_ref())?;
+        let leaf = leaf_hash(&self.hasher, &path, &value);
+        self.update_path(&path, leaf);
+        Ok(self.values.insert(path, value))
+    }
+
+    /// Empty `key`, returning its value; `None` also for a key the tree's [`KeyPath`] rejects.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Bytes> {
+        let path = self.key_path.path(key).ok()?;
+        let old = self.values.remove(&path)?;
+        self.update_path(&path, EMPTY_LEAF);
+        Some(old)
+    }
+
+    /// Proof for `key`: of membership if it has a value, of non-membership otherwise. Panics
+    /// on a key the tree's [`KeyPath`] rejects.
+    pub fn prove(&self, key: &[u8]) -> SmtProof {
+        self.try_prove(key).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_prove(&self, key: &[u8]) -> Result<SmtProof, MerkleError> {
+        let key = &self.key_path.path(key)?;
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+        for height in 0..DEPTH {
+            let sibling = self.node(height, &sibling_prefix(key, height));
+            if sibling != self.defaults[height] {
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(sibling);
+            }
+        }
+        Ok(SmtProof {
+            key_path: self.key_path,
+            bitmap,
+            siblings,
+        })
+    }
+    /// One proof for all `keys`, each of membership or non-membership, see [`SmtMultiProof`].
+    /// Repeated keys are proven once. Panics on a key the tree's [`KeyPath`] rejects.
+    pub fn prove_many<K: AsRef<[u8]>>(&self, keys: &[K]) -> SmtMultiProof {
+        self.try_prove_many(keys)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_prove_many<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<SmtMultiProof, MerkleError> {
+        let paths = self.paths(keys)?;
+        Ok(self.prove_paths(&paths))
+    }
+
+    fn paths<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<BTreeSet<Hash>, MerkleError> {
+        keys.iter()
+            .map(|key| self.key_path.path(key.as_ref()))
+            .collect()
+    }
+
+    fn prove_paths(&self, paths: &BTreeSet<Hash>) -> SmtMultiProof {
+        let mut bitmap = Vec::new();
+        let mut siblings = Vec::new();
+        let mut index = 0;
+        let leaves = paths.iter().map(|path| (*path, EMPTY_LEAF)).collect();
+        // the root of the walk is not needed, only the siblings it asks for
+        let _ = walk_paths::<_, ()>(&self.hasher, leaves, |height, prefix, default| {
+            let sibling = self.node(height, prefix);
+            if index % 8 == 0 {
+                bitmap.push(0);
+            }
+            if sibling != *default {
+                bitmap[index / 8] |= 1 << (index % 8);
+                siblings.push(sibling);
+            }
+            index += 1;
+            Ok(sibling)
+        });
+        SmtMultiProof {
+            key_path: self.key_path,
+            bitmap,
+            siblings,
+        }
+    }
+
+    /// Current values of `keys` with their [`Self::prove_many`] proof, for
+    /// [`apply_updates_with_witness`]. Repeated keys are listed once. Panics on a key the
+    /// tree's [`KeyPath`] rejects.
+    pub fn witness<K: AsRef<[u8]>>(&self, keys: &[K]) -> SmtWitness {
+        self.try_witness(keys).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_witness<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<SmtWitness, MerkleError> {
+        let paths = self.paths(keys)?;
+        let values = paths
+            .iter()
+            .map(|path| (*path, self.values.get(path).cloned()))
+            .collect();
+        Ok(SmtWitness {
+            values,
+            proof: self.prove_paths(&paths),
+        })
+    }
+}
@@ -0,0 +1,344 @@
+//! Port of OpenZeppelin's `StandardMerkleTree` (`@openzeppelin/merkle-tree`): leaves are
+//! ABI-encoded tuples hashed with [`standard_leaf_hash`], sorted by hash and combined with the
+//! sorted-pair keccak256 node hash, so roots and proofs match the JS library and
+//! `MerkleProof.sol`. Trees can be dumped to and loaded from the library's `standard-v1` JSON
+//! format.
+
+use std::collections::HashMap;
+
+use crate::abi::AbiError;
+use crate::consts::STANDARD_FORMAT;
+use crate::dump::{hex_string, invalid_dump, parse_hex32, DumpError, Json};
+use crate::hashes::{
+    standard_leaf_hash, standard_node_hash, try_standard_leaf_hash, Keccak256Hasher,
+};
+use crate::merkle::{
+    check_leaf_node, get_multi_proof, get_proof, is_valid_merkle_tree, try_make_merkle_tree_bytes,
+    verify_proof, Bytes, Hash, MerkleError, MultiProof,
+};
+use crate::tree::TreeConfig;
+
+fn tree_hash(node: &[u8]) -> Hash {
+    node.try_into().unwrap()
+}
+
+fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
+    standard_node_hash(a, b).to_vec()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum StandardTreeError {
+    #[error("Value {index} does not fit the leaf encoding: {source}")]
+    Value { index: usize, source: AbiError },
+    #[error(transparent)]
+    Merkle(#[from] MerkleError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeOptions {
+    /// Sort leaves by hash before building, as the JS library does by default. Enables
+    /// multiproofs whose leaves verify in any order but makes tree order differ from input.
+    pub sort_leaves: bool,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self { sort_leaves: true }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct StandardValue {
+    pub value: Vec<String>,
+    /// Index of the value's leaf in the flat tree.
+    pub tree_index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct StandardMerkleTree {
+    tree: Vec<Bytes>,
+    values: Vec<StandardValue>,
+    leaf_encoding: Vec<String>,
+    hash_lookup: HashMap<Hash, usize>,
+}
+
+impl StandardMerkleTree {
+    /// Tree over `values`, each ABI-encoded as the tuple `leaf_encoding` (e.g.
+    /// `["address", "uint256"]`), with leaves sorted. Panics on an empty value list or a value
+    /// that does not fit the encoding.
+    pub fn of(values: Vec<Vec<String>>, leaf_encoding: Vec<String>) -> Self {
+        Self::of_with_options(values, leaf_encoding, TreeOptions::default())
+    }
+
+    pub fn try_of(
+        values: Vec<Vec<String>>,
+        leaf_encoding: Vec<String>,
+    ) -> Result<Self, StandardTreeError> {
+        Self::try_of_with_options(values, leaf_encoding, TreeOptions::default())
+    }
+
+    pub fn of_with_options(
+        values: Vec<Vec<String>>,
+        leaf_encoding: Vec<String>,
+        options: TreeOptions,
+    ) -> Self {
+        Self::try_of_with_options(values, leaf_encoding, options)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_of_with_options(
+        values: Vec<Vec<String>>,
+        leaf_encoding: Vec<String>,
+        options: TreeOptions,
+    ) -> Result<Self, StandardTreeError> {
+        let mut hashed = values
+            .iter()
+            .enumerate()
+            .map(|(index, v)| {
+                try_standard_leaf_hash(&leaf_encoding, v)
+                    .map(|h| (index, h))
+                    .map_err(|source| StandardTreeError::Value { index, source })
+            })
+            .collect::<Result<Vec<(usize, Hash)>, _>>()?;
+        if options.sort_leaves {
+            hashed.sort_by_key(|&(_, h)| h);
+        }
+
+        let tree = try_make_merkle_tree_bytes(
+            hashed.iter().map(|(_, h)| h.to_vec()).collect(),
+            node_hash,
+        )?;
+
+        let mut tree_indices = vec![0; values.len()];
+        for (leaf_index, (value_index, _)) in hashed.iter().enumerate() {
+            tree_indices[*value_index] = tree.len() - 1 - leaf_index;
+        }
+        let values: Vec<StandardValue> = values
+            .into_iter()
+            .zip(tree_indices)
+            .map(|(value, tree_index)| StandardValue { value, tree_index })
+            .collect();
+        Ok(Self::from_parts(tree, values, leaf_encoding))
+    }
+
+    fn from_parts(
+        tree: Vec<Bytes>,
+        values: Vec<StandardValue>,
+        leaf_encoding: Vec<String>,
+    ) -> Self {
+        let hash_lookup = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (tree_hash(&tree[v.tree_index]), i))
+            .collect();
+        Self {
+            tree,
+            values,
+            leaf_encoding,
+            hash_lookup,
+        }
+    }
+
+    /// Check a proof against `root` without building a tree, like the JS static `verify`. A
+    /// value that does not fit `leaf_encoding` verifies as `false`.
+    pub fn verify(
+        root: &[u8],
+        leaf_encoding: &[String],
+        value: &[String],
+        proof: &[Bytes],
+    ) -> bool {
+        try_standard_leaf_hash(leaf_encoding, value)
+            .is_ok_and(|leaf| verify_proof(root, &leaf, proof, node_hash))
+    }
+
+    pub fn root(&self) -> &[u8] {
+        &self.tree[0]
+    }
+
+    pub fn leaf_encoding(&self) -> &[String] {
+        &self.leaf_encoding
+    }
+
+    /// Sorted keccak256 pairs over `abi(..)` leaves, the same config as a
+    /// [`crate::encoder::TypedMerkleTree`] with an [`crate::encoder::AbiEncoder`].
+    pub fn config(&self) -> TreeConfig {
+        TreeConfig {
+            leaf_encoding: format!("abi({})", self.leaf_encoding.join(",")),
+            ..TreeConfig::default()
+        }
+    }
+
+    /// The root bound to [`Self::config`], see [`TreeConfig::commit_root`].
+    pub fn root_with_config_commitment(&self) -> Hash {
+        self.config().commit_root(self.root(), &Keccak256Hasher)
+    }
+
+    pub fn tree(&self) -> &[Bytes] {
+        &self.tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Values in input order.
+    pub fn values(&self) -> &[StandardValue] {
+        &self.values
+    }
+
+    pub fn leaf_hash(&self, value: &[String]) -> Hash {
+        standard_leaf_hash(&self.leaf_encoding, value)
+    }
+
+    /// Index (in input order) of `value`, if it is in the tree; `None` also for a value that
+    /// does not fit the leaf encoding.
+    pub fn leaf_lookup(&self, value: &[String]) -> Option<usize> {
+        let leaf = try_standard_leaf_hash(&self.leaf_encoding, value).ok()?;
+        self.hash_lookup.get(&leaf).copied()
+    }
+
+    fn value_at(&self, value_index: usize) -> &StandardValue {
+        self.values.get(value_index).unwrap_or_else(|| {
+            panic!(
+                "Index {} out of range for {} values",
+                value_index,
+                self.values.len()
+            )
+        })
+    }
+
+    pub fn get_proof(&self, value_index: usize) -> Vec<Bytes> {
+        get_proof(&self.tree, self.value_at(value_index).tree_index)
+    }
+
+    pub fn get_proof_for_value(&self, value: &[String]) -> Vec<Bytes> {
+        let index = self.leaf_lookup(value).expect("Leaf is not in tree");
+        self.get_proof(index)
+    }
+
+    pub fn get_multi_proof(&self, value_indices: &[usize]) -> MultiProof {
+        let indices = value_indices
+            .iter()
+            .map(|&i| self.value_at(i).tree_index)
+            .collect();
+        get_multi_proof(&self.tree, indices)
+    }
+
+    pub fn verify_proof(&self, value: &[String], proof: &[Bytes]) -> bool {
+        Self::verify(self.root(), &self.leaf_encoding, value, proof)
+    }
+
+    /// `{"format":"standard-v1","leafEncoding":[...],"tree":[...],"values":[...]}`, the same
+    /// JSON as the JS library's `JSON.stringify(tree.dump())`.
+    pub fn dump(&self) -> String {
+        let strings =
+            |items: &[String]| Json::Array(items.iter().map(|s| Json::String(s.clone())).collect());
+        Json::Object(vec![
+            (
+                "format".to_string(),
+                Json::String(STANDARD_FORMAT.to_string()),
+            ),
+            ("leafEncoding".to_string(), strings(&self.leaf_encoding)),
+            (
+                "tree".to_string(),
+                Json::Array(self.tree.iter().map(|n| hex_string(n)).collect()),
+            ),
+            (
+                "values".to_string(),
+                Json::Array(
+                    self.values
+                        .iter()
+                        .map(|v| {
+                            Json::Object(vec![
+                                ("value".to_string(), strings(&v.value)),
+                                (
+                                    "treeIndex".to_string(),
+                                    Json::Number(v.tree_index.to_string()),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+        .to_json_string()
+    }
+
+    /// Load a dump written by [`Self::dump`] or the JS library. Numbers and booleans in values
+    /// are accepted as written, since JS dumps keep whatever type the caller passed in.
+    pub fn load(dump: &str) -> Result<Self, DumpError> {
+        let json = Json::parse(dump)?;
+        let format = json.field("format")?.as_str()?;
+        if format != STANDARD_FORMAT {
+            return Err(DumpError::UnknownFormat(format.to_string()));
+        }
+
+        let leaf_encoding = json
+            .field("leafEncoding")?
+            .as_array()?
+            .iter()
+            .map(|t| t.as_str().map(str::to_string))
+            .collect::<Result<Vec<String>, DumpError>>()?;
+        let tree = json
+            .field("tree")?
+            .as_array()?
+            .iter()
+            .map(parse_hex32)
+            .collect::<Result<Vec<Bytes>, DumpError>>()?;
+        let mut values = Vec::new();
+        for v in json.field("values")?.as_array()? {
+            let value = v
+                .field("value")?
+                .as_array()?
+                .iter()
+                .map(|item| match item {
+                    Json::String(s) | Json::Number(s) => Ok(s.clone()),
+                    Json::Bool(b) => Ok(b.to_string()),
+                    _ => Err(invalid_dump("expected scalar value")),
+                })
+                .collect::<Result<Vec<String>, DumpError>>()?;
+            values.push(StandardValue {
+                value,
+                tree_index: v.field("treeIndex")?.as_usize()?,
+            });
+        }
+
+        Self::from_dump_parts(tree, values, leaf_encoding)
+    }
+
+    /// Rebuild a tree from dumped parts, checking that the tree is consistent and every value
+    /// hashes to its leaf.
+    pub(crate) fn from_dump_parts(
+        tree: Vec<Bytes>,
+        values: Vec<StandardValue>,
+        leaf_encoding: Vec<String>,
+    ) -> Result<Self, DumpError> {
+        if !is_valid_merkle_tree(&tree, node_hash) {
+            return Err(invalid_dump("tree is not a valid merkle tree"));
+        }
+        for v in values.iter() {
+            let leaf = try_standard_leaf_hash(&leaf_encoding, &v.value).map_err(|e| {
+                invalid_dump(format!(
+                    "value {:?} does not fit leaf encoding: {}",
+                    v.value, e
+                ))
+            })?;
+            if check_leaf_node(tree.len(), v.tree_index).is_err() || tree[v.tree_index] != leaf {
+                return Err(invalid_dump(format!(
+                    "value {:?} does not match leaf {}",
+                    v.value, v.tree_index
+                )));
+            }
+        }
+        Ok(Self::from_parts(tree, values, leaf_encoding))
+    }
+}
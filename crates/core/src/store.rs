@@ -0,0 +1,361 @@
+//! Trees kept in a [`NodeStore`] instead of a `Vec`, so they can live in a database, survive
+//! restarts and be updated in place. Nodes are keyed by their heap index, in the same layout as
+//! [`crate::merkle::make_merkle_tree_bytes`]. [`MemoryStore`] is always available,
+//! [`SledStore`] comes with feature `sled` and [`RocksStore`] with feature `rocksdb`.
+
+use std::io;
+
+use crate::hashes::Hasher;
+use crate::merkle::{leaf_count, try_leaf_tree_index, Bytes, Hash, MerkleError};
+use crate::tree::MerkleTree;
+
+/// Node storage by heap index.
+pub trait NodeStore {
+    fn get(&self, index: usize) -> io::Result<Option<Hash>>;
+    fn put(&mut self, index: usize, node: &Hash) -> io::Result<()>;
+    /// Write several nodes at once. Stores that can should apply the batch atomically, so a
+    /// crash never leaves part of it written; the default just calls [`Self::put`] in turn.
+    fn put_batch(&mut self, nodes: &[(usize, Hash)]) -> io::Result<()> {
+        for (index, node) in nodes.iter() {
+            self.put(*index, node)?;
+        }
+        Ok(())
+    }
+    /// One more than the highest index stored, 0 when empty.
+    fn len(&self) -> io::Result<usize>;
+    /// Make previous writes durable. Does nothing by default.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+    /// Remove every node.
+    fn clear(&mut self) -> io::Result<()>;
+}
+
+/// Nodes in a `Vec`, for tests and short-lived trees.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    nodes: Vec<Option<Hash>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NodeStore for MemoryStore {
+    fn get(&self, index: usize) -> io::Result<Option<Hash>> {
+        Ok(self.nodes.get(index).copied().flatten())
+    }
+
+    fn put(&mut self, index: usize, node: &Hash) -> io::Result<()> {
+        if index >= self.nodes.len() {
+            self.nodes.resize(index + 1, None);
+        }
+        self.nodes[index] = Some(*node);
+        Ok(())
+    }
+
+    fn len(&self) -> io::Result<usize> {
+        Ok(self.nodes.len())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.nodes.clear();
+        Ok(())
+    }
+}
+
+/// Nodes in a [`sled::Tree`] under big-endian `u64` index keys (feature `sled`).
+#[cfg(feature = "sled")]
+#[derive(Clone, Debug)]
+pub struct SledStore {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledStore {
+    pub fn new(tree: sled::Tree) -> Self {
+        Self { tree }
+    }
+
+    pub fn sled_tree(&self) -> &sled::Tree {
+        &self.tree
+    }
+}
+
+#[cfg(feature = "sled")]
+impl NodeStore for SledStore {
+    fn get(&self, index: usize) -> io::Result<Option<Hash>> {
+        match self.tree.get((index as u64).to_be_bytes())? {
+            None => Ok(None),
+            Some(v) => v.as_ref().try_into().map(Some).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    MerkleError::InvalidNode(v.len()),
+                )
+            }),
+        }
+    }
+
+    fn put(&mut self, index: usize, node: &Hash) -> io::Result<()> {
+        self.tree.insert((index as u64).to_be_bytes(), &node[..])?;
+        Ok(())
+    }
+
+    /// Applied as one [`sled::Batch`], which sled writes atomically.
+    fn put_batch(&mut self, nodes: &[(usize, Hash)]) -> io::Result<()> {
+        let mut batch = sled::Batch::default();
+        for (index, node) in nodes.iter() {
+            batch.insert(&(*index as u64).to_be_bytes(), &node[..]);
+        }
+        self.tree.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn len(&self) -> io::Result<usize> {
+        match self.tree.last()? {
+            None => Ok(0),
+            Some((k, _)) => {
+                let key: [u8; 8] = k.as_ref().try_into().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Expected 8-byte node key")
+                })?;
+                Ok(u64::from_be_bytes(key) as usize + 1)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.tree.clear()?;
+        Ok(())
+    }
+}
+
+/// Nodes in a RocksDB database under big-endian `u64` index keys (feature `rocksdb`). The
+/// database should hold nothing else, since [`NodeStore::len`] reads its last key.
+#[cfg(feature = "rocksdb")]
+#[derive(Debug)]
+pub struct RocksStore {
+    db: rocksdb::DB,
+}
+
+#[cfg(feature = "rocksdb")]
+fn rocks_error(e: rocksdb::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+#[cfg(feature = "rocksdb")]
+impl RocksStore {
+    pub fn new(db: rocksdb::DB) -> Self {
+        Self { db }
+    }
+
+    pub fn db(&self) -> &rocksdb::DB {
+        &self.db
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl NodeStore for RocksStore {
+    fn get(&self, index: usize) -> io::Result<Option<Hash>> {
+        match self
+            .db
+            .get((index as u64).to_be_bytes())
+            .map_err(rocks_error)?
+        {
+            None => Ok(None),
+            Some(v) => v.as_slice().try_into().map(Some).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    MerkleError::InvalidNode(v.len()),
+                )
+            }),
+        }
+    }
+
+    fn put(&mut self, index: usize, node: &Hash) -> io::Result<()> {
+        self.db
+            .put((index as u64).to_be_bytes(), node)
+            .map_err(rocks_error)
+    }
+
+    /// Applied as one [`rocksdb::WriteBatch`], which RocksDB writes atomically.
+    fn put_batch(&mut self, nodes: &[(usize, Hash)]) -> io::Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        for (index, node) in nodes.iter() {
+            batch.put((*index as u64).to_be_bytes(), node);
+        }
+        self.db.write(batch).map_err(rocks_error)
+    }
+
+    fn len(&self) -> io::Result<usize> {
+        match self.db.iterator(rocksdb::IteratorMode::End).next() {
+            None => Ok(0),
+            Some(entry) => {
+                let (k, _) = entry.map_err(rocks_error)?;
+                let key: [u8; 8] = k.as_ref().try_into().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Expected 8-byte node key")
+                })?;
+                Ok(u64::from_be_bytes(key) as usize + 1)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.db.flush().map_err(rocks_error)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        let mut batch = rocksdb::WriteBatch::default();
+        for entry in self.db.iterator(rocksdb::IteratorMode::Start) {
+            let (k, _) = entry.map_err(rocks_error)?;
+            batch.delete(k);
+        }
+        self.db.write(batch).map_err(rocks_error)
+    }
+}
+
+fn tree_error(e: MerkleError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+/// A sorted-pair tree, as [`MerkleTree::new`] builds it, whose nodes live in a [`NodeStore`].
+#[derive(Clone, Debug)]
+pub struct StoredMerkleTree<S: NodeStore, H: Hasher> {
+    store: S,
+    hasher: H,
+    len: usize,
+}
+
+impl<S: NodeStore, H: Hasher> StoredMerkleTree<S, H> {
+    /// Build a tree over `leaves` and write it to `store`, replacing whatever the store held,
+    /// so [`NodeStore::len`] is exactly the new tree's node count. The nodes are written with
+    /// one [`NodeStore::put_batch`], highest index first: stores with atomic batches hold the
+    /// whole tree or none of it, and in others the root is written last and marks the tree
+    /// complete for [`Self::open`].
+    pub fn build(leaves: Vec<Bytes>, mut store: S, hasher: H) -> io::Result<Self> {
+        let tree = MerkleTree::try_new(leaves, &hasher).map_err(tree_error)?;
+        let nodes: Vec<(usize, Hash)> = tree
+            .as_slice()
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, node)| (i, node.as_slice().try_into().unwrap()))
+            .collect();
+        store.clear()?;
+        store.put_batch(&nodes)?;
+        store.flush()?;
+        let len = tree.as_slice().len();
+        Ok(Self { store, hasher, len })
+    }
+
+    /// Reopen a tree previously built in `store`. Fails with [`io::ErrorKind::InvalidData`] if
+    /// the store is empty or does not hold a whole tree, e.g. after a build that did not
+    /// finish.
+    pub fn open(store: S, hasher: H) -> io::Result<Self> {
+        let len = store.len()?;
+        if len % 2 == 0 || store.get(0)?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Store holds {} nodes, not a tree", len),
+            ));
+        }
+        Ok(Self { store, hasher, len })
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Always `false`: a tree has at least one node.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        leaf_count(self.len)
+    }
+
+    fn node(&self, index: usize) -> io::Result<Hash> {
+        self.store.get(index)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Store has no node at index {}", index),
+            )
+        })
+    }
+
+    pub fn root(&self) -> io::Result<Hash> {
+        self.node(0)
+    }
+
+    pub fn leaf(&self, leaf_position: usize) -> io::Result<Hash> {
+        self.node(try_leaf_tree_index(self.len, leaf_position).map_err(tree_error)?)
+    }
+
+    /// Proof for the leaf at 0-based `leaf_position`, the same as [`MerkleTree::proof`].
+    pub fn proof(&self, leaf_position: usize) -> io::Result<Vec<Bytes>> {
+        let mut index = try_leaf_tree_index(self.len, leaf_position).map_err(tree_error)?;
+        let mut proof = Vec::new();
+        while index > 0 {
+            let sibling = if index.is_multiple_of(2) {
+                index - 1
+            } else {
+                index + 1
+            };
+            proof.push(self.node(sibling)?.to_vec());
+            index = (index - 1) / 2;
+        }
+        Ok(proof)
+    }
+
+    /// Replace the leaf at `leaf_position` and rewrite the nodes on its path to the root. The
+    /// new path is computed first and written with one [`NodeStore::put_batch`], so stores
+    /// with atomic batches never hold a half-updated path.
+    pub fn update_leaf(&mut self, leaf_position: usize, new_leaf: &[u8]) -> io::Result<()> {
+        let mut index = try_leaf_tree_index(self.len, leaf_position).map_err(tree_error)?;
+        let mut node: Hash = new_leaf
+            .try_into()
+            .map_err(|_| tree_error(MerkleError::InvalidNode(new_leaf.len())))?;
+        let mut path = vec![(index, node)];
+        while index > 0 {
+            let sibling = self.node(if index.is_multiple_of(2) {
+                index - 1
+            } else {
+                index + 1
+            })?;
+            // siblings are untouched by the update, so they can be read from the store
+            let (left, right) = if index.is_multiple_of(2) {
+                (sibling, node)
+            } else {
+                (node, sibling)
+            };
+            node = self.hasher.hash_node(&left, &right);
+            index = (index - 1) / 2;
+            path.push((index, node));
+        }
+        self.store.put_batch(&path)?;
+        self.store.flush()
+    }
+}
@@ -0,0 +1,194 @@
+use crate::merkle::{constant_time_eq, get_proof, make_merkle_tree_bytes, verify_proof, Bytes};
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TimestampError {
+    #[error("Expected 32-byte document hash, got length {0}")]
+    InvalidDocumentHash(usize),
+    #[error("Receipt does not reduce to its root under the selected hash op")]
+    RootMismatch,
+}
+
+/// Inclusion receipt for one document of a sealed epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipt {
+    pub epoch: u64,
+    pub root: Bytes,
+    pub document_hash: Bytes,
+    pub proof: Vec<Bytes>,
+}
+
+/// Accumulates 32-byte document hashes for the current epoch and, when sealed, builds the
+/// epoch tree and hands out one [`Receipt`] per document. Publishing (and thereby
+/// timestamping) each epoch root is left to the caller.
+pub struct TimestampBatcher<F>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    epoch: u64,
+    pending: Vec<Bytes>,
+    node_hash: F,
+}
+
+impl<F> TimestampBatcher<F>
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    pub fn new(first_epoch: u64, node_hash: F) -> Self {
+        Self {
+            epoch: first_epoch,
+            pending: Vec::new(),
+            node_hash,
+        }
+    }
+
+    /// The epoch the next [`seal`](Self::seal) will produce receipts for.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queue a document for the current epoch. Panics unless `document_hash` is 32 bytes.
+    pub fn add(&mut self, document_hash: Bytes) {
+        self.try_add(document_hash)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_add(&mut self, document_hash: Bytes) -> Result<(), TimestampError> {
+        if document_hash.len() != 32 {
+            return Err(TimestampError::InvalidDocumentHash(document_hash.len()));
+        }
+        self.pending.push(document_hash);
+        Ok(())
+    }
+
+    /// Build the tree for the current epoch and return receipts in insertion order. An epoch
+    /// with no documents yields no receipts and does not advance.
+    pub fn seal(&mut self) -> Vec<Receipt> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+
+        let documents = std::mem::take(&mut self.pending);
+        let tree = make_merkle_tree_bytes(documents.clone(), &self.node_hash);
+        let root = tree[0].clone();
+        let epoch = self.epoch;
+        self.epoch += 1;
+
+        documents
+            .into_iter()
+            .enumerate()
+            .map(|(i, document_hash)| Receipt {
+                epoch,
+                root: root.clone(),
+                document_hash,
+                proof: get_proof(&tree, tree.len() - 1 - i),
+            })
+            .collect()
+    }
+}
+
+/// Check that `receipt` proves inclusion of `document_hash` under `trusted_root`, the epoch
+/// root as published for `receipt.epoch`. The receipt's own `root` is not trusted. Malformed
+/// receipts verify as `false`.
+pub fn verify_receipt<F>(
+    receipt: &Receipt,
+    document_hash: &[u8],
+    trusted_root: &[u8],
+    node_hash: F,
+) -> bool
+where
+    F: Fn(&[u8], &[u8]) -> Bytes,
+{
+    constant_time_eq(&receipt.document_hash, document_hash)
+        && verify_proof(trusted_root, document_hash, &receipt.proof, node_hash)
+}
+
+/// Hash operation used both for the document digest and for combining tree nodes when
+/// exporting to OpenTimestamps. Receipts only translate correctly if the tree was built with
+/// the sorted-pair node hash `H(min(a, b) ‖ max(a, b))` using the same function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtsHashOp {
+    Sha256,
+    Keccak256,
+}
+
+impl OtsHashOp {
+    fn tag(self) -> u8 {
+        match self {
+            OtsHashOp::Sha256 => 0x08,
+            OtsHashOp::Keccak256 => 0x67,
+        }
+    }
+}
+
+const OTS_HEADER_MAGIC: &[u8] =
+    b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const OTS_MAJOR_VERSION: u64 = 1;
+const OTS_OP_APPEND: u8 = 0xf0;
+const OTS_OP_PREPEND: u8 = 0xf1;
+const OTS_ATTESTATION: u8 = 0x00;
+const OTS_PENDING_ATTESTATION_TAG: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+
+fn write_varuint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_varbytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+impl Receipt {
+    /// Serialize the receipt as an OpenTimestamps detached timestamp (`.ots` file contents).
+    ///
+    /// Each proof step becomes an append or prepend of the sibling followed by `hash_op`, and
+    /// the epoch root is attested with a pending attestation pointing at `calendar_uri`, where
+    /// the root is expected to be published. Fails with [`TimestampError::RootMismatch`] if
+    /// the proof does not lead to the receipt's root under `hash_op`, e.g. for a receipt from
+    /// a tree built with another hash function.
+    pub fn to_ots(&self, hash_op: OtsHashOp, calendar_uri: &str) -> Result<Bytes, TimestampError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(OTS_HEADER_MAGIC);
+        write_varuint(&mut out, OTS_MAJOR_VERSION);
+        out.push(hash_op.tag());
+        out.extend_from_slice(&self.document_hash);
+
+        let mut computed = self.document_hash.clone();
+        for sibling in self.proof.iter() {
+            let (tag, parent) = if computed <= *sibling {
+                (OTS_OP_APPEND, [&computed[..], &sibling[..]].concat())
+            } else {
+                (OTS_OP_PREPEND, [&sibling[..], &computed[..]].concat())
+            };
+            out.push(tag);
+            write_varbytes(&mut out, sibling);
+            out.push(hash_op.tag());
+            computed = match hash_op {
+                OtsHashOp::Sha256 => crate::hashes::sha256(&parent).to_vec(),
+                OtsHashOp::Keccak256 => crate::hashes::keccak256(&parent).to_vec(),
+            };
+        }
+        if computed != self.root {
+            return Err(TimestampError::RootMismatch);
+        }
+
+        out.push(OTS_ATTESTATION);
+        out.extend_from_slice(&OTS_PENDING_ATTESTATION_TAG);
+        let mut payload = Vec::new();
+        write_varbytes(&mut payload, calendar_uri.as_bytes());
+        write_varbytes(&mut out, &payload);
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,1162 @@
+//! Owned Merkle tree over the flat heap layout, so callers work with 0-based leaf positions
+//! instead of raw tree indices.
+
+use std::collections::BTreeSet;
+
+use crate::consts::{CONFIG_DOMAIN, ZERO_HASH};
+use crate::dump::{hex_string, invalid_dump, parse_hex32, DumpError, Json};
+use crate::hashes::{
+    hasher_by_name, keccak256, Hasher, PositionalKeccak256Hasher, PositionalSha256Hasher,
+    Rfc6962Hasher,
+};
+use crate::merkle::{
+    check_zero_leaves, constant_time_eq, get_multi_proof, get_positional_multi_proof, get_proof,
+    is_valid_merkle_tree, is_valid_positional_merkle_tree, leaf_count, max_proof_len,
+    try_get_positional_proof, try_leaf_tree_index, try_make_merkle_tree_by_levels,
+    try_make_positional_merkle_tree_bytes, try_node_tree_index, verify_multi_proof,
+    verify_positional_multi_proof, verify_positional_proof, verify_proof, Bytes, Hash, MerkleError,
+    MultiProof, PositionalLayout, PositionalMultiProof, Side, ZeroLeafPolicy,
+};
+
+/// Root reported for a tree with no leaves. [`MerkleTree`] always has at least one leaf, so
+/// protocols that define an empty-root constant use [`root_of`] instead of special-casing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EmptyRoot {
+    /// Fail with [`MerkleError::EmptyTree`], like [`MerkleTree::new`].
+    #[default]
+    Reject,
+    /// The all-zero hash.
+    Zero,
+    /// `hash_leaf(tag)`; an empty tag with SHA-256 gives RFC 6962's `SHA-256("")`.
+    Tagged(Bytes),
+}
+
+impl EmptyRoot {
+    pub fn root<H: Hasher>(&self, hasher: &H) -> Result<Hash, MerkleError> {
+        match self {
+            EmptyRoot::Reject => Err(MerkleError::EmptyTree),
+            EmptyRoot::Zero => Ok(ZERO_HASH),
+            EmptyRoot::Tagged(tag) => Ok(hasher.hash_leaf(tag)),
+        }
+    }
+}
+
+/// Root of a tree over `leaves`, or the root `empty` defines when there are none.
+pub fn root_of<H: Hasher>(
+    leaves: Vec<Bytes>,
+    hasher: &H,
+    empty: &EmptyRoot,
+) -> Result<Bytes, MerkleError> {
+    if leaves.is_empty() {
+        return empty.root(hasher).map(|h| h.to_vec());
+    }
+    let mut tree = try_make_merkle_tree_by_levels(leaves, |pairs| hasher.hash_pairs(pairs))?;
+    Ok(tree.swap_remove(0))
+}
+
+/// Aligned perfect subtrees over consecutive positions of one tree level, merged as soon as
+/// two siblings are complete: the "peaks" of the positions pushed so far.
+struct PeakFold<F: Fn(&Hash, &Hash) -> Hash> {
+    /// `(start, height, hash)`, leftmost first.
+    peaks: Vec<(usize, u32, Hash)>,
+    combine: F,
+}
+
+impl<F: Fn(&Hash, &Hash) -> Hash> PeakFold<F> {
+    fn push(&mut self, start: usize, height: u32, hash: Hash) {
+        let (mut start, mut height, mut hash) = (start, height, hash);
+        while let Some(&(s, h, left)) = self.peaks.last() {
+            if h != height || !s.is_multiple_of(2 << h) {
+                break;
+            }
+            self.peaks.pop();
+            hash = (self.combine)(&left, &hash);
+            (start, height) = (s, h + 1);
+        }
+        self.peaks.push((start, height, hash));
+    }
+}
+
+fn fold_root<I, F>(leaves: I, combine: F) -> Result<Hash, MerkleError>
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: AsRef<[u8]>,
+    F: Fn(&Hash, &Hash) -> Hash,
+{
+    let mut leaves = leaves.into_iter();
+    let n = leaves.len();
+    if n == 0 {
+        return Err(MerkleError::EmptyTree);
+    }
+    let mut next = || -> Result<Hash, MerkleError> {
+        let leaf = leaves
+            .next()
+            .expect("Iterator yielded fewer leaves than its length");
+        leaf.as_ref()
+            .try_into()
+            .map_err(|_| MerkleError::InvalidNode(leaf.as_ref().len()))
+    };
+
+    // The heap keeps the first 2k leaves on a partial bottom level. Their k parents and the
+    // other leaves form a perfect level of `base` nodes which, read from the right, holds the
+    // remaining leaves in order followed by the parents in order.
+    let base = 1 << (usize::BITS - 1 - n.leading_zeros());
+    let k = n - base;
+    let mut parents = PeakFold {
+        peaks: Vec::new(),
+        combine: &combine,
+    };
+    for i in 0..k {
+        let (first, second) = (next()?, next()?);
+        parents.push(base - k + i, 0, combine(&first, &second));
+    }
+    let mut level = PeakFold {
+        peaks: Vec::new(),
+        combine: &combine,
+    };
+    for i in 0..base - k {
+        level.push(i, 0, next()?);
+    }
+    for (start, height, hash) in parents.peaks {
+        level.push(start, height, hash);
+    }
+    Ok(level.peaks[0].2)
+}
+
+/// Root of [`MerkleTree::new`] over `leaves`, streamed with `O(log n)` memory instead of
+/// building the `2n - 1` node tree. The iterator must report its exact length.
+pub fn compute_root<I, H>(leaves: I, hasher: &H) -> Result<Hash, MerkleError>
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: AsRef<[u8]>,
+    H: Hasher,
+{
+    // heap children are (later, earlier) in leaf order
+    fold_root(leaves, |earlier, later| hasher.hash_node(later, earlier))
+}
+
+/// Root of [`MerkleTree::new_positional`] over `leaves`, like [`compute_root`].
+pub fn compute_positional_root<I, H>(leaves: I, hasher: &H) -> Result<Hash, MerkleError>
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: AsRef<[u8]>,
+    H: Hasher,
+{
+    // perfect subtrees of the leaves so far, largest first, as in `IncrementalMerkleTree`
+    let mut frontier: Vec<Hash> = Vec::new();
+    for (i, leaf) in leaves.into_iter().enumerate() {
+        let leaf = leaf.as_ref();
+        let mut carry: Hash = leaf
+            .try_into()
+            .map_err(|_| MerkleError::InvalidNode(leaf.len()))?;
+        let mut n = i;
+        while n & 1 == 1 {
+            carry = hasher.hash_node(&frontier.pop().unwrap(), &carry);
+            n >>= 1;
+        }
+        frontier.push(carry);
+    }
+    let mut peaks = frontier.into_iter().rev();
+    let last = peaks.next().ok_or(MerkleError::EmptyTree)?;
+    Ok(peaks.fold(last, |acc, peak| hasher.hash_node(&peak, &acc)))
+}
+
+/// How the two children of a node are ordered before hashing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum PairOrdering {
+    /// Hashed in ascending order, as OpenZeppelin's trees do, in the heap layout.
+    #[default]
+    Sorted,
+    /// Hashed `left ‖ right` in leaf order, in the RFC 6962 shape of
+    /// [`MerkleTree::new_positional`].
+    Positional,
+}
+
+/// Multiproof format a tree produces and a verifier expects. The proof nodes come in a
+/// different order under each, so a proof only verifies under the algorithm it was made with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum MultiProofAlgorithm {
+    /// OpenZeppelin's `multiProofVerify`: a [`MultiProof`] whose flags drive a queue over the
+    /// sorted pairs of the heap layout.
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "ozCompatible"))]
+    OZCompatible,
+    /// A [`PositionalMultiProof`], whose sides follow from the leaf positions, over the RFC
+    /// 6962 shape of positional trees.
+    Positional,
+}
+
+impl MultiProofAlgorithm {
+    /// The algorithm trees with `pair_ordering` are built for.
+    pub fn for_ordering(pair_ordering: PairOrdering) -> Self {
+        match pair_ordering {
+            PairOrdering::Sorted => Self::OZCompatible,
+            PairOrdering::Positional => Self::Positional,
+        }
+    }
+}
+
+/// How a tree fills the leaves it does not have.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum Padding {
+    /// No filler: the heap layout, or for positional trees the last node of an odd level is
+    /// carried up.
+    #[default]
+    None,
+    /// `2^depth` leaves, the unfilled ones set to `zero_value`, as in
+    /// [`crate::incremental::FixedDepthTree`].
+    #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+    ZeroSubtrees {
+        depth: usize,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::hash"))]
+        zero_value: Hash,
+    },
+}
+
+/// Root of a tree with a single leaf, which external formats define differently.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum SingleLeafRoot {
+    /// The leaf itself, with an empty proof, as in OpenZeppelin's trees and RFC 6962 (whose
+    /// leaves are already leaf hashes).
+    #[default]
+    Leaf,
+    /// [`Hasher::hash_leaf`] of the leaf, for formats that hash every root at least once.
+    Hashed,
+}
+
+impl SingleLeafRoot {
+    /// Root of a tree whose only leaf is `leaf`.
+    pub fn root<H: Hasher>(&self, leaf: &[u8], hasher: &H) -> Bytes {
+        match self {
+            SingleLeafRoot::Leaf => leaf.to_vec(),
+            SingleLeafRoot::Hashed => hasher.hash_leaf(leaf).to_vec(),
+        }
+    }
+
+    /// Whether `root` is the root of a tree whose only leaf is `leaf`.
+    pub(crate) fn matches<H: Hasher>(&self, root: &[u8], leaf: &[u8], hasher: &H) -> bool {
+        constant_time_eq(&self.root(leaf, hasher), root)
+    }
+}
+
+/// Settings a proof has to be checked under. A proof verified with the wrong settings, e.g.
+/// sorted instead of positional pairs, fails or proves something else, so a tree keeps its
+/// config, dumps record it and a root can be bound to it with [`TreeConfig::commit_root`].
+/// The default is OpenZeppelin's: sorted keccak256 pairs over pre-hashed leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct TreeConfig {
+    /// [`Hasher::name`] of the hash function, e.g. `"keccak256"`; empty for custom hashers.
+    pub hasher: String,
+    pub pair_ordering: PairOrdering,
+    pub multi_proof: MultiProofAlgorithm,
+    /// How values become leaves, e.g. `"abi(address,uint256)"`; empty for pre-hashed leaves.
+    pub leaf_encoding: String,
+    /// Whether all-zero leaves are accepted.
+    pub leaf_policy: ZeroLeafPolicy,
+    pub padding: Padding,
+    /// Children per node; only binary trees are built.
+    pub arity: usize,
+    /// Application tag, e.g. a contract address or a log id, that two trees with otherwise
+    /// equal settings can be told apart by.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::node"))]
+    pub domain_tag: Bytes,
+    /// Root of a one-leaf tree; its proofs carry no nodes under either policy.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub single_leaf: SingleLeafRoot,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            hasher: "keccak256".to_string(),
+            pair_ordering: PairOrdering::Sorted,
+            multi_proof: MultiProofAlgorithm::OZCompatible,
+            leaf_encoding: String::new(),
+            leaf_policy: ZeroLeafPolicy::Allow,
+            padding: Padding::None,
+            arity: 2,
+            domain_tag: Vec::new(),
+            single_leaf: SingleLeafRoot::Leaf,
+        }
+    }
+}
+
+/// Fail if `hasher` reports a pair ordering other than `expected`, e.g. a sorted-pair hasher
+/// for a positional tree, whose proofs would then not verify under the tree's config.
+pub(crate) fn check_pair_ordering<H: Hasher>(
+    hasher: &H,
+    expected: PairOrdering,
+) -> Result<(), MerkleError> {
+    match hasher.pair_ordering() {
+        Some(ordering) if ordering != expected => Err(MerkleError::InvalidConfig(format!(
+            "{:?} hasher {:?} cannot build a {:?} tree",
+            ordering,
+            hasher.name(),
+            expected
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn length_prefixed(data: &[u8]) -> Bytes {
+    [&(data.len() as u32).to_be_bytes()[..], data].concat()
+}
+
+impl TreeConfig {
+    /// `keccak256` over the settings and the hasher's output on fixed inputs, so two hashers
+    /// only share a fingerprint if they agree on those inputs (which tells sorted from ordered
+    /// pairs and one hash function from another).
+    pub fn fingerprint<H: Hasher>(&self, hasher: &H) -> Hash {
+        let (a, b) = ([0u8; 32], [0xffu8; 32]);
+        let padding = match &self.padding {
+            Padding::None => vec![0],
+            Padding::ZeroSubtrees { depth, zero_value } => {
+                [&[1], &(*depth as u64).to_be_bytes()[..], zero_value].concat()
+            }
+        };
+        // absent for the default so configs from before the policy keep their fingerprint
+        let single_leaf: &[u8] = match self.single_leaf {
+            SingleLeafRoot::Leaf => &[],
+            SingleLeafRoot::Hashed => b"single-leaf:hashed",
+        };
+        keccak256(
+            &[
+                CONFIG_DOMAIN,
+                &length_prefixed(self.hasher.as_bytes()),
+                &[
+                    self.pair_ordering as u8,
+                    self.multi_proof as u8,
+                    self.leaf_policy as u8,
+                ],
+                &length_prefixed(self.leaf_encoding.as_bytes()),
+                &padding,
+                &(self.arity as u64).to_be_bytes(),
+                &length_prefixed(&self.domain_tag),
+                single_leaf,
+                &hasher.hash_leaf(&[]),
+                &hasher.hash_node(&a, &b),
+                &hasher.hash_node(&b, &a),
+            ]
+            .concat(),
+        )
+    }
+
+    /// `keccak256(root ‖ fingerprint)`: publish this instead of the bare root and a verifier
+    /// recomputes it from the root and its own settings before checking proofs.
+    pub fn commit_root<H: Hasher>(&self, root: &[u8], hasher: &H) -> Hash {
+        keccak256(&[root, &self.fingerprint(hasher)[..]].concat())
+    }
+
+    /// The built-in hasher named by [`Self::hasher`] with [`Self::pair_ordering`], e.g.
+    /// [`crate::hashes::PositionalSha256Hasher`] for positional `"sha256"`.
+    pub fn build_hasher(&self) -> Result<Box<dyn Hasher + Send + Sync>, MerkleError> {
+        let hasher: Option<Box<dyn Hasher + Send + Sync>> =
+            match (self.hasher.as_str(), self.pair_ordering) {
+                (name, PairOrdering::Sorted) => hasher_by_name(name)
+                    .filter(|h| h.pair_ordering() != Some(PairOrdering::Positional)),
+                ("keccak256", PairOrdering::Positional) => {
+                    Some(Box::new(PositionalKeccak256Hasher))
+                }
+                ("sha256", PairOrdering::Positional) => Some(Box::new(PositionalSha256Hasher)),
+                ("rfc6962", PairOrdering::Positional) => Some(Box::new(Rfc6962Hasher)),
+                _ => None,
+            };
+        hasher.ok_or_else(|| {
+            MerkleError::InvalidConfig(format!(
+                "no built-in {:?} hasher named {:?}",
+                self.pair_ordering, self.hasher
+            ))
+        })
+    }
+
+    /// Fail unless [`MerkleTree`] can build this config with `hasher`: binary, unpadded,
+    /// proven with the multiproof algorithm of its pair ordering and hashed by a hasher of the
+    /// recorded name and, if it reports one, the same [`Hasher::pair_ordering`].
+    pub fn check_supported<H: Hasher>(&self, hasher: &H) -> Result<(), MerkleError> {
+        if self.arity != 2 {
+            return Err(MerkleError::InvalidConfig(format!(
+                "arity {} is not supported",
+                self.arity
+            )));
+        }
+        if self.padding != Padding::None {
+            return Err(MerkleError::InvalidConfig(
+                "padded trees are built with FixedDepthTree".to_string(),
+            ));
+        }
+        if self.multi_proof != MultiProofAlgorithm::for_ordering(self.pair_ordering) {
+            return Err(MerkleError::InvalidConfig(format!(
+                "{:?} multiproofs do not cover {:?} trees",
+                self.multi_proof, self.pair_ordering
+            )));
+        }
+        if hasher.name() != self.hasher {
+            return Err(MerkleError::InvalidConfig(format!(
+                "config names hasher {:?}, got {:?}",
+                self.hasher,
+                hasher.name()
+            )));
+        }
+        check_pair_ordering(hasher, self.pair_ordering)
+    }
+
+    /// Tree over `leaves` with the built-in hasher of this config, see
+    /// [`MerkleTree::try_with_config`].
+    pub fn build(
+        &self,
+        leaves: Vec<Bytes>,
+    ) -> Result<MerkleTree<Box<dyn Hasher + Send + Sync>>, MerkleError> {
+        MerkleTree::try_with_config(leaves, self.build_hasher()?, self.clone())
+    }
+
+    pub(crate) fn to_json(&self) -> Json {
+        let padding = match &self.padding {
+            Padding::None => Json::String("none".to_string()),
+            Padding::ZeroSubtrees { depth, zero_value } => Json::Object(vec![(
+                "zeroSubtrees".to_string(),
+                Json::Object(vec![
+                    ("depth".to_string(), Json::Number(depth.to_string())),
+                    ("zeroValue".to_string(), hex_string(zero_value)),
+                ]),
+            )]),
+        };
+        let name = |s: &str| Json::String(s.to_string());
+        Json::Object(vec![
+            ("hasher".to_string(), name(&self.hasher)),
+            (
+                "pairOrdering".to_string(),
+                name(match self.pair_ordering {
+                    PairOrdering::Sorted => "sorted",
+                    PairOrdering::Positional => "positional",
+                }),
+            ),
+            (
+                "multiProof".to_string(),
+                name(match self.multi_proof {
+                    MultiProofAlgorithm::OZCompatible => "ozCompatible",
+                    MultiProofAlgorithm::Positional => "positional",
+                }),
+            ),
+            ("leafEncoding".to_string(), name(&self.leaf_encoding)),
+            (
+                "leafPolicy".to_string(),
+                name(match self.leaf_policy {
+                    ZeroLeafPolicy::Allow => "allow",
+                    ZeroLeafPolicy::Warn => "warn",
+                    ZeroLeafPolicy::Reject => "reject",
+                }),
+            ),
+            ("padding".to_string(), padding),
+            ("arity".to_string(), Json::Number(self.arity.to_string())),
+            ("domainTag".to_string(), hex_string(&self.domain_tag)),
+            (
+                "singleLeaf".to_string(),
+                name(match self.single_leaf {
+                    SingleLeafRoot::Leaf => "leaf",
+                    SingleLeafRoot::Hashed => "hashed",
+                }),
+            ),
+        ])
+    }
+
+    pub(crate) fn from_json(json: &Json) -> Result<Self, DumpError> {
+        let unknown =
+            |field: &str, value: &str| invalid_dump(format!("unknown {} {:?}", field, value));
+        let pair_ordering = match json.field("pairOrdering")?.as_str()? {
+            "sorted" => PairOrdering::Sorted,
+            "positional" => PairOrdering::Positional,
+            other => return Err(unknown("pair ordering", other)),
+        };
+        let multi_proof = match json.field("multiProof")?.as_str()? {
+            "ozCompatible" => MultiProofAlgorithm::OZCompatible,
+            "positional" => MultiProofAlgorithm::Positional,
+            other => return Err(unknown("multiproof algorithm", other)),
+        };
+        let leaf_policy = match json.field("leafPolicy")?.as_str()? {
+            "allow" => ZeroLeafPolicy::Allow,
+            "warn" => ZeroLeafPolicy::Warn,
+            "reject" => ZeroLeafPolicy::Reject,
+            other => return Err(unknown("leaf policy", other)),
+        };
+        let padding = match json.field("padding")? {
+            Json::String(s) if s == "none" => Padding::None,
+            padded => {
+                let padded = padded.field("zeroSubtrees")?;
+                Padding::ZeroSubtrees {
+                    depth: padded.field("depth")?.as_usize()?,
+                    zero_value: parse_hex32(padded.field("zeroValue")?)?.try_into().unwrap(),
+                }
+            }
+        };
+        // optional, as dumps from before the policy do not have it
+        let single_leaf = match json.get("singleLeaf").map(Json::as_str).transpose()? {
+            None | Some("leaf") => SingleLeafRoot::Leaf,
+            Some("hashed") => SingleLeafRoot::Hashed,
+            Some(other) => return Err(unknown("single-leaf root", other)),
+        };
+        let tag = json.field("domainTag")?.as_str()?;
+        let domain_tag = tag
+            .strip_prefix("0x")
+            .and_then(|digits| hex::decode(digits).ok())
+            .ok_or_else(|| invalid_dump(format!("invalid hex value {:?}", tag)))?;
+        Ok(Self {
+            hasher: json.field("hasher")?.as_str()?.to_string(),
+            pair_ordering,
+            multi_proof,
+            leaf_encoding: json.field("leafEncoding")?.as_str()?.to_string(),
+            leaf_policy,
+            padding,
+            arity: json.field("arity")?.as_usize()?,
+            domain_tag,
+            single_leaf,
+        })
+    }
+
+    /// Check a [`MerkleTree::proof`] of a sorted-pair tree under this config; `false` for a
+    /// positional config or one without a built-in hasher.
+    pub fn verify_proof(&self, root: &[u8], leaf: &[u8], proof: &[Bytes]) -> bool {
+        self.build_hasher()
+            .is_ok_and(|hasher| self.check_proof(root, leaf, proof, &hasher))
+    }
+
+    /// Check a [`MerkleTree::positional_proof`] under this config; `false` for a sorted config
+    /// or one without a built-in hasher.
+    pub fn verify_positional_proof(
+        &self,
+        root: &[u8],
+        leaf: &[u8],
+        proof: &[(Side, Bytes)],
+    ) -> bool {
+        self.build_hasher()
+            .is_ok_and(|hasher| self.check_positional_proof(root, leaf, proof, &hasher))
+    }
+
+    /// Check a [`MerkleTree::multi_proof`] under this config; `false` unless it selects
+    /// [`MultiProofAlgorithm::OZCompatible`] and has a built-in hasher.
+    pub fn verify_multi_proof(&self, root: &[u8], proof: &MultiProof) -> bool {
+        self.build_hasher()
+            .is_ok_and(|hasher| self.check_multi_proof(root, proof, &hasher))
+    }
+
+    /// Check a [`MerkleTree::positional_multi_proof`] under this config; `false` unless it
+    /// selects [`MultiProofAlgorithm::Positional`] and has a built-in hasher.
+    pub fn verify_positional_multi_proof(&self, root: &[u8], proof: &PositionalMultiProof) -> bool {
+        self.build_hasher()
+            .is_ok_and(|hasher| self.check_positional_multi_proof(root, proof, &hasher))
+    }
+
+    // The checks below take the hasher so bound proofs can use the caller's. A proof without
+    // nodes can only come from a one-leaf tree, whose root follows `single_leaf`.
+
+    fn check_proof<H: Hasher>(
+        &self,
+        root: &[u8],
+        leaf: &[u8],
+        proof: &[Bytes],
+        hasher: &H,
+    ) -> bool {
+        if self.pair_ordering != PairOrdering::Sorted {
+            return false;
+        }
+        if proof.is_empty() {
+            return self.single_leaf.matches(root, leaf, hasher);
+        }
+        verify_proof(root, leaf, proof, |a, b| hasher.hash_node(a, b).to_vec())
+    }
+
+    fn check_positional_proof<H: Hasher>(
+        &self,
+        root: &[u8],
+        leaf: &[u8],
+        proof: &[(Side, Bytes)],
+        hasher: &H,
+    ) -> bool {
+        if self.pair_ordering != PairOrdering::Positional {
+            return false;
+        }
+        if proof.is_empty() {
+            return self.single_leaf.matches(root, leaf, hasher);
+        }
+        verify_positional_proof(root, leaf, proof, |a, b| hasher.hash_node(a, b).to_vec())
+    }
+
+    fn check_multi_proof<H: Hasher>(&self, root: &[u8], proof: &MultiProof, hasher: &H) -> bool {
+        if self.multi_proof != MultiProofAlgorithm::OZCompatible {
+            return false;
+        }
+        if let ([leaf], [], []) = (&proof.leaves[..], &proof.proof[..], &proof.proof_flags[..]) {
+            return self.single_leaf.matches(root, leaf, hasher);
+        }
+        verify_multi_proof(root, proof, |a, b| hasher.hash_node(a, b).to_vec())
+    }
+
+    fn check_positional_multi_proof<H: Hasher>(
+        &self,
+        root: &[u8],
+        proof: &PositionalMultiProof,
+        hasher: &H,
+    ) -> bool {
+        if self.multi_proof != MultiProofAlgorithm::Positional {
+            return false;
+        }
+        if let (1, [0], [leaf], []) = (
+            proof.leaf_count,
+            &proof.positions[..],
+            &proof.leaves[..],
+            &proof.proof[..],
+        ) {
+            return self.single_leaf.matches(root, leaf, hasher);
+        }
+        verify_positional_multi_proof(root, proof, |a, b| hasher.hash_node(a, b).to_vec())
+    }
+}
+
+/// A proof carrying the [`TreeConfig::fingerprint`] of the tree it came from, which names the
+/// tree through [`TreeConfig::domain_tag`]. It only verifies under a config with the same
+/// fingerprint, so a proof checked against another tree's or epoch's root is rejected up
+/// front instead of failing, or passing, for the wrong reason.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoundProof<P> {
+    pub fingerprint: Hash,
+    pub proof: P,
+}
+
+impl<P> BoundProof<P> {
+    fn bound_to<H: Hasher>(&self, config: &TreeConfig, hasher: &H) -> bool {
+        constant_time_eq(&self.fingerprint, &config.fingerprint(hasher))
+    }
+}
+
+impl BoundProof<Vec<Bytes>> {
+    /// Check the fingerprint against `config` and `hasher`, then the proof of `leaf` against
+    /// `root` like [`verify_proof`].
+    pub fn verify<H: Hasher>(
+        &self,
+        root: &[u8],
+        leaf: &[u8],
+        config: &TreeConfig,
+        hasher: &H,
+    ) -> bool {
+        self.bound_to(config, hasher) && config.check_proof(root, leaf, &self.proof, hasher)
+    }
+}
+
+impl BoundProof<Vec<(Side, Bytes)>> {
+    /// Positional counterpart of [`BoundProof::verify`].
+    pub fn verify<H: Hasher>(
+        &self,
+        root: &[u8],
+        leaf: &[u8],
+        config: &TreeConfig,
+        hasher: &H,
+    ) -> bool {
+        self.bound_to(config, hasher)
+            && config.check_positional_proof(root, leaf, &self.proof, hasher)
+    }
+}
+
+impl BoundProof<MultiProof> {
+    /// Multiproof counterpart of [`BoundProof::verify`]; the leaves are in the proof.
+    pub fn verify<H: Hasher>(&self, root: &[u8], config: &TreeConfig, hasher: &H) -> bool {
+        self.bound_to(config, hasher) && config.check_multi_proof(root, &self.proof, hasher)
+    }
+}
+
+impl BoundProof<PositionalMultiProof> {
+    /// Positional multiproof counterpart of [`BoundProof::verify`].
+    pub fn verify<H: Hasher>(&self, root: &[u8], config: &TreeConfig, hasher: &H) -> bool {
+        self.bound_to(config, hasher)
+            && config.check_positional_multi_proof(root, &self.proof, hasher)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleTree<H: Hasher> {
+    tree: Vec<Bytes>,
+    hasher: H,
+    config: TreeConfig,
+    /// Root of a one-leaf tree under [`SingleLeafRoot::Hashed`], which is not a tree node.
+    hashed_root: Option<Bytes>,
+    /// Positions of zero leaves under [`ZeroLeafPolicy::Warn`]; empty under other policies.
+    zero_leaves: BTreeSet<usize>,
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Build a tree over `leaves` in the given order. Panics on an empty leaf list or a leaf
+    /// that is not 32 bytes, like [`crate::merkle::make_merkle_tree_bytes`].
+    pub fn new(leaves: Vec<Bytes>, hasher: H) -> Self {
+        Self::try_new(leaves, hasher).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Build a tree with positional (unsorted) pair hashing in the RFC 6962 shape, see
+    /// [`crate::merkle::make_positional_merkle_tree_bytes`] for the layout. Its root equals
+    /// [`crate::consistency::log_root`] and [`crate::incremental::IncrementalMerkleTree::root`]
+    /// over the same leaves. Prove its leaves with [`Self::positional_proof`]; the sorted-pair
+    /// proofs do not apply.
+    pub fn new_positional(leaves: Vec<Bytes>, hasher: H) -> Self {
+        Self::try_new_positional(leaves, hasher).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new_positional(leaves: Vec<Bytes>, hasher: H) -> Result<Self, MerkleError> {
+        let config = TreeConfig {
+            hasher: hasher.name().to_string(),
+            pair_ordering: PairOrdering::Positional,
+            multi_proof: MultiProofAlgorithm::Positional,
+            ..TreeConfig::default()
+        };
+        Self::try_with_config(leaves, hasher, config)
+    }
+
+    pub fn try_new(leaves: Vec<Bytes>, hasher: H) -> Result<Self, MerkleError> {
+        let config = TreeConfig {
+            hasher: hasher.name().to_string(),
+            ..TreeConfig::default()
+        };
+        Self::try_with_config(leaves, hasher, config)
+    }
+
+    /// Build a tree with the pair ordering and leaf policy of `config`, which the tree keeps
+    /// for [`Self::config`] and its dumps. Fails on settings
+    /// [`TreeConfig::check_supported`] rejects, e.g. a `hasher` of another name.
+    pub fn try_with_config(
+        leaves: Vec<Bytes>,
+        hasher: H,
+        config: TreeConfig,
+    ) -> Result<Self, MerkleError> {
+        config.check_supported(&hasher)?;
+        let zero_leaves = check_zero_leaves(&leaves, config.leaf_policy)?;
+        let tree = match config.pair_ordering {
+            PairOrdering::Sorted => {
+                try_make_merkle_tree_by_levels(leaves, |pairs| hasher.hash_pairs(pairs))?
+            }
+            PairOrdering::Positional => try_make_positional_merkle_tree_bytes(leaves, |a, b| {
+                hasher.hash_node(a, b).to_vec()
+            })?,
+        };
+        Ok(Self::from_parts(tree, hasher, config, zero_leaves))
+    }
+
+    fn from_parts(
+        tree: Vec<Bytes>,
+        hasher: H,
+        config: TreeConfig,
+        zero_leaves: Vec<usize>,
+    ) -> Self {
+        let mut tree = Self {
+            tree,
+            hasher,
+            config,
+            hashed_root: None,
+            zero_leaves: zero_leaves.into_iter().collect(),
+        };
+        tree.update_hashed_root();
+        tree
+    }
+
+    fn update_hashed_root(&mut self) {
+        self.hashed_root = (self.tree.len() == 1
+            && self.config.single_leaf == SingleLeafRoot::Hashed)
+            .then(|| self.config.single_leaf.root(&self.tree[0], &self.hasher));
+    }
+
+    /// `{"tree":[...],"config":{...}}`, the same JSON as the serde representation.
+    pub fn dump(&self) -> String {
+        Json::Object(vec![
+            (
+                "tree".to_string(),
+                Json::Array(self.tree.iter().map(|n| hex_string(n)).collect()),
+            ),
+            ("config".to_string(), self.config.to_json()),
+        ])
+        .to_json_string()
+    }
+
+    /// Load a [`Self::dump`], checking the tree against `hasher` under the recorded config.
+    pub fn load(dump: &str, hasher: H) -> Result<Self, DumpError> {
+        let json = Json::parse(dump)?;
+        let tree = json
+            .field("tree")?
+            .as_array()?
+            .iter()
+            .map(parse_hex32)
+            .collect::<Result<Vec<Bytes>, DumpError>>()?;
+        let config = TreeConfig::from_json(json.field("config")?)?;
+        Self::from_dump_parts(tree, hasher, config)
+    }
+
+    /// Rebuild a tree from dumped parts, checking the config against `hasher` and every node
+    /// against its children.
+    pub(crate) fn from_dump_parts(
+        tree: Vec<Bytes>,
+        hasher: H,
+        config: TreeConfig,
+    ) -> Result<Self, DumpError> {
+        config
+            .check_supported(&hasher)
+            .map_err(|e| invalid_dump(e.to_string()))?;
+        let node_hash = |a: &[u8], b: &[u8]| hasher.hash_node(a, b).to_vec();
+        let valid = match config.pair_ordering {
+            PairOrdering::Sorted => is_valid_merkle_tree(&tree, node_hash),
+            PairOrdering::Positional => is_valid_positional_merkle_tree(&tree, node_hash),
+        };
+        if !valid {
+            return Err(invalid_dump("tree is not a valid merkle tree"));
+        }
+        let policy = config.leaf_policy;
+        let mut tree = Self::from_parts(tree, hasher, config, Vec::new());
+        let leaves: Vec<Bytes> = tree.leaves().map(<[u8]>::to_vec).collect();
+        tree.zero_leaves = check_zero_leaves(&leaves, policy)
+            .map_err(|e| invalid_dump(e.to_string()))?
+            .into_iter()
+            .collect();
+        Ok(tree)
+    }
+
+    /// Positions of the zero leaves, reported under [`ZeroLeafPolicy::Warn`] and kept up to
+    /// date by updates. Always empty under `Allow`, and under `Reject` there are none.
+    pub fn zero_leaves(&self) -> Vec<usize> {
+        self.zero_leaves.iter().copied().collect()
+    }
+
+    pub fn is_positional(&self) -> bool {
+        self.config.pair_ordering == PairOrdering::Positional
+    }
+
+    /// Settings the tree was built with.
+    pub fn config(&self) -> TreeConfig {
+        self.config.clone()
+    }
+
+    pub fn config_fingerprint(&self) -> Hash {
+        self.config.fingerprint(&self.hasher)
+    }
+
+    /// The root bound to [`Self::config`], see [`TreeConfig::commit_root`].
+    pub fn root_with_config_commitment(&self) -> Hash {
+        self.config.commit_root(self.root(), &self.hasher)
+    }
+
+    /// The top node, or for a one-leaf tree the root [`TreeConfig::single_leaf`] defines.
+    pub fn root(&self) -> &[u8] {
+        self.hashed_root.as_deref().unwrap_or(&self.tree[0])
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        leaf_count(self.tree.len())
+    }
+
+    /// Length of the longest proof; 0 for a single-leaf tree.
+    pub fn depth(&self) -> usize {
+        max_proof_len(self.tree.len())
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// The underlying node vector: the heap layout (root at index 0, leaves at the end in
+    /// reverse), or for positional trees the layout of
+    /// [`crate::merkle::make_positional_merkle_tree_bytes`].
+    pub fn as_slice(&self) -> &[Bytes] {
+        &self.tree
+    }
+
+    fn tree_index(&self, leaf_position: usize) -> usize {
+        self.try_tree_index(leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_tree_index(&self, leaf_position: usize) -> Result<usize, MerkleError> {
+        let index = try_leaf_tree_index(self.tree.len(), leaf_position)?;
+        if self.is_positional() {
+            // positional trees keep their leaves in order
+            Ok(self.tree.len() - self.leaf_count() + leaf_position)
+        } else {
+            Ok(index)
+        }
+    }
+
+    pub fn leaf(&self, leaf_position: usize) -> &[u8] {
+        &self.tree[self.tree_index(leaf_position)]
+    }
+
+    pub fn try_leaf(&self, leaf_position: usize) -> Result<&[u8], MerkleError> {
+        Ok(&self.tree[self.try_tree_index(leaf_position)?])
+    }
+
+    /// Node at `position` within heap `level`, see [`Self::levels`]. Panics if there is none.
+    pub fn node(&self, level: usize, position: usize) -> &[u8] {
+        self.try_node(level, position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_node(&self, level: usize, position: usize) -> Result<&[u8], MerkleError> {
+        if self.is_positional() {
+            let layout = PositionalLayout::new(self.leaf_count());
+            return (level <= layout.depth())
+                .then(|| layout.depth() - level)
+                .filter(|&layer| position < layout.formed(layer))
+                .map(|layer| self.tree[layout.offset(layer) + position].as_slice())
+                .ok_or(MerkleError::NodeOutOfRange { level, position });
+        }
+        let index = try_node_tree_index(self.tree.len(), level, position)?;
+        Ok(&self.tree[index])
+    }
+
+    /// Leaves in insertion order.
+    pub fn leaves(&self) -> impl DoubleEndedIterator<Item = &[u8]> + ExactSizeIterator + '_ {
+        (0..self.leaf_count()).map(|p| self.leaf(p))
+    }
+
+    /// Heap levels from the root down; level `k` holds indices `2^k - 1 .. 2^(k+1) - 1`, so the
+    /// last level may be partial and, for unbalanced trees, the last two levels hold leaves.
+    /// For positional trees level `k` holds the nodes hashed there in leaf order, without the
+    /// nodes carried up from the level below, and the last level holds all leaves.
+    pub fn levels(&self) -> impl Iterator<Item = &[Bytes]> + '_ {
+        let len = self.tree.len();
+        let layout = self
+            .is_positional()
+            .then(|| PositionalLayout::new(self.leaf_count()));
+        (0..=self.depth()).map(move |k| match &layout {
+            Some(layout) => {
+                let layer = layout.depth() - k;
+                let start = layout.offset(layer);
+                &self.tree[start..start + layout.formed(layer)]
+            }
+            None => {
+                let start = (1usize << k) - 1;
+                let end = ((1usize << (k + 1)) - 1).min(len);
+                &self.tree[start..end]
+            }
+        })
+    }
+
+    /// Sorted-pair proof for the leaf at `leaf_position`. Panics on an out-of-range position
+    /// or a positional tree, whose proofs need the sides of [`Self::positional_proof`].
+    pub fn proof(&self, leaf_position: usize) -> Vec<Bytes> {
+        self.try_proof(leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_proof(&self, leaf_position: usize) -> Result<Vec<Bytes>, MerkleError> {
+        self.check_pair_ordering(PairOrdering::Sorted, "positional_proof")?;
+        Ok(get_proof(&self.tree, self.try_tree_index(leaf_position)?))
+    }
+
+    /// Proof with sibling sides, for trees built with [`Self::new_positional`]. Panics on an
+    /// out-of-range position or a sorted-pair tree.
+    pub fn positional_proof(&self, leaf_position: usize) -> Vec<(Side, Bytes)> {
+        self.try_positional_proof(leaf_position)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_positional_proof(
+        &self,
+        leaf_position: usize,
+    ) -> Result<Vec<(Side, Bytes)>, MerkleError> {
+        self.check_pair_ordering(PairOrdering::Positional, "proof")?;
+        try_get_positional_proof(&self.tree, leaf_position)
+    }
+
+    fn check_pair_ordering(&self, expected: PairOrdering, other: &str) -> Result<(), MerkleError> {
+        if self.config.pair_ordering == expected {
+            return Ok(());
+        }
+        Err(MerkleError::InvalidConfig(format!(
+            "{:?} trees are proven with MerkleTree::{}",
+            self.config.pair_ordering, other
+        )))
+    }
+
+    /// Replace the leaf at `leaf_position` and rehash just its path to the root. Panics on an
+    /// out-of-range position or a leaf that is not 32 bytes.
+    pub fn update_leaf(&mut self, leaf_position: usize, new_leaf: &[u8]) {
+        self.try_update_leaf(leaf_position, new_leaf)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_update_leaf(
+        &mut self,
+        leaf_position: usize,
+        new_leaf: &[u8],
+    ) -> Result<(), MerkleError> {
+        self.try_batch_update(&[(leaf_position, new_leaf)])
+    }
+
+    /// Like [`Self::update_leaf`] for several leaves at once; nodes shared by their paths are
+    /// rehashed once. Later updates of the same position win. Nothing changes on error, which
+    /// includes a zero leaf under [`ZeroLeafPolicy::Reject`].
+    pub fn batch_update<L: AsRef<[u8]>>(&mut self, updates: &[(usize, L)]) {
+        self.try_batch_update(updates)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_batch_update<L: AsRef<[u8]>>(
+        &mut self,
+        updates: &[(usize, L)],
+    ) -> Result<(), MerkleError> {
+        let mut indices = Vec::with_capacity(updates.len());
+        for (position, leaf) in updates.iter() {
+            let leaf = leaf.as_ref();
+            if leaf.len() != 32 {
+                return Err(MerkleError::InvalidNode(leaf.len()));
+            }
+            indices.push(self.try_tree_index(*position)?);
+            if self.config.leaf_policy == ZeroLeafPolicy::Reject && leaf == ZERO_HASH {
+                return Err(MerkleError::ZeroLeaf(*position));
+            }
+        }
+        if self.config.leaf_policy == ZeroLeafPolicy::Warn {
+            for (position, leaf) in updates.iter() {
+                if leaf.as_ref() == ZERO_HASH {
+                    self.zero_leaves.insert(*position);
+                } else {
+                    self.zero_leaves.remove(position);
+                }
+            }
+        }
+        if self.is_positional() {
+            self.update_positional(updates);
+            self.update_hashed_root();
+            return Ok(());
+        }
+
+        let mut dirty = BTreeSet::new();
+        for (index, (_, leaf)) in indices.into_iter().zip(updates.iter()) {
+            self.tree[index] = leaf.as_ref().to_vec();
+            if index > 0 {
+                dirty.insert((index - 1) / 2);
+            }
+        }
+        // parents have lower indices than their children, so the highest dirty node is always
+        // ready to rehash
+        while let Some(index) = dirty.pop_last() {
+            let (left, right) = (&self.tree[2 * index + 1], &self.tree[2 * index + 2]);
+            self.tree[index] = self.hasher.hash_node(left, right).to_vec();
+            if index > 0 {
+                dirty.insert((index - 1) / 2);
+            }
+        }
+        self.update_hashed_root();
+        Ok(())
+    }
+
+    /// [`Self::try_batch_update`] of a positional tree with checked updates.
+    fn update_positional<L: AsRef<[u8]>>(&mut self, updates: &[(usize, L)]) {
+        let layout = PositionalLayout::new(self.leaf_count());
+        let mut dirty = BTreeSet::new();
+        for (position, leaf) in updates.iter() {
+            self.tree[layout.offset(0) + position] = leaf.as_ref().to_vec();
+            dirty.insert((0, *position / 2));
+        }
+        // `(layer, position)` of the parents to rehash; lower layers come first
+        while let Some((layer, parent)) = dirty.pop_first() {
+            if layer == layout.depth() {
+                continue;
+            }
+            // a parent past the formed nodes is the carried child itself
+            if parent < layout.formed(layer + 1) {
+                let left = &self.tree[layout.index(layer, 2 * parent)];
+                let right = &self.tree[layout.index(layer, 2 * parent + 1)];
+                self.tree[layout.offset(layer + 1) + parent] =
+                    self.hasher.hash_node(left, right).to_vec();
+            }
+            dirty.insert((layer + 1, parent / 2));
+        }
+    }
+
+    /// [`Self::proof`] bound to [`Self::config_fingerprint`].
+    pub fn bound_proof(&self, leaf_position: usize) -> BoundProof<Vec<Bytes>> {
+        BoundProof {
+            fingerprint: self.config_fingerprint(),
+            proof: self.proof(leaf_position),
+        }
+    }
+
+    /// [`Self::positional_proof`] bound to [`Self::config_fingerprint`].
+    pub fn bound_positional_proof(&self, leaf_position: usize) -> BoundProof<Vec<(Side, Bytes)>> {
+        BoundProof {
+            fingerprint: self.config_fingerprint(),
+            proof: self.positional_proof(leaf_position),
+        }
+    }
+
+    /// [`Self::multi_proof`] bound to [`Self::config_fingerprint`].
+    pub fn bound_multi_proof(&self, leaf_positions: &[usize]) -> BoundProof<MultiProof> {
+        BoundProof {
+            fingerprint: self.config_fingerprint(),
+            proof: self.multi_proof(leaf_positions),
+        }
+    }
+
+    /// [`MultiProofAlgorithm::OZCompatible`] multiproof; panics for trees built for another
+    /// algorithm, such as positional trees.
+    pub fn multi_proof(&self, leaf_positions: &[usize]) -> MultiProof {
+        assert_eq!(
+            self.config.multi_proof,
+            MultiProofAlgorithm::OZCompatible,
+            "Multiproof algorithm of the tree"
+        );
+        let indices = leaf_positions.iter().map(|&p| self.tree_index(p)).collect();
+        let mut proof = get_multi_proof(&self.tree, indices);
+        if proof.leaves.is_empty() {
+            // the proof of no leaves is the root itself
+            proof.proof = vec![self.root().to_vec()];
+        }
+        proof
+    }
+
+    /// [`MultiProofAlgorithm::Positional`] multiproof, for trees built with
+    /// [`Self::new_positional`]; panics for other trees.
+    pub fn positional_multi_proof(&self, leaf_positions: &[usize]) -> PositionalMultiProof {
+        assert_eq!(
+            self.config.multi_proof,
+            MultiProofAlgorithm::Positional,
+            "Multiproof algorithm of the tree"
+        );
+        let mut proof = get_positional_multi_proof(&self.tree, leaf_positions);
+        if proof.leaves.is_empty() {
+            proof.proof = vec![self.root().to_vec()];
+        }
+        proof
+    }
+
+    /// [`Self::positional_multi_proof`] bound to [`Self::config_fingerprint`].
+    pub fn bound_positional_multi_proof(
+        &self,
+        leaf_positions: &[usize],
+    ) -> BoundProof<PositionalMultiProof> {
+        BoundProof {
+            fingerprint: self.config_fingerprint(),
+            proof: self.positional_multi_proof(leaf_positions),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use merklerust_core::abi::{encode, try_encode, AbiError};
+use merklerust_core::hashes::{standard_leaf_hash, standard_node_hash, try_standard_leaf_hash};
+
+fn word(hex_str: &str) -> Vec<u8> {
+    hex::decode(format!("{:0>64}", hex_str)).unwrap()
+}
+
+#[test]
+fn encodes_static_types() {
+    let encoded = encode(
+        &["address", "uint256", "bool", "int8", "bytes2"],
+        &[
+            "0x1111111111111111111111111111111111111111",
+            "0x10",
+            "true",
+            "-1",
+            "0xabcd",
+        ],
+    );
+    let mut expected = word("1111111111111111111111111111111111111111");
+    expected.extend(word("10"));
+    expected.extend(word("1"));
+    expected.extend(vec![0xff; 32]);
+    let mut bytes2 = vec![0u8; 32];
+    bytes2[..2].copy_from_slice(&[0xab, 0xcd]);
+    expected.extend(bytes2);
+    assert_eq!(encoded, expected);
+}
+
+#[test]
+fn encodes_dynamic_types() {
+    let encoded = encode(&["string", "uint8", "bytes"], &["hi", "7", "0x0102"]);
+    let mut expected = word("60");
+    expected.extend(word("7"));
+    expected.extend(word("a0"));
+    expected.extend(word("2"));
+    let mut hi = b"hi".to_vec();
+    hi.resize(32, 0);
+    expected.extend(hi);
+    expected.extend(word("2"));
+    let mut data = vec![1u8, 2];
+    data.resize(32, 0);
+    expected.extend(data);
+    assert_eq!(encoded, expected);
+}
+
+#[test]
+#[should_panic(expected = "out of range for uint8")]
+fn rejects_out_of_range_values() {
+    let _ = encode(&["uint8"], &["256"]);
+}
+
+#[test]
+fn try_encode_reports_invalid_values() {
+    assert_eq!(
+        try_encode(&["uint8"], &["256"]),
+        Err(AbiError::OutOfRange {
+            value: "256".to_string(),
+            ty: "uint8".to_string()
+        })
+    );
+    assert_eq!(
+        try_encode(&["address"], &["0x12"]),
+        Err(AbiError::InvalidAddress("0x12".to_string()))
+    );
+    assert_eq!(
+        try_encode(&["uint7"], &["1"]),
+        Err(AbiError::UnsupportedType("uint7".to_string()))
+    );
+    assert!(matches!(
+        try_standard_leaf_hash(&["uint256", "bool"], &["1"]),
+        Err(AbiError::ValueCount {
+            expected: 2,
+            got: 1
+        })
+    ));
+    assert_eq!(
+        try_standard_leaf_hash(&["uint8"], &["7"]).unwrap(),
+        standard_leaf_hash(&["uint8"], &["7"])
+    );
+}
+
+#[test]
+fn standard_leaf_hash_matches_openzeppelin() {
+    // root of the two-entry example from the @openzeppelin/merkle-tree README
+    let types = ["address", "uint256"];
+    let a = standard_leaf_hash(
+        &types,
+        &[
+            "0x1111111111111111111111111111111111111111",
+            "5000000000000000000",
+        ],
+    );
+    let b = standard_leaf_hash(
+        &types,
+        &[
+            "0x2222222222222222222222222222222222222222",
+            "2500000000000000000",
+        ],
+    );
+    assert_eq!(
+        hex::encode(standard_node_hash(&a, &b)),
+        "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+    );
+}
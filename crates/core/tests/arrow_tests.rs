@@ -0,0 +1,174 @@
+#![cfg(feature = "arrow")]
+
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, Int64Array, RecordBatch, StringArray};
+use merklerust_core::arrow::{leaves_from_batch, row_bytes, tree_from_batches};
+use merklerust_core::hashes::{keccak256, Keccak256Hasher};
+use merklerust_core::tree::MerkleTree;
+
+fn batch(ids: Vec<Option<i64>>, names: Vec<&str>) -> RecordBatch {
+    let flags = BooleanArray::from((0..ids.len()).map(|i| i % 2 == 0).collect::<Vec<_>>());
+    RecordBatch::try_from_iter([
+        ("id", Arc::new(Int64Array::from(ids)) as ArrayRef),
+        ("name", Arc::new(StringArray::from(names)) as ArrayRef),
+        ("flag", Arc::new(flags) as ArrayRef),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn rows_serialize_cell_by_cell() {
+    let b = batch(vec![Some(7), None], vec!["ab", ""]);
+    let rows = row_bytes(b.columns()).unwrap();
+    assert_eq!(
+        rows[0],
+        [
+            &[1, 0, 0, 0, 8][..],
+            &7i64.to_le_bytes(),
+            &[1, 0, 0, 0, 2],
+            b"ab",
+            &[1, 0, 0, 0, 1, 1],
+        ]
+        .concat()
+    );
+    // a null cell differs from an empty one
+    assert_eq!(rows[1], [0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0]);
+
+    let leaves = leaves_from_batch(&b, &Keccak256Hasher).unwrap();
+    assert_eq!(leaves[0], keccak256(&rows[0]).to_vec());
+}
+
+#[test]
+fn tree_spans_batches_in_order() {
+    let first = batch(vec![Some(1), Some(2)], vec!["a", "b"]);
+    let second = batch(vec![Some(3)], vec!["c"]);
+    let tree = tree_from_batches([Ok(first.clone()), Ok(second.clone())], Keccak256Hasher).unwrap();
+
+    let mut leaves = leaves_from_batch(&first, &Keccak256Hasher).unwrap();
+    leaves.extend(leaves_from_batch(&second, &Keccak256Hasher).unwrap());
+    assert_eq!(tree.root(), MerkleTree::new(leaves, Keccak256Hasher).root());
+
+    assert!(tree_from_batches(std::iter::empty(), Keccak256Hasher).is_err());
+}
+
+#[test]
+fn unsupported_and_ragged_columns_fail() {
+    let list = Arc::new(arrow_array::ListArray::from_iter_primitive::<
+        arrow_array::types::Int32Type,
+        _,
+        _,
+    >(vec![Some(vec![Some(1)])])) as ArrayRef;
+    assert!(row_bytes(&[list]).is_err());
+
+    let a = Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef;
+    let b = Arc::new(Int64Array::from(vec![1])) as ArrayRef;
+    assert!(row_bytes(&[a, b]).is_err());
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_file_round_trip() {
+    use merklerust_core::arrow::tree_from_parquet;
+    use parquet::arrow::ArrowWriter;
+
+    let b = batch(vec![Some(1), None, Some(3)], vec!["a", "b", "c"]);
+    let path = std::env::temp_dir().join(format!("merklerust-{}.parquet", std::process::id()));
+    let mut writer =
+        ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), b.schema(), None).unwrap();
+    writer.write(&b).unwrap();
+    writer.close().unwrap();
+
+    let tree =
+        tree_from_parquet(std::fs::File::open(&path).unwrap(), None, Keccak256Hasher).unwrap();
+    let expected = tree_from_batches([Ok(b.clone())], Keccak256Hasher).unwrap();
+    assert_eq!(tree.root(), expected.root());
+
+    let ids = tree_from_parquet(
+        std::fs::File::open(&path).unwrap(),
+        Some(&["id"]),
+        Keccak256Hasher,
+    )
+    .unwrap();
+    let only_ids = RecordBatch::try_new(
+        Arc::new(b.schema().project(&[0]).unwrap()),
+        vec![b.column(0).clone()],
+    )
+    .unwrap();
+    assert_eq!(
+        ids.root(),
+        tree_from_batches([Ok(only_ids)], Keccak256Hasher)
+            .unwrap()
+            .root()
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn proofs_batch_rows_verify() {
+    use arrow_array::cast::AsArray;
+    use merklerust_core::arrow::{proofs_batch, proofs_schema};
+    use merklerust_core::merkle::verify_proof;
+
+    let leaves: Vec<_> = (0..5u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = MerkleTree::new(leaves, Keccak256Hasher);
+    let batch = proofs_batch(&tree, [4, 0, 2]).unwrap();
+    assert_eq!(batch.schema().as_ref(), &proofs_schema());
+    assert_eq!(batch.num_rows(), 3);
+
+    let index = batch
+        .column(0)
+        .as_primitive::<arrow_array::types::UInt64Type>();
+    let leaf = batch.column(1).as_fixed_size_binary();
+    let proof = batch.column(2).as_list::<i32>();
+    for row in 0..3 {
+        let position = index.value(row) as usize;
+        assert_eq!(leaf.value(row), tree.leaf(position));
+        let nodes: Vec<_> = proof
+            .value(row)
+            .as_fixed_size_binary()
+            .iter()
+            .map(|n| n.unwrap().to_vec())
+            .collect();
+        assert_eq!(nodes, tree.proof(position));
+        assert!(verify_proof(
+            tree.root(),
+            leaf.value(row),
+            &nodes,
+            |a, b| { merklerust_core::hashes::standard_node_hash(a, b).to_vec() }
+        ));
+    }
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn proofs_parquet_file_has_every_leaf_and_the_root() {
+    use merklerust_core::arrow::{write_proofs_parquet, ROOT_METADATA_KEY};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let leaves: Vec<_> = (0..11u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = MerkleTree::new(leaves, Keccak256Hasher);
+    let path =
+        std::env::temp_dir().join(format!("merklerust-proofs-{}.parquet", std::process::id()));
+    write_proofs_parquet(&tree, std::fs::File::create(&path).unwrap(), 4).unwrap();
+
+    let builder =
+        ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap()).unwrap();
+    let metadata = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .unwrap();
+    let root = metadata
+        .iter()
+        .find(|kv| kv.key == ROOT_METADATA_KEY)
+        .unwrap();
+    assert_eq!(
+        root.value.as_deref(),
+        Some(format!("0x{}", hex::encode(tree.root())).as_str())
+    );
+
+    let batches: Vec<RecordBatch> = builder.build().unwrap().map(|b| b.unwrap()).collect();
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 11);
+    std::fs::remove_file(path).unwrap();
+}
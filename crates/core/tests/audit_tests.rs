@@ -0,0 +1,113 @@
+use merklerust_core::audit::{verify_entry, AuditLog};
+use merklerust_core::consistency::verify_consistency;
+use merklerust_core::hashes::{Hasher, Keccak256Hasher, PositionalSha256Hasher, Rfc6962Hasher};
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::monitor::{HeadStatus, LogMonitor};
+use merklerust_core::tree::MerkleTree;
+
+fn log(n: usize) -> AuditLog<Rfc6962Hasher> {
+    let mut log = AuditLog::new(Rfc6962Hasher);
+    for i in 0..n {
+        assert_eq!(log.append(format!("entry {}", i).into_bytes()), i);
+    }
+    log
+}
+
+#[test]
+fn proves_entries_under_every_head() {
+    let log = log(13);
+    assert_eq!(log.entry(4), Some(&b"entry 4"[..]));
+    assert_eq!(log.entry(13), None);
+    assert_eq!(log.head_at(13), Some(log.head()));
+    assert_eq!(log.head_at(14), None);
+
+    for size in 1..=13 {
+        let head = log.head_at(size).unwrap();
+        let leaves = (0..size)
+            .map(|i| Rfc6962Hasher.hash_leaf(log.entry(i).unwrap()).to_vec())
+            .collect();
+        let tree = MerkleTree::new_positional(leaves, Rfc6962Hasher);
+        assert_eq!(head.root, tree.root());
+        for i in 0..size {
+            let proof = log.prove_entry(i, size);
+            assert_eq!(proof, tree.positional_proof(i));
+            assert!(verify_entry(
+                &head,
+                i,
+                log.entry(i).unwrap(),
+                &proof,
+                &Rfc6962Hasher
+            ));
+            // the proof fixes both the entry and its index
+            assert!(!verify_entry(&head, i, b"forged", &proof, &Rfc6962Hasher));
+            if size > 1 {
+                let other = (i + 1) % size;
+                assert!(!verify_entry(
+                    &head,
+                    other,
+                    log.entry(i).unwrap(),
+                    &proof,
+                    &Rfc6962Hasher
+                ));
+            }
+        }
+    }
+    assert_eq!(
+        log.try_prove_entry(5, 5),
+        Err(MerkleError::LeafOutOfRange {
+            position: 5,
+            leaves: 5
+        })
+    );
+    assert_eq!(
+        log.try_prove_entry(0, 14),
+        Err(MerkleError::LeafOutOfRange {
+            position: 13,
+            leaves: 13
+        })
+    );
+}
+
+#[test]
+fn proves_consistency_between_heads() {
+    let log = log(11);
+    for new in 1..=11 {
+        for old in 1..=new {
+            let proof = log.prove_consistency(old, new);
+            let (a, b) = (log.head_at(old).unwrap(), log.head_at(new).unwrap());
+            assert!(verify_consistency(
+                old,
+                new,
+                &a.root,
+                &b.root,
+                &proof,
+                &Rfc6962Hasher
+            ));
+        }
+    }
+    assert!(log.try_prove_consistency(0, 4).unwrap().is_empty());
+    assert!(log.try_prove_consistency(5, 4).is_err());
+    assert!(log.try_prove_consistency(4, 12).is_err());
+
+    // a monitor follows the log's heads with its proofs
+    let mut monitor = LogMonitor::new(Rfc6962Hasher, log.head_at(0).unwrap());
+    let mut verified = 0;
+    for size in [1, 4, 7, 11] {
+        let proof = log.prove_consistency(verified, size);
+        let head = log.head_at(size).unwrap();
+        assert_eq!(monitor.observe(head, &proof), Ok(HeadStatus::Advanced));
+        verified = size;
+    }
+}
+
+#[test]
+fn rejects_sorted_pair_hashers() {
+    assert!(matches!(
+        AuditLog::try_new(Keccak256Hasher),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+    let mut log = AuditLog::new(PositionalSha256Hasher);
+    log.append(b"only".to_vec());
+    assert_eq!(log.head().size, 1);
+    assert!(log.prove_entry(0, 1).is_empty());
+}
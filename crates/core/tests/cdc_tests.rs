@@ -0,0 +1,76 @@
+#![cfg(feature = "cdc")]
+
+use merklerust_core::cdc::{chunk_leaves, chunks_covering, ChunkerConfig, FastCdc};
+use merklerust_core::hashes::keccak256;
+
+fn pseudo_random(len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + 32);
+    let mut state = keccak256(b"seed");
+    while out.len() < len {
+        out.extend_from_slice(&state);
+        state = keccak256(&state);
+    }
+    out.truncate(len);
+    out
+}
+
+#[test]
+fn chunks_respect_size_bounds_and_cover_input() {
+    let config = ChunkerConfig::default();
+    let cdc = FastCdc::new(config);
+    let data = pseudo_random(512 * 1024);
+    let chunks = cdc.chunks(&data);
+
+    let mut offset = 0u64;
+    for (i, c) in chunks.iter().enumerate() {
+        assert_eq!(c.offset, offset);
+        assert!(c.len <= config.max_size);
+        if i + 1 < chunks.len() {
+            assert!(c.len > config.min_size);
+        }
+        assert_eq!(c.hash, keccak256(&data[c.offset as usize..][..c.len]));
+        offset += c.len as u64;
+    }
+    assert_eq!(offset, data.len() as u64);
+    assert_eq!(chunk_leaves(&chunks).len(), chunks.len());
+}
+
+#[test]
+fn insertion_only_changes_nearby_chunks() {
+    let cdc = FastCdc::new(ChunkerConfig::default());
+    let data = pseudo_random(512 * 1024);
+    let mut shifted = data.clone();
+    shifted.splice(100_000..100_000, b"inserted bytes".iter().copied());
+
+    let before: Vec<_> = cdc.chunks(&data).into_iter().map(|c| c.hash).collect();
+    let after: Vec<_> = cdc.chunks(&shifted).into_iter().map(|c| c.hash).collect();
+    let shared = after.iter().filter(|h| before.contains(h)).count();
+    assert!(shared + 3 >= before.len(), "{} of {}", shared, before.len());
+}
+
+#[test]
+fn covering_range() {
+    let cdc = FastCdc::new(ChunkerConfig {
+        min_size: 64,
+        avg_size: 256,
+        max_size: 1024,
+    });
+    let data = pseudo_random(16 * 1024);
+    let chunks = cdc.chunks(&data);
+
+    let range = chunks_covering(&chunks, 5000, 3000);
+    assert!(chunks[range.start].offset <= 5000);
+    let last = &chunks[range.end - 1];
+    assert!(last.offset < 8000 && last.offset + last.len as u64 >= 8000);
+    assert_eq!(chunks_covering(&chunks, 0, 0), 0..0);
+}
+
+#[test]
+#[should_panic(expected = "power-of-two average chunk size")]
+fn rejects_bad_config() {
+    FastCdc::new(ChunkerConfig {
+        min_size: 10,
+        avg_size: 1000,
+        max_size: 4000,
+    });
+}
@@ -0,0 +1,62 @@
+use merklerust_core::clock::{ClockError, ClockOrdering, MerkleClock};
+
+fn sync(from: &MerkleClock, to: &mut MerkleClock) {
+    for event in from.diff(to) {
+        to.insert(event).unwrap();
+    }
+}
+
+#[test]
+fn concurrent_replicas_converge() {
+    let mut a = MerkleClock::new();
+    a.append(b"genesis".to_vec());
+    let mut b = a.clone();
+    assert_eq!(a.compare(&b), ClockOrdering::Equal);
+
+    a.append(b"a1".to_vec());
+    a.append(b"a2".to_vec());
+    assert_eq!(a.compare(&b), ClockOrdering::After);
+    assert_eq!(b.compare(&a), ClockOrdering::Before);
+
+    b.append(b"b1".to_vec());
+    assert_eq!(a.compare(&b), ClockOrdering::Concurrent);
+
+    let missing = a.diff(&b);
+    assert_eq!(
+        missing
+            .iter()
+            .map(|e| e.payload.clone())
+            .collect::<Vec<_>>(),
+        vec![b"a1".to_vec(), b"a2".to_vec()]
+    );
+
+    sync(&a, &mut b);
+    sync(&b, &mut a);
+    assert_eq!(a.compare(&b), ClockOrdering::Equal);
+    assert_eq!(a.heads(), b.heads());
+    assert_eq!(a.heads().len(), 2);
+    assert_eq!(a.len(), 4);
+
+    // a merge event joins both heads
+    let merge = a.append(b"merge".to_vec());
+    assert_eq!(a.heads(), vec![merge]);
+    assert_eq!(a.get(&merge).unwrap().parents, b.heads());
+}
+
+#[test]
+fn insert_requires_known_parents() {
+    let mut a = MerkleClock::new();
+    let x = a.append(b"x".to_vec());
+    a.append(b"y".to_vec());
+    let events = a.diff(&MerkleClock::new());
+    let mut b = MerkleClock::new();
+    let err = b.insert(events[1].clone()).unwrap_err();
+    assert_eq!(err, ClockError::UnknownParent(x));
+    assert!(err.to_string().starts_with("Unknown parent event 0x"));
+    assert!(b.is_empty());
+    assert!(b.heads().is_empty());
+
+    assert_eq!(b.insert(events[0].clone()), Ok(x));
+    assert!(b.insert(events[1].clone()).is_ok());
+    assert_eq!(b.heads(), a.heads());
+}
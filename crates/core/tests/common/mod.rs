@@ -0,0 +1,16 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::Bytes;
+
+/// Sorted-pair keccak-256, as built by [`merklerust_core::merkle::make_merkle_tree_bytes`].
+pub fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak256(&[left, right].concat()).to_vec()
+}
+
+/// `n` distinct leaves, `keccak256([i])` for each `i`.
+pub fn leaves(n: u8) -> Vec<Bytes> {
+    (0..n).map(|i| keccak256(&[i]).to_vec()).collect()
+}
@@ -0,0 +1,144 @@
+use merklerust_core::composite::{CompositeTree, RankNeighbour, RankProof};
+use merklerust_core::consts::COMPOSITE_DOMAIN;
+use merklerust_core::hashes::{keccak256, PositionalKeccak256Hasher};
+use merklerust_core::merkle::{positional_proof_sides, Bytes, Side};
+use merklerust_core::tree::MerkleTree;
+
+fn shuffled(n: u8) -> Vec<Bytes> {
+    // keccak256 of the index scrambles the byte order relative to insertion order
+    (0..n).map(|i| keccak256(&[i]).to_vec()).collect()
+}
+
+fn composite_root_of(leaf_count: u64, ordered_root: &[u8], sorted_root: &[u8]) -> [u8; 32] {
+    keccak256(
+        &[
+            COMPOSITE_DOMAIN,
+            &leaf_count.to_be_bytes(),
+            ordered_root,
+            sorted_root,
+        ]
+        .concat(),
+    )
+}
+
+#[test]
+fn proves_position_and_rank() {
+    let h = PositionalKeccak256Hasher;
+    for n in 1..=9u8 {
+        let all = shuffled(n);
+        let tree = CompositeTree::new(all.clone(), h);
+        let root = tree.root();
+        let mut sorted = all.clone();
+        sorted.sort();
+        for (position, leaf) in all.iter().enumerate() {
+            let proof = tree.proof(position);
+            assert_eq!(sorted[proof.rank], *leaf);
+            assert_eq!(tree.rank(position), proof.rank);
+            assert!(proof.verify(&root, leaf, &h), "n = {}", n);
+
+            // a claimed position or rank other than the proven one is rejected
+            if n > 1 {
+                let other = (position + 1) % n as usize;
+                let mut moved = proof.clone();
+                moved.position = other;
+                assert!(!moved.verify(&root, leaf, &h));
+                let mut reranked = proof.clone();
+                reranked.rank = (proof.rank + 1) % n as usize;
+                assert!(!reranked.verify(&root, leaf, &h));
+            }
+            let mut resized = proof.clone();
+            resized.leaf_count += 1;
+            assert!(!resized.verify(&root, leaf, &h));
+            resized.leaf_count = usize::MAX;
+            assert!(!resized.verify(&root, leaf, &h));
+        }
+    }
+}
+
+#[test]
+fn proves_rank_between_neighbours() {
+    let h = PositionalKeccak256Hasher;
+    for n in 1..=9u8 {
+        let all = shuffled(n);
+        let tree = CompositeTree::new(all.clone(), h);
+        let root = tree.root();
+        let mut sorted = all.clone();
+        sorted.sort();
+        for (rank, leaf) in sorted.iter().enumerate() {
+            let proof = tree.rank_proof(rank);
+            assert!(proof.verify(&root, leaf, &h), "n = {}", n);
+            assert_eq!(proof.previous.is_none(), rank == 0);
+            assert_eq!(proof.next.is_none(), rank + 1 == n as usize);
+
+            if n > 1 {
+                let mut reranked = proof.clone();
+                reranked.rank = (rank + 1) % n as usize;
+                assert!(!reranked.verify(&root, leaf, &h));
+                let mut truncated = proof.clone();
+                truncated.previous = None;
+                truncated.next = None;
+                assert!(!truncated.verify(&root, leaf, &h));
+            }
+            let mut resized = proof.clone();
+            resized.leaf_count = usize::MAX;
+            assert!(!resized.verify(&root, leaf, &h));
+        }
+        assert!(tree.try_rank_proof(n as usize).is_err());
+    }
+}
+
+#[test]
+fn rank_proof_rejects_out_of_order_neighbours() {
+    // a builder that commits an unsorted "sorted" tree is caught at the unsorted rank
+    let h = PositionalKeccak256Hasher;
+    let leaves = vec![vec![3u8; 32], vec![1u8; 32], vec![2u8; 32]];
+    let tree = CompositeTree::new(leaves, h);
+    let mut proof = tree.rank_proof(1);
+    assert!(proof.verify(&tree.root(), &[2u8; 32], &h));
+    std::mem::swap(&mut proof.previous, &mut proof.next);
+    assert!(!proof.verify(&tree.root(), &[2u8; 32], &h));
+
+    let unsorted = MerkleTree::new_positional(vec![vec![1u8; 32], vec![3u8; 32], vec![2u8; 32]], h);
+    let ordered_root = tree.ordered().root().to_vec();
+    let root = composite_root_of(3, &ordered_root, unsorted.root());
+    let forged = RankProof {
+        leaf_count: 3,
+        rank: 1,
+        ordered_root,
+        proof: unsorted.positional_proof(1),
+        previous: Some(RankNeighbour {
+            leaf: vec![1u8; 32],
+            proof: unsorted.positional_proof(0),
+        }),
+        next: Some(RankNeighbour {
+            leaf: vec![2u8; 32],
+            proof: unsorted.positional_proof(2),
+        }),
+    };
+    assert!(!forged.verify(&root, &[3u8; 32], &h));
+}
+
+#[test]
+fn equal_leaves_keep_insertion_order() {
+    let leaves = vec![vec![2u8; 32], vec![1u8; 32], vec![2u8; 32]];
+    let tree = CompositeTree::new(leaves.clone(), PositionalKeccak256Hasher);
+    assert_eq!(
+        (0..3).map(|p| tree.rank(p)).collect::<Vec<_>>(),
+        vec![1, 0, 2]
+    );
+    assert!(tree.try_proof(3).is_err());
+    assert!(!tree
+        .proof(0)
+        .verify(&tree.root(), &leaves[1], &PositionalKeccak256Hasher));
+}
+
+#[test]
+fn proof_sides_encode_the_position() {
+    assert_eq!(
+        positional_proof_sides(3, 2),
+        vec![Side::Left],
+        "the odd leaf is carried up once"
+    );
+    assert_eq!(positional_proof_sides(4, 1), vec![Side::Left, Side::Right]);
+    assert!(positional_proof_sides(1, 0).is_empty());
+}
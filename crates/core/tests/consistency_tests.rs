@@ -0,0 +1,162 @@
+use merklerust_core::consistency::{
+    get_consistency_proof, get_consistency_proof_with_single_leaf, log_root,
+    log_root_with_single_leaf, verify_consistency, verify_consistency_with_single_leaf,
+};
+use merklerust_core::hashes::{Hasher, Rfc6962Hasher};
+use merklerust_core::merkle::Bytes;
+use merklerust_core::tree::SingleLeafRoot;
+
+/// Hashed leaves of the certificate-transparency reference test tree.
+fn ct_leaves() -> Vec<Bytes> {
+    [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ]
+    .iter()
+    .map(|h| Rfc6962Hasher.hash_leaf(&hex::decode(h).unwrap()).to_vec())
+    .collect()
+}
+
+fn hexes(nodes: &[Bytes]) -> Vec<String> {
+    nodes.iter().map(hex::encode).collect()
+}
+
+#[test]
+fn matches_ct_reference_roots() {
+    let roots = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+    let leaves = ct_leaves();
+    for (i, root) in roots.iter().enumerate() {
+        assert_eq!(hex::encode(log_root(&leaves[..=i], &Rfc6962Hasher)), *root);
+    }
+}
+
+#[test]
+fn matches_ct_reference_proofs() {
+    let leaves = ct_leaves();
+    let cases: [(usize, usize, &[&str]); 3] = [
+        (
+            1,
+            8,
+            &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ],
+        ),
+        (
+            6,
+            8,
+            &[
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ],
+        ),
+        (
+            2,
+            5,
+            &[
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ],
+        ),
+    ];
+    for (old, new, expected) in cases {
+        let proof = get_consistency_proof(&leaves[..new], old, &Rfc6962Hasher);
+        assert_eq!(hexes(&proof), expected.to_vec(), "{} -> {}", old, new);
+    }
+}
+
+#[test]
+fn proofs_verify_between_all_sizes() {
+    let leaves = ct_leaves();
+    let hasher = Rfc6962Hasher;
+    for new in 1..=leaves.len() {
+        let new_root = log_root(&leaves[..new], &hasher);
+        for old in 1..=new {
+            let old_root = log_root(&leaves[..old], &hasher);
+            let proof = get_consistency_proof(&leaves[..new], old, &hasher);
+            assert!(verify_consistency(
+                old, new, &old_root, &new_root, &proof, &hasher
+            ));
+
+            if old < new {
+                assert!(!verify_consistency(
+                    old, new, &new_root, &new_root, &proof, &hasher
+                ));
+                let mut tampered = proof.clone();
+                tampered[0][0] ^= 1;
+                assert!(!verify_consistency(
+                    old, new, &old_root, &new_root, &tampered, &hasher
+                ));
+            }
+        }
+    }
+    assert!(!verify_consistency(0, 1, &[0; 32], &[0; 32], &[], &hasher));
+}
+
+#[test]
+#[should_panic(expected = "Old size 3 out of range for 2 leaves")]
+fn rejects_old_size_beyond_log() {
+    get_consistency_proof(&ct_leaves()[..2], 3, &Rfc6962Hasher);
+}
+
+#[test]
+fn hashed_single_leaf_root_is_proven_through_the_leaf() {
+    let leaves = ct_leaves();
+    let hashed = SingleLeafRoot::Hashed;
+    let h = Rfc6962Hasher;
+    let old_root = log_root_with_single_leaf(&leaves[..1], &h, hashed);
+    assert_eq!(old_root, h.hash_leaf(&leaves[0]).to_vec());
+    assert_eq!(
+        log_root_with_single_leaf(&leaves, &h, hashed),
+        log_root(&leaves, &h)
+    );
+
+    for new_size in 2..=leaves.len() {
+        let new_root = log_root(&leaves[..new_size], &h);
+        let proof = get_consistency_proof_with_single_leaf(&leaves[..new_size], 1, &h, hashed);
+        assert_eq!(proof[0], leaves[0]);
+        assert!(verify_consistency_with_single_leaf(
+            1, new_size, &old_root, &new_root, &proof, &h, hashed
+        ));
+        // the leaf is not the hashed root, nor the hashed root the leaf
+        assert!(!verify_consistency(
+            1, new_size, &old_root, &new_root, &proof, &h
+        ));
+        let plain = get_consistency_proof(&leaves[..new_size], 1, &h);
+        assert!(!verify_consistency_with_single_leaf(
+            1, new_size, &leaves[0], &new_root, &plain, &h, hashed
+        ));
+    }
+
+    // proofs from larger old trees are unchanged
+    assert_eq!(
+        get_consistency_proof_with_single_leaf(&leaves, 3, &h, hashed),
+        get_consistency_proof(&leaves, 3, &h)
+    );
+    assert!(verify_consistency_with_single_leaf(
+        1,
+        1,
+        &old_root,
+        &old_root,
+        &[],
+        &h,
+        hashed
+    ));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use merklerust_core::abi::encode;
+use merklerust_core::cross_check::{cross_check, Drift, EncodingDrift, OrderingDrift, Verdict};
+use merklerust_core::dump::DumpError;
+use merklerust_core::hashes::{keccak256, Keccak256Hasher, PositionalSha256Hasher, Sha256Hasher};
+use merklerust_core::standard::{StandardMerkleTree, TreeOptions};
+use merklerust_core::tree::{MerkleTree, PairOrdering};
+
+use common::leaves;
+
+fn strings(xs: &[&str]) -> Vec<String> {
+    xs.iter().map(|s| s.to_string()).collect()
+}
+
+fn values() -> Vec<Vec<String>> {
+    (1..=5)
+        .map(|i| strings(&[&format!("0x{:040x}", i), &(i * 1000).to_string()]))
+        .collect()
+}
+
+fn unsorted_standard() -> StandardMerkleTree {
+    StandardMerkleTree::of_with_options(
+        values(),
+        strings(&["address", "uint256"]),
+        TreeOptions { sort_leaves: false },
+    )
+}
+
+#[test]
+fn matching_roots_report_a_match() {
+    let tree = MerkleTree::new_positional(leaves(6), PositionalSha256Hasher);
+    let report = cross_check(&tree.dump(), &hex::encode(tree.root())).unwrap();
+    assert!(report.is_match());
+    assert_eq!(report.config, tree.config());
+    assert_eq!(report.root, tree.root());
+
+    let standard = unsorted_standard();
+    let prefixed = format!("0x{}", hex::encode(standard.root()));
+    assert!(cross_check(&standard.dump(), &prefixed).unwrap().is_match());
+}
+
+#[test]
+fn explains_config_drift() {
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let external = MerkleTree::new(leaves(5), Sha256Hasher);
+    let report = cross_check(&tree.dump(), &hex::encode(external.root())).unwrap();
+    assert_eq!(
+        report.verdict,
+        Verdict::Explained(vec![Drift::Config {
+            hasher: "sha256".to_string(),
+            pair_ordering: PairOrdering::Sorted,
+        }])
+    );
+    assert!(report.to_string().contains("sha256"));
+
+    let committed = tree.root_with_config_commitment();
+    let report = cross_check(&tree.dump(), &hex::encode(committed)).unwrap();
+    assert_eq!(
+        report.verdict,
+        Verdict::Explained(vec![Drift::ConfigCommitment])
+    );
+}
+
+#[test]
+fn explains_encoding_and_ordering_drift() {
+    let standard = unsorted_standard();
+    let dump = standard.dump();
+
+    // the JS library sorts leaves by default
+    let sorted = StandardMerkleTree::of(values(), strings(&["address", "uint256"]));
+    let report = cross_check(&dump, &hex::encode(sorted.root())).unwrap();
+    assert_eq!(
+        report.verdict,
+        Verdict::Explained(vec![Drift::Ordering(OrderingDrift::Sorted)])
+    );
+
+    // merkletreejs over keccak256(abi.encode(..)) leaves, sorted pairs, unsorted leaves
+    let types = strings(&["address", "uint256"]);
+    let single: Vec<Vec<u8>> = values()
+        .iter()
+        .map(|v| keccak256(&encode(&types, v)).to_vec())
+        .collect();
+    let external = MerkleTree::new(single, Keccak256Hasher);
+    let report = cross_check(&dump, &hex::encode(external.root())).unwrap();
+    assert_eq!(
+        report.verdict,
+        Verdict::Explained(vec![Drift::Encoding(EncodingDrift::SingleHashed)])
+    );
+
+    let report = cross_check(&dump, &"ab".repeat(32)).unwrap();
+    assert_eq!(report.verdict, Verdict::Unexplained);
+    assert!(!report.is_match());
+}
+
+#[test]
+fn rejects_bad_input() {
+    let tree = MerkleTree::new(leaves(3), Sha256Hasher);
+    assert!(matches!(
+        cross_check(&tree.dump(), "not hex"),
+        Err(DumpError::InvalidDump(_))
+    ));
+    assert_eq!(
+        cross_check(r#"{"format":"other-v1"}"#, "00"),
+        Err(DumpError::UnknownFormat("other-v1".to_string()))
+    );
+    let tampered = tree.dump().replacen("0x", "0x00", 1);
+    assert!(cross_check(&tampered, "00").is_err());
+}
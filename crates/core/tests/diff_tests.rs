@@ -0,0 +1,114 @@
+mod common;
+
+use merklerust_core::diff::{get_change_proof, try_get_change_proof, verify_change_proof};
+use merklerust_core::merkle::{make_merkle_tree_bytes, Bytes, MerkleError};
+
+use common::node_hash;
+
+fn leaves(n: u8) -> Vec<Bytes> {
+    (0..n).map(|i| vec![i; 32]).collect()
+}
+
+#[test]
+fn change_proof_covers_changed_leaves() {
+    let old_leaves = leaves(5);
+    let mut new_leaves = old_leaves.clone();
+    new_leaves[1] = vec![0xaa; 32];
+    new_leaves[4] = vec![0xbb; 32];
+
+    let old_tree = make_merkle_tree_bytes(old_leaves, node_hash);
+    let new_tree = make_merkle_tree_bytes(new_leaves, node_hash);
+
+    let cp = get_change_proof(&old_tree, &new_tree);
+    let n = old_tree.len();
+    assert_eq!(cp.indices, vec![n - 1 - 1, n - 1 - 4]);
+    assert!(verify_change_proof(
+        &old_tree[0],
+        &new_tree[0],
+        &cp,
+        node_hash
+    ));
+    assert!(!verify_change_proof(
+        &new_tree[0],
+        &old_tree[0],
+        &cp,
+        node_hash
+    ));
+}
+
+#[test]
+fn change_proof_rejects_hidden_changes() {
+    let old_leaves = leaves(4);
+    let mut new_leaves = old_leaves.clone();
+    new_leaves[0] = vec![0xaa; 32];
+    new_leaves[3] = vec![0xbb; 32];
+
+    let old_tree = make_merkle_tree_bytes(old_leaves.clone(), node_hash);
+    let new_tree = make_merkle_tree_bytes(new_leaves, node_hash);
+
+    // a proof that only admits to the first change cannot link the two roots
+    let mut partial_leaves = old_leaves;
+    partial_leaves[0] = vec![0xaa; 32];
+    let partial_tree = make_merkle_tree_bytes(partial_leaves, node_hash);
+    let cp = get_change_proof(&old_tree, &partial_tree);
+    assert!(!verify_change_proof(
+        &old_tree[0],
+        &new_tree[0],
+        &cp,
+        node_hash
+    ));
+}
+
+#[test]
+fn change_proof_for_identical_trees() {
+    let tree = make_merkle_tree_bytes(leaves(3), node_hash);
+    let cp = get_change_proof(&tree, &tree);
+    assert!(cp.indices.is_empty());
+    assert!(verify_change_proof(&tree[0], &tree[0], &cp, node_hash));
+}
+
+#[test]
+fn change_proof_rejects_malformed_proofs() {
+    let old_tree = make_merkle_tree_bytes(leaves(4), node_hash);
+    let mut new_leaves = leaves(4);
+    new_leaves[1] = vec![0xaa; 32];
+    let new_tree = make_merkle_tree_bytes(new_leaves, node_hash);
+
+    let mut cp = get_change_proof(&old_tree, &new_tree);
+    cp.old.proof_flags.push(true);
+    cp.new.proof_flags.push(true);
+    assert!(!verify_change_proof(
+        &old_tree[0],
+        &new_tree[0],
+        &cp,
+        node_hash
+    ));
+}
+
+#[test]
+fn change_proof_of_different_shapes_fails() {
+    let old_tree = make_merkle_tree_bytes(leaves(3), node_hash);
+    let new_tree = make_merkle_tree_bytes(leaves(4), node_hash);
+    assert_eq!(
+        try_get_change_proof(&old_tree, &new_tree),
+        Err(MerkleError::ShapeMismatch { old: 5, new: 7 })
+    );
+    let mut short = old_tree.clone();
+    short[4] = vec![0; 31];
+    assert_eq!(
+        try_get_change_proof(&old_tree, &short),
+        Err(MerkleError::InvalidNode(31))
+    );
+    assert_eq!(
+        try_get_change_proof(&old_tree, &old_tree),
+        Ok(get_change_proof(&old_tree, &old_tree))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Expected trees of equal shape, got 5 and 7 nodes")]
+fn change_proof_of_different_shapes_panics() {
+    let old_tree = make_merkle_tree_bytes(leaves(3), node_hash);
+    let new_tree = make_merkle_tree_bytes(leaves(4), node_hash);
+    get_change_proof(&old_tree, &new_tree);
+}
@@ -0,0 +1,208 @@
+use merklerust_core::encoder::{
+    AbiEncoder, LeafEncoder, Normalization, RawEncoder, StringEncoder, TextEncoding,
+    TypedMerkleTree, TypedTreeError,
+};
+use merklerust_core::hashes::{Keccak256Hasher, Sha256Hasher};
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::standard::StandardMerkleTree;
+
+fn strings(xs: &[&str]) -> Vec<String> {
+    xs.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn abi_encoder_matches_standard_tree() {
+    let values = vec![
+        strings(&[
+            "0x1111111111111111111111111111111111111111",
+            "5000000000000000000",
+        ]),
+        strings(&[
+            "0x2222222222222222222222222222222222222222",
+            "2500000000000000000",
+        ]),
+        strings(&["0x3333333333333333333333333333333333333333", "1"]),
+    ];
+    let encoder = AbiEncoder::new(&["address", "uint256"]);
+    let typed = TypedMerkleTree::of(values.clone(), encoder.clone(), Keccak256Hasher);
+    let standard = StandardMerkleTree::of(values.clone(), strings(&["address", "uint256"]));
+
+    assert_eq!(typed.root(), standard.root());
+    assert_eq!(typed.encoder_id(), "abi(address,uint256)");
+    for (i, v) in values.iter().enumerate() {
+        assert_eq!(typed.get_proof(i), standard.get_proof(i));
+        assert!(TypedMerkleTree::verify(
+            typed.root(),
+            &encoder,
+            &Keccak256Hasher,
+            v,
+            &typed.get_proof_for_value(v)
+        ));
+    }
+}
+
+#[test]
+fn raw_encoder_trees() {
+    let values: Vec<&str> = vec!["alice", "bob", "carol", "dave", "erin"];
+    let tree = TypedMerkleTree::of(values.clone(), RawEncoder, Sha256Hasher);
+    assert_eq!(tree.encoder_id(), "raw");
+    assert_eq!(
+        LeafEncoder::<str>::encode(&RawEncoder, "bob"),
+        b"bob".to_vec()
+    );
+    for (i, v) in values.iter().enumerate() {
+        assert_eq!(tree.leaf_lookup(v), Some(i));
+        assert!(tree.verify_proof(v, &tree.get_proof(i)));
+    }
+    assert!(!tree.verify_proof(&"mallory", &tree.get_proof(0)));
+    assert_eq!(tree.leaf_lookup(&"mallory"), None);
+
+    let mp = tree.get_multi_proof(&[0, 3]);
+    assert_eq!(mp.leaves.len(), 2);
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn borsh_encoder_commits_to_typed_values() {
+    use merklerust_core::encoder::BorshEncoder;
+
+    let values: Vec<(u64, String)> = (0..4).map(|i| (i, format!("user{}", i))).collect();
+    let tree = TypedMerkleTree::of(values.clone(), BorshEncoder, Keccak256Hasher);
+    assert_eq!(tree.encoder_id(), "borsh");
+    assert_eq!(
+        BorshEncoder.encode(&(1u64, "a".to_string())),
+        [&1u64.to_le_bytes()[..], &1u32.to_le_bytes(), b"a"].concat()
+    );
+    for (i, v) in values.iter().enumerate() {
+        assert!(tree.verify_proof(v, &tree.get_proof(i)));
+    }
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode_encoder_commits_to_typed_values() {
+    use merklerust_core::encoder::BincodeEncoder;
+
+    let values: Vec<(u64, String)> = (0..4).map(|i| (i, format!("user{}", i))).collect();
+    let tree = TypedMerkleTree::of(values.clone(), BincodeEncoder, Keccak256Hasher);
+    assert_eq!(tree.encoder_id(), "bincode");
+    assert_eq!(
+        BincodeEncoder.encode(&(1u64, "a".to_string())),
+        [&1u64.to_le_bytes()[..], &1u64.to_le_bytes(), b"a"].concat()
+    );
+    assert_eq!(
+        BincodeEncoder.try_encode(&values[1]),
+        Ok(BincodeEncoder.encode(&values[1]))
+    );
+    for (i, v) in values.iter().enumerate() {
+        assert!(tree.verify_proof(v, &tree.get_proof(i)));
+    }
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn postcard_encoder_commits_to_typed_values() {
+    use merklerust_core::encoder::PostcardEncoder;
+
+    let values: Vec<(u64, String)> = (0..4).map(|i| (i, format!("user{}", i))).collect();
+    let tree = TypedMerkleTree::of(values.clone(), PostcardEncoder, Keccak256Hasher);
+    assert_eq!(tree.encoder_id(), "postcard");
+    assert_eq!(
+        PostcardEncoder.encode(&(300u64, "a".to_string())),
+        [0xac, 0x02, 1, b'a']
+    );
+    assert_eq!(
+        PostcardEncoder.try_encode(&values[1]),
+        Ok(PostcardEncoder.encode(&values[1]))
+    );
+    for (i, v) in values.iter().enumerate() {
+        assert!(tree.verify_proof(v, &tree.get_proof(i)));
+    }
+}
+
+#[test]
+fn typed_and_standard_trees_share_config_commitment() {
+    let types = ["address", "uint256"];
+    let values: Vec<Vec<String>> = (1..=3)
+        .map(|i| vec![format!("0x{:040x}", i), (i * 100).to_string()])
+        .collect();
+    let typed = TypedMerkleTree::of(values.clone(), AbiEncoder::new(&types), Keccak256Hasher);
+    let standard = StandardMerkleTree::of(values, types.map(String::from).to_vec());
+    assert_eq!(typed.config(), standard.config());
+    assert_eq!(typed.config().leaf_encoding, "abi(address,uint256)");
+    assert_eq!(
+        typed.root_with_config_commitment(),
+        standard.root_with_config_commitment()
+    );
+
+    let raw = TypedMerkleTree::of(vec![b"a".to_vec()], RawEncoder, Keccak256Hasher);
+    assert_eq!(raw.config().leaf_encoding, "raw");
+}
+
+#[test]
+fn string_encoder_normalizes_before_hashing() {
+    let composed = "caf\u{e9}";
+    let decomposed = "cafe\u{301}";
+    let nfc = StringEncoder::default();
+    assert_eq!(LeafEncoder::<str>::id(&nfc), "string(nfc,utf8)");
+    assert_eq!(nfc.encode(composed), nfc.encode(decomposed));
+    assert_eq!(nfc.encode(decomposed), composed.as_bytes());
+
+    let raw = StringEncoder::new(Normalization::None, TextEncoding::Utf8);
+    assert_ne!(raw.encode(composed), raw.encode(decomposed));
+    let utf16 = StringEncoder::new(Normalization::Nfkc, TextEncoding::Utf16Le);
+    assert_eq!(utf16.encode("\u{fb01}"), vec![b'f', 0, b'i', 0]);
+
+    let names = strings(&["alice@example.com", decomposed, "bob"]);
+    let tree = TypedMerkleTree::of(names.clone(), nfc, Keccak256Hasher);
+    assert_eq!(tree.config().leaf_encoding, "string(nfc,utf8)");
+    assert_eq!(
+        StringEncoder::from_id(&tree.config().leaf_encoding),
+        Some(nfc)
+    );
+    assert_eq!(StringEncoder::from_id("string(nfc,latin1)"), None);
+
+    let proof = tree.get_proof(1);
+    assert!(tree.verify_proof(&composed.to_string(), &proof));
+    let other = TypedMerkleTree::of(names, raw, Keccak256Hasher);
+    assert_ne!(other.root(), tree.root());
+}
+
+#[test]
+fn rejected_values_fail_without_panicking() {
+    let encoder = AbiEncoder::new(&["address", "uint256"]);
+    let values = vec![
+        strings(&["0x1111111111111111111111111111111111111111", "1"]),
+        strings(&["0x2222222222222222222222222222222222222222", "2"]),
+    ];
+    let bad = strings(&["0x1111111111111111111111111111111111111111", "-1"]);
+    assert!(encoder.try_encode(&bad).is_err());
+    assert_eq!(
+        LeafEncoder::<str>::try_encode(&RawEncoder, "a"),
+        Ok(b"a".to_vec())
+    );
+
+    let mut with_bad = values.clone();
+    with_bad.push(bad.clone());
+    assert!(matches!(
+        TypedMerkleTree::try_of(with_bad, encoder.clone(), Keccak256Hasher),
+        Err(TypedTreeError::Value { index: 2, .. })
+    ));
+    assert_eq!(
+        TypedMerkleTree::try_of(Vec::<Vec<String>>::new(), encoder.clone(), Keccak256Hasher)
+            .unwrap_err(),
+        TypedTreeError::Merkle(MerkleError::EmptyTree)
+    );
+
+    let tree = TypedMerkleTree::try_of(values, encoder.clone(), Keccak256Hasher).unwrap();
+    let proof = tree.get_proof(0);
+    assert!(!tree.verify_proof(&bad, &proof));
+    assert!(!TypedMerkleTree::verify(
+        tree.root(),
+        &encoder,
+        &Keccak256Hasher,
+        &strings(&["0x1234"]),
+        &proof
+    ));
+    assert_eq!(tree.leaf_lookup(&bad), None);
+}
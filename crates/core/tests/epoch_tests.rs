@@ -0,0 +1,92 @@
+use merklerust_core::consts::ZERO_HASH;
+use merklerust_core::epoch::{EpochManager, EpochSchedule};
+use merklerust_core::hashes::{keccak256, Hasher, Keccak256Hasher};
+use merklerust_core::merkle::Bytes;
+use merklerust_core::mmr::bag_peaks;
+use merklerust_core::tree::MerkleTree;
+
+// three leaves per epoch, except epoch 2 which is empty
+fn leaves(epoch: u64) -> Vec<Bytes> {
+    if epoch == 2 {
+        return Vec::new();
+    }
+    (0..3u64)
+        .map(|i| keccak256(&(epoch * 10 + i).to_be_bytes()).to_vec())
+        .collect()
+}
+
+// peaks of the epoch-root MMR for three leaves: one pair and one single
+fn mmr_peaks(roots: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    vec![Keccak256Hasher.hash_node(&roots[0], &roots[1]), roots[2]]
+}
+
+#[test]
+fn schedule_maps_times_to_epochs() {
+    let schedule = EpochSchedule::new(100, 10);
+    assert_eq!(schedule.epoch_of(99), None);
+    assert_eq!(schedule.epoch_of(100), Some(0));
+    assert_eq!(schedule.epoch_of(125), Some(2));
+    assert_eq!(schedule.bounds(2), 120..130);
+    assert_eq!(schedule.ended_by(109), 0);
+    assert_eq!(schedule.ended_by(130), 3);
+}
+
+#[test]
+fn builds_epochs_and_chains_proofs() {
+    let mut seen = Vec::new();
+    let mut manager = EpochManager::new(
+        EpochSchedule::new(100, 10),
+        |epoch, bounds| {
+            seen.push((epoch, bounds));
+            leaves(epoch)
+        },
+        Keccak256Hasher,
+    );
+    assert_eq!(manager.root(), None);
+    assert_eq!(manager.advance_to(105), 0);
+    assert_eq!(manager.advance_to(131), 3);
+    assert_eq!(manager.advance_to(131), 0);
+    assert_eq!(manager.epoch_count(), 3);
+
+    let roots: Vec<_> = (0..3).map(|e| manager.epoch_root(e).unwrap()).collect();
+    assert_eq!(
+        &roots[0][..],
+        MerkleTree::new(leaves(0), Keccak256Hasher).root()
+    );
+    assert_eq!(roots[2], ZERO_HASH);
+    assert!(manager.epoch_tree(2).is_none());
+    assert_eq!(
+        manager.root(),
+        bag_peaks(&mmr_peaks(&roots), &Keccak256Hasher)
+    );
+
+    let root = manager.root().unwrap();
+    let proof = manager.chained_proof(1, 2);
+    assert!(proof.verify(&root, &leaves(1)[2], &Keccak256Hasher));
+    assert!(!proof.verify(&root, &leaves(1)[1], &Keccak256Hasher));
+    assert!(!proof.verify(&roots[0], &leaves(1)[2], &Keccak256Hasher));
+    let mut hostile = proof.clone();
+    hostile.epoch_proof.mmr_size = u64::MAX;
+    assert!(!hostile.verify(&root, &leaves(1)[2], &Keccak256Hasher));
+
+    // a later epoch changes the root; the refreshed proof verifies against it
+    manager.build_next();
+    let new_root = manager.root().unwrap();
+    assert!(!proof.verify(&new_root, &leaves(1)[2], &Keccak256Hasher));
+    let refreshed = manager.refresh(&proof).unwrap();
+    assert_eq!(refreshed.leaf_proof, proof.leaf_proof);
+    assert!(refreshed.verify(&new_root, &leaves(1)[2], &Keccak256Hasher));
+
+    drop(manager);
+    assert_eq!(seen[1], (1, 110..120));
+    assert_eq!(seen.len(), 4);
+}
+
+#[test]
+#[should_panic(expected = "Epoch 2 has no tree")]
+fn empty_epoch_has_no_proofs() {
+    let mut manager =
+        EpochManager::new(EpochSchedule::new(0, 1), |e, _| leaves(e), Keccak256Hasher);
+    manager.advance_to(3);
+    manager.chained_proof(2, 0);
+}
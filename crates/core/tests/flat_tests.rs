@@ -0,0 +1,81 @@
+mod common;
+
+use merklerust_core::flat::FlatMerkleTree;
+use merklerust_core::merkle::{
+    get_proof, is_valid_merkle_tree, leaf_tree_index, make_merkle_tree_bytes, MerkleError,
+};
+
+use common::{leaves, node_hash};
+
+#[test]
+fn matches_bytes_tree() {
+    for n in 1..=9 {
+        let tree = make_merkle_tree_bytes(leaves(n), node_hash);
+        let flat = FlatMerkleTree::new(leaves(n), node_hash);
+        assert_eq!(flat.to_tree(), tree);
+        assert_eq!(flat.len(), tree.len());
+        assert_eq!(flat.leaf_count(), n as usize);
+        assert_eq!(flat.root()[..], tree[0][..]);
+        assert!(flat.is_valid(node_hash));
+        for pos in 0..n as usize {
+            let index = leaf_tree_index(tree.len(), pos);
+            assert_eq!(flat.leaf(pos)[..], tree[index][..]);
+            assert_eq!(flat.get_proof(index), get_proof(&tree, index));
+            assert_eq!(flat.get_proof_for_leaf(pos), get_proof(&tree, index));
+        }
+    }
+}
+
+#[test]
+fn byte_round_trip() {
+    let flat = FlatMerkleTree::new(leaves(5), node_hash);
+    assert_eq!(flat.as_bytes().len(), 32 * flat.len());
+    assert_eq!(
+        FlatMerkleTree::from_bytes(flat.as_bytes()),
+        Ok(flat.clone())
+    );
+    assert_eq!(FlatMerkleTree::from_tree(&flat.to_tree()), Ok(flat.clone()));
+
+    assert_eq!(
+        FlatMerkleTree::from_bytes(&flat.as_bytes()[..40]),
+        Err(MerkleError::InvalidNode(8))
+    );
+    assert_eq!(FlatMerkleTree::from_bytes(&[]), Err(MerkleError::EmptyTree));
+    assert_eq!(
+        FlatMerkleTree::from_bytes(&flat.as_bytes()[..128]),
+        Err(MerkleError::InvalidTreeLength(4))
+    );
+    assert_eq!(
+        FlatMerkleTree::from_tree(&flat.to_tree()[..4]),
+        Err(MerkleError::InvalidTreeLength(4))
+    );
+    assert_eq!(
+        FlatMerkleTree::from_tree(&[vec![0u8; 31]]),
+        Err(MerkleError::InvalidNode(31))
+    );
+}
+
+#[test]
+fn detects_tampering() {
+    let mut bytes = FlatMerkleTree::new(leaves(6), node_hash)
+        .as_bytes()
+        .to_vec();
+    bytes[32 * 7] ^= 1;
+    let flat = FlatMerkleTree::from_bytes(&bytes).unwrap();
+    assert!(!flat.is_valid(node_hash));
+    assert!(!is_valid_merkle_tree(&flat.to_tree(), node_hash));
+}
+
+#[test]
+fn rejects_internal_node_proofs() {
+    let flat = FlatMerkleTree::new(leaves(4), node_hash);
+    assert_eq!(flat.try_get_proof(2), Err(MerkleError::NotALeaf(2)));
+    assert_eq!(flat.try_get_proof(7), Err(MerkleError::NotALeaf(7)));
+    assert_eq!(
+        flat.try_leaf(4),
+        Err(MerkleError::LeafOutOfRange {
+            position: 4,
+            leaves: 4
+        })
+    );
+}
@@ -0,0 +1,195 @@
+use merklerust_core::consistency::log_root;
+use merklerust_core::hashes::{
+    hash_pairs_batch, hash_pairs_batch_parallel, keccak256, sha256, sha256_pairs_batch,
+    sha256_pairs_batch_parallel, Hasher, Rfc6962Hasher,
+};
+use merklerust_core::tree::MerkleTree;
+
+#[test]
+fn pair_batches_match_single_hashes() {
+    let pairs: Vec<([u8; 32], [u8; 32])> = (0u8..5).map(|i| ([i; 32], [i + 1; 32])).collect();
+
+    let keccak = hash_pairs_batch(&pairs);
+    let sha = sha256_pairs_batch(&pairs);
+    assert_eq!(keccak.len(), pairs.len());
+    for (i, (l, r)) in pairs.iter().enumerate() {
+        assert_eq!(keccak[i], keccak256(&[&l[..], &r[..]].concat()));
+        assert_eq!(sha[i], sha256(&[&l[..], &r[..]].concat()));
+    }
+    assert!(hash_pairs_batch(&[]).is_empty());
+}
+
+#[test]
+fn parallel_pair_batches_match_sequential() {
+    // large enough to be split across threads
+    let pairs: Vec<([u8; 32], [u8; 32])> = (0u32..20_000)
+        .map(|i| (keccak256(&i.to_be_bytes()), sha256(&i.to_be_bytes())))
+        .collect();
+    assert_eq!(hash_pairs_batch_parallel(&pairs), hash_pairs_batch(&pairs));
+    assert_eq!(
+        sha256_pairs_batch_parallel(&pairs),
+        sha256_pairs_batch(&pairs)
+    );
+    assert_eq!(
+        hash_pairs_batch_parallel(&pairs[..3]),
+        hash_pairs_batch(&pairs[..3])
+    );
+    assert!(hash_pairs_batch_parallel(&[]).is_empty());
+}
+
+#[test]
+fn hasher_pair_batches_match_hash_node() {
+    use merklerust_core::hashes::{
+        Keccak256Hasher, PositionalKeccak256Hasher, PositionalSha256Hasher, Sha256Hasher,
+        Sha3_256Hasher,
+    };
+
+    let pairs: Vec<([u8; 32], [u8; 32])> = (0u32..5000)
+        .map(|i| (keccak256(&i.to_be_bytes()), sha256(&i.to_be_bytes())))
+        .collect();
+    let hashers: [&dyn Hasher; 6] = [
+        &Keccak256Hasher,
+        &Sha256Hasher,
+        &Sha3_256Hasher,
+        &PositionalKeccak256Hasher,
+        &PositionalSha256Hasher,
+        &Rfc6962Hasher,
+    ];
+    for hasher in hashers {
+        let expected: Vec<_> = pairs.iter().map(|(l, r)| hasher.hash_node(l, r)).collect();
+        assert_eq!(hasher.hash_pairs(&pairs), expected, "{}", hasher.name());
+    }
+}
+
+#[test]
+fn built_in_hashers_are_sorted_pair() {
+    use merklerust_core::hashes::{
+        hasher_by_name, standard_node_hash, Keccak256Hasher, Sha256Hasher, Sha3_256Hasher,
+    };
+
+    let (a, b) = ([1u8; 32], [2u8; 32]);
+    assert_eq!(
+        Keccak256Hasher.hash_node(&b, &a),
+        standard_node_hash(&a, &b)
+    );
+    assert_eq!(
+        Sha256Hasher.hash_node(&b, &a),
+        sha256(&[&a[..], &b[..]].concat())
+    );
+    assert_eq!(Sha256Hasher.hash_leaf(b"x"), sha256(b"x"));
+    assert_eq!(
+        Sha3_256Hasher.hash_leaf(b""),
+        hex_literal("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a")
+    );
+    assert_eq!(
+        Sha3_256Hasher.hash_node(&a, &b),
+        Sha3_256Hasher.hash_node(&b, &a)
+    );
+
+    let by_name = hasher_by_name("sha256").unwrap();
+    assert_eq!(by_name.hash_node(&a, &b), Sha256Hasher.hash_node(&a, &b));
+    assert!(hasher_by_name("md5").is_none());
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn blake3_hasher() {
+    use merklerust_core::hashes::{hasher_by_name, Blake3Hasher};
+
+    assert_eq!(
+        Blake3Hasher.hash_leaf(b""),
+        hex_literal("af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262")
+    );
+    let (a, b) = ([1u8; 32], [2u8; 32]);
+    assert_eq!(
+        Blake3Hasher.hash_node(&b, &a),
+        Blake3Hasher.hash_leaf(&[&a[..], &b[..]].concat())
+    );
+    assert!(hasher_by_name("blake3").is_some());
+}
+
+fn hex_literal(s: &str) -> [u8; 32] {
+    hex::decode(s).unwrap().try_into().unwrap()
+}
+
+/// Leaves of the certificate-transparency reference test tree.
+fn ct_leaves() -> Vec<Vec<u8>> {
+    [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ]
+    .iter()
+    .map(|h| hex::decode(h).unwrap())
+    .collect()
+}
+
+#[test]
+fn rfc6962_matches_ct_reference_roots() {
+    let hasher = Rfc6962Hasher;
+    assert_eq!(
+        hex::encode(hasher.hash_leaf(b"")),
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d"
+    );
+    let roots = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+    for (i, root) in roots.iter().enumerate() {
+        let leaves: Vec<Vec<u8>> = ct_leaves()[..i + 1]
+            .iter()
+            .map(|l| hasher.hash_leaf(l).to_vec())
+            .collect();
+        assert_eq!(
+            hex::encode(log_root(&leaves, &hasher)),
+            *root,
+            "size {}",
+            i + 1
+        );
+        let tree = MerkleTree::new_positional(leaves, hasher);
+        assert_eq!(hex::encode(tree.root()), *root, "size {}", i + 1);
+    }
+
+    // a leaf never hashes like the node over the same bytes
+    let (l, r) = ([1u8; 32], [2u8; 32]);
+    assert_ne!(hasher.hash_leaf(&[l, r].concat()), hasher.hash_node(&l, &r));
+}
+
+#[test]
+fn consts_match_their_definitions() {
+    use merklerust_core::consts::{EMPTY_KECCAK256, EMPTY_SHA256, RFC6962_LEAF_PREFIX};
+
+    assert_eq!(EMPTY_SHA256, sha256(b""));
+    assert_eq!(EMPTY_KECCAK256, keccak256(b""));
+    assert_eq!(
+        Rfc6962Hasher.hash_leaf(b"x"),
+        sha256(&[&[RFC6962_LEAF_PREFIX][..], b"x"].concat())
+    );
+}
+
+#[test]
+fn rfc6962_hasher_by_name() {
+    use merklerust_core::hashes::hasher_by_name;
+    use merklerust_core::tree::{PairOrdering, TreeConfig};
+
+    let hasher = hasher_by_name("rfc6962").unwrap();
+    assert_eq!(hasher.hash_leaf(b"a"), Rfc6962Hasher.hash_leaf(b"a"));
+    assert_eq!(hasher.pair_ordering(), Some(PairOrdering::Positional));
+    // a sorted config cannot name it
+    let sorted = TreeConfig {
+        hasher: "rfc6962".to_string(),
+        ..TreeConfig::default()
+    };
+    assert!(sorted.build_hasher().is_err());
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use merklerust_core::consistency::{get_consistency_proof, log_root, verify_consistency};
+use merklerust_core::hashes::{
+    keccak256, sha256, Keccak256Hasher, PositionalKeccak256Hasher, PositionalSha256Hasher,
+    Rfc6962Hasher,
+};
+use merklerust_core::incremental::{
+    verify_fixed_depth_proof, FixedDepthTree, IncrementalMerkleTree, NewestLeavesProof,
+};
+use merklerust_core::merkle::{Bytes, MerkleError};
+use merklerust_core::tree::{EmptyRoot, MerkleTree, Padding, SingleLeafRoot};
+
+use common::leaves;
+
+#[test]
+fn root_after_every_push_matches_log_root() {
+    let all = leaves(20);
+    let mut tree = IncrementalMerkleTree::new(Rfc6962Hasher);
+    assert_eq!(tree.root(), Err(MerkleError::EmptyTree));
+
+    let mut previous: Option<Bytes> = None;
+    for (i, leaf) in all.iter().enumerate() {
+        tree.push(leaf);
+        let root = tree.root().unwrap();
+        assert_eq!(tree.len(), i + 1);
+        assert_eq!(tree.frontier().len(), (i + 1).count_ones() as usize);
+        assert_eq!(root, log_root(&all[..=i], &Rfc6962Hasher));
+
+        // successive roots are linked by consistency proofs
+        if let Some(old_root) = previous {
+            let proof = get_consistency_proof(&all[..=i], i, &Rfc6962Hasher);
+            assert!(verify_consistency(
+                i,
+                i + 1,
+                &old_root,
+                &root,
+                &proof,
+                &Rfc6962Hasher
+            ));
+        }
+        previous = Some(root);
+    }
+}
+
+#[test]
+fn matches_positional_tree() {
+    for n in 1..=20 {
+        let mut tree = IncrementalMerkleTree::new(PositionalKeccak256Hasher);
+        tree.extend(leaves(n));
+        assert_eq!(
+            tree.root().unwrap(),
+            MerkleTree::new_positional(leaves(n), PositionalKeccak256Hasher).root()
+        );
+    }
+}
+
+#[test]
+fn empty_root_policy() {
+    let tree = IncrementalMerkleTree::with_empty_root(Keccak256Hasher, EmptyRoot::Zero);
+    assert!(tree.is_empty());
+    assert_eq!(tree.root().unwrap(), vec![0u8; 32]);
+}
+
+#[test]
+#[should_panic(expected = "Expected valid merkle node, got length 31")]
+fn rejects_short_leaves() {
+    IncrementalMerkleTree::new(Keccak256Hasher).push(&[0u8; 31]);
+}
+
+#[test]
+fn fixed_depth_empty_root_matches_eth2_deposit_contract() {
+    let tree = FixedDepthTree::new(32, [0u8; 32], PositionalSha256Hasher);
+    assert_eq!(tree.capacity(), 1 << 32);
+    assert_eq!(tree.zeros()[1], sha256(&[0u8; 64]));
+    // get_deposit_root() mixes in the deposit count as a little-endian uint256
+    let deposit_root = sha256(&[&tree.root()[..], &[0u8; 32]].concat());
+    assert_eq!(
+        hex::encode(deposit_root),
+        "d70a234731285c6804c2a4f56711ddb8c82c99740f207854891028af34e27e5e"
+    );
+}
+
+#[test]
+fn fixed_depth_full_tree_matches_incremental_root() {
+    let all = leaves(8);
+    let mut fixed = FixedDepthTree::new(3, [0u8; 32], PositionalSha256Hasher);
+    let mut incremental = IncrementalMerkleTree::new(PositionalSha256Hasher);
+    for (i, leaf) in all.iter().enumerate() {
+        assert_eq!(fixed.insert(leaf), i);
+        incremental.push(leaf);
+    }
+    assert_eq!(fixed.root().to_vec(), incremental.root().unwrap());
+    assert_eq!(fixed.config().hasher, "sha256");
+    assert_eq!(
+        fixed.config().padding,
+        Padding::ZeroSubtrees {
+            depth: 3,
+            zero_value: [0u8; 32]
+        }
+    );
+}
+
+#[test]
+fn fixed_depth_proofs_cover_filled_and_zero_leaves() {
+    let h = PositionalKeccak256Hasher;
+    let zero = keccak256(b"zero");
+    let all = leaves(5);
+    let mut tree = FixedDepthTree::new(4, zero, h);
+    for leaf in &all {
+        tree.insert(leaf);
+        let root = tree.root();
+        for index in 0..tree.capacity() {
+            let proof = tree.proof(index);
+            assert_eq!(proof.len(), 4);
+            let leaf = all[..tree.len()].get(index).map_or(&zero[..], |l| l);
+            assert!(verify_fixed_depth_proof(&root, leaf, index, &proof, &h));
+            // swapping the sides only fails when the sibling differs from the leaf
+            if proof[0] != leaf {
+                assert!(!verify_fixed_depth_proof(
+                    &root,
+                    leaf,
+                    index ^ 1,
+                    &proof,
+                    &h
+                ));
+            }
+        }
+    }
+    assert!(!verify_fixed_depth_proof(
+        &tree.root(),
+        &all[0],
+        16,
+        &tree.proof(0),
+        &h
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Tree of depth 2 is full")]
+fn fixed_depth_insert_past_capacity_panics() {
+    let mut tree = FixedDepthTree::new(2, [0u8; 32], PositionalKeccak256Hasher);
+    for leaf in leaves(5) {
+        tree.insert(&leaf);
+    }
+}
+
+#[test]
+fn root_history_keeps_the_latest_roots() {
+    let all = leaves(6);
+    let mut tree = IncrementalMerkleTree::with_root_history(Rfc6962Hasher, EmptyRoot::Zero, 3);
+    assert!(tree.is_known_root(&[0u8; 32]));
+    let mut roots = Vec::new();
+    for leaf in all.iter() {
+        tree.push(leaf);
+        roots.push(tree.root().unwrap());
+    }
+    assert_eq!(
+        tree.recent_roots().map(|r| r.to_vec()).collect::<Vec<_>>(),
+        roots[3..].to_vec()
+    );
+    for root in roots[3..].iter() {
+        assert!(tree.is_known_root(root));
+    }
+    assert!(!tree.is_known_root(&roots[2]));
+    assert!(!tree.is_known_root(&[0u8; 32]));
+
+    // without a history only the current root is known
+    let mut plain = IncrementalMerkleTree::new(Rfc6962Hasher);
+    assert!(!plain.is_known_root(&[0u8; 32]));
+    plain.extend(&all);
+    assert_eq!(plain.recent_roots().len(), 0);
+    assert!(plain.is_known_root(&roots[5]));
+    assert!(!plain.is_known_root(&roots[4]));
+}
+
+#[test]
+fn newest_leaves_proof_covers_the_batch() {
+    let all = leaves(13);
+    for split in 0..all.len() {
+        let mut tree = IncrementalMerkleTree::new(Rfc6962Hasher);
+        tree.extend(&all[..split]);
+        let batch = &all[split..];
+        let proof = tree.extend_with_proof(batch);
+        let root = tree.root().unwrap();
+        assert_eq!(root, log_root(&all, &Rfc6962Hasher));
+        assert_eq!(proof.start, split);
+        assert!(proof.frontier.len() <= 4);
+        assert!(
+            proof.verify(&root, batch, &Rfc6962Hasher),
+            "split {}",
+            split
+        );
+
+        let mut changed = batch.to_vec();
+        changed[0][0] ^= 1;
+        assert!(!proof.verify(&root, &changed, &Rfc6962Hasher));
+        assert!(!proof.verify(&root, &batch[1..], &Rfc6962Hasher));
+        let moved = NewestLeavesProof {
+            start: split + 1,
+            ..proof.clone()
+        };
+        assert!(!moved.verify(&root, batch, &Rfc6962Hasher));
+    }
+}
+
+#[test]
+fn hashed_single_leaf_root() {
+    let all = leaves(3);
+    let mut tree = IncrementalMerkleTree::with_single_leaf_root(
+        Keccak256Hasher,
+        EmptyRoot::Reject,
+        0,
+        SingleLeafRoot::Hashed,
+    );
+    let proof = tree.extend_with_proof(&all[..1]);
+    assert_eq!(tree.root().unwrap(), keccak256(&all[0]).to_vec());
+    assert!(proof.verify_with_single_leaf_root(
+        &tree.root().unwrap(),
+        &all[..1],
+        &Keccak256Hasher,
+        SingleLeafRoot::Hashed
+    ));
+    assert!(!proof.verify(&tree.root().unwrap(), &all[..1], &Keccak256Hasher));
+
+    tree.extend(&all[1..]);
+    let mut plain = IncrementalMerkleTree::new(Keccak256Hasher);
+    plain.extend(&all);
+    assert_eq!(tree.root(), plain.root());
+}
@@ -0,0 +1,54 @@
+use merklerust_core::js_verifier::emit_js_verifier;
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::tree::{MultiProofAlgorithm, Padding, PairOrdering, TreeConfig};
+
+fn config(hasher: &str, pair_ordering: PairOrdering) -> TreeConfig {
+    TreeConfig {
+        hasher: hasher.to_string(),
+        pair_ordering,
+        multi_proof: MultiProofAlgorithm::for_ordering(pair_ordering),
+        ..TreeConfig::default()
+    }
+}
+
+#[test]
+fn emits_only_the_verifiers_of_the_config() {
+    let sorted = emit_js_verifier(&TreeConfig::default()).unwrap();
+    assert!(
+        sorted.contains(r#"export const config = {"hasher":"keccak256","pairOrdering":"sorted""#)
+    );
+    assert!(sorted.contains("export function verifyProof("));
+    assert!(sorted.contains("export function verifyMultiProof("));
+    assert!(!sorted.contains("verifyPositionalProof"));
+    assert!(sorted.contains("function keccak("));
+    assert!(!sorted.contains("function sha256("));
+    assert!(!sorted.contains("import "));
+
+    let ct = emit_js_verifier(&config("rfc6962", PairOrdering::Positional)).unwrap();
+    assert!(ct.contains("export function verifyPositionalProof("));
+    assert!(ct.contains("export function verifyPositionalMultiProof("));
+    assert!(!ct.contains("export function verifyProof("));
+    assert!(ct.contains("function sha256("));
+    assert!(ct.contains("const NODE_PREFIX = Uint8Array.of(1);"));
+}
+
+#[test]
+fn rejects_configs_it_cannot_verify() {
+    let invalid = |config: &TreeConfig| {
+        matches!(emit_js_verifier(config), Err(MerkleError::InvalidConfig(_)))
+    };
+    assert!(invalid(&config("", PairOrdering::Sorted)));
+    assert!(invalid(&config("blake3", PairOrdering::Sorted)));
+    assert!(invalid(&config("rfc6962", PairOrdering::Sorted)));
+    assert!(invalid(&TreeConfig {
+        padding: Padding::ZeroSubtrees {
+            depth: 4,
+            zero_value: [0u8; 32],
+        },
+        ..TreeConfig::default()
+    }));
+    assert!(invalid(&TreeConfig {
+        multi_proof: MultiProofAlgorithm::Positional,
+        ..TreeConfig::default()
+    }));
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use merklerust_core::hashes::{keccak256, Keccak256Hasher, Sha256Hasher};
+use merklerust_core::leaf_io::{
+    make_merkle_tree_from_sorted_files, LeafReader, LevelFileTree, SortedLeafMerge, TreeBuilder,
+};
+use merklerust_core::merkle::{make_merkle_tree_bytes, Bytes};
+use merklerust_core::tree::MerkleTree;
+
+use common::node_hash;
+
+fn file(values: &[u8]) -> Vec<u8> {
+    values.iter().flat_map(|&v| [v; 32]).collect()
+}
+
+#[test]
+fn merges_and_dedups_sorted_files() {
+    let a = file(&[1, 4, 4, 7]);
+    let b = file(&[2, 4, 9]);
+    let c = file(&[]);
+
+    let merged: Vec<u8> = SortedLeafMerge::new(vec![&a[..], &b[..], &c[..]])
+        .map(|r| r.unwrap()[0])
+        .collect();
+    assert_eq!(merged, vec![1, 2, 4, 7, 9]);
+
+    let builder = TreeBuilder::in_temp_dir(Keccak256Hasher, 1 << 10).unwrap();
+    let tree = make_merkle_tree_from_sorted_files(vec![&a[..], &b[..]], builder).unwrap();
+    let expected: Vec<Bytes> = [1u8, 2, 4, 7, 9].iter().map(|&v| vec![v; 32]).collect();
+    assert_eq!(
+        tree.to_tree().unwrap(),
+        make_merkle_tree_bytes(expected, node_hash)
+    );
+    tree.remove().unwrap();
+}
+
+#[test]
+fn rejects_unsorted_and_truncated_files() {
+    let unsorted = file(&[3, 1]);
+    let err = SortedLeafMerge::new(vec![&unsorted[..]])
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let truncated = [0u8; 40];
+    let records: Vec<_> = LeafReader::new(&truncated[..]).collect();
+    assert!(records[0].is_ok());
+    assert_eq!(
+        records[1].as_ref().unwrap_err().kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn tree_builder_matches_in_memory_tree() {
+    let dir = std::env::temp_dir().join(format!("merklerust-builder-{}", std::process::id()));
+    for n in [1usize, 2, 3, 5, 8, 13, 64, 100] {
+        let leaves: Vec<Bytes> = (0..n)
+            .map(|i| keccak256(&i.to_be_bytes()).to_vec())
+            .collect();
+        let expected = MerkleTree::new(leaves.clone(), Sha256Hasher);
+
+        let mut builder = TreeBuilder::new(&dir, Sha256Hasher).unwrap();
+        builder.feed(&leaves[0]).unwrap();
+        let rest = leaves[1..].concat();
+        assert_eq!(builder.feed_reader(&rest[..]).unwrap(), n - 1);
+        assert_eq!(builder.len(), n);
+        let tree = builder.finish().unwrap();
+
+        assert_eq!(&tree.root()[..], expected.root());
+        assert_eq!(tree.leaf_count(), n);
+        assert_eq!(tree.to_tree().unwrap(), expected.as_slice());
+        for position in 0..n {
+            assert_eq!(tree.proof(position).unwrap(), expected.proof(position));
+        }
+        for positions in [
+            vec![],
+            vec![0],
+            (0..n).step_by(3).collect(),
+            (0..n).collect(),
+        ] {
+            assert_eq!(
+                tree.multi_proof(&positions).unwrap(),
+                expected.multi_proof(&positions)
+            );
+        }
+        tree.remove().unwrap();
+    }
+
+    let mut builder = TreeBuilder::new(&dir, Sha256Hasher).unwrap();
+    let err = builder.feed(&[0u8; 31]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(builder.is_empty());
+    assert!(builder.finish().is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tree_builder_in_temp_dir_with_small_budget() {
+    let leaves: Vec<Bytes> = (0..37u32)
+        .map(|i| keccak256(&i.to_be_bytes()).to_vec())
+        .collect();
+    let mut builder = TreeBuilder::in_temp_dir(Sha256Hasher, 256).unwrap();
+    let dir = builder.dir().to_path_buf();
+    builder.feed_reader(&leaves.concat()[..]).unwrap();
+    let tree = builder.finish().unwrap();
+
+    let expected = MerkleTree::new(leaves, Sha256Hasher);
+    assert_eq!(&tree.root()[..], expected.root());
+    assert_eq!(tree.proof(36).unwrap(), expected.proof(36));
+    assert!(dir.exists());
+    tree.remove().unwrap();
+    assert!(!dir.exists());
+}
+
+#[test]
+fn temp_dir_is_removed_when_build_is_abandoned() {
+    let builder = TreeBuilder::in_temp_dir(Keccak256Hasher, 1 << 10).unwrap();
+    let dir = builder.dir().to_path_buf();
+    assert!(dir.exists());
+    drop(builder);
+    assert!(!dir.exists());
+
+    let builder = TreeBuilder::in_temp_dir(Keccak256Hasher, 1 << 10).unwrap();
+    let dir = builder.dir().to_path_buf();
+    assert!(builder.finish().is_err());
+    assert!(!dir.exists());
+}
+
+#[test]
+fn level_file_tree_rejects_out_of_range_proof() {
+    let mut builder = TreeBuilder::in_temp_dir(Keccak256Hasher, 1 << 10).unwrap();
+    builder.feed(&[1u8; 32]).unwrap();
+    builder.feed(&[2u8; 32]).unwrap();
+    let tree = builder.finish().unwrap();
+    assert_eq!(
+        tree.proof(2).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    for positions in [[2, 0], [1, 1]] {
+        assert_eq!(
+            tree.multi_proof(&positions).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+    tree.remove().unwrap();
+}
+
+#[test]
+fn level_file_tree_reopens_from_its_header() {
+    let dir = std::env::temp_dir().join(format!("merklerust-reopen-{}", std::process::id()));
+    let leaves: Vec<Bytes> = (0..11u32)
+        .map(|i| keccak256(&i.to_be_bytes()).to_vec())
+        .collect();
+    let mut builder = TreeBuilder::new(&dir, Sha256Hasher).unwrap();
+    builder.feed_reader(&leaves.concat()[..]).unwrap();
+    let built = builder.finish().unwrap();
+    let root = *built.root();
+    drop(built);
+
+    let tree = LevelFileTree::open(&dir, Sha256Hasher).unwrap();
+    let expected = MerkleTree::new(leaves, Sha256Hasher);
+    assert_eq!(&tree.root()[..], expected.root());
+    assert_eq!(*tree.root(), root);
+    assert_eq!(tree.leaf_count(), 11);
+    assert_eq!(tree.levels(), 5);
+    assert_eq!(tree.to_tree().unwrap(), expected.as_slice());
+    for position in 0..11 {
+        assert_eq!(tree.proof(position).unwrap(), expected.proof(position));
+    }
+    drop(tree);
+
+    let err = LevelFileTree::open(&dir, Keccak256Hasher).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    let level = dir.join("level-4.bin");
+    let nodes = std::fs::read(&level).unwrap();
+    std::fs::write(&level, &nodes[..nodes.len() - 32]).unwrap();
+    let err = LevelFileTree::open(&dir, Sha256Hasher).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    std::fs::write(&level, &nodes).unwrap();
+
+    let header = dir.join("tree.json");
+    let json = std::fs::read_to_string(&header).unwrap();
+    std::fs::write(
+        &header,
+        json.replace("\"leafCount\":11", "\"leafCount\":12"),
+    )
+    .unwrap();
+    let err = LevelFileTree::open(&dir, Sha256Hasher).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // leaf counts whose trees do not fit in memory are rejected, not overflowed
+    let huge = 1usize << (usize::BITS - 1);
+    let levels: Vec<String> = (0..usize::BITS)
+        .map(|l| (1usize << l).to_string())
+        .collect();
+    for (count, levels) in [
+        (usize::MAX, "[1]".to_string()),
+        (huge, format!("[{}]", levels.join(","))),
+    ] {
+        let hostile = json
+            .replace("\"leafCount\":11", &format!("\"leafCount\":{}", count))
+            .replace("\"levels\":[1,2,4,8,6]", &format!("\"levels\":{}", levels));
+        assert!(hostile.contains(&levels));
+        std::fs::write(&header, hostile).unwrap();
+        let err = LevelFileTree::open(&dir, Sha256Hasher).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+    std::fs::write(&header, json).unwrap();
+
+    LevelFileTree::open(&dir, Sha256Hasher)
+        .unwrap()
+        .remove()
+        .unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use merklerust_core::manifest::{
+    read_tar, verify_entry, verify_policy, Entry, EntryKind, Manifest, ManifestError, PathPolicy,
+};
+
+use common::node_hash;
+
+fn tar_member(out: &mut Vec<u8>, name: &str, typeflag: u8, link: &str, data: &[u8]) {
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..107].copy_from_slice(b"0000644");
+    header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+    header[156] = typeflag;
+    header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    out.extend_from_slice(&header);
+    out.extend_from_slice(data);
+    out.resize(out.len().div_ceil(512) * 512, 0);
+}
+
+fn sample_tar() -> Vec<u8> {
+    let mut tar = Vec::new();
+    tar_member(&mut tar, "pkg/", b'5', "", b"");
+    tar_member(&mut tar, "pkg/lib.so", b'0', "", b"\x7fELF binary");
+    tar_member(&mut tar, "pkg/README", b'0', "", b"hello");
+    tar_member(&mut tar, "pkg/latest", b'2', "lib.so", b"");
+    tar_member(&mut tar, "pkg/README.copy", b'1', "pkg/README", b"");
+    tar.extend_from_slice(&[0u8; 1024]);
+    tar
+}
+
+#[test]
+fn reads_tar_members() {
+    let entries = read_tar(sample_tar().as_slice()).unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            Entry::file("pkg/lib.so", b"\x7fELF binary"),
+            Entry::file("pkg/README", b"hello"),
+            Entry::symlink("pkg/latest", "lib.so"),
+            Entry::file("pkg/README.copy", b"hello"),
+        ]
+    );
+}
+
+#[test]
+fn verifies_member_extraction() {
+    let manifest = Manifest::new(read_tar(sample_tar().as_slice()).unwrap(), node_hash);
+    let root = manifest.root().to_vec();
+    assert_eq!(manifest.entries()[0].path, "pkg/README");
+
+    let proof = manifest.proof("pkg/lib.so").unwrap();
+    let extracted = Entry::file("pkg/lib.so", b"\x7fELF binary");
+    assert!(verify_entry(&root, &extracted, &proof, node_hash));
+
+    let tampered = Entry::file("pkg/lib.so", b"\x7fELF backdoor");
+    assert!(!verify_entry(&root, &tampered, &proof, node_hash));
+    // malformed proofs are rejected rather than panicking
+    let mut short = proof.clone();
+    short[0].pop();
+    assert!(!verify_entry(&root, &extracted, &short, node_hash));
+    let as_link = Entry {
+        kind: EntryKind::Symlink,
+        ..extracted
+    };
+    assert!(!verify_entry(&root, &as_link, &proof, node_hash));
+    assert!(manifest.proof("pkg/missing").is_none());
+}
+
+#[test]
+fn root_is_independent_of_member_order() {
+    let mut entries = read_tar(sample_tar().as_slice()).unwrap();
+    let a = Manifest::new(entries.clone(), node_hash);
+    entries.reverse();
+    let b = Manifest::new(entries, node_hash);
+    assert_eq!(a.root(), b.root());
+}
+
+#[test]
+fn rejects_corrupt_header() {
+    let mut tar = sample_tar();
+    tar[512 + 3] ^= 1;
+    assert!(read_tar(tar.as_slice()).is_err());
+}
+
+#[test]
+fn rejects_out_of_range_base256_sizes() {
+    let with_size = |field: [u8; 12]| {
+        let mut tar = Vec::new();
+        tar_member(&mut tar, "a", b'0', "", b"1");
+        tar[124..136].copy_from_slice(&field);
+        tar[148..156].copy_from_slice(b"        ");
+        let sum: u32 = tar[..512].iter().map(|&b| b as u32).sum();
+        tar[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        tar.extend_from_slice(&[0u8; 1024]);
+        read_tar(tar.as_slice())
+    };
+    let mut max = [0xffu8; 12];
+    max[..4].copy_from_slice(&[0x80, 0, 0, 0]);
+    let mut wide = [0u8; 12];
+    (wide[0], wide[3]) = (0x80, 1);
+    for field in [[0xffu8; 12], max, wide] {
+        let err = with_size(field).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let mut one = [0u8; 12];
+    (one[0], one[11]) = (0x80, 1);
+    assert_eq!(with_size(one).unwrap()[0].path, "a");
+}
+
+#[test]
+fn rejects_malformed_pax_record() {
+    for body in [&b"2 x"[..], b"9 path=a", b"x path=a\n"] {
+        let mut tar = Vec::new();
+        tar_member(&mut tar, "pax", b'x', "", body);
+        tar_member(&mut tar, "a", b'0', "", b"1");
+        tar.extend_from_slice(&[0u8; 1024]);
+        let err = read_tar(tar.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    let mut tar = Vec::new();
+    tar_member(&mut tar, "pax", b'x', "", b"12 path=b/c\n");
+    tar_member(&mut tar, "a", b'0', "", b"1");
+    tar.extend_from_slice(&[0u8; 1024]);
+    assert_eq!(read_tar(tar.as_slice()).unwrap()[0].path, "b/c");
+}
+
+#[test]
+#[should_panic(expected = "Duplicate manifest path")]
+fn rejects_duplicate_paths() {
+    Manifest::new(
+        vec![Entry::file("a", b"1"), Entry::file("a", b"2")],
+        node_hash,
+    );
+}
+
+#[test]
+fn reports_duplicate_paths_after_canonicalization() {
+    let entries = vec![Entry::file("A", b"1"), Entry::file("a", b"2")];
+    assert!(Manifest::try_with_policy(entries.clone(), PathPolicy::default(), node_hash).is_ok());
+    assert_eq!(
+        Manifest::try_with_policy(entries, PathPolicy::portable(), node_hash).err(),
+        Some(ManifestError::DuplicatePath("a".to_string()))
+    );
+    assert_eq!(
+        Manifest::try_with_policy(Vec::new(), PathPolicy::default(), node_hash).err(),
+        Some(ManifestError::NoEntries)
+    );
+
+    let dir = std::env::temp_dir().join(format!("merklerust-case-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("A"), b"1").unwrap();
+    std::fs::write(dir.join("a"), b"2").unwrap();
+    // case-insensitive filesystems hold only one of the two
+    if std::fs::read_dir(&dir).unwrap().count() == 2 {
+        let err = merklerust_core::manifest::merkleize_dir(&dir, PathPolicy::portable(), node_hash)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn merkleizes_directory() {
+    use merklerust_core::manifest::merkleize_dir;
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("merklerust-dir-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("bin/empty")).unwrap();
+    fs::write(dir.join("bin/tool"), b"tool binary").unwrap();
+    fs::write(dir.join("README"), b"hello").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("bin/tool", dir.join("tool")).unwrap();
+
+    let manifest = merkleize_dir(&dir, PathPolicy::default(), node_hash).unwrap();
+    let mut expected = vec![
+        Entry::file("README", b"hello"),
+        Entry::file("bin/tool", b"tool binary"),
+    ];
+    #[cfg(unix)]
+    expected.push(Entry::symlink("tool", "bin/tool"));
+    assert_eq!(manifest.entries(), expected.as_slice());
+
+    let proof = manifest.proof("bin/tool").unwrap();
+    let root = manifest.root().to_vec();
+    assert!(verify_entry(
+        &root,
+        &Entry::file("bin/tool", b"tool binary"),
+        &proof,
+        node_hash
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn portable_policy_agrees_across_platforms() {
+    let policy = PathPolicy::portable();
+    assert_eq!(
+        policy.apply(".\\Docs\\\\Re\u{301}sume\u{301}.TXT"),
+        "docs/r\u{e9}sum\u{e9}.txt"
+    );
+    assert_eq!(PathPolicy::from_byte(policy.to_byte()), Some(policy));
+    assert_eq!(PathPolicy::from_byte(8), None);
+    assert_eq!(PathPolicy::default().apply("A\\b"), "A\\b");
+
+    let windows = Manifest::with_policy(
+        vec![
+            Entry::file("Bin\\Tool.EXE", b"x"),
+            Entry::file("README", b"y"),
+        ],
+        policy,
+        node_hash,
+    );
+    let unix = Manifest::with_policy(
+        vec![
+            Entry::file("bin/tool.exe", b"x"),
+            Entry::file("./readme", b"y"),
+        ],
+        policy,
+        node_hash,
+    );
+    assert_eq!(windows.root(), unix.root());
+    assert_eq!(windows.policy(), policy);
+    assert!(windows.proof("BIN/tool.exe").is_some());
+
+    // the root commits to the policy
+    let proof = windows.policy_proof();
+    assert!(verify_policy(windows.root(), policy, &proof, node_hash));
+    assert!(!verify_policy(
+        windows.root(),
+        PathPolicy::default(),
+        &proof,
+        node_hash
+    ));
+    let plain = Manifest::new(vec![Entry::file("readme", b"y")], node_hash);
+    let folded = Manifest::with_policy(vec![Entry::file("readme", b"y")], policy, node_hash);
+    assert_ne!(plain.root(), folded.root());
+}
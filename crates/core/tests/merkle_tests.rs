@@ -1,14 +1,29 @@
-use merklerust_core::hashes::keccak256;
+mod common;
+
+use merklerust_core::hashes::{keccak256, positional_node_hash, Hasher, Keccak256Hasher};
 use merklerust_core::merkle::{
-    get_multi_proof, get_proof, is_valid_merkle_tree, make_merkle_tree_bytes, process_multi_proof,
-    process_proof, render_merkle_tree, Bytes,
+    bind_leaves_to_context, check_tree_size, check_zero_leaves, constant_time_eq,
+    estimated_tree_bytes, get_multi_proof, get_multi_proof_for_leaves, get_multi_proof_parallel,
+    get_multi_proof_with_threads, get_padded_proof, get_positional_proof, get_proof,
+    get_proof_for_leaf, get_proof_guarded, get_proofs, get_proofs_parallel, is_valid_merkle_tree,
+    leaf_count, leaf_position, leaf_tree_index, make_merkle_tree_bytes,
+    make_merkle_tree_bytes_cached, make_merkle_tree_bytes_with_limits,
+    make_positional_merkle_tree_bytes, max_proof_len, process_multi_proof,
+    process_multi_proof_iter, process_padded_proof, process_positional_proof, process_proof,
+    process_proof_guarded, proof_len, render_merkle_tree, render_merkle_tree_dot,
+    render_merkle_tree_json, sample_verify, try_get_multi_proof, try_get_multi_proof_for_leaves,
+    try_get_multi_proof_parallel, try_get_multi_proof_with_threads, try_get_padded_proof,
+    try_get_proof, try_get_proof_for_leaf, try_get_proofs, try_leaf_position,
+    try_make_merkle_tree_by_levels, try_make_merkle_tree_bytes, try_process_multi_proof,
+    try_process_multi_proof_iter, try_process_padded_proof, try_process_proof, verify_all_proofs,
+    verify_multi_proof, verify_padded_proof, verify_positional_proof, verify_proof,
+    verify_proof_in_context, verify_proofs_batch, verify_proofs_batch_parallel, Bytes, MerkleError,
+    MultiProof, NodeHashCache, SampleReport, Side, TreeLimits, TreeSizeError, ZeroLeafPolicy,
+    PROOF_PADDING,
 };
 use proptest::prelude::*;
 
-fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
-    let (left, right) = if a <= b { (a, b) } else { (b, a) };
-    keccak256(&[left, right].concat()).to_vec()
-}
+use common::{leaves, node_hash};
 
 proptest! {
     #[test]
@@ -19,7 +34,7 @@ proptest! {
 
         let tree = make_merkle_tree_bytes(leaves_bytes.clone(), node_hash);
         let root = tree[0].clone();
-        prop_assert!(root.len() > 0);
+        prop_assert!(!root.is_empty());
 
         // test for every leaf index we could pick one — here choose 0 to keep test simple
         let tree_index = tree.len() - 1 - leaf_index;
@@ -37,7 +52,7 @@ proptest! {
 
         // choose some indices deterministically for simplicity: pick first half non-empty
         let mut leaf_indices: Vec<usize> = (0..len).collect();
-        leaf_indices.truncate((len+1)/2);
+        leaf_indices.truncate(len.div_ceil(2));
 
         let tree = make_merkle_tree_bytes(leaves_bytes.clone(), node_hash);
         let root = tree[0].clone();
@@ -77,16 +92,16 @@ fn multiproof_duplicate_index() {
 #[test]
 fn tree_validity() {
     let zero: Bytes = vec![0u8; 32];
-    assert_eq!(is_valid_merkle_tree(&Vec::new(), node_hash), false);
-    assert_eq!(is_valid_merkle_tree(&vec![vec![0u8; 1]], node_hash), false);
-    assert_eq!(
-        is_valid_merkle_tree(&vec![zero.clone(), zero.clone()], node_hash),
-        false
-    );
-    assert_eq!(
-        is_valid_merkle_tree(&vec![zero.clone(), zero.clone(), zero.clone()], node_hash),
-        false
-    );
+    assert!(!is_valid_merkle_tree(&[], node_hash));
+    assert!(!is_valid_merkle_tree(&[vec![0u8; 1]], node_hash));
+    assert!(!is_valid_merkle_tree(
+        &[zero.clone(), zero.clone()],
+        node_hash
+    ));
+    assert!(!is_valid_merkle_tree(
+        &[zero.clone(), zero.clone(), zero.clone()],
+        node_hash
+    ));
     // render empty should panic
     let res = std::panic::catch_unwind(|| render_merkle_tree(&Vec::new()));
     assert!(res.is_err());
@@ -112,3 +127,824 @@ fn get_proof_for_internal_node() {
     let tree = make_merkle_tree_bytes(vec![zero.clone(), zero.clone()], node_hash);
     let _ = get_proof(&tree, 0);
 }
+
+#[test]
+fn render_dot_and_json() {
+    let zero: Bytes = vec![0u8; 32];
+    let tree = make_merkle_tree_bytes(vec![zero.clone(), zero.clone()], node_hash);
+
+    let dot = render_merkle_tree_dot(&tree);
+    assert!(dot.starts_with("digraph merkle {"));
+    assert!(dot.contains("n0 -> n1;"));
+    assert!(dot.contains("n0 -> n2;"));
+
+    let json = render_merkle_tree_json(&tree);
+    assert!(json.starts_with(&format!("{{\"root\":\"0x{}\"", hex::encode(&tree[0]))));
+    assert!(json.contains("{\"index\":1,\"hash\":\"0x"));
+    assert!(json.contains("\"left\":null,\"right\":null"));
+}
+
+#[test]
+fn guarded_proof_rejects_internal_node_as_leaf() {
+    let leaves: Vec<Bytes> = (0u8..4).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    // the left child of the root is internal; presenting it as a leaf yields a valid-looking proof
+    let forged_leaf = tree[1].clone();
+    let forged_proof = vec![tree[2].clone()];
+    assert_eq!(
+        process_proof(&forged_leaf, &forged_proof, node_hash),
+        tree[0]
+    );
+    assert_eq!(
+        process_proof_guarded(&tree, &forged_leaf, &forged_proof, node_hash, false),
+        Err(MerkleError::LeafIsInternalNode)
+    );
+    // with domain separation the caller may allow it
+    assert_eq!(
+        process_proof_guarded(&tree, &forged_leaf, &forged_proof, node_hash, true).as_ref(),
+        Ok(&tree[0])
+    );
+
+    let mut colliding = tree.clone();
+    colliding[6] = tree[1].clone();
+    assert_eq!(
+        get_proof_guarded(&colliding, 6, false),
+        Err(MerkleError::LeafIsInternalNode)
+    );
+    assert!(get_proof_guarded(&colliding, 6, true).is_ok());
+    assert_eq!(
+        get_proof_guarded(&tree, 0, false),
+        Err(MerkleError::NotALeaf(0))
+    );
+}
+
+#[test]
+fn guarded_proof_accepts_real_leaves() {
+    let leaves: Vec<Bytes> = (0u8..4).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let proof = get_proof_guarded(&tree, 3, false).unwrap();
+    assert_eq!(proof, get_proof(&tree, 3));
+    assert_eq!(
+        process_proof_guarded(&tree, &tree[3], &proof, node_hash, false),
+        Ok(tree[0].clone())
+    );
+}
+
+#[test]
+fn tree_limits() {
+    let zero: Bytes = vec![0u8; 32];
+    let leaves = vec![zero.clone(); 4];
+
+    let limits = TreeLimits {
+        max_leaves: 3,
+        ..TreeLimits::default()
+    };
+    assert_eq!(
+        make_merkle_tree_bytes_with_limits(leaves.clone(), &limits, node_hash),
+        Err(MerkleError::TreeSize(TreeSizeError::TooManyLeaves {
+            leaves: 4,
+            max: 3
+        }))
+    );
+
+    let limits = TreeLimits {
+        max_bytes: estimated_tree_bytes(4) - 1,
+        ..TreeLimits::default()
+    };
+    assert!(matches!(
+        make_merkle_tree_bytes_with_limits(leaves.clone(), &limits, node_hash),
+        Err(MerkleError::TreeSize(TreeSizeError::TooManyBytes { .. }))
+    ));
+
+    // malformed input is reported through the same error type instead of panicking
+    let limits = TreeLimits::default();
+    assert_eq!(
+        make_merkle_tree_bytes_with_limits(Vec::new(), &limits, node_hash),
+        Err(MerkleError::EmptyTree)
+    );
+    assert_eq!(
+        make_merkle_tree_bytes_with_limits(vec![vec![0u8; 31]], &limits, node_hash),
+        Err(MerkleError::InvalidNode(31))
+    );
+
+    let tree = make_merkle_tree_bytes_with_limits(leaves, &limits, node_hash).unwrap();
+    assert_eq!(tree.len(), 7);
+
+    assert!(check_tree_size(usize::MAX, &TreeLimits::default()).is_err());
+}
+
+#[test]
+fn single_leaf_tree() {
+    let leaf: Bytes = vec![7u8; 32];
+    let tree = make_merkle_tree_bytes(vec![leaf.clone()], node_hash);
+    assert_eq!(tree, vec![leaf.clone()]);
+    assert!(is_valid_merkle_tree(&tree, node_hash));
+
+    let proof = get_proof(&tree, 0);
+    assert!(proof.is_empty());
+    assert_eq!(process_proof(&leaf, &proof, node_hash), leaf);
+
+    let mp = get_multi_proof(&tree, vec![0]);
+    assert_eq!(mp.leaves, vec![leaf.clone()]);
+    assert!(mp.proof.is_empty() && mp.proof_flags.is_empty());
+    assert_eq!(process_multi_proof(&mp, node_hash), leaf);
+
+    let empty_mp = get_multi_proof(&tree, vec![]);
+    assert_eq!(process_multi_proof(&empty_mp, node_hash), leaf);
+}
+
+proptest! {
+    #[test]
+    fn streaming_multiproof_matches(leaves in prop::collection::vec(prop::collection::vec(any::<u8>(), 32), 1..=16), mask in any::<u16>()) {
+        let tree = make_merkle_tree_bytes(leaves.clone(), node_hash);
+        let tree_indices: Vec<usize> = (0..leaves.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| tree.len() - 1 - i)
+            .collect();
+        let mp = get_multi_proof(&tree, tree_indices);
+        let streamed = process_multi_proof_iter(
+            mp.leaves.iter(),
+            mp.proof.iter(),
+            mp.proof_flags.iter().copied(),
+            node_hash,
+        );
+        prop_assert_eq!(&streamed, &tree[0]);
+        prop_assert_eq!(streamed, process_multi_proof(&mp, node_hash));
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invariant error")]
+fn streaming_multiproof_invariants() {
+    let zero: Bytes = vec![0u8; 32];
+    let _ = process_multi_proof_iter(
+        vec![zero.clone(), zero.clone()],
+        vec![zero.clone(), zero.clone()],
+        vec![true, true, false],
+        node_hash,
+    );
+}
+
+#[test]
+fn try_streaming_multiproof_reports_errors() {
+    let zero: Bytes = vec![0u8; 32];
+    assert_eq!(
+        try_process_multi_proof_iter(
+            vec![zero.clone(), zero.clone()],
+            vec![zero.clone(), zero.clone()],
+            vec![true, true, false],
+            node_hash,
+        ),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    assert_eq!(
+        try_process_multi_proof_iter(
+            vec![zero.clone(), vec![0u8; 5]],
+            Vec::<Bytes>::new(),
+            vec![true],
+            node_hash,
+        ),
+        Err(MerkleError::InvalidNode(5))
+    );
+    assert_eq!(
+        try_process_multi_proof_iter(vec![zero.clone()], vec![zero.clone()], vec![], node_hash),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    assert_eq!(
+        try_process_multi_proof_iter(
+            vec![zero.clone(), zero.clone()],
+            Vec::<Bytes>::new(),
+            vec![true],
+            node_hash
+        ),
+        Ok(node_hash(&zero, &zero))
+    );
+}
+
+#[test]
+fn cached_rebuild_reuses_unchanged_subtrees() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0usize);
+    let counting_hash = |a: &[u8], b: &[u8]| {
+        calls.set(calls.get() + 1);
+        node_hash(a, b)
+    };
+
+    let mut leaves: Vec<Bytes> = (0u8..8).map(|i| vec![i; 32]).collect();
+    let mut cache = NodeHashCache::new();
+    let first = make_merkle_tree_bytes_cached(leaves.clone(), &mut cache, counting_hash);
+    assert_eq!(first, make_merkle_tree_bytes(leaves.clone(), node_hash));
+    assert_eq!(calls.get(), 7);
+    assert_eq!(cache.len(), 7);
+
+    // changing one leaf only touches the 3 nodes on its path
+    calls.set(0);
+    leaves[5] = vec![0xff; 32];
+    let second = make_merkle_tree_bytes_cached(leaves.clone(), &mut cache, counting_hash);
+    assert_eq!(second, make_merkle_tree_bytes(leaves.clone(), node_hash));
+    assert_eq!(calls.get(), 3);
+    // the 3 replaced parents were evicted
+    assert_eq!(cache.len(), 7);
+
+    // a reloaded cache serves the same tree without hashing
+    calls.set(0);
+    let bytes = cache.to_bytes();
+    assert_eq!(bytes.len(), 7 * 96);
+    let mut reloaded = NodeHashCache::from_bytes(&bytes, usize::MAX).unwrap();
+    assert_eq!(
+        make_merkle_tree_bytes_cached(leaves.clone(), &mut reloaded, counting_hash),
+        second
+    );
+    assert_eq!(calls.get(), 0);
+    assert_eq!(
+        NodeHashCache::from_bytes(&bytes[1..], usize::MAX).err(),
+        Some(MerkleError::InvalidNode(95))
+    );
+    assert_eq!(NodeHashCache::from_bytes(&bytes, 2).unwrap().len(), 2);
+
+    // a bounded cache stops memoizing but still builds the same tree
+    let mut bounded = NodeHashCache::with_max_entries(4);
+    assert_eq!(
+        make_merkle_tree_bytes_cached(leaves, &mut bounded, counting_hash),
+        second
+    );
+    assert_eq!(bounded.len(), 4);
+}
+
+#[test]
+fn constant_time_comparison() {
+    assert!(constant_time_eq(&[1, 2, 3], &[1, 2, 3]));
+    assert!(!constant_time_eq(&[1, 2, 3], &[1, 2, 4]));
+    assert!(!constant_time_eq(&[1, 2, 3], &[1, 2]));
+}
+
+#[test]
+fn zero_leaf_policy() {
+    let leaves: Vec<Bytes> = vec![vec![1u8; 32], vec![0u8; 32], vec![0u8; 32]];
+    assert_eq!(
+        check_zero_leaves(&leaves, ZeroLeafPolicy::Allow),
+        Ok(vec![])
+    );
+    assert_eq!(
+        check_zero_leaves(&leaves, ZeroLeafPolicy::Warn),
+        Ok(vec![1, 2])
+    );
+    assert_eq!(
+        check_zero_leaves(&leaves[..1], ZeroLeafPolicy::Reject),
+        Ok(vec![])
+    );
+    assert_eq!(
+        check_zero_leaves(&leaves, ZeroLeafPolicy::Reject),
+        Err(MerkleError::ZeroLeaf(1))
+    );
+
+    // empty and short leaves are not zero hashes
+    for policy in [ZeroLeafPolicy::Allow, ZeroLeafPolicy::Warn] {
+        assert_eq!(
+            check_zero_leaves(&[vec![]], policy),
+            Err(MerkleError::InvalidNode(0))
+        );
+        assert_eq!(
+            check_zero_leaves(&[vec![0u8; 31]], policy),
+            Err(MerkleError::InvalidNode(31))
+        );
+    }
+}
+
+#[test]
+fn padded_proofs_have_uniform_length() {
+    assert_eq!(PROOF_PADDING, keccak256(b"merklerust-proof-padding-v1"));
+    let leaves: Vec<Bytes> = (1u8..=5).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    assert_eq!(max_proof_len(tree.len()), 3);
+
+    for i in (tree.len() / 2)..tree.len() {
+        let proof = get_padded_proof(&tree, i);
+        assert_eq!(proof.len(), 3);
+        assert_eq!(proof_len(i), get_proof(&tree, i).len());
+        assert_eq!(process_padded_proof(&tree[i], &proof, node_hash), tree[0]);
+    }
+    // the shallow leaves really do get padding
+    assert!(get_proof(&tree, 4).len() < 3);
+
+    // a real all-zero sibling is not mistaken for padding
+    let zero_sibling = vec![vec![7u8; 32], vec![0u8; 32], vec![9u8; 32]];
+    let tree = make_merkle_tree_bytes(zero_sibling, node_hash);
+    for i in (tree.len() / 2)..tree.len() {
+        let proof = get_padded_proof(&tree, i);
+        assert_eq!(process_padded_proof(&tree[i], &proof, node_hash), tree[0]);
+    }
+
+    // a last sibling equal to the padding would be dropped, so it cannot be padded
+    let tree = make_merkle_tree_bytes(vec![PROOF_PADDING.to_vec(), vec![1u8; 32]], node_hash);
+    assert_eq!(
+        try_get_padded_proof(&tree, 1),
+        Err(MerkleError::PaddingCollision)
+    );
+    assert_eq!(
+        try_get_padded_proof(&tree, 2).unwrap(),
+        vec![tree[1].clone()]
+    );
+}
+
+#[test]
+fn malformed_padded_proofs_fail_without_panicking() {
+    let leaves: Vec<Bytes> = (1u8..=5).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let proof = get_padded_proof(&tree, 4);
+    assert!(verify_padded_proof(&tree[0], &tree[4], &proof, node_hash));
+    assert!(!verify_padded_proof(&tree[0], &tree[5], &proof, node_hash));
+
+    let mut short = proof.clone();
+    short[2] = vec![0u8; 3];
+    assert_eq!(
+        try_process_padded_proof(&tree[4], &short, node_hash),
+        Err(MerkleError::InvalidNode(3))
+    );
+    assert!(!verify_padded_proof(&tree[0], &tree[4], &short, node_hash));
+    assert!(!verify_padded_proof(
+        &tree[0], &[1u8; 31], &proof, node_hash
+    ));
+}
+
+#[test]
+fn try_variants_report_errors() {
+    assert_eq!(
+        try_make_merkle_tree_bytes(vec![], node_hash),
+        Err(MerkleError::EmptyTree)
+    );
+    assert_eq!(
+        try_make_merkle_tree_bytes(vec![vec![0u8; 31]], node_hash),
+        Err(MerkleError::InvalidNode(31))
+    );
+    assert_eq!(
+        try_make_merkle_tree_bytes(vec![vec![0u8; 32]; 2], |_, _| vec![0u8; 20]),
+        Err(MerkleError::InvalidNodeHash)
+    );
+
+    let leaves: Vec<Bytes> = (0u8..4).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = try_make_merkle_tree_bytes(leaves, node_hash).unwrap();
+    assert_eq!(try_get_proof(&tree, 0), Err(MerkleError::NotALeaf(0)));
+    assert_eq!(try_get_proof(&tree, 99), Err(MerkleError::NotALeaf(99)));
+    assert!(matches!(
+        try_get_multi_proof(&tree, vec![3, 3]),
+        Err(MerkleError::DuplicateIndex)
+    ));
+
+    let proof = try_get_proof(&tree, 6).unwrap();
+    assert_eq!(
+        try_process_proof(&tree[6], &proof, node_hash).as_ref(),
+        Ok(&tree[0])
+    );
+    assert_eq!(
+        try_process_proof(&[0u8; 3], &proof, node_hash),
+        Err(MerkleError::InvalidNode(3))
+    );
+
+    // only the proof path is checked
+    let mut bad = tree.clone();
+    bad[3] = vec![0u8; 5];
+    assert_eq!(try_get_proof(&bad, 6), Ok(proof.clone()));
+    bad[5] = vec![0u8; 7];
+    assert_eq!(try_get_proof(&bad, 6), Err(MerkleError::InvalidNode(7)));
+
+    let mp = try_get_multi_proof(&tree, vec![3, 4]).unwrap();
+    assert_eq!(
+        try_process_multi_proof(&mp, node_hash).as_ref(),
+        Ok(&tree[0])
+    );
+    let malformed = MultiProof::new(mp.leaves.clone(), vec![], mp.proof_flags.clone());
+    assert_eq!(
+        try_process_multi_proof(&malformed, node_hash),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    assert_eq!(
+        MerkleError::NotALeaf(0).to_string(),
+        "Expected leaf node at index 0"
+    );
+}
+
+#[test]
+fn verify_helpers_compare_roots() {
+    let leaves: Vec<Bytes> = (0u8..5).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves.clone(), node_hash);
+    let root = tree[0].clone();
+
+    let proof = get_proof(&tree, tree.len() - 1 - 2);
+    assert!(verify_proof(&root, &leaves[2], &proof, node_hash));
+    assert!(!verify_proof(&root, &leaves[3], &proof, node_hash));
+    assert!(!verify_proof(&root[..31], &leaves[2], &proof, node_hash));
+    assert!(!verify_proof(&root, &[0u8; 5], &proof, node_hash));
+
+    let mp = get_multi_proof(&tree, vec![5, 7]);
+    assert!(verify_multi_proof(&root, &mp, node_hash));
+    assert!(!verify_multi_proof(&leaves[0], &mp, node_hash));
+    let malformed = MultiProof::new(mp.leaves.clone(), vec![], mp.proof_flags.clone());
+    assert!(!verify_multi_proof(&root, &malformed, node_hash));
+}
+
+#[test]
+fn leaf_position_api() {
+    let leaves: Vec<Bytes> = (0u8..5).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves.clone(), node_hash);
+
+    assert_eq!(leaf_count(tree.len()), 5);
+    for (i, leaf) in leaves.iter().enumerate() {
+        let index = leaf_tree_index(tree.len(), i);
+        assert_eq!(&tree[index], leaf);
+        assert_eq!(leaf_position(tree.len(), index), i);
+        assert_eq!(get_proof_for_leaf(&tree, i), get_proof(&tree, index));
+    }
+    assert_eq!(
+        get_multi_proof_for_leaves(&tree, &[0, 4]),
+        get_multi_proof(&tree, vec![8, 4])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Leaf position 5 out of range for 5 leaves")]
+fn leaf_position_out_of_range() {
+    let leaves: Vec<Bytes> = (0u8..5).map(|i| keccak256(&[i]).to_vec()).collect();
+    get_proof_for_leaf(&make_merkle_tree_bytes(leaves, node_hash), 5);
+}
+
+#[test]
+fn leaf_position_api_errors() {
+    let leaves: Vec<Bytes> = (0u8..5).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let out_of_range = MerkleError::LeafOutOfRange {
+        position: 5,
+        leaves: 5,
+    };
+    assert_eq!(try_get_proof_for_leaf(&tree, 5), Err(out_of_range.clone()));
+    assert_eq!(
+        try_get_multi_proof_for_leaves(&tree, &[0, 5]),
+        Err(out_of_range)
+    );
+    assert_eq!(
+        try_get_multi_proof_for_leaves(&tree, &[1, 1]),
+        Err(MerkleError::DuplicateIndex)
+    );
+    assert_eq!(
+        try_leaf_position(tree.len(), 2),
+        Err(MerkleError::NotALeaf(2))
+    );
+    assert_eq!(try_leaf_position(tree.len(), 8), Ok(0));
+    assert_eq!(
+        try_get_proof_for_leaf(&tree, 1).unwrap(),
+        get_proof_for_leaf(&tree, 1)
+    );
+}
+
+fn positional_hash(a: &[u8], b: &[u8]) -> Bytes {
+    positional_node_hash(a, b).to_vec()
+}
+
+#[test]
+fn positional_tree_uses_leaf_order() {
+    let leaves: Vec<Bytes> = (0..4u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_positional_merkle_tree_bytes(leaves.clone(), positional_hash);
+    let h = |a: &[u8], b: &[u8]| keccak256(&[a, b].concat()).to_vec();
+    let expected = h(&h(&leaves[0], &leaves[1]), &h(&leaves[2], &leaves[3]));
+    assert_eq!(tree[0], expected);
+
+    let proof = get_positional_proof(&tree, 1);
+    assert_eq!(
+        proof,
+        vec![
+            (Side::Left, leaves[0].clone()),
+            (Side::Right, h(&leaves[2], &leaves[3])),
+        ]
+    );
+}
+
+#[test]
+fn positional_proofs_verify_for_every_leaf() {
+    for n in 1..=9u8 {
+        let leaves: Vec<Bytes> = (0..n).map(|i| keccak256(&[i]).to_vec()).collect();
+        let tree = make_positional_merkle_tree_bytes(leaves.clone(), positional_hash);
+        for (pos, leaf) in leaves.iter().enumerate() {
+            let proof = get_positional_proof(&tree, pos);
+            assert_eq!(
+                process_positional_proof(leaf, &proof, positional_hash),
+                tree[0]
+            );
+            assert!(verify_positional_proof(
+                &tree[0],
+                leaf,
+                &proof,
+                positional_hash
+            ));
+
+            if let Some((side, _)) = proof.first() {
+                // the same siblings on the wrong side give another root
+                let mut flipped = proof.clone();
+                flipped[0].0 = if *side == Side::Left {
+                    Side::Right
+                } else {
+                    Side::Left
+                };
+                assert!(!verify_positional_proof(
+                    &tree[0],
+                    leaf,
+                    &flipped,
+                    positional_hash
+                ));
+            }
+        }
+    }
+    assert!(!verify_positional_proof(
+        &[0u8; 32],
+        &[1u8; 31],
+        &[],
+        positional_hash
+    ));
+}
+
+/// xorshift64, so sampling is reproducible.
+fn test_rng(mut state: u64) -> impl FnMut() -> u64 {
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+#[test]
+fn sample_verify_spot_checks_internal_nodes() {
+    let leaves: Vec<Bytes> = (0..64u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    let mut tree = make_merkle_tree_bytes(leaves, node_hash);
+
+    let report = sample_verify(&tree, test_rng(1), 50, node_hash);
+    assert!(report.is_ok());
+    assert_eq!((report.checked, report.internal_nodes), (50, 63));
+    assert!(report.detection_probability(0.1) > 0.99);
+    let bound = report.max_corrupt_fraction(0.95);
+    assert!(bound > 0.05 && bound < 0.06, "{}", bound);
+
+    // corrupt every fourth node of the last internal level; each breaks itself and its parent
+    let corrupted: Vec<usize> = (31..63).step_by(4).collect();
+    for &i in corrupted.iter() {
+        tree[i][0] ^= 1;
+    }
+    let report = sample_verify(&tree, test_rng(1), 200, node_hash);
+    assert!(!report.is_ok());
+    for &i in report.failed.iter() {
+        assert!(
+            corrupted.iter().any(|&c| c == i || (c - 1) / 2 == i),
+            "{}",
+            i
+        );
+    }
+
+    let single = make_merkle_tree_bytes(vec![vec![7u8; 32]], node_hash);
+    let report = sample_verify(&single, test_rng(1), 10, node_hash);
+    assert_eq!(report.checked, 0);
+    assert_eq!(report.max_corrupt_fraction(0.95), 1.0);
+
+    // an even-length tree is corrupt, not out of bounds
+    let even = &tree[..4];
+    let report = sample_verify(even, test_rng(1), 50, node_hash);
+    assert!(report.failed.contains(&1));
+
+    let huge = SampleReport {
+        checked: usize::MAX,
+        internal_nodes: 1,
+        failed: Vec::new(),
+    };
+    assert_eq!(huge.detection_probability(0.5), 1.0);
+}
+
+#[test]
+fn context_bound_proofs_do_not_replay() {
+    let leaves: Vec<Bytes> = (0..5u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(bind_leaves_to_context(&leaves, b"session-1"), node_hash);
+
+    for (pos, leaf) in leaves.iter().enumerate() {
+        let proof = get_proof_for_leaf(&tree, pos);
+        assert!(verify_proof_in_context(
+            &tree[0],
+            leaf,
+            b"session-1",
+            &proof,
+            node_hash
+        ));
+        assert!(!verify_proof_in_context(
+            &tree[0],
+            leaf,
+            b"session-2",
+            &proof,
+            node_hash
+        ));
+        assert!(!verify_proof(&tree[0], leaf, &proof, node_hash));
+    }
+    // the length prefix keeps context and leaf bytes from sliding into each other
+    assert_ne!(
+        bind_leaves_to_context(&[vec![1, 2]], b"a"),
+        bind_leaves_to_context(&[vec![2]], b"a\x01")
+    );
+}
+
+#[test]
+fn hash_api_matches_bytes_api() {
+    use merklerust_core::hashes::standard_node_hash;
+    use merklerust_core::merkle::{
+        get_proof_hashes, is_valid_merkle_tree_hashes, make_merkle_tree_hashes,
+        process_proof_hashes, try_get_proof_hashes, verify_proof_hashes, Hash, MerkleError,
+    };
+
+    for n in 1..=9u8 {
+        let leaves: Vec<Hash> = (0..n).map(|i| keccak256(&[i])).collect();
+        let tree = make_merkle_tree_hashes(&leaves, standard_node_hash);
+        let bytes_tree =
+            make_merkle_tree_bytes(leaves.iter().map(|l| l.to_vec()).collect(), node_hash);
+        assert_eq!(
+            tree.iter().map(|h| h.to_vec()).collect::<Vec<_>>(),
+            bytes_tree
+        );
+        assert!(is_valid_merkle_tree_hashes(&tree, standard_node_hash));
+
+        for index in tree.len() - n as usize..tree.len() {
+            let proof = get_proof_hashes(&tree, index);
+            assert_eq!(
+                proof.iter().map(|h| h.to_vec()).collect::<Vec<_>>(),
+                get_proof(&bytes_tree, index)
+            );
+            assert_eq!(
+                process_proof_hashes(&tree[index], &proof, standard_node_hash),
+                tree[0]
+            );
+            assert!(verify_proof_hashes(
+                &tree[0],
+                &tree[index],
+                &proof,
+                standard_node_hash
+            ));
+            assert!(!verify_proof_hashes(
+                &tree[0],
+                &[7u8; 32],
+                &proof,
+                standard_node_hash
+            ));
+        }
+    }
+
+    let mut tree = make_merkle_tree_hashes(&[[1u8; 32], [2u8; 32]], standard_node_hash);
+    assert_eq!(
+        try_get_proof_hashes(&tree, 0),
+        Err(MerkleError::NotALeaf(0))
+    );
+    tree[1][0] ^= 1;
+    assert!(!is_valid_merkle_tree_hashes(&tree, standard_node_hash));
+}
+
+#[test]
+fn parallel_multi_proof_matches_sequential() {
+    for n in 1u8..70 {
+        let leaves: Vec<Bytes> = (0..n).map(|i| keccak256(&[i]).to_vec()).collect();
+        let tree = make_merkle_tree_bytes(leaves, node_hash);
+        let first = tree.len() / 2;
+        for step in [1, 2, 3, 7] {
+            let indices: Vec<usize> = (first..tree.len()).step_by(step).collect();
+            let expected = get_multi_proof(&tree, indices.clone());
+            assert_eq!(get_multi_proof_parallel(&tree, indices.clone()), expected);
+            for threads in [2, 3, 8, 64] {
+                assert_eq!(
+                    get_multi_proof_with_threads(&tree, indices.clone(), threads),
+                    expected
+                );
+            }
+        }
+    }
+
+    let leaves: Vec<Bytes> = (0u8..37).map(|i| keccak256(&[i]).to_vec()).collect();
+    let mut tree = make_merkle_tree_bytes(leaves, node_hash);
+    for indices in [vec![], vec![40], vec![36, 72, 71, 50, 40, 39]] {
+        assert_eq!(
+            get_multi_proof_parallel(&tree, indices.clone()),
+            get_multi_proof(&tree, indices)
+        );
+    }
+    assert_eq!(
+        try_get_multi_proof_parallel(&tree, vec![40, 40]),
+        Err(MerkleError::DuplicateIndex)
+    );
+    assert_eq!(
+        try_get_multi_proof_parallel(&tree, vec![3]),
+        Err(MerkleError::NotALeaf(3))
+    );
+    // only the nodes of the proof are validated
+    let expected = get_multi_proof(&tree, vec![40]);
+    tree[60].pop();
+    assert_eq!(try_get_multi_proof_parallel(&tree, vec![40]), Ok(expected));
+    tree[39].pop();
+    assert_eq!(
+        try_get_multi_proof_parallel(&tree, vec![40]),
+        Err(MerkleError::InvalidNode(31))
+    );
+}
+
+#[test]
+fn batch_proofs_match_single_proofs() {
+    let leaves: Vec<Bytes> = (0u8..37).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let indices: Vec<usize> = (tree.len() / 2..tree.len()).rev().chain([40, 40]).collect();
+
+    let expected: Vec<Vec<Bytes>> = indices.iter().map(|&i| get_proof(&tree, i)).collect();
+    assert_eq!(get_proofs(&tree, &indices), expected);
+    assert_eq!(get_proofs_parallel(&tree, &indices), expected);
+    assert!(get_proofs(&tree, &[]).is_empty());
+    assert!(get_proofs_parallel(&tree, &[]).is_empty());
+    assert!(matches!(
+        try_get_proofs(&tree, &[40, 0]),
+        Err(MerkleError::NotALeaf(0))
+    ));
+
+    // only the nodes on the proof paths are checked
+    let mut bad = tree.clone();
+    bad[72] = vec![0u8; 3];
+    assert_eq!(
+        try_get_proofs(&bad, &[40, 41]),
+        Ok(vec![get_proof(&tree, 40), get_proof(&tree, 41)])
+    );
+    bad[10] = vec![0u8; 4];
+    assert_eq!(
+        try_get_proofs(&bad, &[41, 40]),
+        Err(MerkleError::InvalidNode(4))
+    );
+}
+
+#[test]
+fn batch_verification_matches_single_checks() {
+    let leaves: Vec<Bytes> = (0u8..9).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves.clone(), node_hash);
+    let mut items: Vec<(Bytes, Vec<Bytes>)> = (0..9)
+        .map(|p| (leaves[p].clone(), get_proof_for_leaf(&tree, p)))
+        .collect();
+    assert!(verify_all_proofs(&tree[0], &items, node_hash));
+
+    items[4].0 = leaves[5].clone();
+    let expected: Vec<bool> = (0..9).map(|i| i != 4).collect();
+    assert_eq!(verify_proofs_batch(&tree[0], &items, node_hash), expected);
+    assert_eq!(
+        verify_proofs_batch_parallel(&tree[0], &items, node_hash),
+        expected
+    );
+    assert!(!verify_all_proofs(&tree[0], &items, node_hash));
+    assert!(
+        verify_proofs_batch_parallel::<Bytes, Vec<Bytes>, _>(&tree[0], &[], node_hash).is_empty()
+    );
+}
+
+#[test]
+fn level_builder_matches_pairwise_builder() {
+    for n in 1..40 {
+        let expected = make_merkle_tree_bytes(leaves(n), node_hash);
+        let built =
+            try_make_merkle_tree_by_levels(leaves(n), |pairs| Keccak256Hasher.hash_pairs(pairs));
+        assert_eq!(built, Ok(expected), "{} leaves", n);
+    }
+    assert_eq!(
+        try_make_merkle_tree_by_levels(vec![], |_| vec![]),
+        Err(MerkleError::EmptyTree)
+    );
+    assert_eq!(
+        try_make_merkle_tree_by_levels(vec![vec![0; 31]], |_| vec![]),
+        Err(MerkleError::InvalidNode(31))
+    );
+    assert_eq!(
+        try_make_merkle_tree_by_levels(leaves(3), |_| vec![]),
+        Err(MerkleError::InvalidNodeHash)
+    );
+}
+
+#[test]
+fn multi_proofs_of_even_length_trees_fail() {
+    let mut tree = make_merkle_tree_bytes(leaves(3), node_hash);
+    tree.pop();
+    assert_eq!(
+        try_get_multi_proof(&tree, vec![3]),
+        Err(MerkleError::InvalidTreeLength(4))
+    );
+    assert_eq!(
+        try_get_multi_proof_for_leaves(&tree, &[0]),
+        Err(MerkleError::InvalidTreeLength(4))
+    );
+    for threads in [1, 4] {
+        assert_eq!(
+            try_get_multi_proof_with_threads(&tree, vec![3], threads),
+            Err(MerkleError::InvalidTreeLength(4))
+        );
+    }
+    assert_eq!(
+        try_get_multi_proof_parallel(&tree, vec![2, 3]),
+        Err(MerkleError::InvalidTreeLength(4))
+    );
+    // no leaves to prove still needs a root
+    assert_eq!(
+        try_get_multi_proof(&[], vec![]),
+        Err(MerkleError::EmptyTree)
+    );
+}
@@ -0,0 +1,79 @@
+use merklerust_core::encoder::{RawEncoder, TypedMerkleTree};
+use merklerust_core::hashes::{Keccak256Hasher, Sha256Hasher};
+use merklerust_core::migration::{rehash_tree, verify_transition, TransitionProof};
+use merklerust_core::standard::TreeOptions;
+
+fn values() -> Vec<Vec<u8>> {
+    (0u8..5).map(|i| vec![i; 7]).collect()
+}
+
+#[test]
+fn rehashed_tree_keeps_values_and_options() {
+    let options = TreeOptions { sort_leaves: false };
+    let old = TypedMerkleTree::of_with_options(values(), RawEncoder, Sha256Hasher, options);
+    let new = rehash_tree(&old, Keccak256Hasher);
+
+    assert_eq!(new.values(), old.values());
+    assert_eq!(new.options(), options);
+    assert_ne!(new.root(), old.root());
+    assert_eq!(
+        new.root(),
+        TypedMerkleTree::of_with_options(values(), RawEncoder, Keccak256Hasher, options).root()
+    );
+}
+
+#[test]
+fn transition_proof_covers_both_roots() {
+    let old = TypedMerkleTree::of(values(), RawEncoder, Sha256Hasher);
+    let new = rehash_tree(&old, Keccak256Hasher);
+    let (old_root, new_root) = (old.root(), new.root());
+
+    for (i, value) in values().iter().enumerate() {
+        let proof = TransitionProof::new(&old, &new, i);
+        assert!(proof.verify(
+            old_root,
+            new_root,
+            &RawEncoder,
+            &Sha256Hasher,
+            &Keccak256Hasher,
+            value
+        ));
+        assert!(!proof.verify(
+            new_root,
+            old_root,
+            &RawEncoder,
+            &Sha256Hasher,
+            &Keccak256Hasher,
+            value
+        ));
+    }
+    let proof = TransitionProof::new(&old, &new, 0);
+    assert!(!proof.verify(
+        old_root,
+        new_root,
+        &RawEncoder,
+        &Sha256Hasher,
+        &Keccak256Hasher,
+        &values()[1]
+    ));
+
+    let options = TreeOptions::default();
+    assert!(verify_transition(
+        &values(),
+        &RawEncoder,
+        options,
+        Sha256Hasher,
+        old_root,
+        Keccak256Hasher,
+        new_root
+    ));
+    assert!(!verify_transition(
+        &values()[1..],
+        &RawEncoder,
+        options,
+        Sha256Hasher,
+        old_root,
+        Keccak256Hasher,
+        new_root
+    ));
+}
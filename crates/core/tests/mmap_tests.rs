@@ -0,0 +1,59 @@
+#![cfg(feature = "mmap")]
+
+mod common;
+
+use merklerust_core::flat::FlatMerkleTree;
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::{
+    get_multi_proof, get_multi_proof_for_leaves, get_proof, make_merkle_tree_bytes, Bytes,
+    MerkleError,
+};
+use merklerust_core::mmap::MappedMerkleTree;
+
+use common::node_hash;
+
+#[test]
+fn serves_proofs_from_mapped_file() {
+    let leaves: Vec<Bytes> = (0u8..11).map(|i| keccak256(&[i]).to_vec()).collect();
+    let tree = make_merkle_tree_bytes(leaves.clone(), node_hash);
+    let path = std::env::temp_dir().join(format!("merklerust-mmap-{}.bin", std::process::id()));
+    std::fs::write(&path, FlatMerkleTree::from_tree(&tree).unwrap().as_bytes()).unwrap();
+
+    let mapped = MappedMerkleTree::open_mmap(&path).unwrap();
+    assert_eq!(&mapped.root()[..], &tree[0][..]);
+    assert_eq!(mapped.len(), tree.len());
+    assert_eq!(mapped.leaf_count(), 11);
+    assert_eq!(&mapped.leaf(3)[..], &leaves[3][..]);
+    for i in 10..tree.len() {
+        assert_eq!(mapped.get_proof(i), get_proof(&tree, i));
+    }
+    assert!(mapped.try_get_proof(0).is_err());
+    assert_eq!(
+        mapped.get_multi_proof(vec![10, 14, 20]),
+        get_multi_proof(&tree, vec![10, 14, 20])
+    );
+    assert_eq!(
+        mapped.get_multi_proof_for_leaves(&[0, 5, 10]),
+        get_multi_proof_for_leaves(&tree, &[0, 5, 10])
+    );
+    assert_eq!(
+        mapped.get_multi_proof(vec![]),
+        get_multi_proof(&tree, vec![])
+    );
+    assert_eq!(
+        mapped.try_get_multi_proof(vec![3]),
+        Err(MerkleError::NotALeaf(3))
+    );
+    assert_eq!(
+        mapped.try_get_multi_proof(vec![12, 12]),
+        Err(MerkleError::DuplicateIndex)
+    );
+    assert!(mapped.is_valid(node_hash));
+
+    for bytes in [&[0u8; 33][..], &[0u8; 128], &[]] {
+        std::fs::write(&path, bytes).unwrap();
+        let err = MappedMerkleTree::open_mmap(&path).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
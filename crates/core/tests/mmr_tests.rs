@@ -0,0 +1,197 @@
+mod common;
+
+use merklerust_core::consistency::log_root;
+use merklerust_core::hashes::{keccak256, Hasher, PositionalKeccak256Hasher, Rfc6962Hasher};
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::mmr::{
+    bag_peaks, leaf_index_to_pos, mmr_size, peak_positions, Mmr, MmrProof, MAX_LEAVES,
+};
+
+use common::leaves;
+
+#[test]
+fn positions_and_sizes() {
+    assert_eq!(
+        (0..8).map(leaf_index_to_pos).collect::<Vec<_>>(),
+        vec![0, 1, 3, 4, 7, 8, 10, 11]
+    );
+    assert_eq!(mmr_size(7), 11);
+    assert_eq!(peak_positions(11), Some(vec![6, 9, 10]));
+    assert_eq!(peak_positions(0), Some(vec![]));
+    // a dangling leaf without its parent is not an MMR
+    assert_eq!(peak_positions(5), None);
+}
+
+#[test]
+fn root_matches_log_root() {
+    let all = leaves(20);
+    let mut mmr = Mmr::new(Rfc6962Hasher);
+    assert_eq!(mmr.root(), None);
+    for (i, leaf) in all.iter().enumerate() {
+        assert_eq!(mmr.push(leaf), i as u64);
+        assert_eq!(mmr.size(), mmr_size(i as u64 + 1));
+        assert_eq!(mmr.peaks().len(), (i + 1).count_ones() as usize);
+        assert_eq!(
+            mmr.root().unwrap().to_vec(),
+            log_root(&all[..=i], &Rfc6962Hasher)
+        );
+    }
+}
+
+#[test]
+fn bag_peaks_folds_right_to_left() {
+    let h = PositionalKeccak256Hasher;
+    let [a, b, c] = [0u8, 1, 2].map(|i| keccak256(&[i]));
+    assert_eq!(bag_peaks(&[], &h), None);
+    assert_eq!(bag_peaks(&[a], &h), Some(a));
+    assert_eq!(
+        bag_peaks(&[a, b, c], &h),
+        Some(h.hash_node(&a, &h.hash_node(&b, &c)))
+    );
+}
+
+#[test]
+fn proofs_verify_for_every_leaf() {
+    let h = PositionalKeccak256Hasher;
+    let all = leaves(13);
+    let mut mmr = Mmr::new(h);
+    for leaf in &all {
+        mmr.push(leaf);
+        let root = mmr.root().unwrap();
+        for i in 0..mmr.leaf_count() {
+            let proof = mmr.proof(i);
+            assert!(proof.verify(&root, &all[i as usize], &h));
+            let other = &all[(i as usize + 1) % all.len()];
+            assert!(!proof.verify(&root, other, &h) || other == &all[i as usize]);
+        }
+    }
+}
+
+#[test]
+fn tampered_proofs_fail() {
+    let h = PositionalKeccak256Hasher;
+    let all = leaves(11);
+    let mut mmr = Mmr::new(h);
+    for leaf in &all {
+        mmr.push(leaf);
+    }
+    let root = mmr.root().unwrap();
+    let proof = mmr.proof(4);
+
+    let mut p = proof.clone();
+    p.leaf_index = 5;
+    assert!(!p.verify(&root, &all[4], &h));
+
+    let mut p = proof.clone();
+    p.siblings[0][0] ^= 1;
+    assert!(!p.verify(&root, &all[4], &h));
+
+    let mut p = proof.clone();
+    p.peaks.pop();
+    assert!(!p.verify(&root, &all[4], &h));
+
+    let mut p = proof.clone();
+    p.mmr_size += 1;
+    assert!(!p.verify(&root, &all[4], &h));
+
+    let mut p = proof.clone();
+    p.leaf_index = u64::MAX;
+    assert!(!p.verify(&root, &all[4], &h));
+
+    // sizes whose peaks or leaf positions do not fit in a u64 fail instead of overflowing
+    for size in [u64::MAX, 1 << 63, mmr_size(MAX_LEAVES) + 1] {
+        for leaf_index in [4, MAX_LEAVES, size - 1] {
+            let mut p = proof.clone();
+            (p.mmr_size, p.leaf_index) = (size, leaf_index);
+            assert!(!p.verify(&root, &all[4], &h));
+        }
+    }
+    assert_eq!(peak_positions(u64::MAX).map(|p| p.len()), Some(1));
+}
+
+#[test]
+fn prune_keeps_later_proofs_and_appends() {
+    let h = PositionalKeccak256Hasher;
+    let all = leaves(30);
+    let mut full = Mmr::new(h);
+    let mut pruned = Mmr::new(h);
+    for leaf in &all[..20] {
+        full.push(leaf);
+        pruned.push(leaf);
+    }
+    pruned.prune(12);
+    assert!(pruned.retained_nodes() < full.retained_nodes());
+    assert_eq!(pruned.root(), full.root());
+
+    for leaf in &all[20..] {
+        full.push(leaf);
+        pruned.push(leaf);
+    }
+    let root = full.root().unwrap();
+    assert_eq!(pruned.root().unwrap(), root);
+    for i in 12..30 {
+        assert_eq!(pruned.proof(i), full.proof(i));
+        assert!(pruned.proof(i).verify(&root, &all[i as usize], &h));
+    }
+
+    // pruning everything keeps only the peaks
+    pruned.prune(30);
+    assert_eq!(pruned.retained_nodes(), pruned.peaks().len());
+    assert_eq!(pruned.root().unwrap(), root);
+}
+
+#[test]
+#[should_panic(expected = "Leaf 3 has been pruned")]
+fn proof_of_pruned_leaf_panics() {
+    let mut mmr = Mmr::new(PositionalKeccak256Hasher);
+    for leaf in leaves(8) {
+        mmr.push(&leaf);
+    }
+    mmr.prune(4);
+    mmr.proof(3);
+}
+
+#[test]
+#[should_panic(expected = "Leaf index 8 out of range for 8 leaves")]
+fn proof_out_of_range_panics() {
+    let mut mmr = Mmr::new(PositionalKeccak256Hasher);
+    for leaf in leaves(8) {
+        mmr.push(&leaf);
+    }
+    mmr.proof(8);
+}
+
+#[test]
+fn proofs_round_trip_through_bytes() {
+    let h = PositionalKeccak256Hasher;
+    let all = leaves(11);
+    let mut mmr = Mmr::new(h);
+    for leaf in &all {
+        mmr.push(leaf);
+    }
+    let proof = mmr.proof(4);
+    let bytes = proof.to_bytes();
+    assert_eq!(
+        bytes.len(),
+        20 + 32 * (proof.siblings.len() + proof.peaks.len())
+    );
+    let read = MmrProof::from_bytes(&bytes).unwrap();
+    assert!(read.verify(&mmr.root().unwrap(), &all[4], &h));
+    assert_eq!(read, proof);
+
+    assert_eq!(
+        MmrProof::from_bytes(&bytes[..19]),
+        Err(MerkleError::InvalidNode(19))
+    );
+    assert_eq!(
+        MmrProof::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(MerkleError::InvalidNode(31))
+    );
+    // more siblings than hashes
+    let mut short = bytes[..20].to_vec();
+    short[19] = 1;
+    assert_eq!(
+        MmrProof::from_bytes(&short),
+        Err(MerkleError::InvalidNode(20))
+    );
+}
@@ -0,0 +1,118 @@
+use merklerust_core::consistency::{get_consistency_proof, log_root};
+use merklerust_core::hashes::{Hasher, Rfc6962Hasher};
+use merklerust_core::merkle::Bytes;
+use merklerust_core::monitor::{ForkProof, HeadStatus, LogMonitor, MonitorAlert, TreeHead};
+
+fn entries(n: usize) -> Vec<Bytes> {
+    (0..n)
+        .map(|i| Rfc6962Hasher.hash_leaf(&i.to_be_bytes()).to_vec())
+        .collect()
+}
+
+fn head(leaves: &[Bytes]) -> TreeHead {
+    TreeHead::new(leaves.len(), log_root(leaves, &Rfc6962Hasher))
+}
+
+#[test]
+fn follows_a_growing_log() {
+    let log = entries(20);
+    let mut monitor = LogMonitor::new(Rfc6962Hasher, TreeHead::new(0, vec![0u8; 32]));
+    assert_eq!(
+        monitor.observe(head(&log[..3]), &[]),
+        Ok(HeadStatus::Advanced)
+    );
+    for (old, new) in [(3, 7), (7, 8), (8, 20)] {
+        let proof = get_consistency_proof(&log[..new], old, &Rfc6962Hasher);
+        assert_eq!(
+            monitor.observe(head(&log[..new]), &proof),
+            Ok(HeadStatus::Advanced)
+        );
+    }
+    assert_eq!(monitor.verified(), &head(&log));
+    assert_eq!(monitor.observe(head(&log), &[]), Ok(HeadStatus::Unchanged));
+
+    // an older head is accepted with a proof from it to the verified head
+    let proof = get_consistency_proof(&log, 5, &Rfc6962Hasher);
+    assert_eq!(
+        monitor.observe(head(&log[..5]), &proof),
+        Ok(HeadStatus::Stale)
+    );
+    assert_eq!(monitor.verified(), &head(&log));
+}
+
+#[test]
+fn raises_alerts_on_rewritten_history() {
+    let log = entries(10);
+    let mut monitor = LogMonitor::new(Rfc6962Hasher, head(&log[..6]));
+
+    let mut forked = log.clone();
+    forked[2] = Rfc6962Hasher.hash_leaf(b"rewritten").to_vec();
+    assert_eq!(
+        monitor.observe(head(&forked[..6]), &[]),
+        Err(MonitorAlert::Fork(
+            ForkProof::conflicting(head(&log[..6]), head(&forked[..6])).unwrap()
+        ))
+    );
+
+    let proof = get_consistency_proof(&forked, 6, &Rfc6962Hasher);
+    assert_eq!(
+        monitor.observe(head(&forked), &proof),
+        Err(MonitorAlert::Inconsistent {
+            verified: head(&log[..6]),
+            head: head(&forked)
+        })
+    );
+    // a wrong or missing proof is not accepted either
+    assert!(monitor.observe(head(&log), &[]).is_err());
+    let stale = get_consistency_proof(&forked[..6], 4, &Rfc6962Hasher);
+    assert!(monitor.observe(head(&forked[..4]), &stale).is_err());
+    assert_eq!(monitor.verified(), &head(&log[..6]));
+
+    let proof = get_consistency_proof(&log, 6, &Rfc6962Hasher);
+    assert_eq!(
+        monitor.observe(head(&log), &proof),
+        Ok(HeadStatus::Advanced)
+    );
+}
+
+#[test]
+fn fork_proofs_verify_on_their_own() {
+    let log = entries(10);
+    let mut forked = log.clone();
+    forked[2] = Rfc6962Hasher.hash_leaf(b"rewritten").to_vec();
+
+    // the verified head and a larger head whose prefix of the same size differs
+    let proof = get_consistency_proof(&forked, 6, &Rfc6962Hasher);
+    let prefix_root = log_root(&forked[..6], &Rfc6962Hasher);
+    let fork = ForkProof::new(
+        head(&log[..6]),
+        head(&forked),
+        prefix_root.clone(),
+        proof.clone(),
+        &Rfc6962Hasher,
+    )
+    .unwrap();
+    assert!(fork.verify(&Rfc6962Hasher));
+
+    // heads of one history, or a proof that does not link the prefix, are no fork
+    let honest = log_root(&log[..6], &Rfc6962Hasher);
+    let proof_of_log = get_consistency_proof(&log, 6, &Rfc6962Hasher);
+    assert!(ForkProof::new(
+        head(&log[..6]),
+        head(&log),
+        honest,
+        proof_of_log,
+        &Rfc6962Hasher
+    )
+    .is_none());
+    let mut bad = fork.clone();
+    bad.proof[0][0] ^= 1;
+    assert!(!bad.verify(&Rfc6962Hasher));
+    assert!(ForkProof::conflicting(head(&log[..6]), head(&log[..6])).is_none());
+    assert!(ForkProof::conflicting(head(&log[..6]), head(&forked)).is_none());
+    assert!(ForkProof::conflicting(
+        TreeHead::new(0, vec![1u8; 32]),
+        TreeHead::new(0, vec![2u8; 32])
+    )
+    .is_none());
+}
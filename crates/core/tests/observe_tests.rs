@@ -0,0 +1,70 @@
+mod common;
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use merklerust_core::hashes::Keccak256Hasher;
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::observe::{ObservedTree, UpdateEvent};
+use merklerust_core::tree::MerkleTree;
+
+use common::leaves;
+
+#[test]
+fn hooks_see_every_change_with_the_new_root() {
+    let mut tree = ObservedTree::new(MerkleTree::new(leaves(5), Keccak256Hasher));
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let sink = seen.clone();
+    tree.on_update(
+        move |position: usize, old: &[u8], new: &[u8], root: &[u8]| {
+            sink.lock()
+                .unwrap()
+                .push((position, old.to_vec(), new.to_vec(), root.to_vec()));
+        },
+    );
+    let (sender, receiver) = mpsc::channel::<UpdateEvent>();
+    tree.on_update(sender);
+
+    tree.update_leaf(2, &[7u8; 32]);
+    let root = tree.tree().root().to_vec();
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![(2, leaves(5)[2].clone(), vec![7u8; 32], root.clone())]
+    );
+    assert_eq!(
+        receiver.try_recv().unwrap(),
+        UpdateEvent {
+            position: 2,
+            old: leaves(5)[2].clone(),
+            new: vec![7u8; 32],
+            new_root: root,
+        }
+    );
+
+    // a repeated position reports the value written earlier in the batch
+    tree.batch_update(&[(0, [8u8; 32]), (0, [9u8; 32])]);
+    let events: Vec<UpdateEvent> = receiver.try_iter().collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].old, leaves(5)[0]);
+    assert_eq!(events[1].old, vec![8u8; 32]);
+    assert!(events.iter().all(|e| e.new_root == tree.tree().root()));
+}
+
+#[test]
+fn failed_updates_are_not_reported() {
+    let mut tree = ObservedTree::new(MerkleTree::new(leaves(3), Keccak256Hasher));
+    let (sender, receiver) = mpsc::channel();
+    tree.on_update(sender);
+    assert_eq!(
+        tree.try_batch_update(&[(0, [1u8; 32]), (3, [2u8; 32])]),
+        Err(MerkleError::LeafOutOfRange {
+            position: 3,
+            leaves: 3
+        })
+    );
+    assert!(receiver.try_recv().is_err());
+    assert_eq!(
+        tree.into_inner(),
+        MerkleTree::new(leaves(3), Keccak256Hasher)
+    );
+}
@@ -0,0 +1,22 @@
+use merklerust_core::merkle::{check_tree_size, TreeLimits};
+use merklerust_core::profile::ResourceProfile;
+
+#[test]
+fn profiles_round_trip_and_differ() {
+    for profile in [ResourceProfile::Standard, ResourceProfile::LowMemory] {
+        assert_eq!(ResourceProfile::from_name(profile.name()), Some(profile));
+    }
+    assert_eq!(ResourceProfile::from_name("tiny"), None);
+    assert_eq!(ResourceProfile::default(), ResourceProfile::Standard);
+    assert_eq!(
+        ResourceProfile::Standard.tree_limits(),
+        TreeLimits::default()
+    );
+
+    let low = ResourceProfile::LowMemory;
+    assert_eq!(low.threads(), 1);
+    assert!(low.build_buffer() < ResourceProfile::Standard.build_buffer());
+    assert!(check_tree_size(1 << 20, &low.tree_limits()).is_ok());
+    assert!(check_tree_size(1 << 24, &low.tree_limits()).is_err());
+    assert!(check_tree_size(1 << 24, &ResourceProfile::Standard.tree_limits()).is_ok());
+}
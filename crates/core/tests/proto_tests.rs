@@ -0,0 +1,75 @@
+#![cfg(feature = "protobuf")]
+
+mod common;
+
+use merklerust_core::hashes::{keccak256, PositionalKeccak256Hasher};
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::merkle::{
+    get_multi_proof, make_merkle_tree_bytes, process_multi_proof, Bytes,
+};
+use merklerust_core::proto;
+use merklerust_core::smt::{self, KeyPath, SparseMerkleTree};
+use prost::Message;
+
+use common::node_hash;
+
+#[test]
+fn multi_proof_round_trip() {
+    let leaves: Vec<Bytes> = (0u8..4).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let mp = get_multi_proof(&tree, vec![3, 5]);
+
+    let encoded = proto::MultiProof::from(&mp).encode_to_vec();
+    let decoded: merklerust_core::merkle::MultiProof =
+        proto::MultiProof::decode(encoded.as_slice())
+            .unwrap()
+            .into();
+    assert_eq!(process_multi_proof(&decoded, node_hash), tree[0]);
+
+    let meta = proto::TreeMetadata::from_tree(&tree);
+    assert_eq!(meta.leaf_count, 4);
+    assert_eq!(meta.node_count, 7);
+    let decoded_meta = proto::TreeMetadata::decode(meta.encode_to_vec().as_slice()).unwrap();
+    assert_eq!(decoded_meta, meta);
+
+    let wire = proto::Tree::from(&tree[..]).encode_to_vec();
+    assert_eq!(proto::Tree::decode(wire.as_slice()).unwrap().nodes, tree);
+}
+
+#[test]
+fn smt_proofs_round_trip() {
+    let mut tree = SparseMerkleTree::with_key_path(PositionalKeccak256Hasher, KeyPath::Sha256);
+    for i in 0u8..5 {
+        tree.insert([i], vec![i]);
+    }
+    let proof = tree.prove(&[3]);
+    let wire = proto::SmtProof::from(&proof).encode_to_vec();
+    let decoded = proto::SmtProof::decode(wire.as_slice()).unwrap();
+    assert_eq!(decoded.key_path, 1);
+    assert_eq!(smt::SmtProof::try_from(decoded.clone()), Ok(proof));
+
+    let multi = tree.prove_many(&[[1], [3], [9]]);
+    let wire = proto::SmtMultiProof::from(&multi).encode_to_vec();
+    let decoded = proto::SmtMultiProof::decode(wire.as_slice()).unwrap();
+    assert_eq!(smt::SmtMultiProof::try_from(decoded), Ok(multi));
+
+    // malformed messages are rejected when converted
+    let mut bad = proto::SmtProof::from(&tree.prove(&[3]));
+    bad.bitmap.pop();
+    assert_eq!(
+        smt::SmtProof::try_from(bad),
+        Err(MerkleError::InvalidNode(31))
+    );
+    let mut bad = proto::SmtProof::from(&tree.prove(&[3]));
+    bad.key_path = 256;
+    assert!(matches!(
+        smt::SmtProof::try_from(bad),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+    let mut bad = proto::SmtMultiProof::from(&tree.prove_many(&[keccak256(&[1])]));
+    bad.siblings.push(vec![0; 31]);
+    assert_eq!(
+        smt::SmtMultiProof::try_from(bad),
+        Err(MerkleError::InvalidNode(31))
+    );
+}
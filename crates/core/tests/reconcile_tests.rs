@@ -0,0 +1,160 @@
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::Hash;
+use merklerust_core::reconcile::{ReconcileError, ReconcileMessage, SetReconciler};
+
+fn key(i: u32) -> Hash {
+    keccak256(&i.to_be_bytes())
+}
+
+/// Run the protocol to quiescence and return the keys each side found missing.
+fn reconcile(a: &SetReconciler, b: &SetReconciler) -> (BTreeSet<Hash>, BTreeSet<Hash>, usize) {
+    let mut a_missing = BTreeSet::new();
+    let mut b_missing = BTreeSet::new();
+    // (message, addressed to a?)
+    let mut queue: VecDeque<(ReconcileMessage, bool)> = VecDeque::new();
+    queue.push_back((a.start(), false));
+    let mut sent = 1;
+    while let Some((msg, to_a)) = queue.pop_front() {
+        let (receiver, missing) = if to_a {
+            (a, &mut a_missing)
+        } else {
+            (b, &mut b_missing)
+        };
+        let step = receiver.respond(&msg).unwrap();
+        missing.extend(step.missing);
+        for m in step.messages {
+            sent += 1;
+            queue.push_back((m, !to_a));
+        }
+    }
+    (a_missing, b_missing, sent)
+}
+
+#[test]
+fn equal_sets_stop_after_root() {
+    let mut a = SetReconciler::new(8);
+    let mut b = SetReconciler::new(8);
+    for i in 0..100 {
+        a.insert(key(i));
+        b.insert(key(i));
+    }
+    assert_eq!(a.root(), b.root());
+    let (a_missing, b_missing, sent) = reconcile(&a, &b);
+    assert!(a_missing.is_empty() && b_missing.is_empty());
+    assert_eq!(sent, 1);
+}
+
+#[test]
+fn finds_missing_keys_on_both_sides() {
+    let mut a = SetReconciler::new(10);
+    let mut b = SetReconciler::new(10);
+    for i in 0..1000 {
+        a.insert(key(i));
+        b.insert(key(i));
+    }
+    let only_a: BTreeSet<Hash> = [1000, 1001, 1002].iter().map(|&i| key(i)).collect();
+    let only_b: BTreeSet<Hash> = [2000, 2001].iter().map(|&i| key(i)).collect();
+    only_a.iter().for_each(|k| {
+        a.insert(*k);
+    });
+    only_b.iter().for_each(|k| {
+        b.insert(*k);
+    });
+    b.remove(&key(5));
+
+    let (a_missing, b_missing, _) = reconcile(&a, &b);
+    assert_eq!(a_missing, only_b);
+    let mut expected_b = only_a.clone();
+    expected_b.insert(key(5));
+    assert_eq!(b_missing, expected_b);
+}
+
+#[test]
+fn empty_replica_learns_everything() {
+    let mut a = SetReconciler::new(4);
+    let b = SetReconciler::new(4);
+    for i in 0..50 {
+        a.insert(key(i));
+    }
+    let (a_missing, b_missing, _) = reconcile(&a, &b);
+    assert!(a_missing.is_empty());
+    assert_eq!(b_missing.len(), 50);
+    assert!(b.is_empty());
+}
+
+#[test]
+fn rejects_out_of_range_peer_messages() {
+    let r = SetReconciler::new(4);
+    let digests = |level, prefix| ReconcileMessage::Digests {
+        level,
+        entries: vec![(prefix, [1u8; 32])],
+    };
+    assert_eq!(
+        r.respond(&digests(5, 0)),
+        Err(ReconcileError::LevelOutOfRange { level: 5, depth: 4 })
+    );
+    assert_eq!(
+        r.respond(&digests(2, 4)),
+        Err(ReconcileError::PrefixOutOfRange {
+            prefix: 4,
+            level: 2
+        })
+    );
+    assert!(r.respond(&digests(2, 3)).is_ok());
+
+    let keys = |prefix| ReconcileMessage::Keys {
+        prefix,
+        keys: vec![key(0)],
+        reply: true,
+    };
+    assert_eq!(
+        r.respond(&keys(16)),
+        Err(ReconcileError::PrefixOutOfRange {
+            prefix: 16,
+            level: 4
+        })
+    );
+    assert_eq!(r.respond(&keys(15)).unwrap().messages.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "Expected depth of at most")]
+fn rejects_excessive_depth() {
+    SetReconciler::new(25);
+}
+
+#[test]
+fn incremental_digests_match_rebuild() {
+    let mut a = SetReconciler::with_buckets(64);
+    assert_eq!(a.bucket_count(), 64);
+    assert_eq!(a.depth(), 6);
+    for i in 0..500 {
+        a.insert(key(i));
+    }
+    for i in (0..500).step_by(3) {
+        a.remove(&key(i));
+    }
+    assert!(!a.insert(key(1)));
+    assert!(!a.remove(&key(0)));
+
+    let mut rebuilt = SetReconciler::with_buckets(64);
+    for i in (0..500).filter(|i| i % 3 != 0) {
+        rebuilt.insert(key(i));
+    }
+    assert_eq!(a.root(), rebuilt.root());
+    assert_eq!(a.len(), rebuilt.len());
+
+    for i in 0..500 {
+        a.remove(&key(i));
+    }
+    assert_eq!(a.root(), [0u8; 32]);
+}
+
+#[test]
+#[should_panic(expected = "Expected a power-of-two bucket count")]
+fn rejects_non_power_of_two_buckets() {
+    SetReconciler::with_buckets(100);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::{
+    get_multi_proof_for_leaves, make_merkle_tree_bytes, process_multi_proof, Bytes,
+};
+use merklerust_core::rle::{compress_runs, expand_runs, expanded_len, CompressedMultiProof, Run};
+
+use common::node_hash;
+
+#[test]
+fn runs_round_trip() {
+    let (a, b) = (vec![1u8], vec![2u8]);
+    let nodes = vec![a.clone(), a.clone(), b.clone(), a.clone()];
+    let runs = compress_runs(&nodes);
+    assert_eq!(
+        runs,
+        vec![
+            Run {
+                value: a.clone(),
+                count: 2
+            },
+            Run { value: b, count: 1 },
+            Run { value: a, count: 1 },
+        ]
+    );
+    assert_eq!(expanded_len(&runs), Some(4));
+    assert_eq!(expand_runs(&runs), nodes);
+    assert!(compress_runs::<Bytes>(&[]).is_empty());
+}
+
+#[test]
+fn padded_multiproof_compresses() {
+    let mut leaves: Vec<Bytes> = (0..3u8).map(|i| keccak256(&[i]).to_vec()).collect();
+    leaves.resize(64, vec![0u8; 32]);
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let positions: Vec<usize> = (0..40).collect();
+    let mp = get_multi_proof_for_leaves(&tree, &positions);
+
+    let compressed = CompressedMultiProof::compress(&mp);
+    assert!(compressed.stored_nodes() * 4 < mp.leaves.len() + mp.proof.len());
+    let expanded = compressed.expand().unwrap();
+    assert_eq!(expanded, mp);
+    assert_eq!(process_multi_proof(&expanded, node_hash), tree[0]);
+}
+
+#[test]
+fn expand_rejects_runs_larger_than_flags_allow() {
+    let mut compressed = CompressedMultiProof {
+        leaves: vec![Run {
+            value: vec![0u8; 32],
+            count: 3,
+        }],
+        proof: Vec::new(),
+        proof_flags: vec![true, true],
+    };
+    assert!(compressed.expand().is_some());
+    compressed.leaves[0].count = usize::MAX;
+    assert_eq!(compressed.expand(), None);
+}
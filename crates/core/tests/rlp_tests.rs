@@ -0,0 +1,69 @@
+#![cfg(feature = "rlp")]
+
+mod common;
+
+use merklerust_core::merkle::{get_multi_proof, get_proof, make_merkle_tree_bytes, Bytes};
+use merklerust_core::rlp::{
+    decode_multi_proof, decode_proof, encode_multi_proof, encode_proof, RlpError,
+};
+
+use common::node_hash;
+
+#[test]
+fn proof_round_trip() {
+    let leaves: Vec<Bytes> = (0u8..5).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let proof = get_proof(&tree, 5);
+
+    let encoded = encode_proof(&proof);
+    // long list of 32-byte strings: each item is 0xa0 followed by 32 bytes
+    assert_eq!(proof.len(), 2);
+    assert_eq!(&encoded[..3], &[0xf8, 66, 0xa0]);
+    assert_eq!(decode_proof(&encoded).unwrap(), proof);
+}
+
+#[test]
+fn multi_proof_round_trip() {
+    let leaves: Vec<Bytes> = (0u8..6).map(|i| vec![i; 32]).collect();
+    let tree = make_merkle_tree_bytes(leaves, node_hash);
+    let mp = get_multi_proof(&tree, vec![5, 6, 10]);
+
+    let decoded = decode_multi_proof(&encode_multi_proof(&mp)).unwrap();
+    assert_eq!(decoded.leaves, mp.leaves);
+    assert_eq!(decoded.proof, mp.proof);
+    assert_eq!(decoded.proof_flags, mp.proof_flags);
+}
+
+#[test]
+fn rejects_malformed_input() {
+    assert_eq!(decode_proof(&[]), Err(RlpError::UnexpectedEnd));
+    assert_eq!(decode_proof(&[0x81, 0x05]), Err(RlpError::NonCanonical));
+    assert_eq!(decode_proof(&[0xc0, 0x00]), Err(RlpError::TrailingBytes));
+    assert_eq!(decode_proof(&[0x80]), Err(RlpError::ExpectedList));
+    assert_eq!(decode_proof(&[0xc2, 0x81]), Err(RlpError::UnexpectedEnd));
+}
+
+#[test]
+fn rejects_deep_nesting_without_recursing() {
+    // ~400 KB of 100k canonically nested lists, headers built from the inside out
+    let mut headers = Vec::new();
+    let mut len = 1;
+    for _ in 0..100_000 {
+        let header = if len < 56 {
+            vec![0xc0 + len as u8]
+        } else {
+            let be = (len as u32).to_be_bytes();
+            let skip = be.iter().position(|&b| b != 0).unwrap();
+            [&[0xf7 + (4 - skip) as u8][..], &be[skip..]].concat()
+        };
+        len += header.len();
+        headers.push(header);
+    }
+    let mut data: Vec<u8> = headers.into_iter().rev().flatten().collect();
+    data.push(0xc0);
+    assert_eq!(decode_proof(&data), Err(RlpError::TooDeep));
+    assert_eq!(decode_multi_proof(&data).unwrap_err(), RlpError::TooDeep);
+
+    // a list inside a proof is still a shape error, not a depth error
+    assert_eq!(decode_proof(&[0xc1, 0xc0]), Err(RlpError::ExpectedBytes));
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use merklerust_core::hashes::{keccak256, Keccak256Hasher, PositionalSha256Hasher, Sha256Hasher};
+use merklerust_core::merkle::MultiProof;
+use merklerust_core::simple::SimpleMerkleTree;
+use merklerust_core::standard::StandardMerkleTree;
+use merklerust_core::tree::MerkleTree;
+
+use common::leaves;
+
+fn standard_tree() -> StandardMerkleTree {
+    StandardMerkleTree::of(
+        vec![
+            vec![
+                "0x1111111111111111111111111111111111111111".into(),
+                "5000000000000000000".into(),
+            ],
+            vec![
+                "0x2222222222222222222222222222222222222222".into(),
+                "2500000000000000000".into(),
+            ],
+        ],
+        vec!["address".into(), "uint256".into()],
+    )
+}
+
+#[test]
+fn multi_proof_json_uses_hex() {
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let mp = tree.multi_proof(&[0, 3]);
+    let json = serde_json::to_string(&mp).unwrap();
+    assert!(json.starts_with("{\"leaves\":[\"0x"));
+    assert!(json.contains("\"proofFlags\":["));
+    assert_eq!(serde_json::from_str::<MultiProof>(&json).unwrap(), mp);
+
+    let bad = json.replacen("\"0x", "\"", 1);
+    assert!(serde_json::from_str::<MultiProof>(&bad).is_err());
+}
+
+#[test]
+fn multi_proof_bincode_is_compact() {
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let mp = tree.multi_proof(&[1, 2]);
+    let bytes = bincode::serialize(&mp).unwrap();
+    let nodes = mp.leaves.len() + mp.proof.len();
+    // raw 32-byte nodes with 8-byte length prefixes, not hex text
+    assert_eq!(bytes.len(), 3 * 8 + nodes * 40 + mp.proof_flags.len());
+    assert_eq!(bincode::deserialize::<MultiProof>(&bytes).unwrap(), mp);
+}
+
+#[test]
+fn merkle_tree_round_trip() {
+    let tree = MerkleTree::new(leaves(4), Sha256Hasher);
+    let json = serde_json::to_string(&tree).unwrap();
+    assert!(json.contains("\"multiProof\":\"ozCompatible\""));
+    assert_eq!(json, tree.dump());
+    let loaded: MerkleTree<Sha256Hasher> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, tree);
+    let bytes = bincode::serialize(&tree).unwrap();
+    assert_eq!(
+        bincode::deserialize::<MerkleTree<Sha256Hasher>>(&bytes).unwrap(),
+        tree
+    );
+
+    // the tree was built with another hasher
+    assert!(serde_json::from_str::<MerkleTree<Keccak256Hasher>>(&json).is_err());
+
+    let positional = MerkleTree::new_positional(leaves(5), PositionalSha256Hasher);
+    let json = serde_json::to_string(&positional).unwrap();
+    assert!(json.contains(
+        "\"hasher\":\"sha256\",\"pairOrdering\":\"positional\",\"multiProof\":\"positional\""
+    ));
+    let loaded: MerkleTree<PositionalSha256Hasher> = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, positional);
+    assert_eq!(loaded.config(), positional.config());
+    let heap_order = json.replace("\"positional\"", "\"sorted\"");
+    assert!(serde_json::from_str::<MerkleTree<PositionalSha256Hasher>>(&heap_order).is_err());
+    // the recorded hasher name has to match
+    let renamed = json.replace("\"sha256\"", "\"keccak256\"");
+    assert!(serde_json::from_str::<MerkleTree<PositionalSha256Hasher>>(&renamed).is_err());
+    // serde and the hand-written dump agree
+    assert_eq!(json, positional.dump());
+}
+
+#[test]
+fn standard_tree_matches_dump() {
+    let tree = standard_tree();
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, tree.dump());
+
+    let loaded: StandardMerkleTree = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.root(), tree.root());
+    assert_eq!(loaded.values(), tree.values());
+    assert_eq!(loaded.leaf_lookup(&tree.values()[1].value), Some(1));
+
+    let bytes = bincode::serialize(&tree).unwrap();
+    let loaded: StandardMerkleTree = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(loaded.root(), tree.root());
+}
+
+#[test]
+fn standard_tree_rejects_invalid() {
+    let json = serde_json::to_string(&standard_tree()).unwrap();
+    let cases = [
+        json.replace("standard-v1", "standard-v2"),
+        json.replace("2500000000000000000", "2500000000000000001"),
+        json.replace("2500000000000000000", "not a number"),
+        json.replace("\"treeIndex\":1", "\"treeIndex\":0"),
+    ];
+    for case in cases.iter() {
+        assert_ne!(case, &json);
+        assert!(
+            serde_json::from_str::<StandardMerkleTree>(case).is_err(),
+            "{}",
+            case
+        );
+    }
+}
+
+#[test]
+fn simple_tree_matches_dump() {
+    let values: Vec<[u8; 32]> = (0..3u8).map(|i| keccak256(&[i])).collect();
+    let tree = SimpleMerkleTree::of(values);
+    let json = serde_json::to_string(&tree).unwrap();
+    assert_eq!(json, tree.dump());
+
+    let loaded: SimpleMerkleTree = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.values(), tree.values());
+    let bytes = bincode::serialize(&tree).unwrap();
+    let loaded: SimpleMerkleTree = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(loaded.root(), tree.root());
+
+    let other = format!("0x{}", hex::encode([9u8; 32]));
+    let tampered = json.replacen(
+        &format!("0x{}", hex::encode(tree.values()[0].value)),
+        &other,
+        2,
+    );
+    assert!(serde_json::from_str::<SimpleMerkleTree>(&tampered).is_err());
+}
@@ -0,0 +1,119 @@
+use merklerust_core::dump::DumpError;
+use merklerust_core::hashes::keccak256;
+use merklerust_core::merkle::Hash;
+use merklerust_core::simple::SimpleMerkleTree;
+
+fn values(n: u8) -> Vec<Hash> {
+    (0..n).map(|i| keccak256(&[i])).collect()
+}
+
+#[test]
+fn proofs_by_value() {
+    let tree = SimpleMerkleTree::of(values(6));
+    for (i, v) in values(6).iter().enumerate() {
+        assert_eq!(tree.leaf_lookup(v), Some(i));
+        let proof = tree.get_proof_for_value(v);
+        assert_eq!(proof, tree.get_proof(i));
+        assert!(tree.verify_proof(v, &proof));
+        assert!(SimpleMerkleTree::verify(tree.root(), v, &proof));
+    }
+    assert!(!tree.verify_proof(&[7u8; 32], &tree.get_proof(0)));
+
+    // sorted leaves: the smallest value is the first leaf
+    let smallest = *values(6).iter().min().unwrap();
+    let first = tree.values().iter().find(|v| v.value == smallest).unwrap();
+    assert_eq!(first.tree_index, tree.tree().len() - 1);
+}
+
+#[test]
+fn dump_round_trip() {
+    let tree = SimpleMerkleTree::of(values(3));
+    let dump = tree.dump();
+    assert!(dump.starts_with("{\"format\":\"simple-v1\",\"tree\":[\"0x"));
+    assert!(dump.contains("\"treeIndex\":"));
+
+    let loaded = SimpleMerkleTree::load(&dump).unwrap();
+    assert_eq!(loaded.root(), tree.root());
+    assert_eq!(loaded.values(), tree.values());
+    assert_eq!(loaded.dump(), dump);
+
+    // whitespace and field order are irrelevant
+    let pretty = dump.replace(",", ",\n  ").replace(":", ": ");
+    assert_eq!(SimpleMerkleTree::load(&pretty).unwrap().root(), tree.root());
+}
+
+#[test]
+fn load_rejects_bad_dumps() {
+    let dump = SimpleMerkleTree::of(values(3)).dump();
+
+    assert_eq!(
+        SimpleMerkleTree::load(&dump.replace("simple-v1", "standard-v1")).unwrap_err(),
+        DumpError::UnknownFormat("standard-v1".to_string())
+    );
+    assert!(matches!(
+        SimpleMerkleTree::load(&dump[..dump.len() - 1]),
+        Err(DumpError::InvalidJson(_))
+    ));
+
+    let root = &dump[dump.find("0x").unwrap()..][..66];
+    let tampered = dump.replacen(root, &format!("0x{}", "00".repeat(32)), 1);
+    assert!(matches!(
+        SimpleMerkleTree::load(&tampered),
+        Err(DumpError::InvalidDump(_))
+    ));
+
+    let wrong_index = dump.replacen("\"treeIndex\":4", "\"treeIndex\":0", 1);
+    assert_ne!(wrong_index, dump);
+    assert!(matches!(
+        SimpleMerkleTree::load(&wrong_index),
+        Err(DumpError::InvalidDump(_))
+    ));
+
+    // deep nesting is rejected instead of overflowing the stack
+    assert!(matches!(
+        SimpleMerkleTree::load(&"[".repeat(200_000)),
+        Err(DumpError::InvalidJson(_))
+    ));
+}
+
+fn padded(real: u8, total: usize) -> Vec<Hash> {
+    let mut v = values(real);
+    v.resize(total, [0u8; 32]);
+    v
+}
+
+#[test]
+fn compressed_dump_round_trip() {
+    let tree = SimpleMerkleTree::of(padded(3, 16));
+    let dump = tree.dump_compressed();
+    assert!(dump.starts_with("{\"format\":\"simple-rle-v1\",\"tree\":[{\"node\":\"0x"));
+    assert!(dump.len() < tree.dump().len() / 2);
+
+    let loaded = SimpleMerkleTree::load(&dump).unwrap();
+    assert_eq!(loaded.root(), tree.root());
+    assert_eq!(loaded.values(), tree.values());
+    assert_eq!(loaded.dump(), tree.dump());
+
+    // unsorted trees without repeats round-trip too
+    let plain = SimpleMerkleTree::of(values(5));
+    let loaded = SimpleMerkleTree::load(&plain.dump_compressed()).unwrap();
+    assert_eq!(loaded.values(), plain.values());
+}
+
+#[test]
+fn compressed_load_rejects_oversized_runs() {
+    let dump = SimpleMerkleTree::of(padded(1, 4)).dump_compressed();
+    let huge = dump.replacen("\"count\":", &format!("\"count\":{}", u64::MAX / 4), 1);
+    assert!(matches!(
+        SimpleMerkleTree::load(&huge),
+        Err(DumpError::InvalidDump(_))
+    ));
+
+    let last = dump.rfind("\"count\":").unwrap();
+    let mut bad = dump.clone();
+    bad.replace_range(last..last + 9, "\"count\":9");
+    assert!(matches!(
+        SimpleMerkleTree::load(&bad),
+        Err(DumpError::InvalidDump(_))
+    ));
+}
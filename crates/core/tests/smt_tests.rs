@@ -0,0 +1,317 @@
+use merklerust_core::hashes::{keccak256, sha256, Keccak256Hasher, PositionalKeccak256Hasher};
+use merklerust_core::merkle::MerkleError;
+use merklerust_core::smt::{
+    apply_updates_with_witness, KeyPath, SmtMultiProof, SmtProof, SparseMerkleTree,
+};
+
+fn key(i: u8) -> [u8; 32] {
+    keccak256(&[i])
+}
+
+#[test]
+fn insert_get_remove() {
+    let mut tree = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    let empty = tree.root();
+    assert_eq!(empty, tree.empty_root());
+
+    assert_eq!(tree.insert(key(1), b"one".to_vec()), None);
+    assert_eq!(tree.insert(key(2), b"two".to_vec()), None);
+    let two_keys = tree.root();
+    assert_ne!(two_keys, empty);
+    assert_eq!(tree.get(&key(1)), Some(&b"one"[..]));
+    assert_eq!(tree.get(&key(3)), None);
+    assert_eq!(tree.len(), 2);
+
+    assert_eq!(tree.insert(key(1), b"uno".to_vec()), Some(b"one".to_vec()));
+    assert_ne!(tree.root(), two_keys);
+    assert_eq!(tree.insert(key(1), b"one".to_vec()), Some(b"uno".to_vec()));
+    assert_eq!(tree.root(), two_keys);
+
+    // the root depends only on the contents, not on insertion order
+    let mut other = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    other.insert(key(2), b"two".to_vec());
+    other.insert(key(1), b"one".to_vec());
+    assert_eq!(other.root(), two_keys);
+
+    assert_eq!(tree.remove(&key(1)), Some(b"one".to_vec()));
+    assert_eq!(tree.remove(&key(1)), None);
+    tree.remove(&key(2));
+    assert!(tree.is_empty());
+    assert_eq!(tree.root(), empty);
+}
+
+#[test]
+fn membership_and_non_membership_proofs() {
+    let hasher = PositionalKeccak256Hasher;
+    let mut tree = SparseMerkleTree::new(hasher);
+    for i in 0..10u8 {
+        tree.insert(key(i), vec![i; 3]);
+    }
+    let root = tree.root();
+
+    for i in 0..10u8 {
+        let proof = tree.prove(&key(i));
+        assert!(proof.verify_membership(&root, &key(i), &[i; 3], &hasher));
+        assert!(!proof.verify_membership(&root, &key(i), &[i; 4], &hasher));
+        assert!(!proof.verify_non_membership(&root, &key(i), &hasher));
+        // only the non-default siblings are carried
+        assert!(proof.siblings.len() < 16);
+    }
+
+    let absent = key(200);
+    let proof = tree.prove(&absent);
+    assert!(proof.verify_non_membership(&root, &absent, &hasher));
+    assert!(!proof.verify_membership(&root, &absent, b"", &hasher));
+    // the proof is tied to the key's path
+    assert!(!proof.verify_non_membership(&root, &key(201), &hasher));
+
+    let mut truncated = proof.clone();
+    truncated.siblings.pop();
+    assert!(!truncated.verify_non_membership(&root, &absent, &hasher));
+
+    let empty = SparseMerkleTree::new(hasher);
+    let proof = empty.prove(&absent);
+    assert!(proof.siblings.is_empty());
+    assert!(proof.verify_non_membership(&empty.root(), &absent, &hasher));
+}
+
+#[test]
+fn multi_proof_shares_siblings() {
+    let mut tree = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    for i in 0..16 {
+        tree.insert(key(i), vec![i]);
+    }
+    let root = tree.root();
+    let keys: Vec<_> = (10..20).map(key).collect();
+    let proof = tree.prove_many(&keys);
+    let values: Vec<_> = (10..20u8).map(|i| [i]).collect();
+    let entries: Vec<_> = (10..20u8)
+        .map(|i| (key(i), (i < 16).then_some(&values[i as usize - 10][..])))
+        .collect();
+    assert!(proof.verify(&root, &entries, &PositionalKeccak256Hasher));
+
+    let separate: usize = keys.iter().map(|k| tree.prove(k).siblings.len()).sum();
+    assert!(proof.siblings.len() < separate);
+
+    // repeated keys are proven once
+    let doubled: Vec<_> = keys.iter().chain(&keys).copied().collect();
+    assert_eq!(tree.prove_many(&doubled), proof);
+
+    // a wrong value, a missing entry, a duplicate entry or a tampered proof fails
+    let mut wrong = entries.clone();
+    wrong[0].1 = Some(b"other");
+    assert!(!proof.verify(&root, &wrong, &PositionalKeccak256Hasher));
+    assert!(!proof.verify(&root, &entries[1..], &PositionalKeccak256Hasher));
+    let mut duplicate = entries.clone();
+    duplicate.push(entries[0]);
+    assert!(!proof.verify(&root, &duplicate, &PositionalKeccak256Hasher));
+    assert!(!proof.verify(&root, &[], &PositionalKeccak256Hasher));
+    let mut extra = proof.clone();
+    extra.siblings.push([0; 32]);
+    assert!(!extra.verify(&root, &entries, &PositionalKeccak256Hasher));
+    let mut padded = proof.clone();
+    padded.bitmap.push(0);
+    assert!(!padded.verify(&root, &entries, &PositionalKeccak256Hasher));
+    let mut flipped = proof;
+    flipped.bitmap[0] ^= 1;
+    assert!(!flipped.verify(&root, &entries, &PositionalKeccak256Hasher));
+}
+
+#[test]
+fn multi_proof_of_one_key_and_empty_tree() {
+    let mut tree = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    let proof = tree.prove_many(&[key(1)]);
+    assert!(proof.siblings.is_empty());
+    assert!(proof.verify(&tree.root(), &[(key(1), None)], &PositionalKeccak256Hasher));
+
+    tree.insert(key(1), b"one".to_vec());
+    tree.insert(key(2), b"two".to_vec());
+    let proof = tree.prove_many(&[key(1)]);
+    assert_eq!(proof.siblings, tree.prove(&key(1)).siblings);
+    assert!(proof.verify(
+        &tree.root(),
+        &[(key(1), Some(&b"one"[..]))],
+        &PositionalKeccak256Hasher
+    ));
+}
+
+#[test]
+fn proofs_round_trip_through_bytes() {
+    let mut tree = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    for i in 0..5 {
+        tree.insert(key(i), vec![i]);
+    }
+    let proof = tree.prove(&key(3));
+    let bytes = proof.to_bytes();
+    assert_eq!(bytes.len(), 1 + 32 + 32 * proof.siblings.len());
+    assert_eq!(SmtProof::from_bytes(&bytes), Ok(proof));
+    assert_eq!(
+        SmtProof::from_bytes(&bytes[..32]),
+        Err(MerkleError::InvalidNode(32))
+    );
+    assert_eq!(
+        SmtProof::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(MerkleError::InvalidNode(31))
+    );
+    let mut unknown = bytes.clone();
+    unknown[0] = 0xff;
+    assert!(matches!(
+        SmtProof::from_bytes(&unknown),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+
+    let multi = tree.prove_many(&[key(1), key(3), key(9)]);
+    let bytes = multi.to_bytes();
+    assert_eq!(SmtMultiProof::from_bytes(&bytes), Ok(multi.clone()));
+    assert_eq!(
+        SmtMultiProof::from_bytes(&bytes[..4]),
+        Err(MerkleError::InvalidNode(4))
+    );
+    assert_eq!(
+        SmtMultiProof::from_bytes(&bytes[..5 + multi.bitmap.len() - 1]),
+        Err(MerkleError::InvalidNode(5 + multi.bitmap.len() - 1))
+    );
+}
+
+#[test]
+fn updates_apply_to_a_witness_without_the_tree() {
+    let mut tree = SparseMerkleTree::new(PositionalKeccak256Hasher);
+    for i in 0..10 {
+        tree.insert(key(i), vec![i]);
+    }
+    let old_root = tree.root();
+    // change, insert and remove one key each
+    let updates: Vec<([u8; 32], Option<&[u8]>)> = vec![
+        (key(2), Some(b"new")),
+        (key(20), Some(b"added")),
+        (key(5), None),
+    ];
+    let witness = tree.witness(&[key(5), key(20), key(2), key(2)]);
+    assert_eq!(witness.values.len(), 3);
+
+    let new_root =
+        apply_updates_with_witness(&old_root, &updates, &witness, &PositionalKeccak256Hasher);
+    tree.insert(key(2), b"new".to_vec());
+    tree.insert(key(20), b"added".to_vec());
+    tree.remove(&key(5));
+    assert_eq!(new_root, Some(tree.root()));
+
+    // a witness key may be left as it is
+    let one = [(key(20), Some(&b"added"[..]))];
+    assert!(
+        apply_updates_with_witness(&old_root, &one, &witness, &PositionalKeccak256Hasher).is_some()
+    );
+
+    // the witness must hold under the old root and cover every update exactly once
+    let hasher = PositionalKeccak256Hasher;
+    assert_eq!(
+        apply_updates_with_witness(&new_root.unwrap(), &updates, &witness, &hasher),
+        None
+    );
+    let mut forged = witness.clone();
+    forged.values[0].1 = Some(b"forged".to_vec());
+    assert_eq!(
+        apply_updates_with_witness(&old_root, &updates, &forged, &hasher),
+        None
+    );
+    let outside = [(key(7), Some(&b"seven"[..]))];
+    assert_eq!(
+        apply_updates_with_witness(&old_root, &outside, &witness, &hasher),
+        None
+    );
+    let repeated = [(key(2), Some(&b"a"[..])), (key(2), Some(&b"b"[..]))];
+    assert_eq!(
+        apply_updates_with_witness(&old_root, &repeated, &witness, &hasher),
+        None
+    );
+}
+
+#[test]
+fn key_paths_map_keys_and_bind_proofs() {
+    let hasher = PositionalKeccak256Hasher;
+    let account = b"alice@example.org";
+    assert_eq!(KeyPath::Sha256.path(account), Ok(sha256(account)));
+    assert_eq!(
+        KeyPath::Raw.path(account),
+        Err(MerkleError::InvalidNode(17))
+    );
+    assert_eq!(KeyPath::Truncated.path(&[7; 40]), Ok([7; 32]));
+    assert_eq!(
+        KeyPath::Truncated.path(&[7; 31]),
+        Err(MerkleError::InvalidNode(31))
+    );
+    assert_eq!(KeyPath::by_name("sha256"), Some(KeyPath::Sha256));
+    assert_eq!(
+        KeyPath::from_id(KeyPath::Truncated.id()),
+        Some(KeyPath::Truncated)
+    );
+
+    let mut tree = SparseMerkleTree::with_key_path(hasher, KeyPath::Sha256);
+    tree.insert(account, b"100".to_vec());
+    tree.insert(b"bob", b"5".to_vec());
+    assert_eq!(tree.get(account), Some(&b"100"[..]));
+    // entries are listed by path
+    assert!(tree
+        .iter()
+        .any(|(path, v)| *path == sha256(account) && v == b"100"));
+
+    // the same contents at raw paths give the same root
+    let mut raw = SparseMerkleTree::new(hasher);
+    raw.insert(sha256(account), b"100".to_vec());
+    raw.insert(sha256(b"bob"), b"5".to_vec());
+    let root = tree.root();
+    assert_eq!(root, raw.root());
+
+    let proof = tree.prove(account);
+    assert_eq!(proof.key_path, KeyPath::Sha256);
+    assert!(proof.verify_membership_with_key_path(
+        &root,
+        account,
+        b"100",
+        &hasher,
+        KeyPath::Sha256
+    ));
+    assert!(SmtProof::from_bytes(&proof.to_bytes()).is_ok_and(|p| p == proof));
+    // a verifier expecting another strategy rejects the proof, even for the matching path
+    assert!(!proof.verify_membership(&root, &sha256(account), b"100", &hasher));
+    let absent = tree.prove(b"carol");
+    assert!(absent.verify_non_membership_with_key_path(&root, b"carol", &hasher, KeyPath::Sha256));
+    assert!(!absent.verify_non_membership_with_key_path(
+        &root,
+        b"carol",
+        &hasher,
+        KeyPath::Truncated
+    ));
+
+    let multi = tree.prove_many(&[&account[..], b"carol"]);
+    let entries = [(&account[..], Some(&b"100"[..])), (&b"carol"[..], None)];
+    assert!(multi.verify_with_key_path(&root, &entries, &hasher, KeyPath::Sha256));
+    let raw_multi = raw.prove_many(&[sha256(account), sha256(b"carol")]);
+    assert_eq!(raw_multi.siblings, multi.siblings);
+    assert!(!raw_multi.verify_with_key_path(&root, &entries, &hasher, KeyPath::Sha256));
+
+    // keys the strategy rejects
+    let mut raw = SparseMerkleTree::new(hasher);
+    assert_eq!(
+        raw.try_insert(b"short", vec![1]),
+        Err(MerkleError::InvalidNode(5))
+    );
+    assert_eq!(raw.get(b"short"), None);
+    assert!(raw.try_prove(b"short").is_err());
+}
+
+#[test]
+fn sorted_pair_hashers_are_rejected() {
+    assert!(matches!(
+        SparseMerkleTree::try_new(Keccak256Hasher),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+    assert!(SparseMerkleTree::try_with_key_path(Keccak256Hasher, KeyPath::Sha256).is_err());
+    assert!(SparseMerkleTree::try_new(PositionalKeccak256Hasher).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "cannot build a Positional tree")]
+fn new_panics_on_a_sorted_pair_hasher() {
+    SparseMerkleTree::new(Keccak256Hasher);
+}
@@ -0,0 +1,194 @@
+use merklerust_core::dump::DumpError;
+use merklerust_core::hashes::standard_node_hash;
+use merklerust_core::merkle::{process_multi_proof, MerkleError};
+use merklerust_core::standard::{StandardMerkleTree, StandardTreeError, TreeOptions};
+
+fn strings(xs: &[&str]) -> Vec<String> {
+    xs.iter().map(|s| s.to_string()).collect()
+}
+
+fn readme_values() -> Vec<Vec<String>> {
+    vec![
+        strings(&[
+            "0x1111111111111111111111111111111111111111",
+            "5000000000000000000",
+        ]),
+        strings(&[
+            "0x2222222222222222222222222222222222222222",
+            "2500000000000000000",
+        ]),
+    ]
+}
+
+#[test]
+fn matches_openzeppelin_readme_root() {
+    let tree = StandardMerkleTree::of(readme_values(), strings(&["address", "uint256"]));
+    assert_eq!(
+        hex::encode(tree.root()),
+        "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+    );
+    assert_eq!(tree.len(), 2);
+}
+
+#[test]
+fn proves_values_by_index_and_value() {
+    let values: Vec<Vec<String>> = (0..7)
+        .map(|i| strings(&[&format!("0x{:040x}", i + 1), &(i * 100).to_string()]))
+        .collect();
+    let encoding = strings(&["address", "uint256"]);
+    let tree = StandardMerkleTree::of(values.clone(), encoding.clone());
+
+    for (i, v) in values.iter().enumerate() {
+        assert_eq!(tree.leaf_lookup(v), Some(i));
+        let proof = tree.get_proof(i);
+        assert_eq!(proof, tree.get_proof_for_value(v));
+        assert!(tree.verify_proof(v, &proof));
+        assert!(StandardMerkleTree::verify(
+            tree.root(),
+            &encoding,
+            v,
+            &proof
+        ));
+    }
+    let other = strings(&["0x0000000000000000000000000000000000000099", "1"]);
+    assert!(!tree.verify_proof(&other, &tree.get_proof(0)));
+    assert_eq!(tree.leaf_lookup(&other), None);
+
+    let mp = tree.get_multi_proof(&[0, 2, 5]);
+    assert_eq!(
+        process_multi_proof(&mp, |a, b| standard_node_hash(a, b).to_vec()),
+        tree.root()
+    );
+}
+
+#[test]
+fn unsorted_option_keeps_input_order() {
+    let encoding = strings(&["address", "uint256"]);
+    let tree = StandardMerkleTree::of_with_options(
+        readme_values(),
+        encoding,
+        TreeOptions { sort_leaves: false },
+    );
+    assert_eq!(tree.values()[0].tree_index, 2);
+    assert_eq!(tree.values()[1].tree_index, 1);
+}
+
+#[test]
+#[should_panic(expected = "Leaf is not in tree")]
+fn proof_for_unknown_value_panics() {
+    let tree = StandardMerkleTree::of(readme_values(), strings(&["address", "uint256"]));
+    tree.get_proof_for_value(&strings(&[
+        "0x0000000000000000000000000000000000000001",
+        "1",
+    ]));
+}
+
+/// `JSON.stringify(tree.dump(), null, 2)` for the README tree, as written by the JS library.
+const README_DUMP: &str = r#"{
+  "format": "standard-v1",
+  "leafEncoding": [
+    "address",
+    "uint256"
+  ],
+  "tree": [
+    "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77",
+    "0xeb02c421cfa48976e66dfb29120745909ea3a0f843456c263cf8f1253483e283",
+    "0xb92c48e9d7abe27fd8dfd6b5dfdbfb1c9a463f80c712b66f3a5180a090cccafc"
+  ],
+  "values": [
+    {
+      "value": [
+        "0x1111111111111111111111111111111111111111",
+        "5000000000000000000"
+      ],
+      "treeIndex": 1
+    },
+    {
+      "value": [
+        "0x2222222222222222222222222222222222222222",
+        "2500000000000000000"
+      ],
+      "treeIndex": 2
+    }
+  ]
+}"#;
+
+#[test]
+fn loads_and_writes_js_dumps() {
+    let tree = StandardMerkleTree::load(README_DUMP).unwrap();
+    let built = StandardMerkleTree::of(readme_values(), strings(&["address", "uint256"]));
+    assert_eq!(tree.root(), built.root());
+    assert_eq!(tree.values(), built.values());
+    assert_eq!(
+        tree.get_proof_for_value(&readme_values()[1]),
+        built.get_proof(1)
+    );
+
+    let compact: String = README_DUMP.split_whitespace().collect();
+    assert_eq!(built.dump(), compact);
+    assert_eq!(
+        StandardMerkleTree::load(&built.dump()).unwrap().dump(),
+        compact
+    );
+
+    // JS callers may pass numbers instead of decimal strings
+    let numeric = README_DUMP.replace("\"2500000000000000000\"", "2500000000000000000");
+    assert_eq!(
+        StandardMerkleTree::load(&numeric).unwrap().values(),
+        built.values()
+    );
+}
+
+#[test]
+fn load_rejects_bad_standard_dumps() {
+    assert_eq!(
+        StandardMerkleTree::load(&README_DUMP.replace("standard-v1", "simple-v1")).unwrap_err(),
+        DumpError::UnknownFormat("simple-v1".to_string())
+    );
+    let cases = [
+        README_DUMP.replace("5000000000000000000", "5000000000000000001"),
+        README_DUMP.replace("5000000000000000000", "-1"),
+        README_DUMP.replace("\"uint256\"", "\"bool\""),
+        README_DUMP.replace("\"treeIndex\": 2", "\"treeIndex\": 0"),
+        README_DUMP.replace("0xeb02", "0x0000"),
+    ];
+    for case in cases.iter() {
+        assert_ne!(case, README_DUMP);
+        assert!(
+            matches!(
+                StandardMerkleTree::load(case),
+                Err(DumpError::InvalidDump(_))
+            ),
+            "{}",
+            case
+        );
+    }
+}
+
+#[test]
+fn malformed_values_fail_without_panicking() {
+    let encoding = strings(&["address", "uint256"]);
+    let mut values = readme_values();
+    values.push(strings(&["0x1234", "1"]));
+    assert!(matches!(
+        StandardMerkleTree::try_of(values, encoding.clone()),
+        Err(StandardTreeError::Value { index: 2, .. })
+    ));
+    assert_eq!(
+        StandardMerkleTree::try_of(Vec::new(), encoding.clone()).unwrap_err(),
+        StandardTreeError::Merkle(MerkleError::EmptyTree)
+    );
+
+    let tree = StandardMerkleTree::try_of(readme_values(), encoding.clone()).unwrap();
+    let proof = tree.get_proof(0);
+    assert!(tree.verify_proof(&readme_values()[0], &proof));
+    let bad = strings(&["0x1111111111111111111111111111111111111111", "-1"]);
+    assert!(!tree.verify_proof(&bad, &proof));
+    assert!(!StandardMerkleTree::verify(
+        tree.root(),
+        &encoding,
+        &strings(&["1"]),
+        &proof
+    ));
+    assert_eq!(tree.leaf_lookup(&bad), None);
+}
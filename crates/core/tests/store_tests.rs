@@ -0,0 +1,94 @@
+mod common;
+
+use merklerust_core::hashes::{keccak256, Keccak256Hasher};
+use merklerust_core::store::{MemoryStore, NodeStore, StoredMerkleTree};
+use merklerust_core::tree::MerkleTree;
+
+use common::leaves;
+
+fn check_against_memory_tree<S: NodeStore>(store: S) -> S {
+    let mut expected = MerkleTree::new(leaves(7), Keccak256Hasher);
+    let mut tree = StoredMerkleTree::build(leaves(7), store, Keccak256Hasher).unwrap();
+    assert_eq!(&tree.root().unwrap()[..], expected.root());
+    assert_eq!(tree.leaf_count(), 7);
+    for i in 0..7 {
+        assert_eq!(tree.proof(i).unwrap(), expected.proof(i));
+    }
+
+    let new_leaf = keccak256(b"new");
+    tree.update_leaf(2, &new_leaf).unwrap();
+    expected.update_leaf(2, &new_leaf);
+    assert_eq!(&tree.root().unwrap()[..], expected.root());
+    assert_eq!(tree.leaf(2).unwrap(), new_leaf);
+    assert!(tree.proof(7).is_err());
+    assert!(tree.update_leaf(0, &[0u8; 31]).is_err());
+
+    // reopening reads the same tree back
+    let reopened = StoredMerkleTree::open(tree.into_store(), Keccak256Hasher).unwrap();
+    assert_eq!(&reopened.root().unwrap()[..], expected.root());
+    assert_eq!(reopened.proof(5).unwrap(), expected.proof(5));
+    reopened.into_store()
+}
+
+#[test]
+fn memory_store_tree_matches_merkle_tree() {
+    let store = check_against_memory_tree(MemoryStore::new());
+    assert_eq!(store.len().unwrap(), 13);
+    assert!(StoredMerkleTree::open(MemoryStore::new(), Keccak256Hasher).is_err());
+}
+
+#[test]
+fn build_replaces_a_larger_tree() {
+    let old = StoredMerkleTree::build(leaves(20), MemoryStore::new(), Keccak256Hasher).unwrap();
+    let tree = StoredMerkleTree::build(leaves(3), old.into_store(), Keccak256Hasher).unwrap();
+    let reopened = StoredMerkleTree::open(tree.into_store(), Keccak256Hasher).unwrap();
+    assert_eq!(reopened.len(), 5);
+    assert_eq!(
+        &reopened.root().unwrap()[..],
+        MerkleTree::new(leaves(3), Keccak256Hasher).root()
+    );
+}
+
+#[test]
+fn open_rejects_a_tree_without_its_root() {
+    // a build cut short before its last write, the root
+    let mut store = MemoryStore::new();
+    let tree = MerkleTree::new(leaves(3), Keccak256Hasher);
+    for (i, node) in tree.as_slice().iter().enumerate().skip(1) {
+        store.put(i, node.as_slice().try_into().unwrap()).unwrap();
+    }
+    assert!(StoredMerkleTree::open(store, Keccak256Hasher).is_err());
+}
+
+#[test]
+fn put_batch_writes_every_node() {
+    let mut store = MemoryStore::new();
+    let nodes = [(0, keccak256(b"a")), (2, keccak256(b"b"))];
+    store.put_batch(&nodes).unwrap();
+    assert_eq!(store.get(0).unwrap(), Some(nodes[0].1));
+    assert_eq!(store.get(1).unwrap(), None);
+    assert_eq!(store.get(2).unwrap(), Some(nodes[1].1));
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn sled_store_tree_matches_merkle_tree() {
+    use merklerust_core::store::SledStore;
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let store = check_against_memory_tree(SledStore::new(db.open_tree("nodes").unwrap()));
+    assert_eq!(store.len().unwrap(), 13);
+}
+
+#[cfg(feature = "rocksdb")]
+#[test]
+fn rocks_store_tree_matches_merkle_tree() {
+    use merklerust_core::store::RocksStore;
+
+    let dir = std::env::temp_dir().join(format!("merklerust-rocks-{}", std::process::id()));
+    let db = rocksdb::DB::open_default(&dir).unwrap();
+    let store = check_against_memory_tree(RocksStore::new(db));
+    assert_eq!(store.len().unwrap(), 13);
+    drop(store);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use merklerust_core::hashes::{keccak256, sha256};
+use merklerust_core::merkle::Bytes;
+use merklerust_core::timestamp::{verify_receipt, OtsHashOp, TimestampBatcher, TimestampError};
+
+use common::node_hash;
+
+#[test]
+fn receipts_verify_per_epoch() {
+    let mut batcher = TimestampBatcher::new(10, node_hash);
+    assert!(batcher.seal().is_empty());
+    assert_eq!(batcher.epoch(), 10);
+
+    let docs: Vec<Bytes> = (0u8..3).map(|i| keccak256(&[i]).to_vec()).collect();
+    for d in docs.iter() {
+        batcher.add(d.clone());
+    }
+    assert_eq!(batcher.pending(), 3);
+
+    let receipts = batcher.seal();
+    assert_eq!(batcher.epoch(), 11);
+    assert_eq!(batcher.pending(), 0);
+    assert_eq!(receipts.len(), 3);
+    for (r, d) in receipts.iter().zip(docs.iter()) {
+        assert_eq!(r.epoch, 10);
+        assert_eq!(r.root, receipts[0].root);
+        assert!(verify_receipt(r, d, &receipts[0].root, node_hash));
+    }
+    assert!(!verify_receipt(
+        &receipts[0],
+        &docs[1],
+        &receipts[0].root,
+        node_hash
+    ));
+
+    // the receipt's own root is not trusted
+    let mut forged = receipts[0].clone();
+    forged.proof = vec![docs[2].clone()];
+    forged.root = node_hash(&docs[0], &docs[2]);
+    assert!(!verify_receipt(
+        &forged,
+        &docs[0],
+        &receipts[0].root,
+        node_hash
+    ));
+    assert!(verify_receipt(&forged, &docs[0], &forged.root, node_hash));
+
+    // malformed proofs fail instead of panicking
+    forged.proof = vec![vec![0u8; 3]];
+    assert!(!verify_receipt(
+        &forged,
+        &docs[0],
+        &receipts[0].root,
+        node_hash
+    ));
+
+    assert_eq!(
+        batcher.try_add(vec![0u8; 31]),
+        Err(TimestampError::InvalidDocumentHash(31))
+    );
+    assert_eq!(batcher.pending(), 0);
+    batcher.add(docs[0].clone());
+    let next = batcher.seal();
+    assert_eq!(next[0].epoch, 11);
+    assert_eq!(next[0].root, docs[0]);
+    assert!(verify_receipt(&next[0], &docs[0], &docs[0], node_hash));
+}
+
+#[test]
+fn receipt_exports_to_ots() {
+    fn sha_node_hash(a: &[u8], b: &[u8]) -> Bytes {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        sha256(&[left, right].concat()).to_vec()
+    }
+
+    let mut batcher = TimestampBatcher::new(0, sha_node_hash);
+    let docs: Vec<Bytes> = (0u8..2).map(|i| sha256(&[i]).to_vec()).collect();
+    for d in docs.iter() {
+        batcher.add(d.clone());
+    }
+    let receipt = batcher.seal().remove(0);
+
+    assert_eq!(
+        receipt.to_ots(OtsHashOp::Keccak256, "https://calendar.example"),
+        Err(TimestampError::RootMismatch)
+    );
+    let ots = receipt
+        .to_ots(OtsHashOp::Sha256, "https://calendar.example")
+        .unwrap();
+    let header = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94\x01";
+    assert!(ots.starts_with(header));
+
+    let mut rest = &ots[header.len()..];
+    assert_eq!(rest[0], 0x08);
+    assert_eq!(&rest[1..33], &docs[0][..]);
+    rest = &rest[33..];
+
+    // one proof step: append/prepend the sibling, then sha256
+    let sibling = &receipt.proof[0];
+    let expected_op = if docs[0] <= *sibling { 0xf0 } else { 0xf1 };
+    assert_eq!(rest[0], expected_op);
+    assert_eq!(rest[1], 32);
+    assert_eq!(&rest[2..34], &sibling[..]);
+    assert_eq!(rest[34], 0x08);
+    rest = &rest[35..];
+
+    // pending attestation
+    assert_eq!(rest[0], 0x00);
+    assert_eq!(
+        &rest[1..9],
+        &[0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e]
+    );
+    let uri = b"https://calendar.example";
+    assert_eq!(rest[9] as usize, uri.len() + 1);
+    assert_eq!(rest[10] as usize, uri.len());
+    assert_eq!(&rest[11..], &uri[..]);
+}
@@ -0,0 +1,804 @@
+mod common;
+
+use merklerust_core::composite::CompositeTree;
+use merklerust_core::consistency::log_root;
+use merklerust_core::hashes::{
+    keccak256, positional_node_hash, sha256, Hasher, Keccak256Hasher, PositionalKeccak256Hasher,
+    PositionalSha256Hasher, Rfc6962Hasher, Sha256Hasher,
+};
+use merklerust_core::incremental::FixedDepthTree;
+use merklerust_core::merkle::{
+    get_proof, make_merkle_tree_bytes, process_multi_proof, process_proof,
+    try_get_positional_multi_proof, try_process_positional_multi_proof,
+    verify_positional_multi_proof, verify_positional_proof, Bytes, MerkleError, Side,
+    ZeroLeafPolicy,
+};
+use merklerust_core::tree::{
+    compute_positional_root, compute_root, root_of, EmptyRoot, MerkleTree, MultiProofAlgorithm,
+    Padding, PairOrdering, SingleLeafRoot, TreeConfig,
+};
+
+use common::{leaves, node_hash};
+
+#[test]
+fn matches_flat_tree() {
+    let flat = make_merkle_tree_bytes(leaves(5), node_hash);
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+
+    assert_eq!(tree.as_slice(), flat.as_slice());
+    assert_eq!(tree.root(), flat[0].as_slice());
+    assert_eq!(tree.leaf_count(), 5);
+    assert_eq!(tree.depth(), 3);
+    assert_eq!(tree.leaf(1), leaves(5)[1].as_slice());
+    assert_eq!(
+        tree.leaves().map(|l| l.to_vec()).collect::<Vec<_>>(),
+        leaves(5)
+    );
+    assert_eq!(tree.proof(2), get_proof(&flat, flat.len() - 1 - 2));
+}
+
+#[test]
+fn proofs_by_leaf_position() {
+    let tree = MerkleTree::new(leaves(7), Keccak256Hasher);
+    for (i, leaf) in leaves(7).iter().enumerate() {
+        assert_eq!(process_proof(leaf, &tree.proof(i), node_hash), tree.root());
+    }
+    let mp = tree.multi_proof(&[0, 3, 6]);
+    assert_eq!(process_multi_proof(&mp, node_hash), tree.root());
+}
+
+#[test]
+fn levels_cover_tree() {
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let sizes: Vec<usize> = tree.levels().map(|l| l.len()).collect();
+    assert_eq!(sizes, vec![1, 2, 4, 2]);
+    assert_eq!(tree.levels().next().unwrap()[0], tree.root());
+
+    let single = MerkleTree::new(leaves(1), Keccak256Hasher);
+    assert_eq!(single.depth(), 0);
+    assert_eq!(single.levels().count(), 1);
+    assert!(single.proof(0).is_empty());
+}
+
+#[test]
+fn try_new_rejects_empty() {
+    assert!(MerkleTree::try_new(vec![], Keccak256Hasher).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Leaf position 3 out of range for 3 leaves")]
+fn rejects_out_of_range_position() {
+    MerkleTree::new(leaves(3), Keccak256Hasher).proof(3);
+}
+
+#[test]
+fn empty_root_policies() {
+    let hasher = Keccak256Hasher;
+    assert_eq!(
+        root_of(vec![], &hasher, &EmptyRoot::Reject),
+        Err(MerkleError::EmptyTree)
+    );
+    assert_eq!(
+        root_of(vec![], &hasher, &EmptyRoot::Zero).unwrap(),
+        vec![0u8; 32]
+    );
+    assert_eq!(
+        root_of(vec![], &hasher, &EmptyRoot::Tagged(b"empty".to_vec())).unwrap(),
+        keccak256(b"empty").to_vec()
+    );
+    // RFC 6962: MTH({}) = SHA-256()
+    assert_eq!(
+        hex::encode(root_of(vec![], &Sha256Hasher, &EmptyRoot::Tagged(vec![])).unwrap()),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+
+    // non-empty trees ignore the policy
+    let tree = MerkleTree::new(leaves(3), Keccak256Hasher);
+    assert_eq!(
+        root_of(leaves(3), &hasher, &EmptyRoot::Zero).unwrap(),
+        tree.root()
+    );
+}
+
+#[test]
+fn positional_tree_proofs() {
+    let leaves = leaves(4);
+    let tree = MerkleTree::new_positional(leaves.clone(), PositionalSha256Hasher);
+    let h = |a: &[u8], b: &[u8]| sha256(&[a, b].concat()).to_vec();
+    assert_eq!(
+        tree.root(),
+        h(&h(&leaves[0], &leaves[1]), &h(&leaves[2], &leaves[3]))
+    );
+    assert_eq!(tree.leaves().collect::<Vec<_>>(), leaves);
+    for (i, leaf) in leaves.iter().enumerate() {
+        let proof = tree.positional_proof(i);
+        assert!(verify_positional_proof(tree.root(), leaf, &proof, h));
+    }
+}
+
+#[test]
+fn positional_tree_has_rfc6962_shape() {
+    // hash of the 7-leaf tree of the certificate-transparency reference tests
+    let ct_leaves: Vec<Bytes> = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+    ]
+    .iter()
+    .map(|h| Rfc6962Hasher.hash_leaf(&hex::decode(h).unwrap()).to_vec())
+    .collect();
+    assert_eq!(
+        hex::encode(MerkleTree::new_positional(ct_leaves, Rfc6962Hasher).root()),
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c"
+    );
+
+    let l = leaves(3);
+    let h = |a: &[u8], b: &[u8]| sha256(&[a, b].concat()).to_vec();
+    let tree = MerkleTree::new_positional(l.clone(), PositionalSha256Hasher);
+    assert_eq!(tree.root(), h(&h(&l[0], &l[1]), &l[2]));
+    assert_eq!(
+        tree.levels().map(|l| l.len()).collect::<Vec<_>>(),
+        [1, 1, 3]
+    );
+    assert_eq!(tree.node(1, 0), h(&l[0], &l[1]));
+    assert!(tree.try_node(1, 1).is_err());
+    assert_eq!(
+        tree.positional_proof(2),
+        vec![(Side::Left, h(&l[0], &l[1]))]
+    );
+
+    for n in 1..=40u8 {
+        let all = leaves(n);
+        let tree = MerkleTree::new_positional(all.clone(), PositionalSha256Hasher);
+        assert_eq!(
+            tree.root(),
+            log_root(&all, &PositionalSha256Hasher),
+            "n = {}",
+            n
+        );
+        assert_eq!(tree.leaves().map(|l| l.to_vec()).collect::<Vec<_>>(), all);
+        for (i, leaf) in all.iter().enumerate() {
+            assert!(verify_positional_proof(
+                tree.root(),
+                leaf,
+                &tree.positional_proof(i),
+                h
+            ));
+        }
+    }
+}
+
+#[test]
+fn checked_node_access() {
+    let tree = MerkleTree::new(leaves(5), Keccak256Hasher);
+    for (level, nodes) in tree.levels().enumerate() {
+        for (position, node) in nodes.iter().enumerate() {
+            assert_eq!(tree.try_node(level, position).unwrap(), node.as_slice());
+        }
+        assert_eq!(
+            tree.try_node(level, nodes.len()),
+            Err(MerkleError::NodeOutOfRange {
+                level,
+                position: nodes.len()
+            })
+        );
+    }
+    assert_eq!(tree.node(0, 0), tree.root());
+    assert!(tree.try_node(tree.depth() + 1, 0).is_err());
+    assert!(tree.try_node(usize::MAX, 0).is_err());
+    // level 1 holds two nodes even though the tree has more after it
+    assert!(tree.try_node(1, 2).is_err());
+
+    assert_eq!(tree.try_leaf(4).unwrap(), tree.leaf(4));
+    let err = tree.try_leaf(5).unwrap_err();
+    assert_eq!(
+        err,
+        MerkleError::LeafOutOfRange {
+            position: 5,
+            leaves: 5
+        }
+    );
+    assert_eq!(err.to_string(), "Leaf position 5 out of range for 5 leaves");
+}
+
+#[test]
+fn update_leaf_matches_rebuild() {
+    let mut new_leaves = leaves(7);
+    let mut tree = MerkleTree::new(leaves(7), Keccak256Hasher);
+    let mut positional = MerkleTree::new_positional(leaves(7), PositionalSha256Hasher);
+    for pos in [0, 3, 6] {
+        let leaf = keccak256(&[100 + pos as u8]).to_vec();
+        new_leaves[pos] = leaf.clone();
+        tree.update_leaf(pos, &leaf);
+        positional.update_leaf(pos, &leaf);
+        assert_eq!(tree, MerkleTree::new(new_leaves.clone(), Keccak256Hasher));
+        assert_eq!(
+            positional,
+            MerkleTree::new_positional(new_leaves.clone(), PositionalSha256Hasher)
+        );
+    }
+}
+
+#[test]
+fn batch_update_matches_rebuild() {
+    let mut tree = MerkleTree::new(leaves(9), Keccak256Hasher);
+    let updates: Vec<(usize, Bytes)> = vec![
+        (1, keccak256(b"a").to_vec()),
+        (2, keccak256(b"b").to_vec()),
+        (8, keccak256(b"c").to_vec()),
+        (1, keccak256(b"d").to_vec()),
+    ];
+    tree.batch_update(&updates);
+
+    let mut expected = leaves(9);
+    for (pos, leaf) in updates.iter() {
+        expected[*pos] = leaf.clone();
+    }
+    assert_eq!(tree, MerkleTree::new(expected, Keccak256Hasher));
+
+    // a bad entry leaves the tree untouched
+    let before = tree.clone();
+    let bad = [(0, vec![1u8; 32]), (9, vec![2u8; 32])];
+    assert_eq!(
+        tree.try_batch_update(&bad),
+        Err(MerkleError::LeafOutOfRange {
+            position: 9,
+            leaves: 9
+        })
+    );
+    assert_eq!(
+        tree.try_update_leaf(0, &[0u8; 31]),
+        Err(MerkleError::InvalidNode(31))
+    );
+    assert_eq!(tree, before);
+}
+
+#[test]
+fn config_commitment_separates_settings() {
+    let sorted = MerkleTree::new(leaves(4), Keccak256Hasher);
+    let positional = MerkleTree::new_positional(leaves(4), PositionalKeccak256Hasher);
+    assert_eq!(sorted.config(), TreeConfig::default());
+    assert_eq!(positional.config().pair_ordering, PairOrdering::Positional);
+
+    // same settings and hasher reproduce the commitment; any difference changes it
+    let commitment = sorted.root_with_config_commitment();
+    assert_eq!(
+        commitment,
+        TreeConfig::default().commit_root(sorted.root(), &Keccak256Hasher)
+    );
+    assert_ne!(
+        commitment,
+        TreeConfig::default().commit_root(sorted.root(), &PositionalKeccak256Hasher)
+    );
+    assert_ne!(
+        commitment,
+        TreeConfig::default().commit_root(sorted.root(), &Sha256Hasher)
+    );
+    let encoded = TreeConfig {
+        leaf_encoding: "raw".to_string(),
+        ..TreeConfig::default()
+    };
+    assert_ne!(
+        commitment,
+        encoded.commit_root(sorted.root(), &Keccak256Hasher)
+    );
+    assert_ne!(sorted.config_fingerprint(), positional.config_fingerprint());
+}
+
+#[test]
+fn bound_proofs_reject_another_trees_config() {
+    let config = |tag: &[u8]| TreeConfig {
+        domain_tag: tag.to_vec(),
+        ..TreeConfig::default()
+    };
+    let epoch_1 =
+        MerkleTree::try_with_config(leaves(5), Keccak256Hasher, config(b"epoch-1")).unwrap();
+    let epoch_2 =
+        MerkleTree::try_with_config(leaves(5), Keccak256Hasher, config(b"epoch-2")).unwrap();
+    // same leaves, same root: only the fingerprint tells the epochs apart
+    assert_eq!(epoch_1.root(), epoch_2.root());
+
+    let proof = epoch_1.bound_proof(2);
+    assert!(proof.verify(
+        epoch_1.root(),
+        epoch_1.leaf(2),
+        &config(b"epoch-1"),
+        &Keccak256Hasher
+    ));
+    assert!(!proof.verify(
+        epoch_2.root(),
+        epoch_2.leaf(2),
+        &config(b"epoch-2"),
+        &Keccak256Hasher
+    ));
+    assert!(!proof.verify(
+        epoch_1.root(),
+        epoch_1.leaf(2),
+        &config(b"epoch-1"),
+        &Sha256Hasher
+    ));
+
+    let multi = epoch_1.bound_multi_proof(&[0, 3]);
+    assert!(multi.verify(epoch_1.root(), &config(b"epoch-1"), &Keccak256Hasher));
+    assert!(!multi.verify(epoch_1.root(), &config(b"epoch-2"), &Keccak256Hasher));
+
+    let positional = MerkleTree::new_positional(leaves(5), PositionalSha256Hasher);
+    let proof = positional.bound_positional_proof(4);
+    let positional_config = positional.config();
+    assert!(proof.verify(
+        positional.root(),
+        positional.leaf(4),
+        &positional_config,
+        &PositionalSha256Hasher
+    ));
+    let sorted_config = TreeConfig {
+        pair_ordering: PairOrdering::Sorted,
+        ..positional_config
+    };
+    assert!(!proof.verify(
+        positional.root(),
+        positional.leaf(4),
+        &sorted_config,
+        &PositionalSha256Hasher
+    ));
+}
+
+#[test]
+fn dump_round_trips_with_its_config() {
+    let config = TreeConfig {
+        hasher: "sha256".to_string(),
+        pair_ordering: PairOrdering::Positional,
+        multi_proof: MultiProofAlgorithm::Positional,
+        leaf_policy: ZeroLeafPolicy::Warn,
+        domain_tag: vec![0xab, 0xcd],
+        ..TreeConfig::default()
+    };
+    let tree = MerkleTree::try_with_config(leaves(5), PositionalSha256Hasher, config).unwrap();
+    let dump = tree.dump();
+    assert!(dump.contains(r#""pairOrdering":"positional","multiProof":"positional","leafEncoding":"","leafPolicy":"warn""#));
+    assert_eq!(
+        MerkleTree::load(&dump, PositionalSha256Hasher).unwrap(),
+        tree
+    );
+    assert!(MerkleTree::load(&dump, Sha256Hasher).is_err());
+    let tampered = dump.replace("\"positional\"", "\"sorted\"");
+    assert!(MerkleTree::load(&tampered, PositionalSha256Hasher).is_err());
+}
+
+#[test]
+fn config_is_kept_and_rebuilds_the_tree() {
+    let config = TreeConfig {
+        hasher: "sha256".to_string(),
+        pair_ordering: PairOrdering::Positional,
+        multi_proof: MultiProofAlgorithm::Positional,
+        leaf_policy: ZeroLeafPolicy::Reject,
+        domain_tag: b"log-1".to_vec(),
+        ..TreeConfig::default()
+    };
+    let tree =
+        MerkleTree::try_with_config(leaves(5), PositionalSha256Hasher, config.clone()).unwrap();
+    assert_eq!(tree.config(), config);
+    assert!(tree.is_positional());
+    assert_eq!(
+        tree.root(),
+        MerkleTree::new_positional(leaves(5), PositionalSha256Hasher).root()
+    );
+
+    // the config alone selects the hasher and layout
+    let rebuilt = config.build(leaves(5)).unwrap();
+    assert_eq!(rebuilt.root(), tree.root());
+    let proof = tree.positional_proof(3);
+    assert!(config.verify_positional_proof(tree.root(), tree.leaf(3), &proof));
+    let unsided: Vec<Bytes> = proof.iter().map(|(_, node)| node.clone()).collect();
+    assert!(!config.verify_proof(tree.root(), tree.leaf(3), &unsided));
+
+    let sorted = TreeConfig::default().build(leaves(5)).unwrap();
+    assert_eq!(
+        sorted.root(),
+        MerkleTree::new(leaves(5), Keccak256Hasher).root()
+    );
+    assert!(TreeConfig::default().verify_proof(sorted.root(), sorted.leaf(2), &sorted.proof(2)));
+    let mp = sorted.multi_proof(&[0, 4]);
+    assert!(TreeConfig::default().verify_multi_proof(sorted.root(), &mp));
+
+    // the domain tag and the leaf policy are part of the fingerprint
+    assert_ne!(
+        config.fingerprint(&PositionalSha256Hasher),
+        TreeConfig {
+            domain_tag: Vec::new(),
+            ..config.clone()
+        }
+        .fingerprint(&PositionalSha256Hasher)
+    );
+
+    let mut zero = leaves(5);
+    zero[2] = vec![0u8; 32];
+    assert_eq!(
+        MerkleTree::try_with_config(zero, PositionalSha256Hasher, config.clone()),
+        Err(MerkleError::ZeroLeaf(2))
+    );
+    for bad in [
+        TreeConfig {
+            hasher: "keccak256".to_string(),
+            ..config.clone()
+        },
+        TreeConfig {
+            arity: 4,
+            ..config.clone()
+        },
+        TreeConfig {
+            multi_proof: MultiProofAlgorithm::OZCompatible,
+            ..config.clone()
+        },
+        TreeConfig {
+            padding: Padding::ZeroSubtrees {
+                depth: 3,
+                zero_value: [0u8; 32],
+            },
+            ..config.clone()
+        },
+    ] {
+        assert!(matches!(
+            MerkleTree::try_with_config(leaves(5), PositionalSha256Hasher, bad),
+            Err(MerkleError::InvalidConfig(_))
+        ));
+    }
+    let unknown = TreeConfig {
+        hasher: "sha3-256".to_string(),
+        pair_ordering: PairOrdering::Positional,
+        multi_proof: MultiProofAlgorithm::Positional,
+        ..TreeConfig::default()
+    };
+    assert!(matches!(
+        unknown.build(leaves(2)),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn positional_multi_proofs_follow_the_config() {
+    let node_hash = |a: &[u8], b: &[u8]| PositionalSha256Hasher.hash_node(a, b).to_vec();
+    for n in 1..=20u8 {
+        let tree = MerkleTree::new_positional(leaves(n), PositionalSha256Hasher);
+        let config = tree.config();
+        assert_eq!(config.multi_proof, MultiProofAlgorithm::Positional);
+        let n = n as usize;
+        for positions in [
+            vec![],
+            vec![n - 1],
+            (0..n).rev().step_by(4).collect(),
+            (0..n).step_by(3).collect(),
+        ] {
+            let mp = tree.positional_multi_proof(&positions);
+            assert_eq!(mp.leaf_count, n);
+            assert!(config.verify_positional_multi_proof(tree.root(), &mp));
+            assert!(!TreeConfig::default().verify_positional_multi_proof(tree.root(), &mp));
+            assert_eq!(
+                try_process_positional_multi_proof(&mp, node_hash).unwrap(),
+                tree.root()
+            );
+
+            let mut extra = mp.clone();
+            extra.proof.push(vec![0u8; 32]);
+            assert!(!verify_positional_multi_proof(
+                tree.root(),
+                &extra,
+                node_hash
+            ));
+        }
+    }
+
+    let tree = MerkleTree::new_positional(leaves(7), PositionalSha256Hasher);
+    let mp = tree.positional_multi_proof(&[5, 1, 2]);
+    assert_eq!(mp.positions, vec![1, 2, 5]);
+    // siblings from the leaves up; the unpaired last leaf is carried up and proven as is
+    assert_eq!(
+        mp.proof,
+        vec![
+            tree.leaf(0).to_vec(),
+            tree.leaf(3).to_vec(),
+            tree.leaf(4).to_vec(),
+            tree.leaf(6).to_vec()
+        ]
+    );
+    let mut moved = mp.clone();
+    moved.positions = vec![1, 2, 4];
+    assert!(!verify_positional_multi_proof(
+        tree.root(),
+        &moved,
+        node_hash
+    ));
+    let mut unsorted = mp.clone();
+    unsorted.positions = vec![2, 1, 5];
+    assert_eq!(
+        try_process_positional_multi_proof(&unsorted, node_hash),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    let mut short = mp.clone();
+    short.proof.pop();
+    assert_eq!(
+        try_process_positional_multi_proof(&short, node_hash),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    // a hostile leaf count or oversized proof fails before any layout is built
+    for leaf_count in [usize::MAX, usize::MAX / 2 + 1, 1 << 40] {
+        let mut huge = mp.clone();
+        huge.leaf_count = leaf_count;
+        assert_eq!(
+            try_process_positional_multi_proof(&huge, node_hash),
+            Err(MerkleError::InvalidMultiProof)
+        );
+    }
+    let mut padded = mp.clone();
+    padded.proof.resize(3 * 3 + 1, vec![0u8; 32]);
+    assert_eq!(
+        try_process_positional_multi_proof(&padded, node_hash),
+        Err(MerkleError::InvalidMultiProof)
+    );
+    assert_eq!(
+        try_get_positional_multi_proof(tree.as_slice(), &[7]),
+        Err(MerkleError::LeafOutOfRange {
+            position: 7,
+            leaves: 7
+        })
+    );
+    assert_eq!(
+        try_get_positional_multi_proof(tree.as_slice(), &[3, 3]),
+        Err(MerkleError::DuplicateIndex)
+    );
+
+    let bound = tree.bound_positional_multi_proof(&[0, 6]);
+    assert!(bound.verify(tree.root(), &tree.config(), &PositionalSha256Hasher));
+    let sorted = TreeConfig {
+        pair_ordering: PairOrdering::Sorted,
+        multi_proof: MultiProofAlgorithm::OZCompatible,
+        ..tree.config()
+    };
+    assert!(!bound.verify(tree.root(), &sorted, &PositionalSha256Hasher));
+}
+
+#[test]
+#[should_panic(expected = "Multiproof algorithm of the tree")]
+fn oz_multi_proof_of_positional_tree_panics() {
+    MerkleTree::new_positional(leaves(4), PositionalSha256Hasher).multi_proof(&[0]);
+}
+
+#[test]
+fn compute_root_matches_built_tree() {
+    // a non-commutative hasher catches any child-order mistake; it reports no pair ordering
+    // so both layouts accept it
+    struct Unordered;
+    impl Hasher for Unordered {
+        fn hash_node(&self, left: &[u8], right: &[u8]) -> [u8; 32] {
+            positional_node_hash(left, right)
+        }
+        fn hash_leaf(&self, data: &[u8]) -> [u8; 32] {
+            keccak256(data)
+        }
+    }
+    let h = &Unordered;
+    for n in 1..=70u8 {
+        let all = leaves(n);
+        let tree = MerkleTree::new(all.clone(), h);
+        assert_eq!(
+            compute_root(&all, &h).unwrap()[..],
+            tree.root()[..],
+            "n = {}",
+            n
+        );
+        let positional = MerkleTree::new_positional(all.clone(), h);
+        assert_eq!(
+            compute_positional_root(all.iter(), &h).unwrap()[..],
+            positional.root()[..],
+            "n = {}",
+            n
+        );
+    }
+    assert_eq!(
+        compute_root(leaves(5), &Keccak256Hasher).unwrap()[..],
+        MerkleTree::new(leaves(5), Keccak256Hasher).root()[..]
+    );
+}
+
+#[test]
+fn compute_root_rejects_bad_leaves() {
+    let none: [Bytes; 0] = [];
+    assert_eq!(
+        compute_root(none, &Keccak256Hasher),
+        Err(MerkleError::EmptyTree)
+    );
+    let mut bad = leaves(3);
+    bad[2].push(0);
+    assert_eq!(
+        compute_root(bad, &Keccak256Hasher),
+        Err(MerkleError::InvalidNode(33))
+    );
+}
+
+#[test]
+fn rejects_hashers_of_the_other_pair_ordering() {
+    fn config_error<T>(r: Result<T, MerkleError>) -> bool {
+        matches!(r, Err(MerkleError::InvalidConfig(_)))
+    }
+    assert!(config_error(MerkleTree::try_new_positional(
+        leaves(4),
+        Keccak256Hasher
+    )));
+    assert!(config_error(MerkleTree::try_new(
+        leaves(4),
+        PositionalKeccak256Hasher
+    )));
+    assert!(config_error(MerkleTree::try_new(leaves(4), Rfc6962Hasher)));
+    assert!(config_error(FixedDepthTree::try_new(
+        3,
+        [0u8; 32],
+        Sha256Hasher
+    )));
+    assert!(config_error(CompositeTree::try_new(
+        leaves(4),
+        Keccak256Hasher
+    )));
+
+    // matching hashers build trees whose config verifies their own proofs
+    let sorted = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let positional = MerkleTree::new_positional(leaves(5), PositionalKeccak256Hasher);
+    for p in 0..5 {
+        assert!(sorted
+            .config()
+            .verify_proof(sorted.root(), sorted.leaf(p), &sorted.proof(p)));
+        assert!(positional.config().verify_positional_proof(
+            positional.root(),
+            positional.leaf(p),
+            &positional.positional_proof(p)
+        ));
+    }
+}
+
+#[test]
+fn proofs_of_the_other_pair_ordering_are_rejected() {
+    let sorted = MerkleTree::new(leaves(5), Keccak256Hasher);
+    let positional = MerkleTree::new_positional(leaves(5), PositionalKeccak256Hasher);
+    assert!(matches!(
+        sorted.try_positional_proof(1),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        positional.try_proof(1),
+        Err(MerkleError::InvalidConfig(_))
+    ));
+    assert_eq!(
+        sorted.try_proof(5),
+        Err(MerkleError::LeafOutOfRange {
+            position: 5,
+            leaves: 5
+        })
+    );
+    assert_eq!(
+        positional.try_positional_proof(1).unwrap(),
+        positional.positional_proof(1)
+    );
+}
+
+#[test]
+#[should_panic(expected = "proven with MerkleTree::proof")]
+fn bound_positional_proof_of_sorted_tree_panics() {
+    MerkleTree::new(leaves(5), Keccak256Hasher).bound_positional_proof(1);
+}
+
+#[test]
+fn single_leaf_root_follows_the_config() {
+    let leaf = vec![7u8; 32];
+    let hashed = |pair_ordering| TreeConfig {
+        hasher: "keccak256".to_string(),
+        pair_ordering,
+        multi_proof: MultiProofAlgorithm::for_ordering(pair_ordering),
+        single_leaf: SingleLeafRoot::Hashed,
+        ..TreeConfig::default()
+    };
+
+    let sorted = TreeConfig::default();
+    let tree =
+        MerkleTree::try_with_config(vec![leaf.clone()], Keccak256Hasher, sorted.clone()).unwrap();
+    assert_eq!(tree.root(), leaf.as_slice());
+    assert!(sorted.verify_proof(tree.root(), &leaf, &tree.proof(0)));
+
+    let config = hashed(PairOrdering::Sorted);
+    let mut tree =
+        MerkleTree::try_with_config(vec![leaf.clone()], Keccak256Hasher, config.clone()).unwrap();
+    assert_eq!(tree.root(), keccak256(&leaf).as_slice());
+    assert!(tree.proof(0).is_empty());
+    assert!(config.verify_proof(tree.root(), &leaf, &tree.proof(0)));
+    assert!(!sorted.verify_proof(tree.root(), &leaf, &tree.proof(0)));
+    assert!(config.verify_multi_proof(tree.root(), &tree.multi_proof(&[0])));
+    assert!(config.verify_multi_proof(tree.root(), &tree.multi_proof(&[])));
+    assert!(tree
+        .bound_proof(0)
+        .verify(tree.root(), &leaf, &config, &Keccak256Hasher));
+    assert_ne!(
+        config.fingerprint(&Keccak256Hasher),
+        sorted.fingerprint(&Keccak256Hasher)
+    );
+
+    // the policy survives dumps and updates, and only applies to one-leaf trees
+    let loaded = MerkleTree::load(&tree.dump(), Keccak256Hasher).unwrap();
+    assert_eq!(loaded, tree);
+    tree.update_leaf(0, &[8u8; 32]);
+    assert_eq!(tree.root(), keccak256(&[8u8; 32]).as_slice());
+    let two = MerkleTree::try_with_config(leaves(2), Keccak256Hasher, config).unwrap();
+    assert_eq!(
+        two.root(),
+        MerkleTree::new(leaves(2), Keccak256Hasher).root()
+    );
+
+    let config = hashed(PairOrdering::Positional);
+    let tree = MerkleTree::try_with_config(
+        vec![leaf.clone()],
+        PositionalKeccak256Hasher,
+        TreeConfig {
+            hasher: PositionalKeccak256Hasher.name().to_string(),
+            ..config.clone()
+        },
+    )
+    .unwrap();
+    let config = tree.config();
+    assert_eq!(tree.root(), keccak256(&leaf).as_slice());
+    assert!(config.verify_positional_proof(tree.root(), &leaf, &tree.positional_proof(0)));
+    assert!(config.verify_positional_multi_proof(tree.root(), &tree.positional_multi_proof(&[0])));
+    assert!(config.verify_positional_multi_proof(tree.root(), &tree.positional_multi_proof(&[])));
+    assert!(!config.verify_positional_proof(&leaf, &leaf, &[]));
+}
+
+#[test]
+fn zero_leaf_policy_applies_to_builds_and_updates() {
+    let zero = vec![0u8; 32];
+    let mut with_zeros = leaves(5);
+    with_zeros[1] = zero.clone();
+    with_zeros[3] = zero.clone();
+    let config = |leaf_policy| TreeConfig {
+        leaf_policy,
+        ..TreeConfig::default()
+    };
+
+    let mut warned = MerkleTree::try_with_config(
+        with_zeros.clone(),
+        Keccak256Hasher,
+        config(ZeroLeafPolicy::Warn),
+    )
+    .unwrap();
+    assert_eq!(warned.zero_leaves(), vec![1, 3]);
+    warned.batch_update(&[(1, leaves(2)[1].clone()), (4, zero.clone())]);
+    assert_eq!(warned.zero_leaves(), vec![3, 4]);
+    let loaded = MerkleTree::load(&warned.dump(), Keccak256Hasher).unwrap();
+    assert_eq!(loaded.zero_leaves(), vec![3, 4]);
+
+    let allowed = MerkleTree::new(with_zeros.clone(), Keccak256Hasher);
+    assert!(allowed.zero_leaves().is_empty());
+
+    assert_eq!(
+        MerkleTree::try_with_config(with_zeros, Keccak256Hasher, config(ZeroLeafPolicy::Reject)),
+        Err(MerkleError::ZeroLeaf(1))
+    );
+    let mut rejecting =
+        MerkleTree::try_with_config(leaves(5), Keccak256Hasher, config(ZeroLeafPolicy::Reject))
+            .unwrap();
+    let root = rejecting.root().to_vec();
+    assert_eq!(
+        rejecting.try_batch_update(&[(0, leaves(9)[8].clone()), (2, zero.clone())]),
+        Err(MerkleError::ZeroLeaf(2))
+    );
+    assert_eq!(
+        rejecting.try_update_leaf(4, &zero),
+        Err(MerkleError::ZeroLeaf(4))
+    );
+    assert_eq!(rejecting.root(), root.as_slice());
+
+    // a dump whose leaves break a rejecting policy does not load
+    let dump = warned
+        .dump()
+        .replace(r#""leafPolicy":"warn""#, r#""leafPolicy":"reject""#);
+    assert!(MerkleTree::load(&dump, Keccak256Hasher).is_err());
+}
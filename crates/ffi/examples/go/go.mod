@@ -0,0 +1,3 @@
+module example.com/merklerust
+
+go 1.21
@@ -0,0 +1,83 @@
+// Minimal cgo wrapper around the merklerust shared library.
+//
+// Build the library first with `cargo build --release -p merklerust-ffi`, then run
+// `go run .` from this directory.
+package main
+
+/*
+#cgo CFLAGS: -I${SRCDIR}/../../include
+#cgo LDFLAGS: -L${SRCDIR}/../../../../target/release -lmerklerust_ffi
+#include "merklerust.h"
+*/
+import "C"
+
+import (
+	"fmt"
+	"unsafe"
+)
+
+const nodeSize = 32
+
+func ptr(b []byte) *C.uint8_t {
+	if len(b) == 0 {
+		return nil
+	}
+	return (*C.uint8_t)(unsafe.Pointer(&b[0]))
+}
+
+func check(code C.int32_t) error {
+	if code < 0 {
+		return fmt.Errorf("merklerust error %d", int(code))
+	}
+	return nil
+}
+
+// Build returns the flat tree (root first) over 32-byte leaves.
+func Build(leaves [][nodeSize]byte) ([]byte, error) {
+	flat := make([]byte, 0, len(leaves)*nodeSize)
+	for _, l := range leaves {
+		flat = append(flat, l[:]...)
+	}
+	var nodes C.size_t
+	if err := check(C.mr_tree_node_count(C.size_t(len(leaves)), &nodes)); err != nil {
+		return nil, err
+	}
+	tree := make([]byte, int(nodes)*nodeSize)
+	err := check(C.mr_make_tree(ptr(flat), C.size_t(len(leaves)), ptr(tree), C.size_t(len(tree))))
+	return tree, err
+}
+
+// Prove returns the proof nodes for the 0-based leaf position.
+func Prove(tree []byte, position int) ([]byte, error) {
+	out := make([]byte, 64*nodeSize)
+	var written C.size_t
+	err := check(C.mr_get_proof(ptr(tree), C.size_t(len(tree)/nodeSize), C.size_t(position),
+		ptr(out), C.size_t(len(out)), &written))
+	return out[:int(written)*nodeSize], err
+}
+
+// Verify checks a proof for leaf against root.
+func Verify(root, leaf, proof []byte) (bool, error) {
+	code := C.mr_verify_proof(ptr(root), ptr(leaf), ptr(proof), C.size_t(len(proof)/nodeSize))
+	return code == 1, check(code)
+}
+
+func main() {
+	leaves := make([][nodeSize]byte, 5)
+	for i := range leaves {
+		leaves[i][0] = byte(i + 1)
+	}
+	tree, err := Build(leaves)
+	if err != nil {
+		panic(err)
+	}
+	proof, err := Prove(tree, 3)
+	if err != nil {
+		panic(err)
+	}
+	ok, err := Verify(tree[:nodeSize], leaves[3][:], proof)
+	if err != nil {
+		panic(err)
+	}
+	fmt.Printf("root %x, leaf 3 verified: %v\n", tree[:nodeSize], ok)
+}
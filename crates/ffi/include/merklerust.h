@@ -0,0 +1,28 @@
+/* C interface to merklerust (crates/ffi). Nodes are 32 bytes; trees are flat buffers of
+ * 2 * leaf_count - 1 nodes with the root first. Functions return MR_OK or a negative
+ * MR_ERR_* code; mr_verify_proof returns 1 (valid) or 0 (invalid) on success.
+ * mr_make_tree enforces the tree size limits of the MERKLERUST_PROFILE resource profile. */
+#ifndef MERKLERUST_H
+#define MERKLERUST_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define MR_OK 0
+#define MR_ERR_NULL_POINTER -1
+#define MR_ERR_INVALID_ARGUMENT -2
+#define MR_ERR_BUFFER_TOO_SMALL -3
+#define MR_ERR_PANIC -4
+
+int32_t mr_tree_node_count(size_t leaf_count, size_t *out_node_count);
+
+int32_t mr_make_tree(const uint8_t *leaves, size_t leaf_count, uint8_t *out_tree,
+                     size_t out_tree_len);
+
+int32_t mr_get_proof(const uint8_t *tree, size_t node_count, size_t leaf_position,
+                     uint8_t *out_proof, size_t out_proof_len, size_t *out_proof_nodes);
+
+int32_t mr_verify_proof(const uint8_t *root, const uint8_t *leaf, const uint8_t *proof,
+                        size_t proof_nodes);
+
+#endif
@@ -0,0 +1,184 @@
+//! C ABI over `merklerust-core` for use from Go (cgo) and other FFI hosts. Trees are passed as
+//! flat buffers of 32-byte nodes in the core heap layout, and nodes are combined with the
+//! sorted-pair keccak256 hash used by the node bindings. See `include/merklerust.h`.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+use std::sync::OnceLock;
+
+use merklerust_core::consts::HASH_LEN;
+use merklerust_core::hashes::standard_node_hash;
+use merklerust_core::merkle::{
+    check_tree_size, get_proof_hashes, leaf_tree_index, make_merkle_tree_bytes, verify_proof, Bytes,
+};
+use merklerust_core::profile::ResourceProfile;
+
+pub const MR_OK: i32 = 0;
+pub const MR_ERR_NULL_POINTER: i32 = -1;
+pub const MR_ERR_INVALID_ARGUMENT: i32 = -2;
+pub const MR_ERR_BUFFER_TOO_SMALL: i32 = -3;
+pub const MR_ERR_PANIC: i32 = -4;
+
+fn node_hash(a: &[u8], b: &[u8]) -> Bytes {
+    standard_node_hash(a, b).to_vec()
+}
+
+/// The resource profile named by `MERKLERUST_PROFILE`, read once.
+fn profile() -> ResourceProfile {
+    static PROFILE: OnceLock<ResourceProfile> = OnceLock::new();
+    *PROFILE.get_or_init(ResourceProfile::from_env)
+}
+
+fn guard<F>(f: F) -> i32
+where
+    F: FnOnce() -> i32,
+{
+    // a panic must not unwind into the host; output buffers may be partially written
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(MR_ERR_PANIC)
+}
+
+/// Bytes in `count` 32-byte nodes, or `None` if no buffer can be that long.
+fn nodes_len(count: usize) -> Option<usize> {
+    count
+        .checked_mul(HASH_LEN)
+        .filter(|&len| len <= isize::MAX as usize)
+}
+
+unsafe fn nodes(ptr: *const u8, count: usize) -> Option<Vec<Bytes>> {
+    let len = nodes_len(count)?;
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let nodes = slice::from_raw_parts(ptr, len)
+        .chunks(HASH_LEN)
+        .map(|c| c.to_vec())
+        .collect();
+    Some(nodes)
+}
+
+/// Number of nodes in a tree over `leaf_count` leaves (`2 * leaf_count - 1`).
+///
+/// # Safety
+/// `out_node_count` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mr_tree_node_count(leaf_count: usize, out_node_count: *mut usize) -> i32 {
+    if out_node_count.is_null() {
+        return MR_ERR_NULL_POINTER;
+    }
+    if leaf_count == 0 || leaf_count > usize::MAX / 64 {
+        return MR_ERR_INVALID_ARGUMENT;
+    }
+    *out_node_count = 2 * leaf_count - 1;
+    MR_OK
+}
+
+/// Build a tree over `leaf_count` consecutive 32-byte leaves into `out_tree`, which must hold
+/// `out_tree_len >= 32 * (2 * leaf_count - 1)` bytes. Trees over the size limits of the
+/// `MERKLERUST_PROFILE` resource profile fail with `MR_ERR_INVALID_ARGUMENT`.
+///
+/// # Safety
+/// `leaves` must be valid for reads of `32 * leaf_count` bytes and `out_tree` for writes of
+/// `out_tree_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mr_make_tree(
+    leaves: *const u8,
+    leaf_count: usize,
+    out_tree: *mut u8,
+    out_tree_len: usize,
+) -> i32 {
+    if leaves.is_null() || out_tree.is_null() {
+        return MR_ERR_NULL_POINTER;
+    }
+    if leaf_count == 0 || leaf_count > usize::MAX / 64 {
+        return MR_ERR_INVALID_ARGUMENT;
+    }
+    if check_tree_size(leaf_count, &profile().tree_limits()).is_err() {
+        return MR_ERR_INVALID_ARGUMENT;
+    }
+    if out_tree_len < HASH_LEN * (2 * leaf_count - 1) {
+        return MR_ERR_BUFFER_TOO_SMALL;
+    }
+
+    let Some(leaves) = nodes(leaves, leaf_count) else {
+        return MR_ERR_INVALID_ARGUMENT;
+    };
+    let out = slice::from_raw_parts_mut(out_tree, out_tree_len);
+    guard(move || {
+        let tree = make_merkle_tree_bytes(leaves, node_hash);
+        for (chunk, node) in out.chunks_mut(HASH_LEN).zip(tree.iter()) {
+            chunk.copy_from_slice(node);
+        }
+        MR_OK
+    })
+}
+
+/// Write the proof for the 0-based `leaf_position` of a `node_count`-node tree into
+/// `out_proof` (capacity `out_proof_len` bytes) and store the number of proof nodes in
+/// `out_proof_nodes`.
+///
+/// # Safety
+/// `tree` must be valid for reads of `32 * node_count` bytes, `out_proof` for writes of
+/// `out_proof_len` bytes and `out_proof_nodes` for writes.
+#[no_mangle]
+pub unsafe extern "C" fn mr_get_proof(
+    tree: *const u8,
+    node_count: usize,
+    leaf_position: usize,
+    out_proof: *mut u8,
+    out_proof_len: usize,
+    out_proof_nodes: *mut usize,
+) -> i32 {
+    if tree.is_null() || out_proof.is_null() || out_proof_nodes.is_null() {
+        return MR_ERR_NULL_POINTER;
+    }
+    if node_count == 0 || node_count.is_multiple_of(2) || leaf_position >= node_count.div_ceil(2) {
+        return MR_ERR_INVALID_ARGUMENT;
+    }
+    let Some(tree_len) = nodes_len(node_count) else {
+        return MR_ERR_INVALID_ARGUMENT;
+    };
+
+    // read the proof nodes in place rather than copying the whole tree
+    let tree = slice::from_raw_parts(tree, tree_len)
+        .as_chunks::<HASH_LEN>()
+        .0;
+    let out = slice::from_raw_parts_mut(out_proof, out_proof_len);
+    let out_nodes = &mut *out_proof_nodes;
+    guard(move || {
+        let proof = get_proof_hashes(tree, leaf_tree_index(node_count, leaf_position));
+        if out.len() < HASH_LEN * proof.len() {
+            return MR_ERR_BUFFER_TOO_SMALL;
+        }
+        for (chunk, node) in out.chunks_mut(HASH_LEN).zip(proof.iter()) {
+            chunk.copy_from_slice(node);
+        }
+        *out_nodes = proof.len();
+        MR_OK
+    })
+}
+
+/// Returns 1 if `proof` (`proof_nodes` consecutive 32-byte nodes) proves `leaf` under `root`,
+/// 0 if it does not, or a negative error code. Fails with `MR_ERR_INVALID_ARGUMENT` if
+/// `32 * proof_nodes` overflows.
+///
+/// # Safety
+/// `root` and `leaf` must be valid for reads of 32 bytes and `proof` for reads of
+/// `32 * proof_nodes` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mr_verify_proof(
+    root: *const u8,
+    leaf: *const u8,
+    proof: *const u8,
+    proof_nodes: usize,
+) -> i32 {
+    if root.is_null() || leaf.is_null() || (proof.is_null() && proof_nodes > 0) {
+        return MR_ERR_NULL_POINTER;
+    }
+
+    let Some(proof) = nodes(proof, proof_nodes) else {
+        return MR_ERR_INVALID_ARGUMENT;
+    };
+    let root = slice::from_raw_parts(root, HASH_LEN);
+    let leaf = slice::from_raw_parts(leaf, HASH_LEN);
+    verify_proof(root, leaf, &proof, node_hash) as i32
+}
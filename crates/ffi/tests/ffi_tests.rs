@@ -0,0 +1,85 @@
+use merklerust_ffi::{
+    mr_get_proof, mr_make_tree, mr_tree_node_count, mr_verify_proof, MR_ERR_BUFFER_TOO_SMALL,
+    MR_ERR_INVALID_ARGUMENT, MR_OK,
+};
+
+#[test]
+fn build_prove_verify_round_trip() {
+    let leaves: Vec<u8> = (0u8..5).flat_map(|i| [i; 32]).collect();
+    unsafe {
+        let mut node_count = 0usize;
+        assert_eq!(mr_tree_node_count(5, &mut node_count), MR_OK);
+        assert_eq!(node_count, 9);
+
+        let mut tree = vec![0u8; 32 * node_count];
+        assert_eq!(
+            mr_make_tree(leaves.as_ptr(), 5, tree.as_mut_ptr(), tree.len() - 1),
+            MR_ERR_BUFFER_TOO_SMALL
+        );
+        assert_eq!(
+            mr_make_tree(leaves.as_ptr(), 5, tree.as_mut_ptr(), tree.len()),
+            MR_OK
+        );
+
+        let mut proof = vec![0u8; 32 * 8];
+        let mut proof_nodes = 0usize;
+        assert_eq!(
+            mr_get_proof(
+                tree.as_ptr(),
+                node_count,
+                2,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_nodes
+            ),
+            MR_OK
+        );
+        assert_eq!(
+            mr_get_proof(
+                tree.as_ptr(),
+                node_count,
+                5,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_nodes
+            ),
+            MR_ERR_INVALID_ARGUMENT
+        );
+
+        let root = &tree[..32];
+        let leaf = &leaves[64..96];
+        assert_eq!(
+            mr_verify_proof(root.as_ptr(), leaf.as_ptr(), proof.as_ptr(), proof_nodes),
+            1
+        );
+        let other = &leaves[..32];
+        assert_eq!(
+            mr_verify_proof(root.as_ptr(), other.as_ptr(), proof.as_ptr(), proof_nodes),
+            0
+        );
+    }
+}
+
+#[test]
+fn overflowing_node_counts_are_rejected() {
+    let node = [0u8; 32];
+    let mut proof = [0u8; 32];
+    let mut proof_nodes = 0usize;
+    unsafe {
+        assert_eq!(
+            mr_verify_proof(node.as_ptr(), node.as_ptr(), node.as_ptr(), usize::MAX / 8),
+            MR_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            mr_get_proof(
+                node.as_ptr(),
+                usize::MAX / 8,
+                0,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_nodes
+            ),
+            MR_ERR_INVALID_ARGUMENT
+        );
+    }
+}
@@ -1,28 +1,80 @@
-use merklerust_core::hashes::keccak256;
+use merklerust_core::consts::HASH_LEN;
+use merklerust_core::hashes::{Hasher, hasher_by_name, keccak256};
+use merklerust_core::merkle::{Hash, MerkleError};
+use merklerust_core::profile::ResourceProfile;
+use merklerust_core::smt::KeyPath;
+use merklerust_core::tree::{PairOrdering, TreeConfig};
+use napi::bindgen_prelude::Buffer;
 use napi_derive::napi;
+use std::sync::{Mutex, PoisonError};
 
 fn default_node_hash(a: &[u8], b: &[u8]) -> Vec<u8> {
     let (left, right) = if a <= b { (a, b) } else { (b, a) };
     keccak256(&[left, right].concat()).to_vec()
 }
 
-fn catch_unwind_result<T, F>(f: F) -> napi::Result<T>
-where
-    F: FnOnce() -> T + std::panic::UnwindSafe,
-{
-    match std::panic::catch_unwind(f) {
-        Ok(v) => Ok(v),
-        Err(e) => {
-            let msg = if let Some(s) = e.downcast_ref::<&str>() {
-                s.to_string()
-            } else if let Some(s) = e.downcast_ref::<String>() {
-                s.clone()
-            } else {
-                "panic in Rust code".to_string()
-            };
-            Err(napi::Error::from_reason(msg))
-        }
+/// Built-in hasher named `hash` (`keccak256` when omitted).
+fn resolve_hasher(hash: Option<String>) -> napi::Result<Box<dyn Hasher + Send + Sync>> {
+    let name = hash.as_deref().unwrap_or("keccak256");
+    hasher_by_name(name)
+        .ok_or_else(|| napi::Error::from_reason(format!("Unknown hash algorithm {:?}", name)))
+}
+
+/// Built-in hasher named `hash` (`keccak256` when omitted) that keeps the left/right order of
+/// pairs: `keccak256`, `sha256` or `rfc6962`.
+fn resolve_positional_hasher(hash: Option<String>) -> napi::Result<Box<dyn Hasher + Send + Sync>> {
+    TreeConfig {
+        hasher: hash.unwrap_or_else(|| "keccak256".to_string()),
+        pair_ordering: PairOrdering::Positional,
+        ..TreeConfig::default()
     }
+    .build_hasher()
+    .map_err(to_napi_error)
+}
+
+/// SMT key-to-path strategy named `key_path` (`raw` when omitted): `raw`, `sha256`, `blake3`
+/// or `truncated`.
+fn resolve_key_path(key_path: Option<String>) -> napi::Result<KeyPath> {
+    let name = key_path.as_deref().unwrap_or("raw");
+    KeyPath::by_name(name).ok_or_else(|| {
+        to_napi_error(MerkleError::InvalidConfig(format!(
+            "Unknown SMT key path {:?}",
+            name
+        )))
+    })
+}
+
+/// The active profile; `None` until first use, when it is read from the environment.
+static PROFILE: Mutex<Option<ResourceProfile>> = Mutex::new(None);
+
+fn profile() -> ResourceProfile {
+    let mut profile = PROFILE.lock().unwrap_or_else(PoisonError::into_inner);
+    *profile.get_or_insert_with(ResourceProfile::from_env)
+}
+
+/// Select `"standard"` or `"low-memory"` for the whole process. The default comes from the
+/// `MERKLERUST_PROFILE` environment variable.
+#[napi]
+pub fn set_resource_profile(name: String) -> napi::Result<()> {
+    let profile = ResourceProfile::from_name(&name)
+        .ok_or_else(|| napi::Error::from_reason(format!("Unknown resource profile {:?}", name)))?;
+    *PROFILE.lock().unwrap_or_else(PoisonError::into_inner) = Some(profile);
+    Ok(())
+}
+
+#[napi]
+pub fn resource_profile() -> String {
+    profile().name().to_string()
+}
+
+fn to_napi_error(e: impl std::fmt::Display) -> napi::Error {
+    napi::Error::from_reason(e.to_string())
+}
+
+/// Check a tree of `leaf_count` leaves against the active profile's limits.
+fn check_tree_size(leaf_count: usize) -> napi::Result<()> {
+    merklerust_core::merkle::check_tree_size(leaf_count, &profile().tree_limits())
+        .map_err(to_napi_error)
 }
 
 #[napi(object)]
@@ -33,56 +85,658 @@ pub struct JsMultiProof {
 }
 
 #[napi]
-pub fn make_merkle_tree(leaves: Vec<Vec<u8>>) -> napi::Result<Vec<Vec<u8>>> {
-    catch_unwind_result(|| {
-        merklerust_core::merkle::make_merkle_tree_bytes(leaves, |a, b| default_node_hash(a, b))
+pub fn make_merkle_tree(leaves: Vec<Vec<u8>>, hash: Option<String>) -> napi::Result<Vec<Vec<u8>>> {
+    let hasher = resolve_hasher(hash)?;
+    check_tree_size(leaves.len())?;
+    merklerust_core::merkle::try_make_merkle_tree_by_levels(leaves, |pairs| {
+        hasher.hash_pairs(pairs)
     })
+    .map_err(to_napi_error)
+}
+
+/// Build a tree over the 32-byte leaf records in `leaf_file` without loading it, writing one
+/// level file per tree level into `dir`, and return the root. File buffers stay within the
+/// active profile's build buffer.
+#[napi]
+pub fn build_tree_files(
+    leaf_file: String,
+    dir: String,
+    hash: Option<String>,
+) -> napi::Result<Vec<u8>> {
+    let hasher = resolve_hasher(hash)?;
+    let profile = profile();
+    let file = std::fs::File::open(&leaf_file).map_err(to_napi_error)?;
+    let mut builder = merklerust_core::leaf_io::TreeBuilder::with_profile(dir, hasher, profile)
+        .map_err(to_napi_error)?;
+    builder
+        .feed_reader(std::io::BufReader::new(file))
+        .map_err(to_napi_error)?;
+    let tree = builder.finish().map_err(to_napi_error)?;
+    Ok(tree.root().to_vec())
 }
 
 #[napi]
 pub fn get_proof(tree: Vec<Vec<u8>>, leaf_index: u32) -> napi::Result<Vec<Vec<u8>>> {
-    catch_unwind_result(|| {
-        let idx = leaf_index as usize;
-        merklerust_core::merkle::get_proof(&tree, idx)
-    })
+    check_tree_size(tree.len().div_ceil(2))?;
+    merklerust_core::merkle::try_get_proof(&tree, leaf_index as usize).map_err(to_napi_error)
 }
 
+/// Proof for the 0-based `leaf_position`, without computing the flat tree index.
 #[napi]
-pub fn process_proof(leaf: Vec<u8>, proof: Vec<Vec<u8>>) -> napi::Result<Vec<u8>> {
-    catch_unwind_result(|| {
-        merklerust_core::merkle::process_proof(leaf.as_slice(), &proof, |a, b| {
-            default_node_hash(a, b)
-        })
+pub fn get_proof_for_leaf(tree: Vec<Vec<u8>>, leaf_position: u32) -> napi::Result<Vec<Vec<u8>>> {
+    check_tree_size(tree.len().div_ceil(2))?;
+    merklerust_core::merkle::try_get_proof_for_leaf(&tree, leaf_position as usize)
+        .map_err(to_napi_error)
+}
+
+/// Writes the proof for `leaf_index` into `out` as concatenated 32-byte nodes and returns the
+/// number of nodes written, so proof servers can reuse one Buffer across requests. `tree` holds
+/// the tree's nodes as concatenated 32-byte nodes and is read in place, not copied.
+#[napi]
+pub fn get_proof_into(tree: Buffer, leaf_index: u32, mut out: Buffer) -> napi::Result<u32> {
+    let (nodes, rest) = tree.as_chunks::<HASH_LEN>();
+    if !rest.is_empty() {
+        return Err(to_napi_error(MerkleError::InvalidNode(rest.len())));
+    }
+    if nodes.is_empty() {
+        return Err(to_napi_error(MerkleError::EmptyTree));
+    }
+    check_tree_size(nodes.len().div_ceil(2))?;
+    let proof = merklerust_core::merkle::try_get_proof_hashes(nodes, leaf_index as usize)
+        .map_err(to_napi_error)?;
+
+    let needed = proof.len() * HASH_LEN;
+    if out.len() < needed {
+        return Err(napi::Error::from_reason(format!(
+            "Output buffer too small: need {} bytes, got {}",
+            needed,
+            out.len()
+        )));
+    }
+    for (chunk, node) in out.chunks_mut(HASH_LEN).zip(proof.iter()) {
+        chunk.copy_from_slice(node);
+    }
+
+    Ok(proof.len() as u32)
+}
+
+#[napi]
+pub fn process_proof(
+    leaf: Vec<u8>,
+    proof: Vec<Vec<u8>>,
+    hash: Option<String>,
+) -> napi::Result<Vec<u8>> {
+    let hasher = resolve_hasher(hash)?;
+    merklerust_core::merkle::try_process_proof(leaf.as_slice(), &proof, |a, b| {
+        hasher.hash_node(a, b).to_vec()
     })
+    .map_err(to_napi_error)
 }
 
+/// Runs on all cores unless the low-memory profile is active.
 #[napi]
 pub fn get_multi_proof(tree: Vec<Vec<u8>>, indices: Vec<u32>) -> napi::Result<JsMultiProof> {
-    catch_unwind_result(|| {
-        let idxs: Vec<usize> = indices.into_iter().map(|i| i as usize).collect();
-        let mp = merklerust_core::merkle::get_multi_proof(&tree, idxs);
-        JsMultiProof {
-            leaves: mp.leaves,
-            proof: mp.proof,
-            proof_flags: mp.proof_flags,
-        }
+    check_tree_size(tree.len().div_ceil(2))?;
+    let idxs: Vec<usize> = indices.into_iter().map(|i| i as usize).collect();
+    let mp =
+        merklerust_core::merkle::try_get_multi_proof_with_threads(&tree, idxs, profile().threads())
+            .map_err(to_napi_error)?;
+    Ok(JsMultiProof {
+        leaves: mp.leaves,
+        proof: mp.proof,
+        proof_flags: mp.proof_flags,
+    })
+}
+
+#[napi]
+pub fn get_multi_proof_for_leaves(
+    tree: Vec<Vec<u8>>,
+    leaf_positions: Vec<u32>,
+) -> napi::Result<JsMultiProof> {
+    check_tree_size(tree.len().div_ceil(2))?;
+    let positions: Vec<usize> = leaf_positions.into_iter().map(|p| p as usize).collect();
+    let mp = merklerust_core::merkle::try_get_multi_proof_for_leaves(&tree, &positions)
+        .map_err(to_napi_error)?;
+    Ok(JsMultiProof {
+        leaves: mp.leaves,
+        proof: mp.proof,
+        proof_flags: mp.proof_flags,
     })
 }
 
 #[napi]
-pub fn process_multi_proof(mp: JsMultiProof) -> napi::Result<Vec<u8>> {
-    catch_unwind_result(|| {
-        let core_mp = merklerust_core::merkle::MultiProof::new(mp.leaves, mp.proof, mp.proof_flags);
-        merklerust_core::merkle::process_multi_proof(&core_mp, |a, b| default_node_hash(a, b))
+pub fn process_multi_proof(mp: JsMultiProof, hash: Option<String>) -> napi::Result<Vec<u8>> {
+    let hasher = resolve_hasher(hash)?;
+    let core_mp = merklerust_core::merkle::MultiProof::new(mp.leaves, mp.proof, mp.proof_flags);
+    merklerust_core::merkle::try_process_multi_proof(&core_mp, |a, b| {
+        hasher.hash_node(a, b).to_vec()
     })
+    .map_err(to_napi_error)
 }
 
 #[napi]
-pub fn is_valid_merkle_tree(tree: Vec<Vec<u8>>) -> bool {
-    merklerust_core::merkle::is_valid_merkle_tree(&tree, |a, b| default_node_hash(a, b))
+pub fn is_valid_merkle_tree(tree: Vec<Vec<u8>>, hash: Option<String>) -> napi::Result<bool> {
+    check_tree_size(tree.len().div_ceil(2))?;
+    let hasher = resolve_hasher(hash)?;
+    Ok(merklerust_core::merkle::is_valid_merkle_tree(
+        &tree,
+        |a, b| hasher.hash_node(a, b).to_vec(),
+    ))
+}
+
+/// The renderers need at least the root.
+fn check_rendered_tree(tree: &[Vec<u8>]) -> napi::Result<()> {
+    if tree.is_empty() {
+        return Err(napi::Error::from_reason(
+            "Expected non-zero number of nodes in merkle tree",
+        ));
+    }
+    Ok(())
 }
 
 #[napi]
 pub fn render_merkle_tree(tree: Vec<Vec<u8>>) -> napi::Result<String> {
-    catch_unwind_result(|| merklerust_core::merkle::render_merkle_tree(&tree))
+    check_tree_size(tree.len().div_ceil(2))?;
+    check_rendered_tree(&tree)?;
+    Ok(merklerust_core::merkle::render_merkle_tree(&tree))
+}
+
+#[napi]
+pub fn render_to_dot(tree: Vec<Vec<u8>>) -> napi::Result<String> {
+    check_tree_size(tree.len().div_ceil(2))?;
+    check_rendered_tree(&tree)?;
+    Ok(merklerust_core::merkle::render_merkle_tree_dot(&tree))
+}
+
+#[napi(js_name = "renderToJSON")]
+pub fn render_to_json(tree: Vec<Vec<u8>>) -> napi::Result<String> {
+    check_tree_size(tree.len().div_ceil(2))?;
+    check_rendered_tree(&tree)?;
+    Ok(merklerust_core::merkle::render_merkle_tree_json(&tree))
+}
+
+#[napi]
+pub fn standard_leaf_hash(value: Vec<String>, encoding: Vec<String>) -> napi::Result<Vec<u8>> {
+    merklerust_core::hashes::try_standard_leaf_hash(&encoding, &value)
+        .map(|h| h.to_vec())
+        .map_err(to_napi_error)
+}
+
+#[napi(object)]
+pub struct JsClaim {
+    pub root: Vec<u8>,
+    pub value: Vec<String>,
+    pub encoding: Vec<String>,
+    pub proof: Vec<Vec<u8>>,
+}
+
+#[napi]
+pub fn verify_claim(claim: JsClaim) -> napi::Result<bool> {
+    let leaf = merklerust_core::hashes::try_standard_leaf_hash(&claim.encoding, &claim.value)
+        .map_err(to_napi_error)?;
+    Ok(merklerust_core::merkle::verify_proof(
+        &claim.root,
+        &leaf,
+        &claim.proof,
+        default_node_hash,
+    ))
+}
+
+type DynTree = merklerust_core::tree::MerkleTree<Box<dyn Hasher + Send + Sync>>;
+
+#[napi(object)]
+pub struct JsTreeStats {
+    pub node_count: u32,
+    pub leaf_count: u32,
+    /// Bytes held in Rust memory for the tree nodes; 0 once disposed.
+    pub bytes: i64,
+    pub backend: String,
+    pub disposed: bool,
+}
+
+/// Tree kept in Rust memory between calls, so proofs do not copy the whole tree across the
+/// boundary each time. Call `dispose()` to free a large tree without waiting for GC.
+#[napi(js_name = "MerkleTree")]
+pub struct JsMerkleTree {
+    inner: Option<DynTree>,
+}
+
+#[napi]
+impl JsMerkleTree {
+    #[napi(constructor)]
+    pub fn new(leaves: Vec<Vec<u8>>, hash: Option<String>) -> napi::Result<Self> {
+        let hasher = resolve_hasher(hash)?;
+        check_tree_size(leaves.len())?;
+        let tree = DynTree::try_new(leaves, hasher).map_err(to_napi_error)?;
+        Ok(Self { inner: Some(tree) })
+    }
+
+    fn tree(&self) -> napi::Result<&DynTree> {
+        self.inner
+            .as_ref()
+            .ok_or_else(|| napi::Error::from_reason("MerkleTree has been disposed"))
+    }
+
+    #[napi]
+    pub fn root(&self) -> napi::Result<Vec<u8>> {
+        Ok(self.tree()?.root().to_vec())
+    }
+
+    /// Proof for the 0-based `leaf_position`.
+    #[napi]
+    pub fn get_proof(&self, leaf_position: u32) -> napi::Result<Vec<Vec<u8>>> {
+        let nodes = self.tree()?.as_slice();
+        let index =
+            merklerust_core::merkle::try_leaf_tree_index(nodes.len(), leaf_position as usize)
+                .map_err(to_napi_error)?;
+        merklerust_core::merkle::try_get_proof(nodes, index).map_err(to_napi_error)
+    }
+
+    /// Proofs for several 0-based leaf positions, in order. Runs on all cores unless the
+    /// low-memory profile is active.
+    #[napi]
+    pub fn get_proofs(&self, leaf_positions: Vec<u32>) -> napi::Result<Vec<Vec<Vec<u8>>>> {
+        let nodes = self.tree()?.as_slice();
+        let indices = leaf_positions
+            .iter()
+            .map(|&p| merklerust_core::merkle::try_leaf_tree_index(nodes.len(), p as usize))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(to_napi_error)?;
+        if profile().threads() > 1 {
+            merklerust_core::merkle::try_get_proofs_parallel(nodes, &indices)
+        } else {
+            merklerust_core::merkle::try_get_proofs(nodes, &indices)
+        }
+        .map_err(to_napi_error)
+    }
+
+    #[napi]
+    pub fn stats(&self) -> JsTreeStats {
+        let nodes = self.inner.as_ref().map_or(0, |t| t.as_slice().len());
+        let per_node = HASH_LEN + std::mem::size_of::<Vec<u8>>();
+        JsTreeStats {
+            node_count: nodes as u32,
+            leaf_count: merklerust_core::merkle::leaf_count(nodes) as u32,
+            bytes: (nodes * per_node) as i64,
+            backend: "memory".to_string(),
+            disposed: self.inner.is_none(),
+        }
+    }
+
+    /// Source of a dependency-free ES module that verifies this tree's proofs, for runtimes
+    /// without the native addon.
+    #[napi]
+    pub fn js_verifier(&self) -> napi::Result<String> {
+        merklerust_core::js_verifier::emit_js_verifier(&self.tree()?.config())
+            .map_err(to_napi_error)
+    }
+
+    /// Free the tree now; later calls other than `stats()` throw. Safe to call twice.
+    #[napi]
+    pub fn dispose(&mut self) {
+        self.inner = None;
+    }
+}
+
+type DynSmt = merklerust_core::smt::SparseMerkleTree<Box<dyn Hasher + Send + Sync>>;
+type DynMmr = merklerust_core::mmr::Mmr<Box<dyn Hasher + Send + Sync>>;
+
+fn to_hash(bytes: &[u8]) -> napi::Result<Hash> {
+    bytes
+        .try_into()
+        .map_err(|_| to_napi_error(MerkleError::InvalidNode(bytes.len())))
+}
+
+fn to_hashes(nodes: &[Vec<u8>]) -> napi::Result<Vec<Hash>> {
+    nodes.iter().map(|n| to_hash(n)).collect()
+}
+
+fn to_js_number(n: u64) -> napi::Result<i64> {
+    i64::try_from(n).map_err(to_napi_error)
+}
+
+fn from_js_number(n: i64) -> napi::Result<u64> {
+    u64::try_from(n).map_err(to_napi_error)
+}
+
+/// Sparse Merkle tree proof; siblings equal to the default hash of their height are left out
+/// and marked by a clear `bitmap` bit. `keyPath` names the tree's key-to-path strategy.
+#[napi(object)]
+pub struct JsSmtProof {
+    pub key_path: String,
+    pub bitmap: Vec<u8>,
+    pub siblings: Vec<Vec<u8>>,
+}
+
+impl From<merklerust_core::smt::SmtProof> for JsSmtProof {
+    fn from(proof: merklerust_core::smt::SmtProof) -> Self {
+        Self {
+            key_path: proof.key_path.name().to_string(),
+            bitmap: proof.bitmap.to_vec(),
+            siblings: proof.siblings.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+}
+
+impl TryFrom<&JsSmtProof> for merklerust_core::smt::SmtProof {
+    type Error = napi::Error;
+
+    fn try_from(proof: &JsSmtProof) -> napi::Result<Self> {
+        Ok(Self {
+            key_path: resolve_key_path(Some(proof.key_path.clone()))?,
+            bitmap: to_hash(&proof.bitmap)?,
+            siblings: to_hashes(&proof.siblings)?,
+        })
+    }
+}
+
+/// One proof for several sparse Merkle tree keys, see `SparseMerkleTree.proveMany`.
+#[napi(object)]
+pub struct JsSmtMultiProof {
+    pub key_path: String,
+    pub bitmap: Vec<u8>,
+    pub siblings: Vec<Vec<u8>>,
+}
+
+impl From<merklerust_core::smt::SmtMultiProof> for JsSmtMultiProof {
+    fn from(proof: merklerust_core::smt::SmtMultiProof) -> Self {
+        Self {
+            key_path: proof.key_path.name().to_string(),
+            bitmap: proof.bitmap,
+            siblings: proof.siblings.iter().map(|s| s.to_vec()).collect(),
+        }
+    }
+}
+
+impl TryFrom<&JsSmtMultiProof> for merklerust_core::smt::SmtMultiProof {
+    type Error = napi::Error;
+
+    fn try_from(proof: &JsSmtMultiProof) -> napi::Result<Self> {
+        Ok(Self {
+            key_path: resolve_key_path(Some(proof.key_path.clone()))?,
+            bitmap: proof.bitmap.clone(),
+            siblings: to_hashes(&proof.siblings)?,
+        })
+    }
+}
+
+/// A key with its value, or with no value to prove that the key is empty.
+#[napi(object)]
+pub struct JsSmtEntry {
+    pub key: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct JsMmrProof {
+    pub mmr_size: i64,
+    pub leaf_index: i64,
+    pub siblings: Vec<Vec<u8>>,
+    pub peaks: Vec<Vec<u8>>,
+}
+
+impl TryFrom<merklerust_core::mmr::MmrProof> for JsMmrProof {
+    type Error = napi::Error;
+
+    fn try_from(proof: merklerust_core::mmr::MmrProof) -> napi::Result<Self> {
+        Ok(Self {
+            mmr_size: to_js_number(proof.mmr_size)?,
+            leaf_index: to_js_number(proof.leaf_index)?,
+            siblings: proof.siblings.iter().map(|s| s.to_vec()).collect(),
+            peaks: proof.peaks.iter().map(|p| p.to_vec()).collect(),
+        })
+    }
+}
+
+impl TryFrom<&JsMmrProof> for merklerust_core::mmr::MmrProof {
+    type Error = napi::Error;
+
+    fn try_from(proof: &JsMmrProof) -> napi::Result<Self> {
+        Ok(Self {
+            mmr_size: from_js_number(proof.mmr_size)?,
+            leaf_index: from_js_number(proof.leaf_index)?,
+            siblings: to_hashes(&proof.siblings)?,
+            peaks: to_hashes(&proof.peaks)?,
+        })
+    }
+}
+
+/// Key-value map committed to by a 256-level sparse Merkle tree, proving both present and
+/// absent keys. `hash` names a positional hasher, `keccak256` by default, and `keyPath` how
+/// keys become paths, `raw` 32-byte keys by default.
+#[napi(js_name = "SparseMerkleTree")]
+pub struct JsSparseMerkleTree {
+    inner: DynSmt,
+}
+
+#[napi]
+impl JsSparseMerkleTree {
+    #[napi(constructor)]
+    pub fn new(hash: Option<String>, key_path: Option<String>) -> napi::Result<Self> {
+        DynSmt::try_with_key_path(
+            resolve_positional_hasher(hash)?,
+            resolve_key_path(key_path)?,
+        )
+        .map(|inner| Self { inner })
+        .map_err(to_napi_error)
+    }
+
+    /// Throw on a key the tree's key path rejects, where the core returns nothing.
+    fn check_key(&self, key: &[u8]) -> napi::Result<()> {
+        self.inner.key_path().path(key).map_err(to_napi_error)?;
+        Ok(())
+    }
+
+    #[napi]
+    pub fn root(&self) -> Vec<u8> {
+        self.inner.root().to_vec()
+    }
+
+    /// Number of keys with a value.
+    #[napi]
+    pub fn size(&self) -> u32 {
+        self.inner.len() as u32
+    }
+
+    #[napi]
+    pub fn get(&self, key: Vec<u8>) -> napi::Result<Option<Vec<u8>>> {
+        self.check_key(&key)?;
+        Ok(self.inner.get(&key).map(<[u8]>::to_vec))
+    }
+
+    /// Set the value of `key` and return the previous one.
+    #[napi]
+    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) -> napi::Result<Option<Vec<u8>>> {
+        self.inner.try_insert(key, value).map_err(to_napi_error)
+    }
+
+    #[napi]
+    pub fn remove(&mut self, key: Vec<u8>) -> napi::Result<Option<Vec<u8>>> {
+        self.check_key(&key)?;
+        Ok(self.inner.remove(&key))
+    }
+
+    /// Proof of the value of `key`, or that it has none.
+    #[napi]
+    pub fn prove(&self, key: Vec<u8>) -> napi::Result<JsSmtProof> {
+        self.inner
+            .try_prove(&key)
+            .map(Into::into)
+            .map_err(to_napi_error)
+    }
+
+    /// One proof for all `keys`, sharing the siblings their paths have in common.
+    #[napi]
+    pub fn prove_many(&self, keys: Vec<Vec<u8>>) -> napi::Result<JsSmtMultiProof> {
+        self.inner
+            .try_prove_many(&keys)
+            .map(Into::into)
+            .map_err(to_napi_error)
+    }
+}
+
+/// Check that `key` holds `value` under `root`, or that it is empty when `value` is omitted.
+/// `keyPath` is the strategy the tree is expected to use; a proof made with another fails.
+#[napi]
+pub fn verify_smt_proof(
+    proof: JsSmtProof,
+    root: Vec<u8>,
+    key: Vec<u8>,
+    value: Option<Vec<u8>>,
+    hash: Option<String>,
+    key_path: Option<String>,
+) -> napi::Result<bool> {
+    let hasher = resolve_positional_hasher(hash)?;
+    let key_path = resolve_key_path(key_path)?;
+    let proof = merklerust_core::smt::SmtProof::try_from(&proof)?;
+    Ok(match value {
+        Some(value) => {
+            proof.verify_membership_with_key_path(&root, &key, &value, &hasher, key_path)
+        }
+        None => proof.verify_non_membership_with_key_path(&root, &key, &hasher, key_path),
+    })
+}
+
+#[napi]
+pub fn verify_smt_multi_proof(
+    proof: JsSmtMultiProof,
+    root: Vec<u8>,
+    entries: Vec<JsSmtEntry>,
+    hash: Option<String>,
+    key_path: Option<String>,
+) -> napi::Result<bool> {
+    let hasher = resolve_positional_hasher(hash)?;
+    let key_path = resolve_key_path(key_path)?;
+    let proof = merklerust_core::smt::SmtMultiProof::try_from(&proof)?;
+    let entries: Vec<_> = entries
+        .iter()
+        .map(|e| (e.key.as_slice(), e.value.as_deref()))
+        .collect();
+    Ok(proof.verify_with_key_path(&root, &entries, &hasher, key_path))
+}
+
+#[napi]
+pub fn serialize_smt_proof(proof: JsSmtProof) -> napi::Result<Buffer> {
+    Ok(merklerust_core::smt::SmtProof::try_from(&proof)?
+        .to_bytes()
+        .into())
+}
+
+#[napi]
+pub fn deserialize_smt_proof(bytes: Buffer) -> napi::Result<JsSmtProof> {
+    merklerust_core::smt::SmtProof::from_bytes(&bytes)
+        .map(Into::into)
+        .map_err(to_napi_error)
+}
+
+#[napi]
+pub fn serialize_smt_multi_proof(proof: JsSmtMultiProof) -> napi::Result<Buffer> {
+    Ok(merklerust_core::smt::SmtMultiProof::try_from(&proof)?
+        .to_bytes()
+        .into())
+}
+
+#[napi]
+pub fn deserialize_smt_multi_proof(bytes: Buffer) -> napi::Result<JsSmtMultiProof> {
+    merklerust_core::smt::SmtMultiProof::from_bytes(&bytes)
+        .map(Into::into)
+        .map_err(to_napi_error)
+}
+
+/// Append-only Merkle Mountain Range over 32-byte leaves that can prove any leaf not yet
+/// pruned. `hash` names a positional hasher, `keccak256` by default.
+#[napi(js_name = "Mmr")]
+pub struct JsMmr {
+    inner: DynMmr,
+}
+
+#[napi]
+impl JsMmr {
+    #[napi(constructor)]
+    pub fn new(hash: Option<String>) -> napi::Result<Self> {
+        Ok(Self {
+            inner: DynMmr::new(resolve_positional_hasher(hash)?),
+        })
+    }
+
+    /// Append a leaf and return its leaf index.
+    #[napi]
+    pub fn push(&mut self, leaf: Vec<u8>) -> napi::Result<i64> {
+        let leaf = to_hash(&leaf)?;
+        to_js_number(self.inner.push(&leaf))
+    }
+
+    #[napi]
+    pub fn leaf_count(&self) -> napi::Result<i64> {
+        to_js_number(self.inner.leaf_count())
+    }
+
+    /// Number of nodes, including pruned ones.
+    #[napi]
+    pub fn size(&self) -> napi::Result<i64> {
+        to_js_number(self.inner.size())
+    }
+
+    /// Bagged peaks, or `null` while empty.
+    #[napi]
+    pub fn root(&self) -> Option<Vec<u8>> {
+        self.inner.root().map(|r| r.to_vec())
+    }
+
+    #[napi]
+    pub fn peaks(&self) -> Vec<Vec<u8>> {
+        self.inner.peaks().iter().map(|p| p.to_vec()).collect()
+    }
+
+    #[napi]
+    pub fn proof(&self, leaf_index: i64) -> napi::Result<JsMmrProof> {
+        let index = from_js_number(leaf_index)?;
+        if index >= self.inner.leaf_count() {
+            return Err(napi::Error::from_reason(format!(
+                "Leaf index {} out of range for {} leaves",
+                index,
+                self.inner.leaf_count()
+            )));
+        }
+        if index < self.inner.pruned_before() {
+            return Err(napi::Error::from_reason(format!(
+                "Leaf {} has been pruned",
+                index
+            )));
+        }
+        self.inner.proof(index).try_into()
+    }
+
+    /// Drop the nodes only needed to prove leaves below `leaf_index`.
+    #[napi]
+    pub fn prune(&mut self, leaf_index: i64) -> napi::Result<()> {
+        self.inner.prune(from_js_number(leaf_index)?);
+        Ok(())
+    }
+}
+
+#[napi]
+pub fn verify_mmr_proof(
+    proof: JsMmrProof,
+    root: Vec<u8>,
+    leaf: Vec<u8>,
+    hash: Option<String>,
+) -> napi::Result<bool> {
+    let hasher = resolve_positional_hasher(hash)?;
+    let proof = merklerust_core::mmr::MmrProof::try_from(&proof)?;
+    Ok(proof.verify(&root, &leaf, &hasher))
+}
+
+#[napi]
+pub fn serialize_mmr_proof(proof: JsMmrProof) -> napi::Result<Buffer> {
+    Ok(merklerust_core::mmr::MmrProof::try_from(&proof)?
+        .to_bytes()
+        .into())
+}
+
+#[napi]
+pub fn deserialize_mmr_proof(bytes: Buffer) -> napi::Result<JsMmrProof> {
+    merklerust_core::mmr::MmrProof::from_bytes(&bytes)
+        .map_err(to_napi_error)?
+        .try_into()
 }
@@ -0,0 +1,72 @@
+//! Parity harness for the bindings. A [`Target`] exposes the operations every binding offers
+//! and [`snapshot`] runs the fixture operations through one, rendering the outputs as text, so
+//! two bindings agree exactly when their snapshots are equal. The tests compare core with the
+//! napi binding, called as plain Rust, and with the C ABI; there is no wasm build.
+
+use std::fmt::Write;
+
+use merklerust_core::hashes::{keccak256, standard_node_hash};
+use merklerust_core::merkle::{get_proof_for_leaf, make_merkle_tree_bytes, verify_proof, Bytes};
+
+/// Leaf counts of the fixture trees: the edge cases, powers of two and their neighbours.
+pub const LEAF_COUNTS: &[u8] = &[1, 2, 3, 4, 5, 7, 8, 9, 13, 32, 33];
+
+/// `keccak256([i])` for `i` in `0..n`.
+pub fn fixture_leaves(n: u8) -> Vec<Bytes> {
+    (0..n).map(|i| keccak256(&[i]).to_vec()).collect()
+}
+
+/// Operations with the default hasher (sorted keccak256 pairs) of a binding.
+pub trait Target {
+    fn make_tree(&self, leaves: &[Bytes]) -> Vec<Bytes>;
+    /// Proof for the 0-based `leaf_position`.
+    fn proof(&self, tree: &[Bytes], leaf_position: usize) -> Vec<Bytes>;
+    fn verify(&self, root: &[u8], leaf: &[u8], proof: &[Bytes]) -> bool;
+}
+
+/// The core crate, the reference the bindings are compared with.
+pub struct Core;
+
+impl Target for Core {
+    fn make_tree(&self, leaves: &[Bytes]) -> Vec<Bytes> {
+        make_merkle_tree_bytes(leaves.to_vec(), |a, b| standard_node_hash(a, b).to_vec())
+    }
+
+    fn proof(&self, tree: &[Bytes], leaf_position: usize) -> Vec<Bytes> {
+        get_proof_for_leaf(tree, leaf_position)
+    }
+
+    fn verify(&self, root: &[u8], leaf: &[u8], proof: &[Bytes]) -> bool {
+        verify_proof(root, leaf, proof, |a, b| standard_node_hash(a, b).to_vec())
+    }
+}
+
+fn hex_nodes(nodes: &[Bytes]) -> String {
+    nodes.iter().map(hex::encode).collect::<Vec<_>>().join(",")
+}
+
+/// One line per output: every fixture tree, the proof of each leaf, and whether it verifies
+/// for its own leaf and for the next one.
+pub fn snapshot<T: Target>(target: &T) -> String {
+    let mut out = String::new();
+    for &n in LEAF_COUNTS {
+        let leaves = fixture_leaves(n);
+        let tree = target.make_tree(&leaves);
+        writeln!(out, "tree {}: {}", n, hex_nodes(&tree)).unwrap();
+        for (position, leaf) in leaves.iter().enumerate() {
+            let proof = target.proof(&tree, position);
+            let next = &leaves[(position + 1) % leaves.len()];
+            writeln!(
+                out,
+                "proof {}/{}: {} {} {}",
+                position,
+                n,
+                hex_nodes(&proof),
+                target.verify(&tree[0], leaf, &proof),
+                target.verify(&tree[0], next, &proof)
+            )
+            .unwrap();
+        }
+    }
+    out
+}
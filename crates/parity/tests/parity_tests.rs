@@ -0,0 +1,129 @@
+use merklerust_core::hashes::{hasher_by_name, Hasher};
+use merklerust_core::merkle::{get_multi_proof_for_leaves, try_make_merkle_tree_bytes, Bytes};
+use merklerust_core::tree::MerkleTree;
+use merklerust_ffi::{mr_get_proof, mr_make_tree, mr_verify_proof, MR_OK};
+use merklerust_node::JsMerkleTree;
+use merklerust_parity::{fixture_leaves, snapshot, Core, Target, LEAF_COUNTS};
+
+/// The napi binding with its default hash.
+struct Napi;
+
+impl Target for Napi {
+    fn make_tree(&self, leaves: &[Bytes]) -> Vec<Bytes> {
+        merklerust_node::make_merkle_tree(leaves.to_vec(), None).unwrap()
+    }
+
+    fn proof(&self, tree: &[Bytes], leaf_position: usize) -> Vec<Bytes> {
+        merklerust_node::get_proof_for_leaf(tree.to_vec(), leaf_position as u32).unwrap()
+    }
+
+    fn verify(&self, root: &[u8], leaf: &[u8], proof: &[Bytes]) -> bool {
+        merklerust_node::process_proof(leaf.to_vec(), proof.to_vec(), None).unwrap() == root
+    }
+}
+
+/// The C ABI.
+struct Ffi;
+
+fn split(buffer: &[u8]) -> Vec<Bytes> {
+    buffer.chunks(32).map(|c| c.to_vec()).collect()
+}
+
+impl Target for Ffi {
+    fn make_tree(&self, leaves: &[Bytes]) -> Vec<Bytes> {
+        let leaves = leaves.concat();
+        let mut tree = vec![0u8; 2 * leaves.len() - 32];
+        let code = unsafe {
+            mr_make_tree(
+                leaves.as_ptr(),
+                leaves.len() / 32,
+                tree.as_mut_ptr(),
+                tree.len(),
+            )
+        };
+        assert_eq!(code, MR_OK);
+        split(&tree)
+    }
+
+    fn proof(&self, tree: &[Bytes], leaf_position: usize) -> Vec<Bytes> {
+        let tree = tree.concat();
+        let mut proof = vec![0u8; 32 * 64];
+        let mut proof_nodes = 0;
+        let code = unsafe {
+            mr_get_proof(
+                tree.as_ptr(),
+                tree.len() / 32,
+                leaf_position,
+                proof.as_mut_ptr(),
+                proof.len(),
+                &mut proof_nodes,
+            )
+        };
+        assert_eq!(code, MR_OK);
+        split(&proof[..32 * proof_nodes])
+    }
+
+    fn verify(&self, root: &[u8], leaf: &[u8], proof: &[Bytes]) -> bool {
+        let proof = proof.concat();
+        let code = unsafe {
+            mr_verify_proof(
+                root.as_ptr(),
+                leaf.as_ptr(),
+                proof.as_ptr(),
+                proof.len() / 32,
+            )
+        };
+        assert!(code >= 0);
+        code == 1
+    }
+}
+
+#[test]
+fn bindings_match_core_snapshot() {
+    let core = snapshot(&Core);
+    assert_eq!(snapshot(&Napi), core);
+    assert_eq!(snapshot(&Ffi), core);
+}
+
+#[test]
+fn napi_hashers_and_multi_proofs_match_core() {
+    let names = ["keccak256", "sha256", "sha3-256", "blake3"];
+    for &n in LEAF_COUNTS {
+        let leaves = fixture_leaves(n);
+        for name in names {
+            let hasher = hasher_by_name(name).unwrap();
+            let node_hash = |a: &[u8], b: &[u8]| hasher.hash_node(a, b).to_vec();
+            let core = try_make_merkle_tree_bytes(leaves.clone(), node_hash).unwrap();
+            let hash = Some(name.to_string());
+            let napi = merklerust_node::make_merkle_tree(leaves.clone(), hash.clone()).unwrap();
+            assert_eq!(napi, core, "{} over {} leaves", name, n);
+            assert!(merklerust_node::is_valid_merkle_tree(napi.clone(), hash.clone()).unwrap());
+
+            let positions: Vec<usize> = (0..n as usize).step_by(3).collect();
+            let expected = get_multi_proof_for_leaves(&core, &positions);
+            let mp = merklerust_node::get_multi_proof_for_leaves(
+                napi.clone(),
+                positions.iter().map(|&p| p as u32).collect(),
+            )
+            .unwrap();
+            assert_eq!(
+                (&mp.leaves, &mp.proof, &mp.proof_flags),
+                (&expected.leaves, &expected.proof, &expected.proof_flags)
+            );
+            assert_eq!(
+                merklerust_node::process_multi_proof(mp, hash.clone()).unwrap(),
+                core[0]
+            );
+
+            let tree = JsMerkleTree::new(leaves.clone(), hash).unwrap();
+            let reference = MerkleTree::new(leaves.clone(), hasher);
+            assert_eq!(tree.root().unwrap(), reference.root());
+            for position in 0..n as usize {
+                assert_eq!(
+                    tree.get_proof(position as u32).unwrap(),
+                    reference.proof(position)
+                );
+            }
+        }
+    }
+}
@@ -1,15 +1,43 @@
 import { describe, it, expect } from "vitest";
 import { test as fcTest } from "@fast-check/vitest";
 import fc from "fast-check";
+import { mkdtempSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+import { pathToFileURL } from "node:url";
 import {
     makeMerkleTree,
     getProof,
+    getProofInto,
+    getProofForLeaf,
+    getMultiProofForLeaves,
     processProof,
     getMultiProof,
     processMultiProof,
     isValidMerkleTree,
     renderMerkleTree,
+    renderToDot,
+    renderToJSON,
+    standardLeafHash,
+    verifyClaim,
+    MerkleTree,
+    setResourceProfile,
+    resourceProfile,
+    buildTreeFiles,
+    SparseMerkleTree,
+    Mmr,
+    verifySmtProof,
+    verifySmtMultiProof,
+    serializeSmtProof,
+    deserializeSmtProof,
+    serializeSmtMultiProof,
+    deserializeSmtMultiProof,
+    verifyMmrProof,
+    serializeMmrProof,
+    deserializeMmrProof,
     type JsMultiProof,
+    type JsSmtProof,
+    type JsMmrProof,
 } from "../index.js";
 
 const ZERO_NODE: number[] = Array.from({ length: 32 }, () => 0);
@@ -142,4 +170,308 @@ describe("Render tree", () => {
         expect(typeof output).toBe("string");
         expect(output.length).toBeGreaterThan(0);
     });
+
+    it("should render a tree as DOT", () => {
+        const tree = makeMerkleTree([ZERO_NODE, ZERO_NODE]);
+        const output = renderToDot(tree);
+        expect(output.startsWith("digraph merkle {")).toBe(true);
+        expect(output).toContain("n0 -> n1;");
+        expect(output).toContain("n0 -> n2;");
+    });
+
+    it("should render a tree as JSON", () => {
+        const tree = makeMerkleTree([ZERO_NODE, ZERO_NODE]);
+        const output = JSON.parse(renderToJSON(tree));
+        expect(output.nodes.length).toBe(tree.length);
+        expect(output.nodes[0].left).toBe(1);
+        expect(output.nodes[1].left).toBeNull();
+        expect(output.root).toBe(output.nodes[0].hash);
+    });
+});
+
+describe("Proof into caller buffer", () => {
+    it("writes the same nodes as getProof", () => {
+        const leaves = [1, 2, 3, 4, 5].map((v) =>
+            Array.from({ length: 32 }, () => v)
+        );
+        const tree = makeMerkleTree(leaves);
+        const expected = getProof(tree, tree.length - 1);
+        const flat = Buffer.concat(tree.map((node) => Buffer.from(node)));
+        const out = Buffer.alloc(32 * 8);
+        const written = getProofInto(flat, tree.length - 1, out);
+        expect(written).toBe(expected.length);
+        expect(Array.from(out.subarray(0, written * 32))).toEqual(
+            expected.flat()
+        );
+    });
+
+    it("throws when the buffer is too small", () => {
+        const tree = makeMerkleTree([ZERO_NODE, ZERO_NODE]);
+        const flat = Buffer.concat(tree.map((node) => Buffer.from(node)));
+        expect(() => getProofInto(flat, 1, Buffer.alloc(16))).toThrow(
+            "Output buffer too small"
+        );
+    });
+
+    it("rejects a tree that is not whole 32-byte nodes", () => {
+        const out = Buffer.alloc(32 * 8);
+        expect(() => getProofInto(Buffer.alloc(33), 0, out)).toThrow("got length 1");
+        expect(() => getProofInto(Buffer.alloc(0), 0, out)).toThrow("non-zero number");
+        expect(() => getProofInto(Buffer.alloc(96), 0, out)).toThrow("Expected leaf node");
+    });
+});
+
+describe("Standard leaf hash", () => {
+    it("matches the OpenZeppelin StandardMerkleTree root", () => {
+        const encoding = ["address", "uint256"];
+        const a = standardLeafHash(
+            ["0x1111111111111111111111111111111111111111", "5000000000000000000"],
+            encoding
+        );
+        const b = standardLeafHash(
+            ["0x2222222222222222222222222222222222222222", "2500000000000000000"],
+            encoding
+        );
+        const tree = makeMerkleTree([a, b]);
+        expect(Buffer.from(tree[0]!).toString("hex")).toBe(
+            "d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+    });
+
+    it("throws on unsupported encodings", () => {
+        expect(() => standardLeafHash(["1"], ["uint7"])).toThrow(
+            "Unsupported ABI type"
+        );
+    });
+});
+
+describe("Verify claim", () => {
+    const encoding = ["address", "uint256"];
+    const values = [
+        ["0x1111111111111111111111111111111111111111", "5000000000000000000"],
+        ["0x2222222222222222222222222222222222222222", "2500000000000000000"],
+    ];
+
+    it("accepts a valid claim and rejects a tampered one", () => {
+        const tree = makeMerkleTree(
+            values.map((v) => standardLeafHash(v, encoding))
+        );
+        const root = tree[0]!;
+        const proof = getProof(tree, tree.length - 1);
+
+        expect(
+            verifyClaim({ root, value: values[0]!, encoding, proof })
+        ).toBe(true);
+        expect(
+            verifyClaim({
+                root,
+                value: [values[0]![0]!, "5000000000000000001"],
+                encoding,
+                proof,
+            })
+        ).toBe(false);
+    });
+});
+
+describe("Hash algorithm selection", () => {
+    const hashes = ["keccak256", "sha256", "sha3-256", "blake3"];
+
+    fcTest.prop([leavesAndIndex])(
+        "proofs verify under every built-in hash",
+        ([xs, i]) => {
+            for (const hash of hashes) {
+                const tree = makeMerkleTree(xs, hash);
+                expect(isValidMerkleTree(tree, hash)).toBe(true);
+                const proof = getProof(tree, tree.length - 1 - i);
+                expect(processProof(xs[i], proof, hash)).toEqual(tree[0]);
+            }
+        }
+    );
+
+    it("defaults to keccak256", () => {
+        const xs = [ZERO_NODE, ZERO_NODE.map(() => 1)];
+        expect(makeMerkleTree(xs)).toEqual(makeMerkleTree(xs, "keccak256"));
+        expect(makeMerkleTree(xs, "sha256")[0]).not.toEqual(
+            makeMerkleTree(xs)[0]
+        );
+    });
+
+    it("throws on unknown algorithm", () => {
+        expect(() => makeMerkleTree([ZERO_NODE], "md5")).toThrow(
+            "Unknown hash algorithm"
+        );
+    });
+});
+
+describe("Leaf position API", () => {
+    fcTest.prop([leavesAndIndices])(
+        "matches flat-index proofs",
+        ([xs, positions]) => {
+            const tree = makeMerkleTree(xs);
+            for (const i of positions) {
+                expect(getProofForLeaf(tree, i)).toEqual(
+                    getProof(tree, tree.length - 1 - i)
+                );
+            }
+            expect(getMultiProofForLeaves(tree, positions)).toEqual(
+                getMultiProof(
+                    tree,
+                    positions.map((i) => tree.length - 1 - i)
+                )
+            );
+        }
+    );
+
+    it("throws on out-of-range position", () => {
+        const tree = makeMerkleTree([ZERO_NODE]);
+        expect(() => getProofForLeaf(tree, 1)).toThrow("out of range");
+    });
+});
+
+describe("MerkleTree class", () => {
+    fcTest.prop([leavesAndIndex])(
+        "matches the flat-tree functions",
+        ([xs, i]) => {
+            const flat = makeMerkleTree(xs);
+            const tree = new MerkleTree(xs);
+            expect(tree.root()).toEqual(flat[0]);
+            expect(tree.getProof(i)).toEqual(getProofForLeaf(flat, i));
+        }
+    );
+
+    it("reports stats and frees on dispose", () => {
+        const tree = new MerkleTree([ZERO_NODE, ZERO_NODE, ZERO_NODE]);
+        const stats = tree.stats();
+        expect(stats.nodeCount).toBe(5);
+        expect(stats.leafCount).toBe(3);
+        expect(stats.bytes).toBeGreaterThanOrEqual(5 * 32);
+        expect(stats.backend).toBe("memory");
+        expect(stats.disposed).toBe(false);
+
+        tree.dispose();
+        tree.dispose();
+        expect(tree.stats()).toMatchObject({ bytes: 0, disposed: true });
+        expect(() => tree.root()).toThrow("disposed");
+    });
+
+    it("serves batch proofs under both resource profiles", () => {
+        const xs = [1, 2, 3, 4, 5].map((v) => Array.from({ length: 32 }, () => v));
+        const flat = makeMerkleTree(xs);
+        const tree = new MerkleTree(xs);
+        const expected = [4, 0, 2].map((i) => getProofForLeaf(flat, i));
+
+        expect(resourceProfile()).toBe("standard");
+        expect(tree.getProofs([4, 0, 2])).toEqual(expected);
+        setResourceProfile("low-memory");
+        expect(resourceProfile()).toBe("low-memory");
+        expect(tree.getProofs([4, 0, 2])).toEqual(expected);
+        expect(() => tree.getProofs([5])).toThrow();
+        setResourceProfile("standard");
+        expect(() => setResourceProfile("tiny")).toThrow("Unknown resource profile");
+    });
+
+    it("emits a JS verifier for its proofs", async () => {
+        const xs = [1, 2, 3, 4, 5].map((v) => Array.from({ length: 32 }, () => v));
+        const dir = mkdtempSync(join(tmpdir(), "merklerust-"));
+        try {
+            for (const hash of ["keccak256", "sha256", "sha3-256"]) {
+                const tree = new MerkleTree(xs, hash);
+                const file = join(dir, `${hash}.mjs`);
+                writeFileSync(file, tree.jsVerifier());
+                const verifier = await import(pathToFileURL(file).href);
+                const flat = makeMerkleTree(xs, hash);
+                for (let i = 0; i < xs.length; i++) {
+                    expect(verifier.verifyProof(tree.root(), xs[i], tree.getProof(i))).toBe(true);
+                    expect(verifier.verifyProof(tree.root(), ZERO_NODE, tree.getProof(i))).toBe(false);
+                }
+                const mp = getMultiProofForLeaves(flat, [0, 3]);
+                expect(verifier.verifyMultiProof(tree.root(), mp)).toBe(true);
+            }
+            expect(() => new MerkleTree(xs, "blake3").jsVerifier()).toThrow("no JS implementation");
+        } finally {
+            rmSync(dir, { recursive: true, force: true });
+        }
+    });
+
+    it("builds level files from a leaf file under the low-memory profile", () => {
+        const xs = [1, 2, 3, 4, 5].map((v) => Array.from({ length: 32 }, () => v));
+        const dir = mkdtempSync(join(tmpdir(), "merklerust-"));
+        try {
+            const leafFile = join(dir, "leaves.in");
+            writeFileSync(leafFile, Buffer.from(xs.flat()));
+            setResourceProfile("low-memory");
+            const root = buildTreeFiles(leafFile, join(dir, "tree"), "sha256");
+            expect(root).toEqual(makeMerkleTree(xs, "sha256")[0]);
+            expect(() => buildTreeFiles(join(dir, "missing"), join(dir, "tree"))).toThrow();
+        } finally {
+            setResourceProfile("standard");
+            rmSync(dir, { recursive: true, force: true });
+        }
+    });
+});
+
+describe("SparseMerkleTree and Mmr classes", () => {
+    const key = (v: number) => Array.from({ length: 32 }, (_, i) => (i === 0 ? v : 0));
+
+    it("proves present and absent keys", () => {
+        const tree = new SparseMerkleTree();
+        expect(tree.insert(key(1), [1])).toBeNull();
+        expect(tree.insert(key(2), [2])).toBeNull();
+        expect(tree.size()).toBe(2);
+        expect(tree.get(key(1))).toEqual([1]);
+        const root = tree.root();
+
+        const proof: JsSmtProof = tree.prove(key(1));
+        expect(verifySmtProof(proof, root, key(1), [1])).toBe(true);
+        expect(verifySmtProof(proof, root, key(1), [2])).toBe(false);
+        const absent = tree.prove(key(3));
+        expect(verifySmtProof(absent, root, key(3))).toBe(true);
+        expect(deserializeSmtProof(serializeSmtProof(proof))).toEqual(proof);
+
+        const multi = tree.proveMany([key(1), key(3)]);
+        const entries = [{ key: key(1), value: [1] }, { key: key(3) }];
+        expect(verifySmtMultiProof(multi, root, entries)).toBe(true);
+        expect(deserializeSmtMultiProof(serializeSmtMultiProof(multi))).toEqual(multi);
+        expect(() => tree.get([1, 2])).toThrow();
+        expect(() => new SparseMerkleTree("sha3-256")).toThrow();
+        expect(() => new SparseMerkleTree(undefined, "md5")).toThrow();
+    });
+
+    it("maps keys to paths with the tree's key path", () => {
+        const tree = new SparseMerkleTree("keccak256", "sha256");
+        const account = Array.from(Buffer.from("alice"));
+        tree.insert(account, [1]);
+        expect(tree.get(account)).toEqual([1]);
+        const root = tree.root();
+
+        const proof = tree.prove(account);
+        expect(proof.keyPath).toBe("sha256");
+        expect(verifySmtProof(proof, root, account, [1], "keccak256", "sha256")).toBe(true);
+        expect(verifySmtProof(proof, root, account, [1])).toBe(false);
+        expect(deserializeSmtProof(serializeSmtProof(proof))).toEqual(proof);
+        const multi = tree.proveMany([account]);
+        const entries = [{ key: account, value: [1] }];
+        expect(verifySmtMultiProof(multi, root, entries, "keccak256", "sha256")).toBe(true);
+    });
+
+    it("proves appended leaves until pruned", () => {
+        const mmr = new Mmr();
+        expect(mmr.root()).toBeNull();
+        const xs = [1, 2, 3, 4, 5].map((v) => Array.from({ length: 32 }, () => v));
+        xs.forEach((x, i) => expect(mmr.push(x)).toBe(i));
+        expect(mmr.leafCount()).toBe(5);
+        expect(mmr.size()).toBe(8);
+        const root = mmr.root()!;
+
+        const proof: JsMmrProof = mmr.proof(2);
+        expect(verifyMmrProof(proof, root, xs[2])).toBe(true);
+        expect(verifyMmrProof(proof, root, xs[3])).toBe(false);
+        expect(deserializeMmrProof(serializeMmrProof(proof))).toEqual(proof);
+        expect(() => deserializeMmrProof(Buffer.alloc(3))).toThrow();
+
+        mmr.prune(3);
+        expect(() => mmr.proof(2)).toThrow("pruned");
+        expect(() => mmr.proof(5)).toThrow("out of range");
+        expect(verifyMmrProof(mmr.proof(4), root, xs[4])).toBe(true);
+    });
 });